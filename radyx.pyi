@@ -1,6 +1,12 @@
-from typing import List, Tuple, Set
+from typing import Callable, List, Literal, Optional, Tuple, Set, Union
+import logging
+import numpy as np
+import numpy.typing as npt
+import pyarrow
 from pyray import Vector2
 
+class RadyxError(ValueError): ...
+
 class Body:
     def __init__(
         self,
@@ -9,6 +15,7 @@ class Body:
         pos: Vector2,
         radius: float,
         is_static: bool,
+        kind: int = 0,
     ): ...
     def collided(self, other: "Body") -> bool: ...
     def get_bounds(self) -> Tuple[float, float, float, float]: ...
@@ -25,16 +32,278 @@ class Collision:
         self.other_entity_index: int
         self.self_body_index: int
         self.other_body_index: int
+        self.overlap: float
+        self.tick: int
+        self.contact_id: Optional[int]
+    @property
+    def self_entity(self) -> Tuple[int, int]: ...
+    @property
+    def other_entity(self) -> Tuple[int, int]: ...
+
+Kind = Union[int, str]
+Metric = Literal["euclidean", "chebyshev", "manhattan"]
+Containment = Literal["intersects", "contains"]
+
+class EventKind:
+    Collision: "EventKind"
+    Sensor: "EventKind"
+    Enter: "EventKind"
+    Exit: "EventKind"
+    Boundary: "EventKind"
+    Left: "EventKind"
+    Reentered: "EventKind"
+
+class Event:
+    kind: EventKind
+    tick: int
+    self_entity_index: int
+    self_body_index: int
+    other_entity_index: Optional[int]
+    other_body_index: Optional[int]
+    contact_id: Optional[int]
+
+class Positions:
+    indices: List[Tuple[int, int]]
+
+    def __dlpack__(self, stream: Optional[int] = None) -> object: ...
+    def __dlpack_device__(self) -> Tuple[int, int]: ...
+    def __len__(self) -> int: ...
+
+class WorldDiff:
+    added: List[Tuple[int, int]]
+    removed: List[Tuple[int, int]]
+    moved: List[Tuple[int, int]]
+
+    def is_empty(self) -> bool: ...
+
+class MemoryUsage:
+    cells: int
+    bodies: int
+    caches: int
+    total: int
+    cell_copies: int
+    duplicated_copies: int
+
+class BenchmarkReport:
+    insert: float
+    moves: float
+    collisions: float
+    area_queries: float
+    raycasts: float
+    total: float
+    collisions_found: int
+
+class EntityHandle:
+    def __init__(self, index: int, generation: int):
+        self.index: int
+        self.generation: int
+
+class Query:
+    def circle(self, center: Vector2, radius: float) -> "Query": ...
+    def rect(self, min: Vector2, max: Vector2) -> "Query": ...
+    def mask(self, kind: Kind) -> "Query": ...
+    def exclude(self, entity_index: int) -> "Query": ...
+    def limit(self, limit: int) -> "Query": ...
+    def nearest_first(self) -> "Query": ...
+    def metric(
+        self, metric: Literal["euclidean", "chebyshev", "manhattan"]
+    ) -> "Query": ...
+    def containment(self, containment: Containment) -> "Query": ...
+    def execute(self) -> List[Tuple[int, int, float]]: ...
 
 class GridPhysics:
-    def __init__(self, size: int, cell_size: int):
+    def __init__(
+        self, size: float, cell_size: float, max_bodies: Optional[int] = None
+    ):
         self.grid: List[List[Body]]
         self.dynamic_bodies: dict[int, List[Body]]
-        self.size: int
-        self.cell_size: int
+        self.static_bodies: dict[int, List[Body]]
+        self.size: float
+        self.cell_size: float
         self.grid_size: int
+        self.wrap: bool
+        self.min_overlap: float
+        self.max_collisions_per_entity: Optional[int]
+        self.pair_dedup: Literal["visited", "after"]
+        self.sweep_dt: float
+        self.max_bodies: Optional[int]
+        self.max_cells_per_body: int
+        self.oversize_policy: Literal["ignore", "warn", "error"]
+        self.deferred: bool
+        self.strict: bool
+        self.integrate: bool
+        self.max_displacement: Optional[float]
+        self.max_substeps: int
+        self.gravity: Tuple[float, float]
+        self.push_out_iterations: int
+        self.sleep_threshold: Optional[float]
+        self.sleep_steps: int
+        self.static_layer: Optional[str]
 
+    @staticmethod
+    def from_tiled(
+        json_str_or_path: str,
+        collision_layer: str = "collisions",
+        cell_size: Optional[float] = None,
+    ) -> "GridPhysics": ...
+    def to_json(self, pretty: bool = True) -> str: ...
+    @staticmethod
+    def from_json(json: str) -> "GridPhysics": ...
+    @staticmethod
+    def hexagonal(
+        size: float, cell_size: float, max_bodies: Optional[int] = None
+    ) -> "GridPhysics": ...
     def reset(self) -> None: ...
+    def create_entity(self) -> EntityHandle: ...
+    def destroy_entity(self, handle: EntityHandle) -> None: ...
+    def is_alive(self, handle: EntityHandle) -> bool: ...
+    def compact(self) -> dict[int, int]: ...
+    def set_deferred(self, deferred: bool) -> None: ...
+    def commit(self) -> int: ...
+    def discard_pending(self) -> int: ...
+    def dirty_cells(self) -> list[tuple[int, int]]: ...
+    def remove_entity(self, entity_index: int) -> None: ...
+    def reserve(self, dynamic_bodies_hint: int, static_bodies_hint: int) -> None: ...
+    def set_entity_transform(
+        self, entity_index: int, position: Vector2, angle: float
+    ) -> None: ...
+    def move_entity(self, entity_index: int, anchor: Vector2) -> None: ...
+    def update_position(
+        self, entity_index: int, body_index: int, pos: Vector2
+    ) -> None: ...
+    def set_radius(
+        self, entity_index: int, body_index: int, new_radius: float
+    ) -> None: ...
+    def scale_entity(self, entity_index: int, factor: float) -> None: ...
+    def teleport(
+        self,
+        entity_index: int,
+        body_index: int,
+        new_pos: Vector2,
+        sweep: bool = False,
+    ) -> Set[Collision]: ...
+    def set_velocity(self, entity_index: int, velocity: Vector2) -> None: ...
+    def get_velocity(self, entity_index: int) -> Tuple[float, float]: ...
+    def apply_impulse(
+        self, entity_index: int, body_index: int, impulse: Vector2
+    ) -> None: ...
+    def apply_force(self, entity_index: int, body_index: int, force: Vector2) -> None: ...
+    def radial_impulse(
+        self,
+        center: Vector2,
+        radius: float,
+        strength: float,
+        falloff: Literal["constant", "linear", "quadratic"] = "linear",
+    ) -> List[Tuple[int, Tuple[float, float]]]: ...
+    def set_drag(self, entity_index: int, drag: float) -> None: ...
+    def set_mass(self, entity_index: int, mass: float) -> None: ...
+    def get_mass(self, entity_index: int) -> float: ...
+    def set_friction(self, entity_index: int, body_index: int, friction: float) -> None: ...
+    def get_friction(self, entity_index: int, body_index: int) -> float: ...
+    def set_pair_cooldown(self, entity_a: int, entity_b: int, ticks: int) -> None: ...
+    def resolve_overlaps(self, iterations: int = 1) -> int: ...
+    def is_sleeping(self, entity_index: int) -> bool: ...
+    def sleeping_entities(self) -> List[int]: ...
+    def wake(self, entity_index: int) -> None: ...
+    def set_entity_max_displacement(
+        self, entity_index: int, max_displacement: Optional[float] = None
+    ) -> None: ...
+    def extrapolate(self, entity_index: int, dt: float) -> List[Tuple[float, float]]: ...
+    def time_of_impact(
+        self,
+        entity_a: int,
+        entity_b: int,
+        velocities: Optional[Tuple[Vector2, Vector2]] = None,
+        dt: Optional[float] = None,
+    ) -> Optional[float]: ...
+    def extrapolated(self, dt: float) -> "GridPhysics": ...
+    def set_sweep_dt(self, sweep_dt: float) -> None: ...
+    def set_wrap(self, wrap: bool) -> None: ...
+    def get_entity_transform(self, entity_index: int) -> Tuple[Tuple[float, float], float]: ...
+    def add_static_ellipse(
+        self,
+        entity_index: int,
+        pos: Vector2,
+        radius_x: float,
+        radius_y: float,
+        angle: float = 0.0,
+        kind: Kind = 0,
+    ) -> None: ...
+    def add_dynamic_ellipse(
+        self,
+        entity_index: int,
+        pos: Vector2,
+        radius_x: float,
+        radius_y: float,
+        angle: float = 0.0,
+        kind: Kind = 0,
+    ) -> None: ...
+    def add_static_chain(
+        self, entity_index: int, points: List[Vector2], kind: Kind = 0
+    ) -> None: ...
+    def add_static_trimesh(
+        self,
+        entity_index: int,
+        vertices: List[Vector2],
+        indices: List[Tuple[int, int, int]],
+        kind: Kind = 0,
+    ) -> None: ...
+    def add_static_obb(
+        self,
+        entity_index: int,
+        center: Vector2,
+        half_extents: Vector2,
+        angle: float = 0.0,
+        kind: Kind = 0,
+    ) -> None: ...
+    def add_dynamic_obb(
+        self,
+        entity_index: int,
+        center: Vector2,
+        half_extents: Vector2,
+        angle: float = 0.0,
+        kind: Kind = 0,
+    ) -> None: ...
+    def register_kind(self, name: str) -> int: ...
+    def kind_name(self, kind: int) -> Optional[str]: ...
+    def register_namespace(self, name: str) -> int: ...
+    def namespaced(self, namespace: str, entity: int) -> int: ...
+    def namespace_name(self, namespace: int) -> Optional[str]: ...
+    def step(self, dt: float = 1.0 / 60.0) -> int: ...
+    def tick(self) -> int: ...
+    def time(self) -> float: ...
+    def advance(self, frame_dt: float) -> List[Event]: ...
+    def on_collision(
+        self,
+        handler: Callable[[Event], object],
+        layer_a: Optional[Union[int, str]] = None,
+        layer_b: Optional[Union[int, str]] = None,
+    ) -> int: ...
+    def remove_collision_handler(self, id: int) -> bool: ...
+    def drain_events(self) -> List[Event]: ...
+    def set_log_hook(
+        self, hook: Optional[Union[Callable[[dict], None], logging.Logger]] = None
+    ) -> None: ...
+    def start_debug_server(self, port: int, host: str = "127.0.0.1") -> int: ...
+    def stop_debug_server(self) -> None: ...
+    def export_debug_image(self, path: str, scale: float = 1.0) -> None: ...
+    def memory_usage(self) -> MemoryUsage: ...
+    def fork(self) -> "GridPhysics": ...
+    @staticmethod
+    def diff(snapshot_a: "GridPhysics", snapshot_b: "GridPhysics") -> WorldDiff: ...
+    def encode_delta(self, since_tick: int = 0) -> bytes: ...
+    def apply_delta(self, data: bytes) -> int: ...
+    def set_state_slots(self, count: int) -> None: ...
+    def save_state(self, slot: int) -> None: ...
+    def load_state(self, slot: int) -> None: ...
+    def load_static_layer(self, name: str, bodies: str) -> None: ...
+    def swap_static_layer(self, name: str) -> None: ...
+    def static_layer_names(self) -> List[str]: ...
+    def set_history_length(self, length: int) -> None: ...
+    def collisions_at(self, tick: int) -> Set[Collision]: ...
+    def was_colliding(self, a: int, b: int, within_ticks: int = 0) -> bool: ...
+    def set_sensor(self, entity_index: int, is_sensor: bool) -> None: ...
+    def resize(self, new_size: float, new_cell_size: float) -> None: ...
     def get_grid_bounds(
         self, bounds: Tuple[float, float, float, float]
     ) -> Tuple[int, int, int, int]: ...
@@ -45,21 +314,220 @@ class GridPhysics:
         radius: float,
         body_index: int,
         is_static: bool,
+        kind: Kind = 0,
     ) -> None: ...
     def add_static_circle(
-        self, entity_index: int, pos: Vector2, radius: float
+        self, entity_index: int, pos: Vector2, radius: float, kind: Kind = 0
     ) -> None: ...
     def add_static_circles(
-        self, entity_index: int, bodies: List[Vector2], radius: float
+        self,
+        entity_index: int,
+        bodies: List[Vector2],
+        radius: float,
+        kind: Kind = 0,
+        anchor: Optional[Vector2] = None,
+    ) -> None: ...
+    def add_static_circles_numpy(
+        self,
+        entity_index: int,
+        positions: npt.NDArray[np.floating],
+        radii: npt.NDArray[np.floating],
+        kind: Kind = 0,
+    ) -> None: ...
+    def add_dynamic_circles_numpy(
+        self,
+        entity_index: int,
+        positions: npt.NDArray[np.floating],
+        radii: npt.NDArray[np.floating],
+        kind: Kind = 0,
     ) -> None: ...
     def add_dynamic_circle(
-        self, entity_index: int, pos: Vector2, radius: float
+        self, entity_index: int, pos: Vector2, radius: float, kind: Kind = 0
     ) -> None: ...
     def add_dynamic_circles(
-        self, entity_index: int, bodies: List[Vector2], radius: float
+        self,
+        entity_index: int,
+        bodies: List[Vector2],
+        radius: float,
+        kind: Kind = 0,
+        anchor: Optional[Vector2] = None,
     ) -> None: ...
-    def get_collisions(self) -> Set[Collision]: ...
+    def get_collisions(
+        self,
+        with_overlap: bool = False,
+        limit: Optional[int] = None,
+        first_only: bool = False,
+        targets: Literal["all", "static", "dynamic"] = "all",
+        result_type: Literal["set", "list", "numpy"] = "set",
+    ) -> Union[Set[Collision], List[Collision], npt.NDArray[np.int64]]: ...
+    def get_sorted_collisions(
+        self,
+        with_overlap: bool = False,
+        parallel: bool = False,
+        num_threads: Optional[int] = None,
+    ) -> List[Collision]: ...
+    def simulation_islands(self) -> List[List[int]]: ...
+    def get_static_overlaps(self) -> Set[Collision]: ...
+    def bake_static_exclusions(self) -> Tuple[int, int]: ...
+    def optimize_statics(self, across_entities: bool = False) -> int: ...
+    def get_collisions_into(self, buffer: npt.NDArray[np.int64]) -> int: ...
+    # Copies numpy columns into pyarrow at call time, not a zero-copy Arrow export
+    def get_collisions_arrow(self, with_overlap: bool = False) -> "pyarrow.RecordBatch": ...
+    def get_group_collisions(
+        self,
+        name: str,
+        with_overlap: bool = False,
+        limit: Optional[int] = None,
+        first_only: bool = False,
+    ) -> Set[Collision]: ...
+    def create_group(self, name: str, entities: List[int]) -> None: ...
+    def set_group_active(self, name: str, active: bool) -> None: ...
+    def get_near_collisions(self, margin: float) -> Set[Collision]: ...
+    def cross_collisions(self, other: "GridPhysics") -> Set[Collision]: ...
+    def get_collision_counts(self) -> dict[Tuple[int, int], int]: ...
     def get_collisions_within_area(
-        self, position: Vector2, radius: float
+        self,
+        position: Vector2,
+        radius: float,
+        metric: Optional[Metric] = None,
+        limit: Optional[int] = None,
+        first_only: bool = False,
+        containment: Containment = "intersects",
     ) -> Set[int]: ...
+    def query_polygon(
+        self, points: List[Vector2], containment: Containment = "intersects"
+    ) -> Set[int]: ...
+    def subscribe(self, center: Vector2, radius: float, id: int) -> None: ...
+    def unsubscribe(self, id: int) -> None: ...
+    def poll_subscription(self, id: int) -> Tuple[List[int], List[int]]: ...
+    def entities_overlapping_aabb(
+        self, min: Vector2, max: Vector2, containment: Containment = "intersects"
+    ) -> List[int]: ...
+    def visible_entities(
+        self, camera_min: Vector2, camera_max: Vector2, margin: float = 0.0
+    ) -> npt.NDArray[np.int64]: ...
+    def raycast(
+        self,
+        origin: Vector2,
+        direction: Vector2,
+        max_distance: Optional[float] = None,
+        limit: Optional[int] = None,
+        first_only: bool = False,
+        supercover: bool = False,
+    ) -> List[Tuple[int, int, float]]: ...
+    def raycast_batch(
+        self,
+        origins: npt.NDArray[np.floating],
+        directions: npt.NDArray[np.floating],
+        max_distances: Union[float, npt.NDArray[np.floating], None] = None,
+        num_threads: Optional[int] = None,
+    ) -> Tuple[
+        npt.NDArray[np.float32], npt.NDArray[np.int64], npt.NDArray[np.int64]
+    ]: ...
+    def ray_cells(
+        self,
+        origin: Vector2,
+        direction: Vector2,
+        max_distance: Optional[float] = None,
+        supercover: bool = False,
+    ) -> List[Tuple[int, int]]: ...
+    def query_circle_bodies(
+        self, center: Vector2, radius: float
+    ) -> List[Tuple[int, int, float]]: ...
+    def query_circle_sorted(
+        self, center: Vector2, radius: float, limit: Optional[int] = None
+    ) -> List[Tuple[int, int, float]]: ...
+    def nearest(
+        self,
+        position: Vector2,
+        k: int = 1,
+        max_distance: Optional[float] = None,
+        metric: Metric = "euclidean",
+        kind: Optional[Kind] = None,
+        mask: Optional[List[Kind]] = None,
+    ) -> List[Tuple[int, int, float]]: ...
+    def distance_matrix(
+        self,
+        entities_a: List[int],
+        entities_b: List[int],
+        max_distance: Optional[float] = None,
+    ) -> npt.NDArray[np.float32]: ...
+    def density_map(
+        self, kind: Optional[Kind] = None
+    ) -> npt.NDArray[np.uint32]: ...
+    def observe(
+        self,
+        entity_indices: List[int],
+        num_nearest: int,
+        num_rays: int,
+        max_distance: Optional[float] = None,
+        num_threads: Optional[int] = None,
+    ) -> npt.NDArray[np.float32]: ...
+    def light_mask(
+        self, origin: Vector2, radius: float, resolution: Optional[float] = None
+    ) -> npt.NDArray[np.bool_]: ...
+    def positions(self, include_static: bool = False) -> Positions: ...
+    def cell_contents(self, cell_x: int, cell_y: int) -> List[Tuple[int, int]]: ...
+    def cell_of(self, position: Vector2) -> Optional[Tuple[int, int]]: ...
+    def cell_neighbors(self, cell_x: int, cell_y: int) -> List[Tuple[int, int]]: ...
+    def world_to_cell(self, position: Vector2) -> Tuple[int, int]: ...
+    def cell_to_world(self, cell_x: int, cell_y: int) -> Tuple[float, float]: ...
+    def cell_rect(
+        self, cell_x: int, cell_y: int
+    ) -> Tuple[float, float, float, float]: ...
+    def grid_dimensions(self) -> Tuple[int, int, float, float]: ...
+
+class World:
+    def __init__(self):
+        self.layers: dict[str, GridPhysics]
+
+    def add_layer(
+        self, name: str, size: float, cell_size: float
+    ) -> GridPhysics: ...
+    def layer(self, name: str) -> GridPhysics: ...
+    def to_json(self, pretty: bool = True) -> str: ...
+    @staticmethod
+    def from_json(json: str) -> "World": ...
+    def layer_names(self) -> List[str]: ...
+    def reset(self) -> None: ...
+    def add_static_circle(
+        self,
+        layer: str,
+        entity_index: int,
+        pos: Vector2,
+        radius: float,
+        kind: Kind = 0,
+    ) -> None: ...
+    def add_static_circles(
+        self,
+        layer: str,
+        entity_index: int,
+        bodies: List[Vector2],
+        radius: float,
+        kind: Kind = 0,
+    ) -> None: ...
+    def add_dynamic_circle(
+        self,
+        layer: str,
+        entity_index: int,
+        pos: Vector2,
+        radius: float,
+        kind: Kind = 0,
+    ) -> None: ...
+    def add_dynamic_circles(
+        self,
+        layer: str,
+        entity_index: int,
+        bodies: List[Vector2],
+        radius: float,
+        kind: Kind = 0,
+    ) -> None: ...
+    def get_collisions(
+        self, with_overlap: bool = False, num_threads: Optional[int] = None
+    ) -> dict[str, Set[Collision]]: ...
 
+def set_num_threads(num_threads: int) -> None: ...
+def get_num_threads() -> int: ...
+def benchmark(
+    num_bodies: int, world_size: float, cell_size: float, frames: int = 60
+) -> BenchmarkReport: ...
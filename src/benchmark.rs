@@ -0,0 +1,151 @@
+//! A fixed workload timing the main operations of a world, so configurations can be
+//! compared and performance regressions reported reproducibly.
+
+use crate::{targets::Targets, Containment, GridPhysics, Layout, Vector2};
+use pyo3::prelude::*;
+use std::time::Instant;
+
+/// Area queries and rays cast per frame.
+const QUERIES_PER_FRAME: usize = 64;
+
+/// Seconds spent in each phase of `benchmark`, summed over its frames.
+#[pyclass(module = "radyx", get_all)]
+#[derive(Clone, Debug, Default)]
+pub struct BenchmarkReport {
+    /// Adding every body.
+    pub insert: f64,
+    /// Moving every body once per frame.
+    pub moves: f64,
+    /// Whole-world collision detection, once per frame.
+    pub collisions: f64,
+    /// Euclidean area queries of two cells radius.
+    pub area_queries: f64,
+    /// Rays across the world, each stopping at its first hit.
+    pub raycasts: f64,
+    pub total: f64,
+    /// Collisions found over all frames, the same for every run of one configuration.
+    pub collisions_found: usize,
+}
+
+/// Xorshift generator, so every run places and moves the bodies the same way.
+pub(crate) struct Rng(pub u64);
+
+impl Rng {
+    /// A number in `[0, 1)`.
+    pub(crate) fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub(crate) fn point(&mut self, size: f32) -> Vector2 {
+        Vector2::new(self.next() * size, self.next() * size)
+    }
+}
+
+fn seconds_since(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64()
+}
+
+fn run(num_bodies: usize, world_size: f32, cell_size: f32, frames: usize) -> BenchmarkReport {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    let mut report = BenchmarkReport::default();
+    let radius = cell_size / 4.0;
+    let speed = cell_size / 8.0;
+
+    let start = Instant::now();
+    let mut grid = GridPhysics::new(world_size, cell_size);
+    let mut bodies: Vec<(Vector2, Vector2)> = Vec::with_capacity(num_bodies);
+    for entity_index in 0..num_bodies {
+        let pos = rng.point(world_size);
+        let velocity = Vector2::new((rng.next() - 0.5) * speed, (rng.next() - 0.5) * speed);
+        grid.add_dynamic_circle(entity_index, pos, radius, 0);
+        bodies.push((pos, velocity));
+    }
+    report.insert = seconds_since(start);
+
+    for _ in 0..frames {
+        let start = Instant::now();
+        for (entity_index, (pos, velocity)) in bodies.iter_mut().enumerate() {
+            for (value, step) in [(&mut pos.x, &mut velocity.x), (&mut pos.y, &mut velocity.y)] {
+                if !(0.0..=world_size).contains(&(*value + *step)) {
+                    *step = -*step;
+                }
+                *value += *step;
+            }
+            grid.update_position(entity_index, 0, *pos)
+                .expect("benchmark bodies exist");
+        }
+        report.moves += seconds_since(start);
+
+        let start = Instant::now();
+        report.collisions_found += grid.get_collisions(false, None, false, Targets::All).len();
+        report.collisions += seconds_since(start);
+
+        let centers: Vec<Vector2> = (0..QUERIES_PER_FRAME)
+            .map(|_| rng.point(world_size))
+            .collect();
+        let start = Instant::now();
+        for center in &centers {
+            grid.get_collisions_within_area(
+                *center,
+                2.0 * cell_size,
+                Some(crate::Metric::Euclidean),
+                None,
+                false,
+                Containment::Intersects,
+            );
+        }
+        report.area_queries += seconds_since(start);
+
+        let rays: Vec<(Vector2, Vector2)> = (0..QUERIES_PER_FRAME)
+            .map(|_| {
+                let angle = rng.next() * std::f32::consts::TAU;
+                (
+                    rng.point(world_size),
+                    Vector2::new(angle.cos(), angle.sin()),
+                )
+            })
+            .collect();
+        let start = Instant::now();
+        for (origin, direction) in rays {
+            grid.raycast(origin, direction, Some(world_size), None, true, false);
+        }
+        report.raycasts += seconds_since(start);
+    }
+    report.total =
+        report.insert + report.moves + report.collisions + report.area_queries + report.raycasts;
+    report
+}
+
+/// Runs a fixed workload in Rust and returns how long each phase took: `num_bodies` dynamic
+/// circles of a quarter cell radius spread over the world, then each frame every body
+/// moves, collisions are detected and 64 area queries and 64 rays are run. The GIL is
+/// released while it runs.
+#[pyfunction]
+#[pyo3(signature = (num_bodies, world_size, cell_size, frames=60))]
+pub fn benchmark(
+    py: Python,
+    num_bodies: usize,
+    world_size: f32,
+    cell_size: f32,
+    frames: usize,
+) -> PyResult<BenchmarkReport> {
+    GridPhysics::check_dimensions(world_size, cell_size, Layout::Square)?;
+    Ok(py.allow_threads(|| run(num_bodies, world_size, cell_size, frames)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_benchmark_repeats() {
+        let first = run(200, 100.0, 10.0, 3);
+        let second = run(200, 100.0, 10.0, 3);
+        assert!(first.collisions_found > 0);
+        assert_eq!(first.collisions_found, second.collisions_found);
+        assert!(first.total >= first.collisions);
+    }
+}
@@ -0,0 +1,149 @@
+//! Batch insertion of many bodies at once, for loading large maps from numpy arrays.
+
+use crate::{kinds::KindArg, threads, Body, GridPhysics, Placed, Vector2};
+use numpy::{
+    ndarray::{Array1, Array2, ArrayView1, ArrayView2},
+    PyReadonlyArray1, PyReadonlyArray2,
+};
+use pyo3::{exceptions::PyValueError, prelude::*};
+use std::{collections::HashMap, sync::Arc};
+use threads::prelude::*;
+
+/// A float32 or float64 `(n, 2)` array from numpy.
+#[derive(FromPyObject)]
+pub enum Points<'py> {
+    F32(PyReadonlyArray2<'py, f32>),
+    F64(PyReadonlyArray2<'py, f64>),
+}
+
+impl Points<'_> {
+    pub fn to_f32(&self) -> Array2<f32> {
+        match self {
+            Points::F32(array) => array.as_array().to_owned(),
+            Points::F64(array) => array.as_array().mapv(|value| value as f32),
+        }
+    }
+}
+
+/// A float32 or float64 `(n,)` array from numpy.
+#[derive(FromPyObject)]
+pub enum Values<'py> {
+    F32(PyReadonlyArray1<'py, f32>),
+    F64(PyReadonlyArray1<'py, f64>),
+}
+
+impl Values<'_> {
+    pub fn to_f32(&self) -> Array1<f32> {
+        match self {
+            Values::F32(array) => array.as_array().to_owned(),
+            Values::F64(array) => array.as_array().mapv(|value| value as f32),
+        }
+    }
+}
+
+impl GridPhysics {
+    /// The numpy circle adds once their arrays are converted, binning without the GIL.
+    pub(crate) fn add_circles_array(
+        &mut self,
+        py: Python,
+        entity_index: usize,
+        positions: Array2<f32>,
+        radii: Array1<f32>,
+        is_static: bool,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        self.ensure_room(radii.len())?;
+        let kind = self.kinds.intern(kind);
+        self.check_body_indices(entity_index, radii.len(), kind)?;
+        py.allow_threads(|| {
+            self.add_circles_batch(
+                entity_index,
+                positions.view(),
+                radii.view(),
+                is_static,
+                kind,
+            )
+        })?;
+        self.report_skipped()
+    }
+
+    /// Adds circles at the rows of `positions` with the matching `radii`, indexed in row
+    /// order.
+    pub(crate) fn add_circles_batch(
+        &mut self,
+        entity_index: usize,
+        positions: ArrayView2<f32>,
+        radii: ArrayView1<f32>,
+        is_static: bool,
+        kind: usize,
+    ) -> PyResult<()> {
+        if positions.ncols() != 2 || positions.nrows() != radii.len() {
+            return Err(PyValueError::new_err(format!(
+                "expected (n, 2) positions and (n,) radii, got {:?} and {:?}",
+                positions.shape(),
+                radii.shape()
+            )));
+        }
+        let bodies = positions
+            .rows()
+            .into_iter()
+            .zip(radii)
+            .enumerate()
+            .map(|(i, (row, radius))| {
+                let pos = Vector2::new(row[0], row[1]);
+                Body::new(entity_index, i, pos, *radius, is_static, kind)
+            })
+            .collect();
+        self.add_bodies(bodies)
+    }
+
+    /// Adds bodies like `add_body`. Bodies are bucketed by cell in parallel, then each cell
+    /// and entity list grows once. Cells end up in the same order as inserting one by one.
+    pub(crate) fn add_bodies(&mut self, bodies: Vec<Body>) -> PyResult<()> {
+        if self.deferred {
+            for body in bodies {
+                self.add_body(body);
+            }
+            return Ok(());
+        }
+        let mut bodies: Vec<Placed> = bodies
+            .into_iter()
+            .map(|body| self.localized(Placed::new(body)))
+            .collect();
+        bodies.retain(|body| !self.screen(body));
+        let binned: Vec<(usize, Body)> = threads::install(None, || {
+            let mut binned: Vec<(usize, Body)> = bodies
+                .par_iter()
+                .flat_map_iter(|body| self.binned_copies(body))
+                .collect();
+            // Stable, so bodies keep their insertion order within a cell
+            binned.par_sort_by_key(|(index, _)| *index);
+            binned
+        })?;
+        for run in binned.chunk_by(|a, b| a.0 == b.0) {
+            let cell = Arc::make_mut(&mut self.grid[run[0].0]);
+            cell.extend(run.iter().map(|(_, body)| *body));
+            crate::sort_cell(cell);
+        }
+
+        let mut entity_counts: HashMap<(usize, bool), usize> = HashMap::new();
+        for body in &bodies {
+            *entity_counts
+                .entry((body.entity_index(), body.is_static()))
+                .or_default() += 1;
+        }
+        for ((entity_index, is_static), count) in entity_counts {
+            let bodies = if is_static {
+                &mut self.static_bodies
+            } else {
+                &mut self.dynamic_bodies
+            };
+            bodies.entry(entity_index).or_default().reserve(count);
+        }
+
+        for body in bodies {
+            self.register_body(body);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+//! Python handlers called with the contacts of each step, filtered by the kinds of the two
+//! bodies so only the pairs a handler cares about cross into Python.
+
+use crate::{events::EventKind, Event, GridPhysics};
+use pyo3::prelude::*;
+use std::collections::HashSet;
+
+#[derive(Clone)]
+pub struct CollisionHandler {
+    pub id: usize,
+    handler: PyObject,
+    /// Kinds of the first and second body, `None` matching any.
+    kinds: (Option<usize>, Option<usize>),
+}
+
+impl CollisionHandler {
+    pub fn new(id: usize, handler: PyObject, kinds: (Option<usize>, Option<usize>)) -> Self {
+        Self { id, handler, kinds }
+    }
+
+    fn admits(&self, kinds: (usize, usize)) -> bool {
+        self.kinds.0.is_none_or(|kind| kind == kinds.0)
+            && self.kinds.1.is_none_or(|kind| kind == kinds.1)
+    }
+
+    /// The event with its `self` side of the first kind, if the pair matches either way
+    /// round.
+    fn matching(&self, event: &Event, kinds: (usize, usize)) -> Option<Event> {
+        if self.admits(kinds) {
+            return Some(event.clone());
+        }
+        if !self.admits((kinds.1, kinds.0)) {
+            return None;
+        }
+        let mut swapped = event.clone();
+        swapped.self_entity_index = event.other_entity_index?;
+        swapped.self_body_index = event.other_body_index?;
+        swapped.other_entity_index = Some(event.self_entity_index);
+        swapped.other_body_index = Some(event.self_body_index);
+        Some(swapped)
+    }
+}
+
+impl GridPhysics {
+    /// Handler calls for the `Collision` and `Sensor` events queued from `start` on, in
+    /// event order. A pair reported from both sides is only passed to each handler once per
+    /// tick.
+    pub(crate) fn collision_calls(&self, start: usize) -> Vec<(PyObject, Event)> {
+        if self.collision_handlers.is_empty() {
+            return Vec::new();
+        }
+        let kind_of = |entity_index, body_index| {
+            self.find_body(entity_index, body_index)
+                .ok()
+                .map(|body| body.kind())
+        };
+        let mut calls = Vec::new();
+        let mut seen = HashSet::new();
+        for event in self.events.iter().skip(start) {
+            if !matches!(event.kind, EventKind::Collision | EventKind::Sensor) {
+                continue;
+            }
+            let (Some(other_entity), Some(other_body)) =
+                (event.other_entity_index, event.other_body_index)
+            else {
+                continue;
+            };
+            let (Some(own), Some(theirs)) = (
+                kind_of(event.self_entity_index, event.self_body_index),
+                kind_of(other_entity, other_body),
+            ) else {
+                continue;
+            };
+            let own_body = (event.self_entity_index, event.self_body_index);
+            let other_body = (other_entity, other_body);
+            let pair = if own_body <= other_body {
+                (own_body, other_body)
+            } else {
+                (other_body, own_body)
+            };
+            for handler in &self.collision_handlers {
+                let Some(matched) = handler.matching(event, (own, theirs)) else {
+                    continue;
+                };
+                if seen.insert((handler.id, event.tick, pair)) {
+                    calls.push((handler.handler.clone(), matched));
+                }
+            }
+        }
+        calls
+    }
+}
+
+/// Calls each handler with its event, stopping at the first one that raises.
+pub fn dispatch(py: Python, calls: Vec<(PyObject, Event)>) -> PyResult<()> {
+    for (handler, event) in calls {
+        handler.call1(py, (event,))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! Cell storage: the bodies binned into a cell, mirrored into columns of the few fields the
+//! narrow phase reads for every candidate, so it can reject most pairs without loading whole
+//! bodies.
+
+use crate::Body;
+use std::{cmp::Ordering, mem::size_of, ops::Deref};
+
+/// Bodies of a cell with their positions, bounding radii and entities as separate arrays,
+/// indexed like the bodies. Reading goes through the bodies; every change goes through the
+/// methods here, which keep the columns in step with the bodies they touch.
+#[derive(Clone, Default)]
+pub struct CellBodies {
+    bodies: Vec<Body>,
+    pub xs: Vec<f32>,
+    pub ys: Vec<f32>,
+    pub radii: Vec<f32>,
+    pub entities: Vec<u32>,
+}
+
+impl CellBodies {
+    pub fn push(&mut self, body: Body) {
+        self.xs.push(body.pos.x);
+        self.ys.push(body.pos.y);
+        self.radii.push(body.radius);
+        self.entities.push(body.entity_index);
+        self.bodies.push(body);
+    }
+
+    pub fn insert(&mut self, index: usize, body: Body) {
+        self.xs.insert(index, body.pos.x);
+        self.ys.insert(index, body.pos.y);
+        self.radii.insert(index, body.radius);
+        self.entities.insert(index, body.entity_index);
+        self.bodies.insert(index, body);
+    }
+
+    pub fn extend(&mut self, bodies: impl IntoIterator<Item = Body>) {
+        for body in bodies {
+            self.push(body);
+        }
+    }
+
+    /// Keeps the bodies `keep` accepts, in order, moving their columns along with them.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Body) -> bool) {
+        let mut kept = 0;
+        for index in 0..self.bodies.len() {
+            if !keep(&self.bodies[index]) {
+                continue;
+            }
+            if kept != index {
+                self.bodies[kept] = self.bodies[index];
+                self.xs[kept] = self.xs[index];
+                self.ys[kept] = self.ys[index];
+                self.radii[kept] = self.radii[index];
+                self.entities[kept] = self.entities[index];
+            }
+            kept += 1;
+        }
+        self.truncate(kept);
+    }
+
+    /// Passes every body to `change`, rewriting the columns of each from its new state.
+    pub fn update(&mut self, mut change: impl FnMut(&mut Body)) {
+        for (index, body) in self.bodies.iter_mut().enumerate() {
+            change(body);
+            self.xs[index] = body.pos.x;
+            self.ys[index] = body.pos.y;
+            self.radii[index] = body.radius;
+            self.entities[index] = body.entity_index;
+        }
+    }
+
+    /// Reorders the bodies, writing the columns out again in the new order.
+    pub fn sort_by(&mut self, compare: impl FnMut(&Body, &Body) -> Ordering) {
+        self.bodies.sort_by(compare);
+        for (index, body) in self.bodies.iter().enumerate() {
+            self.xs[index] = body.pos.x;
+            self.ys[index] = body.pos.y;
+            self.radii[index] = body.radius;
+            self.entities[index] = body.entity_index;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.bodies.reserve(additional);
+        for column in [&mut self.xs, &mut self.ys, &mut self.radii] {
+            column.reserve(additional);
+        }
+        self.entities.reserve(additional);
+    }
+
+    /// Heap bytes of the bodies and columns.
+    pub fn bytes(&self) -> usize {
+        self.bodies.capacity() * size_of::<Body>()
+            + (self.xs.capacity() + self.ys.capacity() + self.radii.capacity()) * size_of::<f32>()
+            + self.entities.capacity() * size_of::<u32>()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.bodies.truncate(len);
+        for column in [&mut self.xs, &mut self.ys, &mut self.radii] {
+            column.truncate(len);
+        }
+        self.entities.truncate(len);
+    }
+}
+
+impl Deref for CellBodies {
+    type Target = Vec<Body>;
+
+    fn deref(&self) -> &Vec<Body> {
+        &self.bodies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vector2;
+
+    fn columns(cell: &CellBodies) -> (Vec<f32>, Vec<f32>, Vec<f32>, Vec<u32>) {
+        (
+            cell.xs.clone(),
+            cell.ys.clone(),
+            cell.radii.clone(),
+            cell.entities.clone(),
+        )
+    }
+
+    #[test]
+    fn check_columns_follow_edits() {
+        let mut cell = CellBodies::default();
+        cell.extend([
+            Body::new(3, 0, Vector2::new(1.0, 2.0), 0.5, false, 0),
+            Body::new(4, 1, Vector2::new(3.0, 4.0), 1.5, false, 0),
+        ]);
+        cell.insert(1, Body::new(5, 0, Vector2::new(5.0, 6.0), 2.5, false, 0));
+        assert_eq!(cell.xs, vec![1.0, 5.0, 3.0]);
+        cell.retain(|body| body.entity_index() != 3);
+        assert_eq!(
+            columns(&cell),
+            (vec![5.0, 3.0], vec![6.0, 4.0], vec![2.5, 1.5], vec![5, 4])
+        );
+        cell.sort_by(|a, b| a.entity_index().cmp(&b.entity_index()));
+        cell.update(|body| body.radius *= 2.0);
+        assert_eq!(
+            columns(&cell),
+            (vec![3.0, 5.0], vec![4.0, 6.0], vec![3.0, 5.0], vec![4, 5])
+        );
+        cell.clear();
+        assert_eq!(columns(&cell), (vec![], vec![], vec![], vec![]));
+    }
+}
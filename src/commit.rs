@@ -0,0 +1,324 @@
+//! Deferred mutation: while a world is deferred, adds, moves and removals are queued and
+//! applied together by `commit`, so queries in between see the last committed state.
+
+use crate::{union_bounds, Body, GridPhysics, Placed, Transform, Vector2};
+use pyo3::PyResult;
+
+/// A queued change, replayed through the same method that queued it.
+#[derive(Clone)]
+pub enum Mutation {
+    /// A body placed in world space, as given to `add_placed`.
+    Add(Placed),
+    AddAnchored {
+        entity_index: usize,
+        offsets: Vec<Vector2>,
+        radius: f32,
+        anchor: Vector2,
+        is_static: bool,
+        kind: usize,
+    },
+    SetTransform(usize, Transform),
+    MoveEntity(usize, Vector2),
+    UpdatePosition(usize, usize, Vector2),
+    SetRadius(usize, usize, f32),
+    Scale(usize, f32),
+    /// A velocity as set directly or reached by an impulse.
+    SetVelocity(usize, Vector2),
+    Remove(usize),
+    /// Removal of a destroyed entity along with its settings.
+    Forget(usize),
+}
+
+/// An entity as the queue leaves it, so a mutation queued after others on the same entity
+/// marks the cells they moved it to rather than those it is committed in.
+#[derive(Clone)]
+pub struct Projection {
+    bodies: Vec<Placed>,
+    transform: Option<Transform>,
+    velocity: Vector2,
+}
+
+impl Mutation {
+    fn entity_index(&self) -> usize {
+        match self {
+            Mutation::Add(body) => body.entity_index(),
+            Mutation::AddAnchored { entity_index, .. }
+            | Mutation::SetTransform(entity_index, _)
+            | Mutation::MoveEntity(entity_index, _)
+            | Mutation::UpdatePosition(entity_index, ..)
+            | Mutation::SetRadius(entity_index, ..)
+            | Mutation::Scale(entity_index, _)
+            | Mutation::SetVelocity(entity_index, _)
+            | Mutation::Remove(entity_index)
+            | Mutation::Forget(entity_index) => *entity_index,
+        }
+    }
+}
+
+impl Projection {
+    /// Moves every body with the entity to `transform`, like `place_entity`.
+    fn place(&mut self, transform: Transform) {
+        if self.bodies.is_empty() {
+            return;
+        }
+        for body in &mut self.bodies {
+            *body = body.transformed(transform);
+        }
+        self.transform = Some(transform);
+    }
+
+    fn edit_body(&mut self, body_index: usize, edit: impl FnOnce(&mut Placed)) {
+        if let Some(body) = self
+            .bodies
+            .iter_mut()
+            .find(|body| body.body_index() == body_index)
+        {
+            edit(body);
+        }
+    }
+
+    /// The state `mutation` leaves the entity in, following what replaying it does.
+    fn apply(&mut self, mutation: &Mutation) {
+        let angle = self.transform.map_or(0.0, |transform| transform.angle);
+        match mutation {
+            Mutation::Add(placed) => {
+                let mut placed = *placed;
+                if let Some(transform) = self.transform {
+                    placed.offset = transform.local(placed.pos);
+                    placed.turn = placed.angle - transform.angle;
+                }
+                self.bodies.push(placed);
+            }
+            Mutation::AddAnchored {
+                entity_index,
+                offsets,
+                radius,
+                anchor,
+                is_static,
+                kind,
+            } => {
+                if !anchor.x.is_finite() || !anchor.y.is_finite() {
+                    return;
+                }
+                let transform = Transform {
+                    position: *anchor,
+                    angle,
+                };
+                for body in &mut self.bodies {
+                    body.offset = transform.local(body.pos);
+                }
+                self.transform = Some(transform);
+                for (i, offset) in offsets.iter().enumerate() {
+                    let pos = transform.apply(*offset);
+                    let body = Body::new(*entity_index, i, pos, *radius, *is_static, *kind);
+                    self.bodies.push(Placed {
+                        offset: *offset,
+                        ..Placed::new(body)
+                    });
+                }
+            }
+            Mutation::SetTransform(_, transform) => self.place(*transform),
+            Mutation::MoveEntity(_, anchor) => self.place(Transform {
+                position: *anchor,
+                angle,
+            }),
+            Mutation::UpdatePosition(_, body_index, pos) => {
+                let transform = self.transform;
+                self.edit_body(*body_index, |body| {
+                    body.body.pos = *pos;
+                    body.offset = transform.map_or(*pos, |transform| transform.local(*pos));
+                });
+            }
+            Mutation::SetRadius(_, body_index, radius) => {
+                self.edit_body(*body_index, |body| *body = body.resized(*radius));
+            }
+            Mutation::Scale(_, factor) => {
+                let transform = self.transform;
+                for body in &mut self.bodies {
+                    *body = body.scaled(*factor, transform);
+                }
+            }
+            Mutation::SetVelocity(_, velocity) => self.velocity = *velocity,
+            Mutation::Remove(_) => {
+                self.bodies.clear();
+                self.transform = None;
+            }
+            Mutation::Forget(_) => {
+                self.bodies.clear();
+                self.transform = None;
+                self.velocity = Vector2::new(0.0, 0.0);
+            }
+        }
+    }
+}
+
+impl GridPhysics {
+    /// Queues a mutation, marking the cells of its entity before and after it.
+    pub(crate) fn defer(&mut self, mutation: Mutation) {
+        let entity_index = mutation.entity_index();
+        let before = self.projection(entity_index);
+        let mut after = before.clone();
+        after.apply(&mutation);
+        let mut cells = self.projected_cells(&before);
+        cells.extend(self.projected_cells(&after));
+        self.dirty.extend(cells);
+        self.projected.insert(entity_index, after);
+        self.pending.push(mutation);
+    }
+
+    /// An entity as the queued mutations leave it.
+    fn projection(&self, entity_index: usize) -> Projection {
+        if let Some(projection) = self.projected.get(&entity_index) {
+            return projection.clone();
+        }
+        let (x, y) = self.get_velocity(entity_index);
+        Projection {
+            bodies: self.placed_of(entity_index).copied().collect(),
+            transform: self.transforms.get(&entity_index).copied(),
+            velocity: Vector2::new(x, y),
+        }
+    }
+
+    /// Cells the bodies of a projected entity are binned into, swept by its velocity.
+    fn projected_cells(&self, projection: &Projection) -> Vec<usize> {
+        let (dx, dy) = (
+            projection.velocity.x * self.sweep_dt,
+            projection.velocity.y * self.sweep_dt,
+        );
+        projection
+            .bodies
+            .iter()
+            .flat_map(|body| {
+                let bounds = body.get_bounds();
+                let (min_x, max_x, min_y, max_y) = bounds;
+                let swept = if body.is_static() {
+                    bounds
+                } else {
+                    union_bounds(bounds, (min_x + dx, max_x + dx, min_y + dy, max_y + dy))
+                };
+                self.cell_span(swept).indices()
+            })
+            .collect()
+    }
+
+    /// A body as the queue leaves it, or as committed when the queue removes it, failing
+    /// like `find_body` when it has neither. Mutations of bodies removed by then fail at
+    /// `commit` instead.
+    pub(crate) fn pending_body(&self, entity_index: usize, body_index: usize) -> PyResult<Body> {
+        let projected = self.projected.get(&entity_index).and_then(|projection| {
+            projection
+                .bodies
+                .iter()
+                .find(|body| body.body_index() == body_index)
+        });
+        match projected {
+            Some(body) => Ok(body.body),
+            None => self.find_body(entity_index, body_index),
+        }
+    }
+
+    /// Whether an entity has bodies now or once the queue is applied.
+    pub(crate) fn pending_entity(&self, entity_index: usize) -> bool {
+        self.entity_bounds.contains_key(&entity_index)
+            || self
+                .projected
+                .get(&entity_index)
+                .is_some_and(|projection| !projection.bodies.is_empty())
+    }
+
+    /// The velocity of an entity once the queue is applied, which impulses add to.
+    pub(crate) fn pending_velocity(&self, entity_index: usize) -> Vector2 {
+        match self.projected.get(&entity_index) {
+            Some(projection) => projection.velocity,
+            None => {
+                let (x, y) = self.get_velocity(entity_index);
+                Vector2::new(x, y)
+            }
+        }
+    }
+
+    /// Bodies queued for adding, counted against `max_bodies`.
+    pub(crate) fn pending_bodies(&self) -> usize {
+        self.pending
+            .iter()
+            .map(|mutation| match mutation {
+                Mutation::Add(_) => 1,
+                Mutation::AddAnchored { offsets, .. } => offsets.len(),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Applies the queued mutations in order, returning how many were applied. A mutation
+    /// that fails, such as a move of an entity that doesn't exist by then, is skipped and
+    /// the first such error is returned once the others are applied.
+    pub(crate) fn apply_pending(&mut self) -> PyResult<usize> {
+        let pending = std::mem::take(&mut self.pending);
+        self.dirty.clear();
+        self.projected.clear();
+        let deferred = std::mem::replace(&mut self.deferred, false);
+        let mut first_error = None;
+        let mut applied = 0;
+        for mutation in pending {
+            let result = match mutation {
+                Mutation::Add(placed) => {
+                    self.add_placed(placed);
+                    Ok(())
+                }
+                Mutation::AddAnchored {
+                    entity_index,
+                    offsets,
+                    radius,
+                    anchor,
+                    is_static,
+                    kind,
+                } => {
+                    self.add_anchored_circles(
+                        entity_index,
+                        offsets,
+                        radius,
+                        anchor,
+                        is_static,
+                        kind,
+                    );
+                    Ok(())
+                }
+                Mutation::SetTransform(entity_index, transform) => {
+                    self.place_entity(entity_index, transform)
+                }
+                Mutation::MoveEntity(entity_index, anchor) => {
+                    self.move_entity(entity_index, anchor)
+                }
+                Mutation::UpdatePosition(entity_index, body_index, pos) => {
+                    self.update_position(entity_index, body_index, pos)
+                }
+                Mutation::SetRadius(entity_index, body_index, radius) => {
+                    self.resize_body(entity_index, body_index, radius)
+                }
+                Mutation::Scale(entity_index, factor) => self.scale_bodies(entity_index, factor),
+                Mutation::SetVelocity(entity_index, velocity) => {
+                    self.set_velocity(entity_index, velocity)
+                }
+                Mutation::Remove(entity_index) => {
+                    self.remove_entity(entity_index);
+                    Ok(())
+                }
+                Mutation::Forget(entity_index) => {
+                    self.forget_entity(entity_index);
+                    Ok(())
+                }
+            };
+            match result {
+                Ok(()) => applied += 1,
+                Err(err) => {
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+        self.deferred = deferred;
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(applied),
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Reassigning dense entity indices after many removals.
+
+use crate::{cooldown, events::ContactKey, layers::StaticLayer, namespaces, Event, GridPhysics};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+fn remap_key(map: &HashMap<usize, usize>, key: &ContactKey) -> Option<ContactKey> {
+    Some((*map.get(&key.0)?, *map.get(&key.1)?, key.2, key.3))
+}
+
+fn remap_set(map: &HashMap<usize, usize>, entities: &HashSet<usize>) -> HashSet<usize> {
+    entities
+        .iter()
+        .filter_map(|entity| map.get(entity).copied())
+        .collect()
+}
+
+fn remap_keys<V>(map: &HashMap<usize, usize>, values: &mut HashMap<usize, V>) {
+    *values = values
+        .drain()
+        .filter_map(|(entity, value)| Some((*map.get(&entity)?, value)))
+        .collect();
+}
+
+fn remap_event(map: &HashMap<usize, usize>, mut event: Event) -> Option<Event> {
+    event.self_entity_index = *map.get(&event.self_entity_index)?;
+    if let Some(other) = event.other_entity_index {
+        event.other_entity_index = Some(*map.get(&other)?);
+    }
+    Some(event)
+}
+
+impl GridPhysics {
+    /// Renumbers the entities with bodies as `0..n` in index order within each namespace,
+    /// returning the old to new mapping. The order of entities is kept, so contact keys stay
+    /// normalized. Entities of stored static layers are renumbered with them, so swapping a
+    /// layer in later can't bring back an index given to another entity.
+    pub(crate) fn compact_entities(&mut self) -> HashMap<usize, usize> {
+        let layers = self.static_layers.values().flat_map(StaticLayer::entities);
+        let mut entities: Vec<usize> = self
+            .dynamic_bodies
+            .keys()
+            .chain(self.static_bodies.keys())
+            .copied()
+            .chain(layers)
+            .collect::<HashSet<usize>>()
+            .into_iter()
+            .collect();
+        entities.sort_unstable();
+        let mut next: HashMap<usize, usize> = HashMap::new();
+        let map: HashMap<usize, usize> = entities
+            .into_iter()
+            .map(|old| {
+                let namespace = namespaces::split(old).0;
+                let entity = next.entry(namespace).or_default();
+                *entity += 1;
+                (old, namespaces::join(namespace, *entity - 1))
+            })
+            .collect();
+
+        for cell in self.grid.iter_mut().filter(|cell| !cell.is_empty()) {
+            Arc::make_mut(cell).update(|body| {
+                body.entity_index = map[&body.entity_index()] as u32;
+            });
+        }
+        for layer in self.static_layers.values_mut() {
+            layer.remap(&map);
+        }
+        for bodies in [&mut self.dynamic_bodies, &mut self.static_bodies] {
+            remap_keys(&map, bodies);
+            for body in bodies.values_mut().flatten() {
+                body.body.entity_index = map[&body.entity_index()] as u32;
+            }
+        }
+        remap_keys(&map, &mut self.entity_bounds);
+        remap_keys(&map, &mut self.transforms);
+        remap_keys(&map, &mut self.velocities);
+        remap_keys(&map, &mut self.max_displacements);
+        remap_keys(&map, &mut self.drags);
+        remap_keys(&map, &mut self.forces);
+        remap_keys(&map, &mut self.masses);
+        self.frictions = self
+            .frictions
+            .drain()
+            .filter_map(|((entity, body), friction)| Some(((*map.get(&entity)?, body), friction)))
+            .collect();
+
+        self.sensors = remap_set(&map, &self.sensors);
+        self.frozen = remap_set(&map, &self.frozen);
+        self.asleep = remap_set(&map, &self.asleep);
+        remap_keys(&map, &mut self.stillness);
+        for pairs in [&mut self.pair_cooldowns, &mut self.cooldown_starts] {
+            *pairs = pairs
+                .drain()
+                .filter_map(|((a, b), ticks)| {
+                    Some((cooldown::entity_pair(*map.get(&a)?, *map.get(&b)?), ticks))
+                })
+                .collect();
+        }
+        for members in self.groups.values_mut() {
+            *members = remap_set(&map, members);
+        }
+        for subscription in self.subscriptions.values_mut() {
+            subscription.remap(&map);
+        }
+
+        let remap_contacts = |keys: &HashSet<ContactKey>| -> HashSet<ContactKey> {
+            keys.iter().filter_map(|key| remap_key(&map, key)).collect()
+        };
+        self.contacts = remap_contacts(&self.contacts);
+        self.contact_ids = self
+            .contact_ids
+            .drain()
+            .filter_map(|(key, id)| Some((remap_key(&map, &key)?, id)))
+            .collect();
+        self.static_exclusions = remap_contacts(&self.static_exclusions);
+        self.collision_scratch.clear();
+        self.history.remap(|key| remap_key(&map, key));
+        self.outside = self
+            .outside
+            .iter()
+            .filter_map(|(entity, body)| Some((*map.get(entity)?, *body)))
+            .collect();
+        self.events = std::mem::take(&mut self.events)
+            .into_iter()
+            .filter_map(|event| remap_event(&map, event))
+            .collect();
+        self.entity_ids.remap(&map);
+        // Saved bodies may belong to entities that no longer have an index
+        self.state_slots.clear();
+
+        // Mirrors drop the old indices and receive the renumbered entities
+        let tick = self.tick;
+        let renamed: Vec<usize> = self.changed.keys().chain(map.keys()).copied().collect();
+        for entity in renamed.into_iter().chain(map.values().copied()) {
+            self.changed.insert(entity, tick);
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{targets::Targets, Vector2};
+
+    #[test]
+    fn check_compact() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(3, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.add_dynamic_circle(5, Vector2::new(50.0, 50.0), 1.0, 0);
+        grid.add_static_circle(7, Vector2::new(16.0, 15.0), 1.0, 0);
+        grid.add_dynamic_circle(10, Vector2::new(80.0, 80.0), 1.0, 0);
+        grid.set_sensor(10, true);
+        grid.step(1.0 / 60.0);
+        grid.remove_entity(5);
+
+        let map = grid.compact();
+        assert_eq!(map, HashMap::from([(3, 0), (7, 1), (10, 2)]));
+        let mut entities: Vec<usize> = grid.dynamic_bodies.keys().copied().collect();
+        entities.sort_unstable();
+        assert_eq!(entities, vec![0, 2]);
+        assert!(grid.static_bodies[&1]
+            .iter()
+            .all(|body| body.entity_index() == 1));
+        assert!(grid.sensors.contains(&2));
+
+        let collisions = grid.get_collisions(false, None, false, Targets::All);
+        let pairs: Vec<(usize, usize)> = collisions
+            .iter()
+            .map(|collision| (collision.self_entity_index, collision.other_entity_index))
+            .collect();
+        assert_eq!(pairs, vec![(0, 1)]);
+        let events = grid.drain_events();
+        assert!(!events.is_empty());
+        assert!(events.iter().all(|event| event.self_entity_index == 0));
+
+        // Compacting dense indices changes nothing
+        let again = grid.compact();
+        assert!(again.iter().all(|(old, new)| old == new));
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{Body, GridPhysics, Vector2};
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+/// What an area query asks of the bodies it returns, passed from Python by name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Containment {
+    /// Bodies touching the area.
+    #[default]
+    Intersects,
+    /// Bodies lying entirely inside the area.
+    Contains,
+}
+
+impl<'source> FromPyObject<'source> for Containment {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract::<&str>()? {
+            "intersects" => Ok(Containment::Intersects),
+            "contains" => Ok(Containment::Contains),
+            name => Err(PyValueError::new_err(format!(
+                "unknown containment {name:?}, expected \"intersects\" or \"contains\""
+            ))),
+        }
+    }
+}
+
+impl Body {
+    /// Whether the body lies entirely inside the circle. Ellipses are tested by their
+    /// bounding circle.
+    pub(crate) fn within_circle(&self, center: Vector2, radius: f32) -> bool {
+        Body::new(0, 0, center, radius, true, 0).contains(self)
+    }
+
+    /// Whether the body lies entirely inside the axis-aligned box from `min` to `max`.
+    pub(crate) fn within_box(&self, min: Vector2, max: Vector2) -> bool {
+        let center = Vector2::new((min.x + max.x) / 2.0, (min.y + max.y) / 2.0);
+        let (half_x, half_y) = ((max.x - min.x) / 2.0, (max.y - min.y) / 2.0);
+        Body::new(0, 0, center, 0.0, true, 0)
+            .oriented_box(half_x, half_y, 0.0)
+            .contains(self)
+    }
+}
+
+impl GridPhysics {
+    /// Whether every body of an entity passes `inside`.
+    pub(crate) fn entity_within(
+        &self,
+        entity_index: usize,
+        inside: impl Fn(&Body) -> bool,
+    ) -> bool {
+        self.bodies_of(entity_index).all(inside)
+    }
+}
@@ -0,0 +1,49 @@
+//! Pair cooldowns, holding a pair of entities back from collision results for some ticks
+//! after a collision between them was reported, like invincibility frames.
+
+use crate::{events::ContactKey, GridPhysics};
+
+/// Entity pair in a fixed order, so both sides of a collision share one cooldown.
+pub fn entity_pair(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+impl GridPhysics {
+    /// Whether collisions between two entities are held back by a running cooldown.
+    pub(crate) fn cooling_down(&self, a: usize, b: usize) -> bool {
+        if self.cooldown_starts.is_empty() {
+            return false;
+        }
+        let pair = entity_pair(a, b);
+        match (
+            self.cooldown_starts.get(&pair),
+            self.pair_cooldowns.get(&pair),
+        ) {
+            (Some(start), Some(ticks)) => {
+                *start < self.tick && self.tick <= start.saturating_add(*ticks)
+            }
+            _ => false,
+        }
+    }
+
+    /// Starts the cooldowns of pairs among `contacts`, the collisions reported this tick,
+    /// and forgets those that ran out.
+    pub(crate) fn start_cooldowns(&mut self, contacts: &[ContactKey]) {
+        if self.pair_cooldowns.is_empty() {
+            return;
+        }
+        let tick = self.tick;
+        let cooldowns = &self.pair_cooldowns;
+        self.cooldown_starts.retain(|pair, start| {
+            cooldowns
+                .get(pair)
+                .is_some_and(|ticks| tick <= start.saturating_add(*ticks))
+        });
+        for key in contacts {
+            let pair = entity_pair(key.0, key.1);
+            if self.pair_cooldowns.contains_key(&pair) && !self.cooling_down(key.0, key.1) {
+                self.cooldown_starts.insert(pair, tick);
+            }
+        }
+    }
+}
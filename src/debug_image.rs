@@ -0,0 +1,294 @@
+//! Pictures of a world for bug reports, as SVG or as PNG rasterized without dependencies.
+//!
+//! World coordinates map to pixels times `scale` with y pointing down. Cell lines are drawn
+//! for square layouts; static bodies are grey, dynamic bodies blue, and bodies in a contact
+//! of the last step red.
+
+use crate::{shape::Shape, Body, GridPhysics, Layout, Vector2};
+use pyo3::{exceptions::PyValueError, PyResult};
+use std::{collections::HashSet, fmt::Write};
+
+const BACKGROUND: [u8; 3] = [255, 255, 255];
+const CELL_LINE: [u8; 3] = [220, 220, 220];
+const STATIC: [u8; 3] = [128, 128, 128];
+const DYNAMIC: [u8; 3] = [60, 110, 200];
+const COLLIDING: [u8; 3] = [220, 50, 50];
+
+/// Largest width or height of a PNG, to keep a mistyped scale from filling the disk.
+const MAX_PIXELS: usize = 16384;
+
+/// Bodies in the order they are drawn, each with its color.
+fn layers(grid: &GridPhysics) -> Vec<(&Body, [u8; 3])> {
+    let colliding: HashSet<(usize, usize)> = grid
+        .contacts
+        .iter()
+        .flat_map(|key| [(key.0, key.2), (key.1, key.3)])
+        .collect();
+    let mut bodies: Vec<&Body> = grid
+        .static_bodies
+        .values()
+        .chain(grid.dynamic_bodies.values())
+        .flatten()
+        .map(|placed| &placed.body)
+        .collect();
+    bodies.sort_unstable_by_key(|body| (!body.is_static(), body.entity_index(), body.body_index()));
+    let color = |body: &Body| {
+        if colliding.contains(&(body.entity_index(), body.body_index())) {
+            COLLIDING
+        } else if body.is_static() {
+            STATIC
+        } else {
+            DYNAMIC
+        }
+    };
+    let (colliding, others): (Vec<&Body>, Vec<&Body>) = bodies
+        .into_iter()
+        .partition(|body| color(body) == COLLIDING);
+    others
+        .into_iter()
+        .chain(colliding)
+        .map(|body| (body, color(body)))
+        .collect()
+}
+
+fn hex_color([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+pub fn svg(grid: &GridPhysics, scale: f32) -> String {
+    let side = grid.size * scale;
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{side}" height="{side}" viewBox="0 0 {side} {side}">"#
+    );
+    let _ = writeln!(
+        out,
+        r#"<rect width="{side}" height="{side}" fill="{}"/>"#,
+        hex_color(BACKGROUND)
+    );
+    if grid.layout == Layout::Square {
+        let mut path = String::new();
+        for line in 0..=grid.grid_size {
+            let at = line as f32 * grid.cell_size * scale;
+            let _ = write!(path, "M{at} 0V{side}M0 {at}H{side}");
+        }
+        let _ = writeln!(
+            out,
+            r#"<path d="{path}" stroke="{}" stroke-width="1" fill="none"/>"#,
+            hex_color(CELL_LINE)
+        );
+    }
+
+    for (body, color) in layers(grid) {
+        let color = hex_color(color);
+        let (x, y) = (body.pos.x * scale, body.pos.y * scale);
+        let degrees = body.angle.to_degrees();
+        let _ = match body.shape() {
+            Shape::Circle => writeln!(
+                out,
+                r#"<circle cx="{x}" cy="{y}" r="{}" fill="{color}"/>"#,
+                body.radius * scale
+            ),
+            Shape::Ellipse { radius_x, radius_y } => writeln!(
+                out,
+                r#"<ellipse cx="{x}" cy="{y}" rx="{}" ry="{}" transform="rotate({degrees} {x} {y})" fill="{color}"/>"#,
+                radius_x * scale,
+                radius_y * scale
+            ),
+            Shape::Box { half_x, half_y } => writeln!(
+                out,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" transform="rotate({degrees} {x} {y})" fill="{color}"/>"#,
+                x - half_x * scale,
+                y - half_y * scale,
+                2.0 * half_x * scale,
+                2.0 * half_y * scale
+            ),
+            Shape::Segment { half_length } => {
+                let (sin, cos) = body.angle.sin_cos();
+                let (dx, dy) = (cos * half_length * scale, sin * half_length * scale);
+                writeln!(
+                    out,
+                    r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{color}" stroke-width="1"/>"#,
+                    x - dx,
+                    y - dy,
+                    x + dx,
+                    y + dy
+                )
+            }
+            Shape::Triangle { .. } => {
+                let points = body
+                    .corners()
+                    .expect("triangle")
+                    .map(|corner| format!("{},{}", corner.x * scale, corner.y * scale))
+                    .join(" ");
+                writeln!(out, r#"<polygon points="{points}" fill="{color}"/>"#)
+            }
+        };
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Row-major RGB pixels of a `side` by `side` picture.
+pub struct Raster {
+    pub side: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+pub fn rasterize(grid: &GridPhysics, scale: f32) -> PyResult<Raster> {
+    let side = (grid.size * scale).ceil().max(1.0) as usize;
+    if scale.is_nan() || scale <= 0.0 || side > MAX_PIXELS {
+        return Err(PyValueError::new_err(format!(
+            "scale {scale} gives a picture {side} pixels wide, at most {MAX_PIXELS} are allowed"
+        )));
+    }
+    let mut pixels = vec![BACKGROUND; side * side];
+    if grid.layout == Layout::Square {
+        for line in 0..=grid.grid_size {
+            let at = (line as f32 * grid.cell_size * scale) as usize;
+            if at >= side {
+                continue;
+            }
+            for other in 0..side {
+                pixels[at * side + other] = CELL_LINE;
+                pixels[other * side + at] = CELL_LINE;
+            }
+        }
+    }
+
+    // Pixels within half a pixel of a shape are filled, so segments stay visible
+    let half_pixel = 0.5 / scale;
+    let pixel_range = |min: f32, max: f32| {
+        let first = ((min - half_pixel) * scale).floor().max(0.0) as usize;
+        let last = ((max + half_pixel) * scale).ceil().clamp(0.0, side as f32) as usize;
+        first..last
+    };
+    for (body, color) in layers(grid) {
+        let (min_x, max_x, min_y, max_y) = body.aabb();
+        for row in pixel_range(min_y, max_y) {
+            for column in pixel_range(min_x, max_x) {
+                let center =
+                    Vector2::new((column as f32 + 0.5) / scale, (row as f32 + 0.5) / scale);
+                if body.surface_distance(center) <= half_pixel {
+                    pixels[row * side + column] = color;
+                }
+            }
+        }
+    }
+    Ok(Raster { side, pixels })
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn chunk(out: &mut Vec<u8>, name: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(name);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Encodes a raster as an 8-bit RGB PNG, with the image data in uncompressed deflate blocks.
+pub fn png(raster: &Raster) -> Vec<u8> {
+    let mut scanlines = Vec::with_capacity(raster.side * (raster.side * 3 + 1));
+    for row in raster.pixels.chunks(raster.side) {
+        scanlines.push(0);
+        scanlines.extend(row.iter().flatten());
+    }
+    let mut zlib = vec![0x78, 0x01];
+    let blocks = scanlines.chunks(u16::MAX as usize);
+    let count = blocks.len();
+    for (i, block) in blocks.enumerate() {
+        zlib.push((i + 1 == count) as u8);
+        let len = block.len() as u16;
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&scanlines).to_be_bytes());
+
+    let side = (raster.side as u32).to_be_bytes();
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&side);
+    header.extend_from_slice(&side);
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &zlib);
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_debug_image() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 2.0, 0);
+        grid.add_static_circle(1, Vector2::new(17.0, 15.0), 2.0, 0);
+        grid.add_dynamic_circle(2, Vector2::new(75.0, 75.0), 3.0, 0);
+        grid.add_static_chain(3, &[Vector2::new(40.0, 55.1), Vector2::new(60.0, 55.1)], 0);
+        grid.step(1.0 / 60.0);
+
+        let raster = rasterize(&grid, 2.0).unwrap();
+        assert_eq!(raster.side, 200);
+        let at = |x: usize, y: usize| raster.pixels[y * raster.side + x];
+        assert_eq!(at(32, 30), COLLIDING);
+        assert_eq!(at(150, 150), DYNAMIC);
+        assert_eq!(at(100, 110), STATIC);
+        assert_eq!(at(25, 150), BACKGROUND);
+        assert_eq!(at(40, 150), CELL_LINE);
+        assert!(rasterize(&grid, 1000.0).is_err());
+
+        let png = png(&raster);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[16..24], &[0, 0, 0, 200, 0, 0, 0, 200]);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+
+        let svg = svg(&grid, 2.0);
+        assert_eq!(svg.matches("<circle").count(), 3);
+        assert_eq!(svg.matches("<line").count(), 1);
+        assert!(svg.contains(&hex_color(COLLIDING)));
+
+        let path = std::env::temp_dir().join(format!("radyx-debug-{}.svg", std::process::id()));
+        let path = path.to_str().unwrap();
+        for scale in [f32::NAN, 0.0, -1.0, f32::INFINITY] {
+            assert!(grid.export_debug_image(path, scale).is_err());
+            assert!(std::fs::metadata(path).is_err());
+        }
+        grid.export_debug_image(path, 2.0).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), svg);
+        std::fs::remove_file(path).unwrap();
+    }
+}
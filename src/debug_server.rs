@@ -0,0 +1,236 @@
+//! A small HTTP server showing a world live in a browser, for watching headless runs.
+//!
+//! `GET /` serves a page drawing the world, which polls `GET /state.json` for the view
+//! published at the last `step`: counters under `stats`, occupied cells as `[x, y, bodies]`
+//! and bodies with their bounding circles.
+
+use crate::GridPhysics;
+use pyo3::{exceptions::PyRuntimeError, PyResult};
+use serde_json::json;
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+const PAGE: &str = r##"<!doctype html>
+<title>radyx</title>
+<pre id="stats"></pre>
+<canvas id="world" width="800" height="800" style="border:1px solid #ccc"></canvas>
+<script>
+const canvas = document.getElementById("world"), ctx = canvas.getContext("2d");
+async function refresh() {
+  const view = await (await fetch("/state.json")).json();
+  document.getElementById("stats").textContent = JSON.stringify(view.stats);
+  const scale = canvas.width / view.size;
+  ctx.clearRect(0, 0, canvas.width, canvas.height);
+  for (const body of view.bodies) {
+    ctx.fillStyle = body.static ? "#808080" : "#3c6ec8";
+    ctx.beginPath();
+    ctx.arc(body.position[0] * scale, body.position[1] * scale, Math.max(body.radius * scale, 1), 0, 2 * Math.PI);
+    ctx.fill();
+  }
+}
+setInterval(() => refresh().catch(() => {}), 500);
+</script>
+"##;
+
+/// A running server, stopped when dropped.
+pub struct DebugServer {
+    pub port: u16,
+    view: Arc<Mutex<String>>,
+    stop: Arc<AtomicBool>,
+    accept: Option<JoinHandle<()>>,
+}
+
+impl DebugServer {
+    pub fn start(host: &str, port: u16) -> PyResult<Self> {
+        if cfg!(target_family = "wasm") {
+            return Err(PyRuntimeError::new_err(
+                "the debug server serves from a thread, which wasm builds can't start",
+            ));
+        }
+        let listener = TcpListener::bind((host, port))?;
+        listener.set_nonblocking(true)?;
+        let mut server = Self {
+            port: listener.local_addr()?.port(),
+            view: Arc::new(Mutex::new("{}".to_string())),
+            stop: Arc::new(AtomicBool::new(false)),
+            accept: None,
+        };
+        let (view, stop) = (server.view.clone(), server.stop.clone());
+        server.accept = Some(thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        // A client that hangs up early only loses its own response
+                        let _ = respond(stream, &view);
+                    }
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(20));
+                    }
+                    Err(_) => {}
+                }
+            }
+        }));
+        Ok(server)
+    }
+
+    pub fn publish(&self, view: String) {
+        *self.view.lock().unwrap_or_else(|err| err.into_inner()) = view;
+    }
+}
+
+impl Drop for DebugServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // The accept thread owns the listener, so the port is free once it has returned
+        if let Some(accept) = self.accept.take() {
+            let _ = accept.join();
+        }
+    }
+}
+
+fn respond(mut stream: TcpStream, view: &Mutex<String>) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|end| end == b"\r\n\r\n") && request.len() < 8192 {
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut words = request.split_whitespace();
+    let (status, content_type, body) = match (words.next(), words.next()) {
+        (Some("GET"), Some("/")) => ("200 OK", "text/html", PAGE.to_string()),
+        (Some("GET"), Some("/state.json")) => (
+            "200 OK",
+            "application/json",
+            view.lock().unwrap_or_else(|err| err.into_inner()).clone(),
+        ),
+        _ => ("404 Not Found", "text/plain", "not found".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Server slot of a world. Forks don't publish to the server of their origin.
+#[derive(Default)]
+pub struct DebugSlot(pub Option<DebugServer>);
+
+impl Clone for DebugSlot {
+    fn clone(&self) -> Self {
+        Self(None)
+    }
+}
+
+/// The JSON view served at `/state.json`.
+pub fn view(grid: &GridPhysics) -> String {
+    let cells: Vec<[isize; 3]> = grid
+        .grid
+        .iter()
+        .enumerate()
+        .filter(|(_, cell)| !cell.is_empty())
+        .map(|(index, cell)| {
+            let (x, y) = grid.cell_coords(index);
+            [x, y, cell.len() as isize]
+        })
+        .collect();
+    let mut bodies: Vec<_> = grid
+        .dynamic_bodies
+        .values()
+        .chain(grid.static_bodies.values())
+        .flatten()
+        .collect();
+    bodies.sort_unstable_by_key(|body| (body.entity_index(), body.is_static(), body.body_index()));
+    let dynamic: usize = grid.dynamic_bodies.values().map(Vec::len).sum();
+    let statics: usize = grid.static_bodies.values().map(Vec::len).sum();
+
+    json!({
+        "tick": grid.tick,
+        "time": grid.time,
+        "size": grid.size,
+        "cell_size": grid.cell_size,
+        "stats": {
+            "dynamic_bodies": dynamic,
+            "static_bodies": statics,
+            "contacts": grid.contacts.len(),
+            "occupied_cells": cells.len(),
+            "most_bodies_in_a_cell": cells.iter().map(|cell| cell[2]).max().unwrap_or(0),
+        },
+        "cells": cells,
+        "bodies": bodies
+            .into_iter()
+            .map(|body| {
+                let (min_x, max_x, min_y, max_y) = body.aabb();
+                json!({
+                    "entity": body.entity_index(),
+                    "body": body.body_index(),
+                    "static": body.is_static(),
+                    "kind": body.kind(),
+                    "position": [body.pos.x, body.pos.y],
+                    "radius": (max_x - min_x).max(max_y - min_y) / 2.0,
+                })
+            })
+            .collect::<Vec<_>>(),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vector2;
+
+    fn get(port: u16, path: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn state(port: u16) -> serde_json::Value {
+        let response = get(port, "/state.json");
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[test]
+    fn check_debug_server() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(16.0, 15.0), 1.0, 0);
+        let port = grid.start_debug_server(0, "127.0.0.1").unwrap();
+
+        let view = state(port);
+        assert_eq!(view["tick"], 0);
+        assert_eq!(view["bodies"].as_array().unwrap().len(), 2);
+        grid.step(1.0 / 60.0);
+        let view = state(port);
+        assert_eq!(view["tick"], 1);
+        assert_eq!(view["stats"]["contacts"], 1);
+        assert_eq!(view["cells"][0], json!([1, 1, 2]));
+
+        assert!(get(port, "/").contains("<canvas"));
+        assert!(get(port, "/missing").starts_with("HTTP/1.1 404"));
+
+        // Restarting on the same port finds it released
+        assert_eq!(grid.start_debug_server(port, "127.0.0.1").unwrap(), port);
+        assert_eq!(state(port)["tick"], 1);
+        grid.stop_debug_server();
+    }
+}
@@ -0,0 +1,38 @@
+//! How collision queries avoid repeating the narrow phase for a pair met in more than one
+//! cell, which happens whenever both bodies span the same few cells.
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+/// Duplicate suppression in multi-cell overlap, passed from Python by name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PairDedup {
+    /// Tests the pair again in every shared cell, leaving the repeats to the sets the
+    /// results are gathered in. Cheapest when bodies rarely span a cell edge.
+    After,
+    /// Remembers the bodies a body spanning several cells was tested against, and skips them
+    /// in the cells after.
+    #[default]
+    Visited,
+}
+
+impl<'source> FromPyObject<'source> for PairDedup {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract::<&str>()? {
+            "after" => Ok(PairDedup::After),
+            "visited" => Ok(PairDedup::Visited),
+            name => Err(PyValueError::new_err(format!(
+                "unknown pair dedup {name:?}, expected \"after\" or \"visited\""
+            ))),
+        }
+    }
+}
+
+impl IntoPy<PyObject> for PairDedup {
+    fn into_py(self, py: Python) -> PyObject {
+        match self {
+            PairDedup::After => "after",
+            PairDedup::Visited => "visited",
+        }
+        .into_py(py)
+    }
+}
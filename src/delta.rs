@@ -0,0 +1,250 @@
+//! Compact binary deltas for mirroring a server-owned world on clients.
+//!
+//! A delta lists every entity changed at or after some tick with all of its bodies, replacing
+//! what the receiver has for that entity; an entity without bodies was removed. Integers and
+//! floats are little-endian, and indices are `u32`:
+//!
+//! ```text
+//! "RDXD" | version u8 = 1 | tick u64 | entities u32
+//! entity: index u32 | position f32 f32 | angle f32 | bodies u32
+//! body:   index u32 | static u8 | kind u32 | position f32 f32 | angle f32
+//!         | offset f32 f32 | turn f32 | radius f32 | shape u8 | shape parameters f32...
+//! ```
+//!
+//! Shapes are `0` circle (no parameters), `1` ellipse (`radius_x`, `radius_y`), `2` box
+//! (`half_x`, `half_y`), `3` segment (`half_length`) and `4` triangle (six corner coordinates
+//! relative to the position).
+
+use crate::{commit::Mutation, shape::Shape, Body, GridPhysics, Placed, Transform, Vector2};
+use pyo3::{exceptions::PyValueError, PyResult};
+
+const MAGIC: &[u8; 4] = b"RDXD";
+const VERSION: u8 = 1;
+
+fn index(value: usize) -> PyResult<[u8; 4]> {
+    u32::try_from(value)
+        .map(u32::to_le_bytes)
+        .map_err(|_| PyValueError::new_err(format!("index {value} doesn't fit the delta format")))
+}
+
+fn floats(out: &mut Vec<u8>, values: &[f32]) {
+    for value in values {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn write_body(out: &mut Vec<u8>, body: &Placed) -> PyResult<()> {
+    out.extend_from_slice(&index(body.body_index())?);
+    out.push(body.is_static() as u8);
+    out.extend_from_slice(&index(body.kind())?);
+    floats(
+        out,
+        &[
+            body.pos.x,
+            body.pos.y,
+            body.angle,
+            body.offset.x,
+            body.offset.y,
+            body.turn,
+            body.radius,
+        ],
+    );
+    match body.shape() {
+        Shape::Circle => out.push(0),
+        Shape::Ellipse { radius_x, radius_y } => {
+            out.push(1);
+            floats(out, &[radius_x, radius_y]);
+        }
+        Shape::Box { half_x, half_y } => {
+            out.push(2);
+            floats(out, &[half_x, half_y]);
+        }
+        Shape::Segment { half_length } => {
+            out.push(3);
+            floats(out, &[half_length]);
+        }
+        Shape::Triangle { corners } => {
+            out.push(4);
+            for corner in corners {
+                floats(out, &[corner.x, corner.y]);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn encode(grid: &GridPhysics, since_tick: u64) -> PyResult<Vec<u8>> {
+    let mut entities: Vec<usize> = grid
+        .changed
+        .iter()
+        .filter(|(_, tick)| **tick >= since_tick)
+        .map(|(entity, _)| *entity)
+        .collect();
+    entities.sort_unstable();
+
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.extend_from_slice(&grid.tick.to_le_bytes());
+    out.extend_from_slice(&index(entities.len())?);
+    for entity_index in entities {
+        let transform = grid
+            .transforms
+            .get(&entity_index)
+            .copied()
+            .unwrap_or_default();
+        let mut bodies: Vec<&Placed> = grid.placed_of(entity_index).collect();
+        bodies.sort_unstable_by_key(|body| (body.is_static(), body.body_index()));
+
+        out.extend_from_slice(&index(entity_index)?);
+        floats(
+            &mut out,
+            &[transform.position.x, transform.position.y, transform.angle],
+        );
+        out.extend_from_slice(&index(bodies.len())?);
+        for body in bodies {
+            write_body(&mut out, body)?;
+        }
+    }
+    Ok(out)
+}
+
+/// Reads values off the front of a delta, failing on truncated input.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> PyResult<[u8; N]> {
+        if self.data.len() < N {
+            return Err(PyValueError::new_err("truncated delta"));
+        }
+        let (head, rest) = self.data.split_at(N);
+        self.data = rest;
+        Ok(head.try_into().expect("split at N"))
+    }
+
+    fn byte(&mut self) -> PyResult<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn index(&mut self) -> PyResult<usize> {
+        Ok(u32::from_le_bytes(self.take()?) as usize)
+    }
+
+    fn float(&mut self) -> PyResult<f32> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+
+    fn point(&mut self) -> PyResult<Vector2> {
+        Ok(Vector2::new(self.float()?, self.float()?))
+    }
+}
+
+fn read_body(reader: &mut Reader, entity_index: usize) -> PyResult<Placed> {
+    let body_index = reader.index()?;
+    let is_static = reader.byte()? != 0;
+    let kind = reader.index()?;
+    let pos = reader.point()?;
+    let angle = reader.float()?;
+    let offset = reader.point()?;
+    let turn = reader.float()?;
+    let radius = reader.float()?;
+    let shape = match reader.byte()? {
+        0 => Shape::Circle,
+        1 => Shape::Ellipse {
+            radius_x: reader.float()?,
+            radius_y: reader.float()?,
+        },
+        2 => Shape::Box {
+            half_x: reader.float()?,
+            half_y: reader.float()?,
+        },
+        3 => Shape::Segment {
+            half_length: reader.float()?,
+        },
+        4 => Shape::Triangle {
+            corners: [reader.point()?, reader.point()?, reader.point()?],
+        },
+        tag => {
+            return Err(PyValueError::new_err(format!(
+                "unknown shape {tag} in delta"
+            )))
+        }
+    };
+
+    let mut body = Body::new(entity_index, body_index, pos, radius, is_static, kind);
+    body.angle = angle;
+    let mut placed = Placed { body, offset, turn };
+    placed.set_shape(shape);
+    Ok(placed)
+}
+
+/// Parses and screens a whole delta before changing anything, so malformed input leaves
+/// `grid` intact. Invalid bodies are skipped and reported like when adding them, and while
+/// deferred the changes are queued for `commit`. Returns the tick the delta was encoded at.
+pub fn apply(grid: &mut GridPhysics, data: &[u8]) -> PyResult<u64> {
+    let mut reader = Reader { data };
+    if &reader.take::<4>()? != MAGIC {
+        return Err(PyValueError::new_err("not a radyx delta"));
+    }
+    let version = reader.byte()?;
+    if version != VERSION {
+        return Err(PyValueError::new_err(format!(
+            "unsupported delta version {version}"
+        )));
+    }
+    let tick = u64::from_le_bytes(reader.take()?);
+
+    let mut entities = Vec::new();
+    for _ in 0..reader.index()? {
+        let entity_index = reader.index()?;
+        let transform = Transform {
+            position: reader.point()?,
+            angle: reader.float()?,
+        };
+        let count = reader.index()?;
+        let bodies = (0..count)
+            .map(|_| read_body(&mut reader, entity_index))
+            .collect::<PyResult<Vec<Placed>>>()?;
+        entities.push((entity_index, transform, bodies));
+    }
+    if !reader.data.is_empty() {
+        return Err(PyValueError::new_err("trailing bytes after delta"));
+    }
+
+    for (entity_index, transform, bodies) in &mut entities {
+        if !bodies.is_empty() && !grid.check_transform(*entity_index, transform)? {
+            bodies.clear();
+        }
+        bodies.retain(|body| !grid.screen(body));
+    }
+    grid.report_skipped()?;
+
+    let replaced: usize = entities
+        .iter()
+        .map(|(entity_index, ..)| grid.bodies_of(*entity_index).count())
+        .sum();
+    let added: usize = entities.iter().map(|(.., bodies)| bodies.len()).sum();
+    grid.ensure_room(added.saturating_sub(replaced))?;
+
+    for (entity_index, transform, bodies) in entities {
+        grid.py_remove_entity(entity_index);
+        if bodies.is_empty() {
+            continue;
+        }
+        if grid.deferred {
+            // Bodies keep their offsets until the transform places them
+            for body in bodies {
+                grid.defer(Mutation::Add(body));
+            }
+            grid.defer(Mutation::SetTransform(entity_index, transform));
+        } else {
+            grid.transforms.insert(entity_index, transform);
+            for body in bodies {
+                grid.push_body(body);
+            }
+        }
+    }
+    Ok(tick)
+}
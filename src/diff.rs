@@ -0,0 +1,95 @@
+//! Differences between two snapshots of a world, for sending delta updates.
+
+use crate::{Body, GridPhysics};
+use pyo3::prelude::*;
+use std::collections::HashMap;
+
+/// Bodies that differ between two worlds, as sorted `(entity_index, body_index)` lists.
+/// `moved` bodies exist in both but changed position, angle or shape.
+#[pyclass(module = "radyx", get_all)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WorldDiff {
+    pub added: Vec<(usize, usize)>,
+    pub removed: Vec<(usize, usize)>,
+    pub moved: Vec<(usize, usize)>,
+}
+
+#[pymethods]
+impl WorldDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+fn bodies(grid: &GridPhysics) -> HashMap<(usize, bool, usize), &Body> {
+    grid.dynamic_bodies
+        .values()
+        .chain(grid.static_bodies.values())
+        .flatten()
+        .map(|placed| {
+            (
+                (
+                    placed.entity_index(),
+                    placed.is_static(),
+                    placed.body_index(),
+                ),
+                &placed.body,
+            )
+        })
+        .collect()
+}
+
+pub fn diff(a: &GridPhysics, b: &GridPhysics) -> WorldDiff {
+    let (before, after) = (bodies(a), bodies(b));
+    let mut diff = WorldDiff::default();
+    for (key, body) in &after {
+        match before.get(key) {
+            None => diff.added.push((key.0, key.2)),
+            Some(old) => {
+                let same = old.pos == body.pos
+                    && old.angle == body.angle
+                    && old.shape() == body.shape()
+                    && old.radius == body.radius;
+                if !same {
+                    diff.moved.push((key.0, key.2));
+                }
+            }
+        }
+    }
+    diff.removed = before
+        .keys()
+        .filter(|key| !after.contains_key(key))
+        .map(|key| (key.0, key.2))
+        .collect();
+    for list in [&mut diff.added, &mut diff.removed, &mut diff.moved] {
+        list.sort_unstable();
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vector2;
+
+    #[test]
+    fn check_world_diff() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(25.0, 15.0), 1.0, 0);
+        grid.add_static_circle(2, Vector2::new(50.0, 50.0), 5.0, 0);
+        let snapshot = grid.fork();
+        assert!(diff(&snapshot, &grid).is_empty());
+
+        grid.move_entity(1, Vector2::new(5.0, 0.0)).unwrap();
+        grid.add_dynamic_circle(3, Vector2::new(70.0, 70.0), 1.0, 0);
+        let door = grid.static_bodies[&2][0];
+        grid.remove_static_body(&door);
+
+        let changes = diff(&snapshot, &grid);
+        assert_eq!(changes.added, vec![(3, 0)]);
+        assert_eq!(changes.removed, vec![(2, 0)]);
+        assert_eq!(changes.moved, vec![(1, 0)]);
+        assert_eq!(diff(&grid, &snapshot).added, vec![(2, 0)]);
+    }
+}
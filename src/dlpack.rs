@@ -0,0 +1,187 @@
+//! Zero-copy export of body positions through the DLPack protocol (`__dlpack__`), so array
+//! libraries like PyTorch and JAX can read them without copying.
+
+use pyo3::{ffi, prelude::*};
+use std::{
+    ffi::{c_void, CStr},
+    ptr,
+};
+
+const DLTENSOR: &CStr = c"dltensor";
+const DL_CPU: i32 = 1;
+const DL_FLOAT: u8 = 2;
+
+#[repr(C)]
+struct DLDevice {
+    device_type: i32,
+    device_id: i32,
+}
+
+#[repr(C)]
+struct DLDataType {
+    code: u8,
+    bits: u8,
+    lanes: u16,
+}
+
+#[repr(C)]
+struct DLTensor {
+    data: *mut c_void,
+    device: DLDevice,
+    ndim: i32,
+    dtype: DLDataType,
+    shape: *mut i64,
+    strides: *mut i64,
+    byte_offset: u64,
+}
+
+#[repr(C)]
+struct DLManagedTensor {
+    dl_tensor: DLTensor,
+    manager_ctx: *mut c_void,
+    deleter: Option<unsafe extern "C" fn(*mut DLManagedTensor)>,
+}
+
+/// Keeps the exporting `Positions` alive for as long as a consumer holds the tensor.
+struct ManagerContext {
+    _owner: PyObject,
+    shape: [i64; 2],
+}
+
+unsafe extern "C" fn delete_managed_tensor(managed: *mut DLManagedTensor) {
+    let managed = Box::from_raw(managed);
+    let context = Box::from_raw(managed.manager_ctx as *mut ManagerContext);
+    Python::with_gil(|_| drop(context));
+}
+
+/// Frees the tensor when the capsule is dropped without ever being consumed. Consumers rename
+/// the capsule to "used_dltensor" and take over calling the deleter.
+unsafe extern "C" fn destroy_capsule(capsule: *mut ffi::PyObject) {
+    if ffi::PyCapsule_IsValid(capsule, DLTENSOR.as_ptr()) == 1 {
+        let managed = ffi::PyCapsule_GetPointer(capsule, DLTENSOR.as_ptr()) as *mut DLManagedTensor;
+        if let Some(deleter) = (*managed).deleter {
+            deleter(managed);
+        }
+    }
+}
+
+/// Body positions gathered into one contiguous `(n, 2)` float32 buffer, with the
+/// `(entity_index, body_index)` of each row. Shared zero-copy through `__dlpack__`, e.g.
+/// `torch.from_dlpack(grid.positions())`.
+#[pyclass(module = "radyx")]
+pub struct Positions {
+    data: Vec<f32>,
+    #[pyo3(get)]
+    indices: Vec<(usize, usize)>,
+}
+
+impl Positions {
+    pub fn new(rows: Vec<(usize, usize, f32, f32)>) -> Self {
+        let mut data = Vec::with_capacity(rows.len() * 2);
+        let mut indices = Vec::with_capacity(rows.len());
+        for (entity_index, body_index, x, y) in rows {
+            data.push(x);
+            data.push(y);
+            indices.push((entity_index, body_index));
+        }
+        Self { data, indices }
+    }
+
+    pub fn data(&self) -> &[f32] {
+        &self.data
+    }
+
+    pub fn indices(&self) -> &[(usize, usize)] {
+        &self.indices
+    }
+}
+
+#[pymethods]
+impl Positions {
+    #[pyo3(signature = (stream=None))]
+    fn __dlpack__(slf: PyRef<Self>, py: Python, stream: Option<PyObject>) -> PyResult<PyObject> {
+        // CPU memory needs no stream synchronization
+        let _ = stream;
+        let rows = slf.indices.len() as i64;
+        let data = slf.data.as_ptr() as *mut c_void;
+        let owner: PyObject = slf.into_py(py);
+
+        let context = Box::into_raw(Box::new(ManagerContext {
+            _owner: owner,
+            shape: [rows, 2],
+        }));
+        let managed = Box::into_raw(Box::new(DLManagedTensor {
+            dl_tensor: DLTensor {
+                data,
+                device: DLDevice {
+                    device_type: DL_CPU,
+                    device_id: 0,
+                },
+                ndim: 2,
+                dtype: DLDataType {
+                    code: DL_FLOAT,
+                    bits: 32,
+                    lanes: 1,
+                },
+                // Safety: the context outlives the tensor, it is only freed by the deleter
+                shape: unsafe { (*context).shape.as_mut_ptr() },
+                strides: ptr::null_mut(),
+                byte_offset: 0,
+            },
+            manager_ctx: context as *mut c_void,
+            deleter: Some(delete_managed_tensor),
+        }));
+
+        unsafe {
+            let capsule = ffi::PyCapsule_New(
+                managed as *mut c_void,
+                DLTENSOR.as_ptr(),
+                Some(destroy_capsule),
+            );
+            if capsule.is_null() {
+                delete_managed_tensor(managed);
+                return Err(PyErr::fetch(py));
+            }
+            Ok(PyObject::from_owned_ptr(py, capsule))
+        }
+    }
+
+    fn __dlpack_device__(&self) -> (i32, i32) {
+        (DL_CPU, 0)
+    }
+
+    fn __len__(&self) -> usize {
+        self.indices.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::AsPyPointer;
+
+    #[test]
+    fn check_dlpack_capsule() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let positions =
+                Py::new(py, Positions::new(vec![(0, 0, 1.0, 2.0), (4, 1, 3.0, 4.0)])).unwrap();
+            let capsule = Positions::__dlpack__(positions.borrow(py), py, None).unwrap();
+            assert_eq!(positions.get_refcnt(py), 2);
+
+            unsafe {
+                let managed = ffi::PyCapsule_GetPointer(capsule.as_ptr(), DLTENSOR.as_ptr())
+                    as *mut DLManagedTensor;
+                let tensor = &(*managed).dl_tensor;
+                assert_eq!(tensor.ndim, 2);
+                assert_eq!(*tensor.shape, 2);
+                assert_eq!(*tensor.shape.add(1), 2);
+                let data = std::slice::from_raw_parts(tensor.data as *const f32, 4);
+                assert_eq!(data, &[1.0, 2.0, 3.0, 4.0]);
+            }
+
+            drop(capsule);
+            assert_eq!(positions.get_refcnt(py), 1);
+        });
+    }
+}
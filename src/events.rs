@@ -0,0 +1,67 @@
+use pyo3::prelude::*;
+
+#[pyclass(module = "radyx")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventKind {
+    /// Two bodies touched during the step.
+    Collision,
+    /// A sensor entity overlapped another body during the step.
+    Sensor,
+    /// Two bodies started touching this step.
+    Enter,
+    /// Two bodies that touched last step no longer do.
+    Exit,
+    /// A dynamic body crossed the edge of the world.
+    Boundary,
+    /// A dynamic body moved entirely out of the world.
+    Left,
+    /// A dynamic body that had left the world is back inside it.
+    Reentered,
+}
+
+/// Something that happened during a `GridPhysics.step()`, or when moving bodies for `Left` and
+/// `Reentered`. `other_*` fields are `None` for events that don't involve a second body.
+/// `contact_id` names an ongoing contact: it is the same in every event of a body pair, from
+/// either side, from its `Enter` to its `Exit`, and never reused.
+#[pyclass(module = "radyx", get_all)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub kind: EventKind,
+    pub tick: u64,
+    pub self_entity_index: usize,
+    pub self_body_index: usize,
+    pub other_entity_index: Option<usize>,
+    pub other_body_index: Option<usize>,
+    pub contact_id: Option<u64>,
+}
+
+impl Event {
+    pub fn contact(kind: EventKind, tick: u64, key: ContactKey, contact_id: Option<u64>) -> Self {
+        let (self_entity_index, other_entity_index, self_body_index, other_body_index) = key;
+        Self {
+            kind,
+            tick,
+            self_entity_index,
+            self_body_index,
+            other_entity_index: Some(other_entity_index),
+            other_body_index: Some(other_body_index),
+            contact_id,
+        }
+    }
+
+    pub fn body(kind: EventKind, tick: u64, entity_index: usize, body_index: usize) -> Self {
+        Self {
+            kind,
+            tick,
+            self_entity_index: entity_index,
+            self_body_index: body_index,
+            other_entity_index: None,
+            other_body_index: None,
+            contact_id: None,
+        }
+    }
+}
+
+/// `(self_entity_index, other_entity_index, self_body_index, other_body_index)`, in the order
+/// of `Collision`'s fields.
+pub type ContactKey = (usize, usize, usize, usize);
@@ -0,0 +1,120 @@
+//! Outward impulses from a point, for explosions and knockback.
+
+use crate::{GridPhysics, Metric, Vector2};
+use pyo3::{exceptions::PyValueError, prelude::*};
+use std::collections::HashMap;
+
+/// How an explosion weakens between its center and its radius, passed from Python by name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Falloff {
+    Constant,
+    #[default]
+    Linear,
+    Quadratic,
+}
+
+impl Falloff {
+    /// Share of the full strength at `fraction` of the radius.
+    fn scale(self, fraction: f32) -> f32 {
+        let remaining = (1.0 - fraction).clamp(0.0, 1.0);
+        match self {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => remaining,
+            Falloff::Quadratic => remaining * remaining,
+        }
+    }
+}
+
+impl<'source> FromPyObject<'source> for Falloff {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract::<&str>()? {
+            "constant" => Ok(Falloff::Constant),
+            "linear" => Ok(Falloff::Linear),
+            "quadratic" => Ok(Falloff::Quadratic),
+            name => Err(PyValueError::new_err(format!(
+                "unknown falloff {name:?}, expected \"constant\", \"linear\" or \"quadratic\""
+            ))),
+        }
+    }
+}
+
+impl GridPhysics {
+    /// Impulse for every active dynamic entity with a body within `radius` of `center`, by
+    /// entity index. Each points from `center` to the entity's nearest body and weakens with
+    /// that body's surface distance. Bodies centered on `center` have no direction to be
+    /// pushed in and are left out.
+    pub(crate) fn radial_impulses(
+        &self,
+        center: Vector2,
+        radius: f32,
+        strength: f32,
+        falloff: Falloff,
+    ) -> Vec<(usize, Vector2)> {
+        let bounds = (
+            center.x - radius,
+            center.x + radius,
+            center.y - radius,
+            center.y + radius,
+        );
+        let mut nearest: HashMap<usize, (f32, Vector2)> = HashMap::new();
+        for body in self.cells_within(bounds).flatten() {
+            if body.is_static() || !self.is_active(body.entity_index()) {
+                continue;
+            }
+            let distance = body.metric_distance(center, Metric::Euclidean);
+            if distance > radius {
+                continue;
+            }
+            let closest = nearest
+                .entry(body.entity_index())
+                .or_insert((distance, body.pos));
+            if distance < closest.0 {
+                *closest = (distance, body.pos);
+            }
+        }
+
+        let mut impulses: Vec<(usize, Vector2)> = nearest
+            .into_iter()
+            .filter_map(|(entity_index, (distance, pos))| {
+                let (dx, dy) = (pos.x - center.x, pos.y - center.y);
+                let length = dx.hypot(dy);
+                if length == 0.0 {
+                    return None;
+                }
+                let fraction = if radius > 0.0 { distance / radius } else { 0.0 };
+                let magnitude = strength * falloff.scale(fraction) / length;
+                Some((entity_index, Vector2::new(dx * magnitude, dy * magnitude)))
+            })
+            .collect();
+        impulses.sort_unstable_by_key(|(entity_index, _)| *entity_index);
+        impulses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_radial_impulses() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(60.0, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(50.0, 45.0), 1.0, 0);
+        grid.add_dynamic_circle(2, Vector2::new(90.0, 50.0), 1.0, 0);
+        grid.add_static_circle(3, Vector2::new(40.0, 50.0), 1.0, 0);
+
+        let center = Vector2::new(50.0, 50.0);
+        let impulses = grid.radial_impulses(center, 18.0, 10.0, Falloff::Linear);
+        let entities: Vec<usize> = impulses.iter().map(|(entity, _)| *entity).collect();
+        assert_eq!(entities, vec![0, 1]);
+        // Entity 0's surface is 9 away, half the radius
+        assert!((impulses[0].1.x - 5.0).abs() < 1e-5 && impulses[0].1.y == 0.0);
+        // Entity 1's surface is 4 away, straight up
+        assert!((impulses[1].1.y + 10.0 * (1.0 - 4.0 / 18.0)).abs() < 1e-5);
+
+        let impulses = grid.radial_impulses(center, 18.0, 10.0, Falloff::Quadratic);
+        assert!((impulses[0].1.x - 2.5).abs() < 1e-5);
+        let impulses = grid.radial_impulses(center, 18.0, 10.0, Falloff::Constant);
+        assert!((impulses[0].1.x - 10.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,493 @@
+//! Randomized calls into the Python-facing methods with hostile arguments: NaN, infinities,
+//! huge and negative values and indices that don't exist. Every call may fail with an error
+//! but none may panic, since a panic reaches Python as an uncatchable `PanicException`.
+//! Methods taking or returning numpy arrays are called through the functions they hand the
+//! converted arrays to, as the tests run without numpy.
+//!
+//! `cargo test fuzz` runs a fixed set of seeds. Set `RADYX_FUZZ_SEEDS` to run more, and
+//! `RADYX_FUZZ_SEED` to replay the one a failure names.
+
+use crate::{
+    benchmark::Rng, delta, explosion::Falloff, kinds::KindArg, targets::Targets, threads,
+    Containment, GridPhysics, Metric, ResultType, Vector2,
+};
+use numpy::ndarray::{Array1, Array2};
+use pyo3::prelude::*;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Calls made on each world.
+const CALLS: usize = 400;
+
+const EDGE_VALUES: [f32; 10] = [
+    f32::NAN,
+    f32::INFINITY,
+    f32::NEG_INFINITY,
+    f32::MAX,
+    f32::MIN,
+    f32::MIN_POSITIVE,
+    0.0,
+    -0.0,
+    -1.0,
+    1e30,
+];
+
+struct Input(Rng);
+
+impl Input {
+    fn chance(&mut self, percent: u32) -> bool {
+        self.0.next() * 100.0 < percent as f32
+    }
+
+    /// A value in the world most of the time, an edge case otherwise.
+    fn float(&mut self) -> f32 {
+        if self.chance(15) {
+            EDGE_VALUES[self.index(EDGE_VALUES.len())]
+        } else {
+            self.0.next() * 140.0 - 20.0
+        }
+    }
+
+    /// A cell size of a few units, so grids stay small enough to walk quickly, or an edge
+    /// case.
+    fn cell_size(&mut self) -> f32 {
+        if self.chance(15) {
+            EDGE_VALUES[self.index(EDGE_VALUES.len())]
+        } else {
+            4.0 + self.0.next() * 20.0
+        }
+    }
+
+    fn point(&mut self) -> Vector2 {
+        Vector2::new(self.float(), self.float())
+    }
+
+    fn index(&mut self, below: usize) -> usize {
+        ((self.0.next() * below as f32) as usize).min(below - 1)
+    }
+
+    /// One of a few entities, or rarely one that can't exist.
+    fn entity(&mut self) -> usize {
+        if self.chance(3) {
+            [usize::MAX, u32::MAX as usize + 1][self.index(2)]
+        } else {
+            self.index(8)
+        }
+    }
+
+    fn count(&mut self) -> usize {
+        if self.chance(3) {
+            usize::MAX
+        } else {
+            self.index(6)
+        }
+    }
+
+    fn points(&mut self) -> Vec<Vector2> {
+        (0..self.count().min(6)).map(|_| self.point()).collect()
+    }
+
+    /// A JSON number from `float`, out of `f32` range where it isn't finite, or `null`.
+    fn json_number(&mut self) -> String {
+        match self.float() {
+            value if value.is_nan() => "null".to_string(),
+            value if value.is_infinite() => format!("{}1e39", if value < 0.0 { "-" } else { "" }),
+            value => value.to_string(),
+        }
+    }
+
+    /// A thread count of a few, or rarely one no machine has.
+    fn threads(&mut self) -> Option<usize> {
+        self.chance(30).then(|| self.count())
+    }
+
+    /// An `(n, columns)` array that mostly has the two columns of points.
+    fn array(&mut self, rows: usize) -> Array2<f32> {
+        let columns = if self.chance(90) { 2 } else { self.index(4) };
+        Array2::from_shape_fn((rows, columns), |_| self.float())
+    }
+
+    fn values(&mut self, len: usize) -> Array1<f32> {
+        Array1::from_shape_fn(len, |_| self.float())
+    }
+}
+
+/// A Tiled map of a few tiles whose objects have hostile ids, sizes and points.
+fn tiled_map(input: &mut Input) -> String {
+    let objects: Vec<String> = (0..input.count().min(4))
+        .map(|_| {
+            let id = match input.index(4) {
+                0 => String::new(),
+                1 => r#""id": "1","#.to_string(),
+                _ => format!(r#""id": {},"#, input.entity()),
+            };
+            let shape = match input.index(4) {
+                0 => r#""ellipse": true"#.to_string(),
+                1 | 2 => {
+                    let key = ["polygon", "polyline"][input.index(2)];
+                    let points: Vec<String> = (0..input.count().min(6))
+                        .map(|_| {
+                            format!(
+                                r#"{{"x": {}, "y": {}}}"#,
+                                input.json_number(),
+                                input.json_number()
+                            )
+                        })
+                        .collect();
+                    format!(r#""{key}": [{}]"#, points.join(", "))
+                }
+                _ => r#""point": false"#.to_string(),
+            };
+            format!(
+                r#"{{{id} "x": {}, "y": {}, "width": {}, "height": {}, "rotation": {}, {shape}}}"#,
+                input.json_number(),
+                input.json_number(),
+                input.json_number(),
+                input.json_number(),
+                input.json_number()
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"width": {}, "height": {}, "tilewidth": {}, "tileheight": {}, "layers": [
+            {{"type": "objectgroup", "name": "collisions", "objects": [{}]}}
+        ]}}"#,
+        input.count(),
+        input.count(),
+        input.cell_size(),
+        input.cell_size(),
+        objects.join(", ")
+    )
+}
+
+/// A JSON list of hostile static body records for `load_static_layer`.
+fn layer_bodies(input: &mut Input) -> String {
+    let records: Vec<String> = (0..input.count().min(4))
+        .map(|_| {
+            let shape = match input.index(3) {
+                0 => format!(r#"{{"type": "circle", "radius": {}}}"#, input.json_number()),
+                1 => format!(
+                    r#"{{"type": "box", "half_x": {}, "half_y": {}}}"#,
+                    input.json_number(),
+                    input.json_number()
+                ),
+                _ => format!(
+                    r#"{{"type": "segment", "half_length": {}}}"#,
+                    input.json_number()
+                ),
+            };
+            format!(
+                r#"{{"entity": {}, "body": {}, "static": {}, "position": [{}, {}], "shape": {shape}}}"#,
+                input.entity(),
+                input.count(),
+                input.chance(90),
+                input.json_number(),
+                input.json_number()
+            )
+        })
+        .collect();
+    format!("[{}]", records.join(", "))
+}
+
+fn world(input: &mut Input) -> GridPhysics {
+    let dimensions = (input.float(), input.cell_size());
+    if let Ok(grid) = GridPhysics::py_new(dimensions.0, dimensions.1, None) {
+        return grid;
+    }
+    if input.chance(50) {
+        GridPhysics::hexagonal(100.0, 10.0, None).expect("valid dimensions")
+    } else {
+        GridPhysics::new(100.0, 10.0)
+    }
+}
+
+fn call(py: Python, grid: &mut GridPhysics, input: &mut Input) {
+    let (entity, body) = (input.entity(), input.count());
+    let kind = KindArg::Id(input.count());
+    let metric = [Metric::Euclidean, Metric::Chebyshev, Metric::Manhattan][input.index(3)];
+    let containment = [Containment::Intersects, Containment::Contains][input.index(2)];
+    let limit = input.chance(30).then(|| input.count());
+    match input.index(47) {
+        0 => {
+            let _ = grid.py_add_circle(entity, input.point(), input.float(), body, false, kind);
+        }
+        1 => {
+            let _ = grid.py_add_static_circle(entity, input.point(), input.float(), kind);
+        }
+        2 => {
+            let anchor = input.chance(50).then(|| input.point());
+            let points = input.points();
+            let _ = grid.py_add_dynamic_circles(entity, points, input.float(), kind, anchor);
+        }
+        3 => {
+            let (pos, radii, angle) = (input.point(), input.point(), input.float());
+            let _ = grid.add_dynamic_ellipse(entity, pos, radii.x, radii.y, angle, kind);
+        }
+        4 => {
+            let (center, halves, angle) = (input.point(), input.point(), input.float());
+            let _ = grid.add_dynamic_obb(entity, center, halves, angle, kind);
+        }
+        5 => {
+            let _ = grid.py_add_static_chain(entity, input.points(), kind);
+        }
+        6 => {
+            let vertices = input.points();
+            let indices = (0..input.count().min(3))
+                .map(|_| (input.count(), input.count(), input.count()))
+                .collect();
+            let _ = grid.py_add_static_trimesh(entity, vertices, indices, kind);
+        }
+        7 => grid.py_remove_entity(entity),
+        8 => {
+            let _ = grid.set_entity_transform(entity, input.point(), input.float());
+        }
+        9 => {
+            let _ = grid.move_entity(entity, input.point());
+        }
+        10 => {
+            let _ = grid.update_position(entity, body, input.point());
+        }
+        11 => {
+            let _ = grid.set_radius(entity, body, input.float());
+        }
+        12 => {
+            let _ = grid.scale_entity(entity, input.float());
+        }
+        13 => {
+            let _ = grid.teleport(entity, body, input.point(), input.chance(50));
+        }
+        14 => {
+            let _ = grid.set_velocity(entity, input.point());
+        }
+        15 => {
+            let _ = grid.apply_impulse(entity, body, input.point());
+        }
+        16 => {
+            let _ = grid.set_mass(entity, input.float());
+        }
+        17 => {
+            let _ = grid.set_drag(entity, input.float());
+        }
+        18 => {
+            let falloff = [Falloff::Constant, Falloff::Linear, Falloff::Quadratic];
+            let falloff = falloff[input.index(3)];
+            let (center, radius, strength) = (input.point(), input.float(), input.float());
+            let _ = grid.radial_impulse(center, radius, strength, falloff);
+        }
+        19 => {
+            let _ = grid.step(input.float() as f64);
+        }
+        20 => {
+            let _ = grid.advance(input.float() as f64);
+        }
+        21 => {
+            let _ = grid.resolve_overlaps(input.count().min(4));
+        }
+        22 => {
+            let targets = [Targets::All, Targets::Static, Targets::Dynamic][input.index(3)];
+            let result_type = [ResultType::Set, ResultType::List][input.index(2)];
+            let overlap = input.chance(50);
+            let _ = grid.py_get_collisions(py, overlap, limit, false, targets, result_type);
+        }
+        23 => {
+            let _ = grid.get_near_collisions(input.float());
+        }
+        24 => {
+            let (center, radius) = (input.point(), input.float());
+            let first = input.chance(50);
+            let _ = grid.get_collisions_within_area(
+                center,
+                radius,
+                Some(metric),
+                limit,
+                first,
+                containment,
+            );
+        }
+        25 => {
+            let _ = grid.query_polygon(input.points(), containment);
+        }
+        26 => {
+            let _ = grid.entities_overlapping_aabb(input.point(), input.point(), containment);
+        }
+        27 => {
+            let (origin, direction) = (input.point(), input.point());
+            let max_distance = input.chance(50).then(|| input.float());
+            let supercover = input.chance(50);
+            let _ = grid.raycast(origin, direction, max_distance, limit, false, supercover);
+            let _ = grid.ray_cells(origin, direction, max_distance, supercover);
+        }
+        28 => {
+            let _ = grid.query_circle_sorted(input.point(), input.float(), limit);
+        }
+        29 => {
+            let (position, k) = (input.point(), input.count());
+            let max_distance = input.chance(50).then(|| input.float());
+            let _ = grid.py_nearest(position, k, max_distance, metric, None, None);
+        }
+        30 => {
+            let cell = (input.float() as isize, input.float() as isize);
+            let _ = grid.cell_contents(cell.0, cell.1);
+            let _ = grid.cell_neighbors(cell.0, cell.1);
+            let _ = grid.cell_rect(cell.0, cell.1);
+            let _ = grid.world_to_cell(input.point());
+        }
+        31 => {
+            let _ = grid.resize(input.float(), input.cell_size());
+        }
+        32 => {
+            let _ = grid.set_wrap(input.chance(50));
+        }
+        33 => {
+            let _ = grid.compact();
+        }
+        34 => {
+            let restored = grid
+                .to_json(false)
+                .and_then(|json| GridPhysics::from_json(&json));
+            if let Ok(restored) = restored {
+                *grid = restored;
+            }
+        }
+        35 => {
+            let _ = grid.time_of_impact(entity, input.entity(), None, Some(input.float()));
+        }
+        36 => {
+            let (radius, resolution) = if input.chance(20) {
+                // Masks far past the tile cap, and an infinite radius of whole tiles
+                let pairs = [
+                    (1e5, 1e-3),
+                    (f32::MAX, f32::MIN_POSITIVE),
+                    (f32::INFINITY, 5.0),
+                ];
+                pairs[input.index(pairs.len())]
+            } else {
+                (input.float(), input.cell_size())
+            };
+            let _ = grid.shadowcast(input.point(), radius, resolution);
+        }
+        37 => {
+            let entities: Vec<usize> = (0..input.count().min(4)).map(|_| input.entity()).collect();
+            let (num_nearest, num_rays, max_distance) =
+                (input.count(), input.count(), input.float());
+            let _ = threads::install(input.threads(), || {
+                grid.observations(&entities, num_nearest, num_rays, max_distance)
+            });
+        }
+        38 => {
+            let mut data = delta::encode(grid, input.count() as u64).unwrap_or_default();
+            for _ in 0..input.count().min(4) {
+                match input.index(3) {
+                    0 if !data.is_empty() => {
+                        let at = input.index(data.len());
+                        data[at] = input.index(256) as u8;
+                    }
+                    1 => data.truncate(input.index(data.len() + 1)),
+                    _ => data.push(input.index(256) as u8),
+                }
+            }
+            let _ = grid.apply_delta(&data);
+        }
+        39 => {
+            let cell_size = input.chance(50).then(|| input.cell_size());
+            let _ = GridPhysics::from_tiled(&tiled_map(input), "collisions", cell_size);
+        }
+        40 => {
+            let name = ["first", "second"][input.index(2)].to_string();
+            let _ = grid.load_static_layer(name, &layer_bodies(input));
+        }
+        41 => {
+            let _ = grid.swap_static_layer(["first", "second", "third"][input.index(3)]);
+        }
+        42 => {
+            let rows = input.count().min(6);
+            let positions = input.array(rows);
+            let radii_len = if input.chance(90) {
+                rows
+            } else {
+                input.index(6)
+            };
+            let radii = input.values(radii_len);
+            let is_static = input.chance(50);
+            let _ = grid.add_circles_array(py, entity, positions, radii, is_static, kind);
+        }
+        43 => {
+            let rows = input.count().min(6);
+            let (origins, directions) = (input.array(rows), input.array(rows));
+            let max_distances = input.chance(50).then(|| input.values(rows));
+            let _ = grid.raycast_many(
+                origins.view(),
+                directions.view(),
+                max_distances.as_ref().map(|distances| distances.view()),
+                input.threads(),
+            );
+        }
+        44 => {
+            let entities_a: Vec<usize> =
+                (0..input.count().min(4)).map(|_| input.entity()).collect();
+            let entities_b: Vec<usize> =
+                (0..input.count().min(4)).map(|_| input.entity()).collect();
+            let max_distance = input.chance(50).then(|| input.float());
+            let _ = grid.min_distances(&entities_a, &entities_b, max_distance);
+        }
+        45 => {
+            let shape = (input.count().min(8), input.index(6));
+            let _ = grid.write_collisions(Array2::zeros(shape).view_mut());
+        }
+        _ => {
+            let extension = ["svg", "png", "bmp"][input.index(3)];
+            let path =
+                std::env::temp_dir().join(format!("radyx-fuzz-{}.{extension}", std::process::id()));
+            let path = path.to_str().expect("temporary paths are unicode");
+            // Small, since large pictures are allowed but slow to draw
+            let scale = if input.chance(20) {
+                EDGE_VALUES[input.index(EDGE_VALUES.len())]
+            } else {
+                input.0.next() * 2.0
+            };
+            let _ = grid.export_debug_image(path, scale);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Runs one seed, returning the panic message if a call panicked.
+fn run(seed: u64) -> Result<(), String> {
+    // Spread over the state, since xorshift starts out near zero from small seeds
+    let mut input = Input(Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1));
+    let mut grid = world(&mut input);
+    Python::with_gil(|py| {
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            for _ in 0..CALLS {
+                call(py, &mut grid, &mut input);
+            }
+        }))
+        .map_err(|payload| {
+            payload
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|text| text.to_string()))
+                .unwrap_or_default()
+        })
+    })
+}
+
+fn env_number(name: &str) -> Option<u64> {
+    std::env::var(name).ok()?.parse().ok()
+}
+
+#[test]
+fn check_no_call_panics() {
+    pyo3::prepare_freethreaded_python();
+    let seeds = match env_number("RADYX_FUZZ_SEED") {
+        Some(seed) => seed..seed + 1,
+        None => 0..env_number("RADYX_FUZZ_SEEDS").unwrap_or(16),
+    };
+    let failures: Vec<String> = seeds
+        .filter_map(|seed| {
+            run(seed)
+                .err()
+                .map(|message| format!("seed {seed}: {message}"))
+        })
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,102 @@
+//! Generational entity handles, so a recycled index can't be mistaken for the entity that
+//! held it before.
+
+use pyo3::{exceptions::PyKeyError, prelude::*};
+use std::collections::HashMap;
+
+/// An entity index with the generation it was created in. Pass `index` as the entity index
+/// of add and query calls.
+#[pyclass(module = "radyx", get_all)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EntityHandle {
+    pub index: usize,
+    pub generation: u32,
+}
+
+#[pymethods]
+impl EntityHandle {
+    #[new]
+    pub fn new(index: usize, generation: u32) -> Self {
+        Self { index, generation }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Slot {
+    generation: u32,
+    alive: bool,
+}
+
+/// Hands out entity indices, reusing destroyed ones with a bumped generation.
+#[derive(Clone, Default)]
+pub struct EntityAllocator {
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+}
+
+impl EntityAllocator {
+    /// A handle to an index that isn't alive and for which `in_use` is false, so indices
+    /// given bodies without a handle are skipped. Skipped free indices stay free, as their
+    /// bodies may only wait on a deferred removal.
+    pub fn create(&mut self, in_use: impl Fn(usize) -> bool) -> EntityHandle {
+        let mut skipped = Vec::new();
+        let index = loop {
+            let index = self.free.pop().unwrap_or_else(|| {
+                self.slots.push(Slot::default());
+                self.slots.len() - 1
+            });
+            if !in_use(index) {
+                break index;
+            }
+            if !self.slots[index].alive {
+                skipped.push(index);
+            }
+        };
+        self.free.extend(skipped.into_iter().rev());
+        let slot = &mut self.slots[index];
+        slot.alive = true;
+        EntityHandle::new(index, slot.generation)
+    }
+
+    pub fn is_alive(&self, handle: EntityHandle) -> bool {
+        self.slots
+            .get(handle.index)
+            .is_some_and(|slot| slot.alive && slot.generation == handle.generation)
+    }
+
+    pub fn destroy(&mut self, handle: EntityHandle) -> PyResult<()> {
+        if !self.is_alive(handle) {
+            return Err(PyKeyError::new_err(format!(
+                "entity {} of generation {} is not alive",
+                handle.index, handle.generation
+            )));
+        }
+        let slot = &mut self.slots[handle.index];
+        slot.alive = false;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        Ok(())
+    }
+
+    /// Frees every index, bumping the generation of those alive so their handles go stale.
+    pub fn release_all(&mut self) {
+        for slot in self.slots.iter_mut().filter(|slot| slot.alive) {
+            slot.alive = false;
+            slot.generation = slot.generation.wrapping_add(1);
+        }
+        self.free = (0..self.slots.len()).rev().collect();
+    }
+
+    /// Moves slots to the indices `map` gives them after compaction, keeping generations.
+    /// Slots without a new index are dropped along with the free list.
+    pub fn remap(&mut self, map: &HashMap<usize, usize>) {
+        let mut slots = vec![Slot::default(); map.len()];
+        for (old, new) in map {
+            if let Some(slot) = self.slots.get(*old) {
+                slots[*new] = *slot;
+            }
+        }
+        self.slots = slots;
+        self.free.clear();
+    }
+}
@@ -0,0 +1,72 @@
+//! Pointy-top hexagonal cells. Cells are addressed by axial `(q, r)` coordinates and stored by
+//! their "odd-r" offset `(column, row)` so the grid stays a dense square array. A cell's
+//! flat-to-flat width is the grid's `cell_size`, and cell `(0, 0)` is centered on the origin.
+
+const SQRT_3: f32 = 1.732_050_8;
+
+/// Axial offsets of the six cells sharing an edge with a cell.
+pub const NEIGHBORS: [(isize, isize); 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+
+pub fn circumradius(cell_size: f32) -> f32 {
+    cell_size / SQRT_3
+}
+
+pub fn row_height(cell_size: f32) -> f32 {
+    1.5 * circumradius(cell_size)
+}
+
+/// Axial coordinates of the cell containing `(x, y)`.
+pub fn axial_of(x: f32, y: f32, cell_size: f32) -> (isize, isize) {
+    let radius = circumradius(cell_size);
+    let q = (SQRT_3 / 3.0 * x - y / 3.0) / radius;
+    let r = (2.0 / 3.0 * y) / radius;
+
+    // Round in cube coordinates, fixing up the component with the largest rounding error
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+    (rq as isize, rr as isize)
+}
+
+/// Center of the cell at axial `(q, r)`.
+pub fn center_of(q: isize, r: isize, cell_size: f32) -> (f32, f32) {
+    (
+        cell_size * (q as f32 + r as f32 / 2.0),
+        row_height(cell_size) * r as f32,
+    )
+}
+
+pub fn offset_of_axial(q: isize, r: isize) -> (isize, isize) {
+    (q.saturating_add((r - (r & 1)) / 2), r)
+}
+
+pub fn axial_of_offset(column: isize, row: isize) -> (isize, isize) {
+    (column.saturating_sub((row - (row & 1)) / 2), row)
+}
+
+/// Rows, and column ranges for even and odd rows, of every cell whose bounding rectangle
+/// overlaps `bounds`.
+pub fn span(
+    bounds: (f32, f32, f32, f32),
+    cell_size: f32,
+) -> ((isize, isize), (isize, isize), (isize, isize)) {
+    let (min_x, max_x, min_y, max_y) = bounds;
+    let radius = circumradius(cell_size);
+    let height = row_height(cell_size);
+    let rows = (
+        ((min_y - radius) / height).ceil() as isize,
+        ((max_y + radius) / height).floor() as isize,
+    );
+    let columns = |shift: f32| {
+        (
+            ((min_x / cell_size) - 0.5 - shift).ceil() as isize,
+            ((max_x / cell_size) + 0.5 - shift).floor() as isize,
+        )
+    };
+    (rows, columns(0.0), columns(0.5))
+}
@@ -0,0 +1,83 @@
+//! Ring buffer of the contacts of recent ticks, for lag compensated hit registration.
+
+use crate::events::ContactKey;
+use std::collections::{HashSet, VecDeque};
+
+#[derive(Clone, Default)]
+pub struct CollisionHistory {
+    capacity: usize,
+    frames: VecDeque<(u64, HashSet<ContactKey>)>,
+}
+
+impl CollisionHistory {
+    /// Keeps the last `capacity` ticks, dropping the oldest ones beyond it.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.frames.len() > capacity {
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn record(&mut self, tick: u64, contacts: &HashSet<ContactKey>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((tick, contacts.clone()));
+    }
+
+    pub fn at(&self, tick: u64) -> Option<&HashSet<ContactKey>> {
+        self.frames
+            .iter()
+            .find(|(frame_tick, _)| *frame_tick == tick)
+            .map(|(_, contacts)| contacts)
+    }
+
+    /// Whether entities `a` and `b` touched in a recorded tick not before `since`.
+    pub fn was_colliding(&self, a: usize, b: usize, since: u64) -> bool {
+        self.frames
+            .iter()
+            .rev()
+            .take_while(|(tick, _)| *tick >= since)
+            .any(|(_, contacts)| {
+                contacts
+                    .iter()
+                    .any(|key| (key.0, key.1) == (a, b) || (key.0, key.1) == (b, a))
+            })
+    }
+
+    /// Forgets ticks after `tick`, which are simulated again after a rollback.
+    pub fn truncate_after(&mut self, tick: u64) {
+        while self
+            .frames
+            .back()
+            .is_some_and(|(frame_tick, _)| *frame_tick > tick)
+        {
+            self.frames.pop_back();
+        }
+    }
+
+    /// Approximate heap bytes of the recorded ticks.
+    pub fn bytes(&self) -> usize {
+        let frame = std::mem::size_of::<(u64, HashSet<ContactKey>)>();
+        self.frames.capacity() * frame
+            + self
+                .frames
+                .iter()
+                .map(|(_, contacts)| contacts.capacity() * (std::mem::size_of::<ContactKey>() + 1))
+                .sum::<usize>()
+    }
+
+    /// Rewrites the recorded contacts, dropping those `map` returns `None` for.
+    pub fn remap(&mut self, map: impl Fn(&ContactKey) -> Option<ContactKey>) {
+        for (_, contacts) in self.frames.iter_mut() {
+            *contacts = contacts.iter().filter_map(&map).collect();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
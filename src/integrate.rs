@@ -0,0 +1,130 @@
+//! Moving entities by their stored velocities during `step`, split into substeps when an
+//! entity would otherwise move further than its maximum displacement in one go.
+
+use crate::{GridPhysics, Vector2};
+
+impl GridPhysics {
+    /// Largest distance the entity may move in one substep, if limited.
+    fn displacement_limit(&self, entity_index: usize) -> Option<f32> {
+        self.max_displacements
+            .get(&entity_index)
+            .copied()
+            .or(self.max_displacement)
+    }
+
+    /// Adds gravity to the velocity of every active dynamic entity and applied forces to
+    /// theirs, then applies drag. Sleeping entities are skipped unless a force was applied
+    /// to them.
+    pub(crate) fn apply_forces(&mut self, dt: f32) {
+        let gravity = Vector2::new(self.gravity.0 * dt, self.gravity.1 * dt);
+        let pulled = gravity.x != 0.0 || gravity.y != 0.0;
+        if !pulled && self.drags.is_empty() && self.forces.is_empty() {
+            return;
+        }
+        let mut entities: Vec<usize> = if pulled {
+            self.dynamic_bodies.keys().copied().collect()
+        } else {
+            self.drags
+                .keys()
+                .chain(self.forces.keys())
+                .copied()
+                .collect()
+        };
+        entities.sort_unstable();
+        entities.dedup();
+        for entity_index in entities {
+            let Some(bounds) = self.entity_bounds.get(&entity_index).copied() else {
+                continue;
+            };
+            if !self.is_active(entity_index)
+                || (self.asleep.contains(&entity_index) && !self.forces.contains_key(&entity_index))
+            {
+                continue;
+            }
+            let before = self
+                .velocities
+                .get(&entity_index)
+                .copied()
+                .unwrap_or(Vector2::new(0.0, 0.0));
+            let force = self
+                .forces
+                .get(&entity_index)
+                .copied()
+                .unwrap_or(Vector2::new(0.0, 0.0));
+            let mass = self.mass_of(entity_index);
+            let mut velocity = Vector2::new(
+                before.x + gravity.x + force.x * dt / mass,
+                before.y + gravity.y + force.y * dt / mass,
+            );
+            if let Some(drag) = self.drags.get(&entity_index) {
+                let decay = (-drag * dt).exp();
+                velocity = Vector2::new(velocity.x * decay, velocity.y * decay);
+            }
+            if velocity != before && velocity.x.is_finite() && velocity.y.is_finite() {
+                self.store_velocity(entity_index, bounds, velocity);
+            }
+        }
+    }
+
+    /// Moves every active entity with a finite velocity `dt` seconds ahead. When a move is
+    /// longer than the entity's limit the step is split into equal substeps, up to
+    /// `max_substeps`, and contacts of every substep are kept for the tick's events. Moves
+    /// still too long after that are clamped to the limit in each substep.
+    pub(crate) fn integrate_velocities(&mut self, dt: f32) -> usize {
+        let mut moving: Vec<(usize, Vector2)> = self
+            .velocities
+            .iter()
+            .filter(|(entity_index, velocity)| {
+                self.is_active(**entity_index)
+                    && self.entity_bounds.contains_key(entity_index)
+                    && velocity.x.is_finite()
+                    && velocity.y.is_finite()
+            })
+            .map(|(entity_index, velocity)| {
+                (
+                    *entity_index,
+                    Vector2::new(velocity.x * dt, velocity.y * dt),
+                )
+            })
+            .filter(|(_, step)| step.x.is_finite() && step.y.is_finite())
+            .collect();
+        moving.sort_unstable_by_key(|(entity_index, _)| *entity_index);
+
+        let needed = moving
+            .iter()
+            .filter_map(|(entity_index, step)| {
+                let limit = self.displacement_limit(*entity_index)?;
+                Some((step.x.hypot(step.y) / limit).ceil())
+            })
+            .fold(1.0_f32, f32::max);
+        let substeps = (needed as usize).clamp(1, self.max_substeps.max(1));
+
+        for substep in 0..substeps {
+            for (entity_index, step) in &moving {
+                let mut delta = Vector2::new(step.x / substeps as f32, step.y / substeps as f32);
+                if let Some(limit) = self.displacement_limit(*entity_index) {
+                    let length = delta.x.hypot(delta.y);
+                    if length > limit {
+                        let scale = limit / length;
+                        delta = Vector2::new(delta.x * scale, delta.y * scale);
+                    }
+                }
+                let mut transform = self
+                    .transforms
+                    .get(entity_index)
+                    .copied()
+                    .unwrap_or_default();
+                transform.position.x += delta.x;
+                transform.position.y += delta.y;
+                // A move overflowing f32 is refused, leaving the entity where it was
+                let _ = self.place_entity(*entity_index, transform);
+            }
+            if substep + 1 < substeps {
+                let contacts = self.all_collisions();
+                self.substep_contacts
+                    .extend(contacts.iter().map(|collision| collision.key()));
+            }
+        }
+        substeps
+    }
+}
@@ -0,0 +1,58 @@
+//! Area of interest subscriptions, reporting entities entering and leaving an area between
+//! polls.
+
+use crate::{Containment, GridPhysics, Metric, Vector2};
+use pyo3::{exceptions::PyKeyError, PyResult};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone)]
+pub struct Subscription {
+    pub center: Vector2,
+    pub radius: f32,
+    /// Entities inside the area at the last poll.
+    members: HashSet<usize>,
+}
+
+impl Subscription {
+    pub fn new(center: Vector2, radius: f32) -> Self {
+        Self {
+            center,
+            radius,
+            members: HashSet::new(),
+        }
+    }
+
+    /// Renames members after entity indices change, dropping those without a new index.
+    pub fn remap(&mut self, map: &HashMap<usize, usize>) {
+        self.members = self
+            .members
+            .iter()
+            .filter_map(|entity| map.get(entity).copied())
+            .collect();
+    }
+}
+
+impl GridPhysics {
+    /// `(entered, left)` entities of a subscription since its last poll, both sorted.
+    pub(crate) fn poll_interest(&mut self, id: usize) -> PyResult<(Vec<usize>, Vec<usize>)> {
+        let Some(subscription) = self.subscriptions.get(&id) else {
+            return Err(PyKeyError::new_err(format!("unknown subscription {id}")));
+        };
+        let current = self.get_collisions_within_area(
+            subscription.center,
+            subscription.radius,
+            Some(Metric::Euclidean),
+            None,
+            false,
+            Containment::Intersects,
+        );
+
+        let subscription = self.subscriptions.get_mut(&id).expect("looked up above");
+        let mut entered: Vec<usize> = current.difference(&subscription.members).copied().collect();
+        let mut left: Vec<usize> = subscription.members.difference(&current).copied().collect();
+        entered.sort_unstable();
+        left.sort_unstable();
+        subscription.members = current;
+        Ok((entered, left))
+    }
+}
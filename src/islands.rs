@@ -0,0 +1,69 @@
+//! Islands of dynamic entities that cannot influence each other within a frame.
+
+use crate::GridPhysics;
+use std::collections::HashMap;
+
+/// Disjoint sets over entity slots, with path halving.
+struct UnionFind {
+    parents: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, mut slot: usize) -> usize {
+        while self.parents[slot] != slot {
+            self.parents[slot] = self.parents[self.parents[slot]];
+            slot = self.parents[slot];
+        }
+        slot
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        // Keep the lower slot as root so islands come out in entity order
+        self.parents[a.max(b)] = a.min(b);
+    }
+}
+
+impl GridPhysics {
+    /// Dynamic entities joined whenever their bodies share a cell, which covers every contact
+    /// they can make. Static bodies never move, so they don't join islands. Each island is
+    /// sorted, and islands are ordered by their lowest entity.
+    pub(crate) fn islands(&self) -> Vec<Vec<usize>> {
+        let mut entities: Vec<usize> = self.dynamic_bodies.keys().copied().collect();
+        entities.sort_unstable();
+        let slots: HashMap<usize, usize> = entities
+            .iter()
+            .enumerate()
+            .map(|(slot, entity)| (*entity, slot))
+            .collect();
+
+        let mut sets = UnionFind::new(entities.len());
+        for cell in &self.grid {
+            let mut dynamic = cell.iter().filter(|body| !body.is_static());
+            if let Some(first) = dynamic.next() {
+                let first = slots[&first.entity_index()];
+                for body in dynamic {
+                    sets.union(first, slots[&body.entity_index()]);
+                }
+            }
+        }
+
+        let mut islands: Vec<Vec<usize>> = Vec::new();
+        let mut island_of_root: HashMap<usize, usize> = HashMap::new();
+        for (slot, entity) in entities.into_iter().enumerate() {
+            let root = sets.find(slot);
+            let island = *island_of_root.entry(root).or_insert_with(|| {
+                islands.push(Vec::new());
+                islands.len() - 1
+            });
+            islands[island].push(entity);
+        }
+        islands
+    }
+}
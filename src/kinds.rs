@@ -0,0 +1,67 @@
+use pyo3::{exceptions::PyKeyError, prelude::*};
+use std::collections::HashMap;
+
+/// A body kind given from Python either as its interned integer or by a registered name.
+#[derive(Clone, Debug, FromPyObject)]
+pub enum KindArg {
+    Id(usize),
+    Name(String),
+}
+
+impl Default for KindArg {
+    fn default() -> Self {
+        KindArg::Id(0)
+    }
+}
+
+/// Interns kind names to small integers. Kind 0 is the unnamed default kind, so registered
+/// kinds start at 1.
+#[derive(Clone, Default)]
+pub struct KindRegistry {
+    ids: HashMap<String, usize>,
+}
+
+impl KindRegistry {
+    pub fn register(&mut self, name: &str) -> usize {
+        let next = self.ids.len() + 1;
+        *self.ids.entry(name.to_string()).or_insert(next)
+    }
+
+    /// Resolves a kind for insertion, registering names seen for the first time.
+    pub fn intern(&mut self, kind: KindArg) -> usize {
+        match kind {
+            KindArg::Id(id) => id,
+            KindArg::Name(name) => self.register(&name),
+        }
+    }
+
+    /// Resolves a kind for a query, where an unregistered name is an error.
+    pub fn resolve(&self, kind: &KindArg) -> PyResult<usize> {
+        match kind {
+            KindArg::Id(id) => Ok(*id),
+            KindArg::Name(name) => self
+                .ids
+                .get(name)
+                .copied()
+                .ok_or_else(|| PyKeyError::new_err(format!("no kind named {name:?}"))),
+        }
+    }
+
+    /// Registered `(name, id)` pairs in id order.
+    pub fn entries(&self) -> Vec<(&str, usize)> {
+        let mut entries: Vec<(&str, usize)> = self
+            .ids
+            .iter()
+            .map(|(name, id)| (name.as_str(), *id))
+            .collect();
+        entries.sort_unstable_by_key(|entry| entry.1);
+        entries
+    }
+
+    pub fn name_of(&self, id: usize) -> Option<&str> {
+        self.ids
+            .iter()
+            .find(|(_, kind)| **kind == id)
+            .map(|(name, _)| name.as_str())
+    }
+}
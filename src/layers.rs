@@ -0,0 +1,303 @@
+//! Static geometry prebuilt per level and swapped into the grid whole, so changing levels
+//! doesn't remove and re-add every wall.
+
+use crate::{
+    events::ContactKey, insert_into_cell, union_bounds, Body, Cell, GridPhysics, Layout, Placed,
+};
+use pyo3::{
+    exceptions::{PyKeyError, PyValueError},
+    PyResult,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Layout, cell size bits, grid size and wrapping of the grid cells were binned for.
+type Geometry = (Layout, u32, usize, bool);
+
+/// Static bodies with their entity bounds and cells holding only them, which the grid starts
+/// from when the layer is swapped in.
+#[derive(Clone)]
+pub struct StaticLayer {
+    bodies: HashMap<usize, Vec<Placed>>,
+    bounds: HashMap<usize, (f32, f32, f32, f32)>,
+    cells: Vec<Cell>,
+    binned_for: Geometry,
+}
+
+impl StaticLayer {
+    pub(crate) fn entities(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bodies.keys().copied()
+    }
+
+    /// Renumbers the layer's entities after compaction. `map` covers all of them.
+    pub(crate) fn remap(&mut self, map: &HashMap<usize, usize>) {
+        self.bodies = self
+            .bodies
+            .drain()
+            .map(|(entity_index, mut bodies)| {
+                for body in &mut bodies {
+                    body.body.entity_index = map[&entity_index] as u32;
+                }
+                (map[&entity_index], bodies)
+            })
+            .collect();
+        self.bounds = self
+            .bounds
+            .drain()
+            .map(|(entity_index, bounds)| (map[&entity_index], bounds))
+            .collect();
+        for cell in self.cells.iter_mut().filter(|cell| !cell.is_empty()) {
+            Arc::make_mut(cell).update(|body| {
+                body.entity_index = map[&body.entity_index()] as u32;
+            });
+        }
+    }
+}
+
+impl GridPhysics {
+    fn geometry(&self) -> Geometry {
+        (
+            self.layout,
+            self.cell_size.to_bits(),
+            self.grid_size,
+            self.wrap,
+        )
+    }
+
+    /// Cells shaped like the grid's, holding `bodies` in the order adding them by entity
+    /// would leave.
+    fn layer_cells(&self, bodies: &HashMap<usize, Vec<Placed>>) -> Vec<Cell> {
+        let mut cells = vec![Cell::default(); self.grid.len()];
+        let mut entities: Vec<&usize> = bodies.keys().collect();
+        entities.sort_unstable();
+        for body in entities.into_iter().flat_map(|entity| &bodies[entity]) {
+            for (index, copy) in self.binned_copies(body) {
+                insert_into_cell(Arc::make_mut(&mut cells[index]), copy);
+            }
+        }
+        cells
+    }
+
+    /// Builds a layer from static bodies, replacing any layer with the same name. Bodies
+    /// failing validation are skipped and reported like when adding them.
+    pub(crate) fn load_layer(&mut self, name: String, bodies: Vec<Placed>) -> PyResult<()> {
+        if let Some(body) = bodies.iter().find(|body| !body.is_static()) {
+            return Err(PyValueError::new_err(format!(
+                "static layers only hold static bodies, entity {} body {} is dynamic",
+                body.entity_index(),
+                body.body_index()
+            )));
+        }
+        for body in &bodies {
+            self.namespaces.check(body.entity_index())?;
+        }
+        let mut layer_bodies: HashMap<usize, Vec<Placed>> = HashMap::new();
+        let mut bounds = HashMap::new();
+        for body in bodies {
+            if self.screen(&body) {
+                continue;
+            }
+            bounds
+                .entry(body.entity_index())
+                .and_modify(|entity| *entity = union_bounds(*entity, body.get_bounds()))
+                .or_insert(body.get_bounds());
+            layer_bodies
+                .entry(body.entity_index())
+                .or_default()
+                .push(body);
+        }
+        self.report_skipped()?;
+        let layer = StaticLayer {
+            cells: self.layer_cells(&layer_bodies),
+            bodies: layer_bodies,
+            bounds,
+            binned_for: self.geometry(),
+        };
+        self.static_layers.insert(name, layer);
+        Ok(())
+    }
+
+    /// Replaces the static bodies in the grid with those of a layer. The grid takes the
+    /// layer's cells as they are, sharing them until written, and only the dynamic bodies
+    /// are binned again. Layers built before a `resize` or `set_wrap` are binned once more.
+    /// Exclusions baked for the outgoing layout are cleared, and contacts of its entities
+    /// forgotten, as levels often reuse entity indices.
+    pub(crate) fn swap_layer(&mut self, name: &str) -> PyResult<()> {
+        if self.deferred {
+            return Err(PyValueError::new_err(
+                "can't swap static layers while mutations are deferred",
+            ));
+        }
+        let geometry = self.geometry();
+        let layer = self
+            .static_layers
+            .get(name)
+            .ok_or_else(|| PyKeyError::new_err(format!("no static layer named {name:?}")))?;
+        if let Some(entity_index) = layer
+            .bodies
+            .keys()
+            .find(|entity_index| self.dynamic_bodies.contains_key(entity_index))
+        {
+            return Err(PyValueError::new_err(format!(
+                "static layer {name:?} reuses dynamic entity {entity_index}"
+            )));
+        }
+        if layer.binned_for != geometry {
+            let cells = self.layer_cells(&layer.bodies);
+            let layer = self.static_layers.get_mut(name).expect("layer exists");
+            layer.cells = cells;
+            layer.binned_for = geometry;
+        }
+        let layer = &self.static_layers[name];
+
+        let outgoing: HashSet<usize> = self.static_bodies.keys().copied().collect();
+        let incoming = |key: &ContactKey| !outgoing.contains(&key.0) && !outgoing.contains(&key.1);
+        self.contacts.retain(incoming);
+        self.contact_ids.retain(|key, _| incoming(key));
+        self.static_exclusions.clear();
+        for (entity_index, _) in std::mem::take(&mut self.static_bodies) {
+            self.changed.insert(entity_index, self.tick);
+            match self.dynamic_bodies.get(&entity_index) {
+                Some(bodies) => {
+                    let bounds = bodies
+                        .iter()
+                        .map(|body| body.get_bounds())
+                        .reduce(union_bounds)
+                        .expect("entities have bodies");
+                    self.entity_bounds.insert(entity_index, bounds);
+                }
+                None => {
+                    self.entity_bounds.remove(&entity_index);
+                    self.transforms.remove(&entity_index);
+                }
+            }
+        }
+        self.grid.clone_from(&layer.cells);
+        self.static_bodies.clone_from(&layer.bodies);
+        self.entity_bounds.extend(
+            layer
+                .bounds
+                .iter()
+                .map(|(entity, bounds)| (*entity, *bounds)),
+        );
+        for (entity_index, bodies) in &layer.bodies {
+            self.changed.insert(*entity_index, self.tick);
+            for body in bodies {
+                self.max_radius = self.max_radius.max(body.radius);
+            }
+        }
+        let dynamic: Vec<Body> = self
+            .dynamic_bodies
+            .values()
+            .flatten()
+            .map(|placed| placed.body)
+            .collect();
+        for body in dynamic {
+            self.insert_into_cells(body);
+        }
+        self.static_layer = Some(name.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vector2;
+
+    /// A static box of the given entity as a JSON body record.
+    fn wall(entity: usize, x: f32) -> String {
+        format!(
+            r#"{{"entity": {entity}, "body": 0, "static": true, "position": [{x}, 50.0],
+                "shape": {{"type": "box", "half_x": 2.0, "half_y": 20.0}}}}"#
+        )
+    }
+
+    fn hits(grid: &GridPhysics) -> Vec<usize> {
+        let mut entities: Vec<usize> = grid
+            .all_collisions()
+            .iter()
+            .map(|collision| collision.other_entity_index)
+            .collect();
+        entities.sort_unstable();
+        entities
+    }
+
+    #[test]
+    fn check_static_layer_swaps() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_static_circle(9, Vector2::new(30.0, 50.0), 3.0, 0);
+        grid.load_static_layer("first".into(), &format!("[{}]", wall(1, 30.0)))
+            .unwrap();
+        let second = format!("[{}, {}]", wall(2, 70.0), wall(3, 33.0));
+        grid.load_static_layer("second".into(), &second).unwrap();
+        assert_eq!(grid.static_layer_names(), vec!["first", "second"]);
+        assert_eq!(hits(&grid), Vec::<usize>::new());
+
+        grid.add_dynamic_circle(0, Vector2::new(32.0, 50.0), 1.5, 0);
+        assert_eq!(hits(&grid), vec![9]);
+        grid.swap_static_layer("first").unwrap();
+        // Static bodies added before are dropped with the level they belonged to
+        assert_eq!(hits(&grid), vec![1]);
+        assert!(!grid.entity_bounds.contains_key(&9));
+        grid.swap_static_layer("second").unwrap();
+        assert_eq!(hits(&grid), vec![3]);
+        assert_eq!(grid.static_layer.as_deref(), Some("second"));
+
+        // Cells binned before a resize are binned again for the new one
+        grid.resize(100.0, 5.0).unwrap();
+        grid.swap_static_layer("first").unwrap();
+        assert_eq!(hits(&grid), vec![1]);
+        assert_eq!(grid.find_body(0, 0).unwrap().pos, Vector2::new(32.0, 50.0));
+
+        assert!(grid.swap_static_layer("third").is_err());
+        let reused = format!("[{}]", wall(0, 80.0));
+        grid.load_static_layer("reused".into(), &reused).unwrap();
+        assert!(grid.swap_static_layer("reused").is_err());
+        assert_eq!(grid.static_layer.as_deref(), Some("first"));
+    }
+
+    #[test]
+    fn check_swap_forgets_layout_pairs() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let first = format!("[{}, {}]", wall(1, 30.0), wall(2, 31.0));
+        grid.load_static_layer("first".into(), &first).unwrap();
+        let second = format!("[{}, {}]", wall(1, 70.0), wall(2, 71.0));
+        grid.load_static_layer("second".into(), &second).unwrap();
+        grid.swap_static_layer("first").unwrap();
+        assert_eq!(grid.bake_static_exclusions(), (1, 0));
+        assert!(grid.get_static_overlaps().is_empty());
+        grid.add_dynamic_circle(0, Vector2::new(28.5, 50.0), 1.0, 0);
+        grid.step(1.0 / 60.0);
+        assert!(!grid.contacts.is_empty());
+
+        // The same indices overlapping in the next level are reported again
+        grid.swap_static_layer("second").unwrap();
+        assert_eq!(grid.get_static_overlaps().len(), 1);
+        assert!(grid.contacts.is_empty());
+        assert!(grid.contact_ids.is_empty());
+    }
+
+    #[test]
+    fn check_compacted_layers() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(5, Vector2::new(32.0, 50.0), 1.5, 0);
+        grid.load_static_layer("first".into(), &format!("[{}]", wall(1, 30.0)))
+            .unwrap();
+        let second = format!("[{}, {}]", wall(2, 70.0), wall(3, 33.0));
+        grid.load_static_layer("second".into(), &second).unwrap();
+        grid.swap_static_layer("first").unwrap();
+
+        // Entities of stored layers are numbered along with those in the grid
+        let map = grid.compact();
+        assert_eq!(map, HashMap::from([(1, 0), (2, 1), (3, 2), (5, 3)]));
+        assert_eq!(hits(&grid), vec![0]);
+        grid.swap_static_layer("second").unwrap();
+        assert_eq!(hits(&grid), vec![2]);
+        grid.swap_static_layer("first").unwrap();
+        assert_eq!(hits(&grid), vec![0]);
+        assert!(grid.dynamic_bodies.contains_key(&3));
+    }
+}
@@ -1,10 +1,105 @@
-use pyo3::prelude::*;
+// The `#[pymethods]` expansion of pyo3 0.19 trips this lint on newer compilers.
+#![allow(non_local_definitions)]
+
+use numpy::{
+    ndarray::{Array1, Array2, ArrayViewMut2},
+    IntoPyArray, PyArray1, PyArray2,
+};
+use pyo3::{
+    exceptions::{PyIndexError, PyKeyError, PyMemoryError, PyTypeError, PyValueError},
+    prelude::*,
+    types::{IntoPyDict, PyBytes},
+};
 use std::{
     collections::{HashMap, HashSet},
-    hash::{self, Hash},
+    hash,
+    ops::{ControlFlow, Deref},
+    sync::Arc,
 };
+use threads::prelude::*;
+
+mod benchmark;
+mod bulk;
+mod callbacks;
+mod cell;
+mod commit;
+mod compact;
+mod containment;
+mod cooldown;
+mod debug_image;
+mod debug_server;
+mod dedup;
+mod delta;
+mod diff;
+mod dlpack;
+mod events;
+mod explosion;
+#[cfg(test)]
+mod fuzz;
+mod handles;
+mod hex;
+mod history;
+mod integrate;
+mod interest;
+mod islands;
+mod kinds;
+mod layers;
+mod light;
+mod logging;
+mod memory;
+mod metric;
+mod namespaces;
+mod optimize;
+mod polygon;
+mod query;
+mod ray;
+mod raybatch;
+#[cfg(test)]
+mod reference;
+mod results;
+mod rollback;
+mod scene;
+mod shape;
+mod sleep;
+mod solver;
+mod targets;
+mod threads;
+mod tiled;
+mod toi;
+mod validate;
+mod world;
+
+pub use containment::Containment;
+pub use diff::WorldDiff;
+pub use dlpack::Positions;
+pub use events::{Event, EventKind};
+pub use handles::EntityHandle;
+pub use kinds::KindArg;
+pub use memory::MemoryUsage;
+pub use metric::Metric;
+pub use query::Query;
+pub use results::ResultType;
+pub use world::World;
+
+use callbacks::CollisionHandler;
+use commit::{Mutation, Projection};
+use debug_server::{DebugServer, DebugSlot};
+use dedup::PairDedup;
+use events::ContactKey;
+use handles::EntityAllocator;
+use history::CollisionHistory;
+use interest::Subscription;
+use kinds::KindRegistry;
+use layers::StaticLayer;
+use logging::{Logging, OversizePolicy, Oversized};
+use namespaces::NamespaceRegistry;
+use rollback::StateSlots;
+use shape::Shape;
+use targets::Targets;
+use validate::Invalid;
+pub use validate::RadyxError;
 
-#[derive(Clone, Copy, FromPyObject)]
+#[derive(Clone, Copy, Debug, PartialEq, FromPyObject)]
 pub struct Vector2 {
     x: f32,
     y: f32,
@@ -16,55 +111,229 @@ impl Vector2 {
     }
 }
 
+/// Placement of an entity. Body offsets are kept in entity-local space and mapped to world
+/// positions by rotating them by `angle` (radians, counter-clockwise) around `position`.
+#[derive(Clone, Copy)]
+struct Transform {
+    position: Vector2,
+    angle: f32,
+}
+
+impl Transform {
+    fn apply(&self, offset: Vector2) -> Vector2 {
+        let (sin, cos) = self.angle.sin_cos();
+        Vector2::new(
+            self.position.x + offset.x * cos - offset.y * sin,
+            self.position.y + offset.x * sin + offset.y * cos,
+        )
+    }
+
+    /// Inverse of `apply`, the entity-local offset of a world position.
+    fn local(&self, pos: Vector2) -> Vector2 {
+        let (sin, cos) = self.angle.sin_cos();
+        let (dx, dy) = (pos.x - self.position.x, pos.y - self.position.y);
+        Vector2::new(dx * cos + dy * sin, dy * cos - dx * sin)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            position: Vector2::new(0.0, 0.0),
+            angle: 0.0,
+        }
+    }
+}
+
+/// The copy of a body binned into every cell it covers. Indices are stored in 32 bits,
+/// checked by `validate::check_indices` where they come in, the shape is packed into two
+/// parameters with its tag and the static flag in one byte, and where the body sits on its
+/// entity lives in `Placed` instead, so copies stay small.
 #[derive(Clone, Copy)]
 pub struct Body {
-    entity_index: usize,
-    body_index: usize,
+    entity_index: u32,
+    body_index: u32,
+    kind: u32,
     pos: Vector2,
     radius: f32,
-    is_static: bool,
+    angle: f32,
+    /// Semi-axes of ellipses, half extents of boxes, the half length of segments and the
+    /// second corner of triangles; see `shape`.
+    params: [f32; 2],
+    flags: u8,
 }
 
 impl Body {
+    fn entity_index(&self) -> usize {
+        self.entity_index as usize
+    }
+
+    fn body_index(&self) -> usize {
+        self.body_index as usize
+    }
+
+    fn kind(&self) -> usize {
+        self.kind as usize
+    }
+
+    fn is_static(&self) -> bool {
+        self.flags & shape::STATIC != 0
+    }
+
     fn new(
         entity_index: usize,
         body_index: usize,
         pos: Vector2,
         radius: f32,
         is_static: bool,
+        kind: usize,
     ) -> Self {
         Self {
-            entity_index,
-            body_index,
+            entity_index: entity_index as u32,
+            body_index: body_index as u32,
+            kind: kind as u32,
             pos,
             radius,
-            is_static,
+            angle: 0.0,
+            params: [0.0; 2],
+            flags: if is_static { shape::STATIC } else { 0 },
         }
     }
 
+    fn ellipse(mut self, radius_x: f32, radius_y: f32, angle: f32) -> Self {
+        self.set_shape(Shape::Ellipse { radius_x, radius_y });
+        self.radius = radius_x.max(radius_y);
+        self.angle = angle;
+        self
+    }
+
+    /// Segment from `a` to `b`, placed at its midpoint.
+    fn segment(mut self, a: Vector2, b: Vector2) -> Self {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let half_length = dx.hypot(dy) / 2.0;
+        self.pos = Vector2::new(a.x + dx / 2.0, a.y + dy / 2.0);
+        self.set_shape(Shape::Segment { half_length });
+        self.radius = half_length;
+        self.angle = dy.atan2(dx);
+        self
+    }
+
+    /// Triangle through `corners`, placed at its centroid.
+    fn triangle(mut self, corners: [Vector2; 3]) -> Self {
+        self.pos = Vector2::new(0.0, 0.0);
+        self.set_shape(Shape::Triangle { corners });
+        self
+    }
+
+    fn oriented_box(mut self, half_x: f32, half_y: f32, angle: f32) -> Self {
+        self.set_shape(Shape::Box { half_x, half_y });
+        self.radius = half_x.hypot(half_y);
+        self.angle = angle;
+        self
+    }
+
     fn collided(&self, other: &Body) -> bool {
         // Static bodies don't collide with anything
-        if self.is_static {
+        if self.is_static() {
             return false;
         }
 
         // Don't collide with self
-        if self.entity_index == other.entity_index {
+        if self.entity_index() == other.entity_index() {
             return false;
         }
 
-        let distance = (self.pos.x - other.pos.x).powi(2) + (self.pos.y - other.pos.y).powi(2);
-        let radius = (self.radius + other.radius).powi(2);
-        distance <= radius
+        self.touches(other)
+    }
+
+    fn touches(&self, other: &Body) -> bool {
+        self.shapes_touch(other)
+    }
+
+    /// Penetration depth of two bodies, negative when they are apart.
+    fn overlap(&self, other: &Body) -> f32 {
+        self.penetration(other)
+    }
+
+    /// Distance from `point` to the body under `metric`: the metric length of the offset to
+    /// its center, less its radius, and zero when `point` is inside it. Shapes other than
+    /// circles are only measured exactly under the euclidean metric.
+    fn metric_distance(&self, point: Vector2, metric: Metric) -> f32 {
+        if self.shape() != Shape::Circle && metric == Metric::Euclidean {
+            return self.surface_distance(point).max(0.0);
+        }
+        (metric.length(self.pos.x - point.x, self.pos.y - point.y) - self.radius).max(0.0)
+    }
+
+    /// Gap between the surfaces of two bodies, zero when they overlap.
+    fn distance(&self, other: &Body) -> f32 {
+        (-self.penetration(other)).max(0.0)
     }
 
     fn get_bounds(&self) -> (f32, f32, f32, f32) {
-        (
-            self.pos.x - self.radius,
-            self.pos.x + self.radius,
-            self.pos.y - self.radius,
-            self.pos.y + self.radius,
-        )
+        self.aabb()
+    }
+}
+
+/// A body as its entity stores it: the copy binned into cells, with its offset and turn
+/// relative to the entity transform, which only moving the entity reads.
+#[derive(Clone, Copy)]
+pub struct Placed {
+    body: Body,
+    offset: Vector2,
+    turn: f32,
+}
+
+impl Placed {
+    /// A body placed in world space, as if its entity had no transform.
+    fn new(body: Body) -> Self {
+        Self {
+            body,
+            offset: body.pos,
+            turn: body.angle,
+        }
+    }
+
+    /// Packs `shape` like `Body::set_shape`, moving and turning the offset with the body.
+    fn set_shape(&mut self, shape: Shape) {
+        let (shift, turned) = self.body.set_shape(shape);
+        let (sin, cos) = self.turn.sin_cos();
+        self.offset = Vector2::new(
+            self.offset.x + shift.x * cos - shift.y * sin,
+            self.offset.y + shift.x * sin + shift.y * cos,
+        );
+        self.turn += turned;
+    }
+
+    /// The body placed where `transform` puts its offset.
+    fn transformed(mut self, transform: Transform) -> Self {
+        self.body.pos = transform.apply(self.offset);
+        self.body.angle = transform.angle + self.turn;
+        self
+    }
+
+    fn resized(mut self, radius: f32) -> Self {
+        self.body = self.body.resized(radius);
+        self
+    }
+
+    /// The body with its radius scaled by `factor`, and its offset too when the entity has
+    /// a `transform` to place it relative to.
+    fn scaled(mut self, factor: f32, transform: Option<Transform>) -> Self {
+        self.body = self.body.resized(self.radius * factor);
+        if let Some(transform) = transform {
+            self.offset = Vector2::new(self.offset.x * factor, self.offset.y * factor);
+            self.body.pos = transform.apply(self.offset);
+        }
+        self
+    }
+}
+
+impl Deref for Placed {
+    type Target = Body;
+
+    fn deref(&self) -> &Body {
+        &self.body
     }
 }
 
@@ -74,6 +343,32 @@ pub struct Collision {
     other_entity_index: usize,
     self_body_index: usize,
     other_body_index: usize,
+    overlap: f32,
+    tick: u64,
+    /// Id of the ongoing contact between the pair, as in its events, when they were already
+    /// touching at the last `step`.
+    contact_id: Option<u64>,
+}
+
+impl Collision {
+    fn key(&self) -> ContactKey {
+        (
+            self.self_entity_index,
+            self.other_entity_index,
+            self.self_body_index,
+            self.other_body_index,
+        )
+    }
+
+    fn with_overlap(mut self, overlap: f32) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    fn at_tick(mut self, tick: u64) -> Self {
+        self.tick = tick;
+        self
+    }
 }
 
 impl hash::Hash for Collision {
@@ -110,204 +405,6861 @@ impl Collision {
             other_entity_index,
             self_body_index,
             other_body_index,
+            overlap: 0.0,
+            tick: 0,
+            contact_id: None,
+        }
+    }
+
+    /// `(namespace, entity)` of the `self` entity, see `GridPhysics.namespaced`.
+    #[getter]
+    fn self_entity(&self) -> (usize, usize) {
+        namespaces::split(self.self_entity_index)
+    }
+
+    /// `(namespace, entity)` of the `other` entity.
+    #[getter]
+    fn other_entity(&self) -> (usize, usize) {
+        namespaces::split(self.other_entity_index)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    Square,
+    Hex,
+}
+
+/// Cells covered by some bounds: a range of rows, and the range of columns covered in even and
+/// odd rows (which only differ for hex cells). Wrapping spans continue past one edge of the
+/// grid at the opposite edge instead of stopping.
+#[derive(Clone, Copy)]
+struct CellSpan {
+    grid_size: usize,
+    rows: (isize, isize),
+    even_columns: (isize, isize),
+    odd_columns: (isize, isize),
+    wrap: bool,
+}
+
+impl CellSpan {
+    /// Storage indices of the covered cells, each once.
+    fn indices(self) -> impl Iterator<Item = usize> {
+        self.walk(self.grid_size as isize).map(|(index, _)| index)
+    }
+
+    /// Storage indices of the covered cells, each with how many grid widths the covered cell
+    /// lies past the grid along x and y, which is only nonzero when wrapping. A wrapping span
+    /// wider than the world covers a cell again at each period it reaches, up to three, so
+    /// the cell holds the body as seen from both sides.
+    fn cells(self) -> impl Iterator<Item = (usize, (isize, isize))> {
+        self.walk(3 * self.grid_size as isize)
+    }
+
+    /// How many cells `indices` visits.
+    fn count(self) -> usize {
+        let width = self.grid_size as isize;
+        let rows = self.clip(self.rows, width);
+        (rows.0..=rows.1)
+            .map(|y| {
+                let (lower, upper) = self.clip(self.columns(y), width);
+                upper.saturating_sub(lower).saturating_add(1).max(0) as usize
+            })
+            .sum()
+    }
+
+    /// Whether `indices` visits the cell at a storage index.
+    fn covers(self, index: usize) -> bool {
+        let grid = self.grid_size as isize;
+        let (x, y) = (
+            (index / self.grid_size) as isize,
+            (index % self.grid_size) as isize,
+        );
+        let inside = |value: isize, (lower, upper): (isize, isize)| {
+            if !self.wrap {
+                (lower..=upper).contains(&value)
+            } else {
+                // Clipped to less than a period, so the difference can't overflow
+                upper >= lower && (value - lower.rem_euclid(grid)).rem_euclid(grid) <= upper - lower
+            }
+        };
+        inside(y, self.clip(self.rows, grid)) && inside(x, self.clip(self.columns(y), grid))
+    }
+
+    fn columns(self, y: isize) -> (isize, isize) {
+        if y & 1 == 0 {
+            self.even_columns
+        } else {
+            self.odd_columns
+        }
+    }
+
+    /// A range of cells limited to those in the grid, or when wrapping to `width` cells from
+    /// its start.
+    fn clip(self, (lower, upper): (isize, isize), width: isize) -> (isize, isize) {
+        if self.wrap {
+            (lower, upper.min(lower.saturating_add(width - 1)))
+        } else {
+            // Only cells in the grid exist, however far the bounds reach
+            (lower.max(0), upper.min(self.grid_size as isize - 1))
         }
     }
+
+    /// Covered cells, taking at most `width` from the start of a wrapping span.
+    fn walk(self, width: isize) -> impl Iterator<Item = (usize, (isize, isize))> {
+        let grid = self.grid_size as isize;
+        let rows = self.clip(self.rows, width);
+        (rows.0..=rows.1).flat_map(move |y| {
+            let (lower_x, upper_x) = self.clip(self.columns(y), width);
+            (lower_x..=upper_x).filter_map(move |x| {
+                let wraps = if self.wrap {
+                    (x.div_euclid(grid), y.div_euclid(grid))
+                } else {
+                    (0, 0)
+                };
+                let (x, y) = if self.wrap {
+                    (x.rem_euclid(grid), y.rem_euclid(grid))
+                } else {
+                    (x, y)
+                };
+                storage_index(self.grid_size, x, y).map(|index| (index, wraps))
+            })
+        })
+    }
+}
+
+/// Maximum number of results for queries taking `limit` and `first_only` options.
+fn result_limit(limit: Option<usize>, first_only: bool) -> usize {
+    if first_only {
+        1
+    } else {
+        limit.unwrap_or(usize::MAX)
+    }
+}
+
+/// A body moved back by `wraps` world widths along x and y.
+fn wrapped(mut body: Body, wraps: (isize, isize), period: f32) -> Body {
+    if wraps != (0, 0) {
+        body.pos.x -= wraps.0 as f32 * period;
+        body.pos.y -= wraps.1 as f32 * period;
+    }
+    body
+}
+
+fn union_bounds(a: (f32, f32, f32, f32), b: (f32, f32, f32, f32)) -> (f32, f32, f32, f32) {
+    (a.0.min(b.0), a.1.max(b.1), a.2.min(b.2), a.3.max(b.3))
+}
+
+fn storage_index(grid_size: usize, x: isize, y: isize) -> Option<usize> {
+    let in_grid = |value: isize| (0..grid_size as isize).contains(&value);
+    (in_grid(x) && in_grid(y)).then(|| x as usize * grid_size + y as usize)
+}
+
+/// Collisions laid out column by column for export.
+#[derive(Default)]
+struct CollisionColumns {
+    self_entity_index: Vec<u64>,
+    other_entity_index: Vec<u64>,
+    self_body_index: Vec<u64>,
+    other_body_index: Vec<u64>,
+    overlap: Vec<f32>,
+    tick: Vec<u64>,
+}
+
+/// Bodies binned into a cell. Cells are shared between forks of a world until one of them
+/// changes the cell. Static bodies come first, sorted by the left edge of their bounds so
+/// the narrow phase can stop at the first one right of the body it tests, followed by
+/// dynamic bodies in insertion order. Changes go through the methods of `CellBodies` so the
+/// columns the narrow phase reads stay in step with the bodies.
+type Cell = Arc<cell::CellBodies>;
+
+/// Most cells a grid is allowed, so a tiny `cell_size` raises instead of exhausting memory.
+const MAX_CELLS: usize = 1 << 26;
+
+/// Most features `observe` computes per entity.
+const MAX_FEATURES: usize = 1 << 16;
+
+/// Share of the grid's cells past which `cells_within` scans the whole grid.
+const FULL_SCAN: f32 = 0.5;
+
+/// Fraction of a cell a segment is binned past its sides and ends, covering rounding at cell
+/// edges.
+const EDGE_EPSILON: f32 = 1e-4;
+
+fn min_x(body: &Body) -> f32 {
+    body.get_bounds().0
+}
+
+fn insert_into_cell(cell: &mut cell::CellBodies, body: Body) {
+    if body.is_static() {
+        let statics = cell.partition_point(|other| other.is_static());
+        let at = cell[..statics].partition_point(|other| min_x(other) <= min_x(&body));
+        cell.insert(at, body);
+    } else {
+        cell.push(body);
+    }
+}
+
+/// Keeps at most `cap` collisions for each `self` entity, those between the nearest body
+/// centers, with ties going to the lower key.
+fn nearest_per_entity(
+    collisions: HashSet<Collision>,
+    distances: &HashMap<ContactKey, f32>,
+    cap: usize,
+) -> HashSet<Collision> {
+    let mut by_entity: HashMap<usize, Vec<Collision>> = HashMap::new();
+    for collision in collisions {
+        by_entity
+            .entry(collision.self_entity_index)
+            .or_default()
+            .push(collision);
+    }
+    let distance = |collision: &Collision| distances[&collision.key()];
+    by_entity
+        .into_values()
+        .flat_map(|mut found| {
+            if found.len() > cap {
+                found.sort_unstable_by(|a, b| {
+                    distance(a)
+                        .total_cmp(&distance(b))
+                        .then_with(|| a.key().cmp(&b.key()))
+                });
+                found.truncate(cap);
+            }
+            found
+        })
+        .collect()
+}
+
+/// Restores the cell order after bodies were appended in bulk.
+fn sort_cell(cell: &mut cell::CellBodies) {
+    cell.sort_by(|a, b| match (a.is_static(), b.is_static()) {
+        (true, true) => min_x(a).total_cmp(&min_x(b)),
+        (a_static, b_static) => b_static.cmp(&a_static),
+    });
 }
 
 #[pyclass(module = "radyx")]
+#[derive(Clone)]
 pub struct GridPhysics {
-    grid: Vec<Vec<Body>>,
-    dynamic_bodies: HashMap<usize, Vec<Body>>,
-    size: usize,
-    cell_size: usize,
+    grid: Vec<Cell>,
+    layout: Layout,
+    dynamic_bodies: HashMap<usize, Vec<Placed>>,
+    static_bodies: HashMap<usize, Vec<Placed>>,
+    #[pyo3(get)]
+    size: f32,
+    #[pyo3(get)]
+    cell_size: f32,
+    #[pyo3(get)]
     grid_size: usize,
+    /// Whether the world is a torus, bodies past one edge touching those at the opposite one.
+    #[pyo3(get)]
+    wrap: bool,
+    /// Contacts overlapping by less than this are not reported as collisions.
+    #[pyo3(get, set)]
+    min_overlap: f32,
+    /// Most collisions reported for one entity, keeping those with the nearest body centers;
+    /// `None` reports all of them.
+    #[pyo3(get, set)]
+    max_collisions_per_entity: Option<usize>,
+    /// How a pair met in several cells is kept from being tested again, `"visited"` or
+    /// `"after"`.
+    #[pyo3(get, set)]
+    pair_dedup: PairDedup,
+    /// Time step over which stored velocities inflate the binning bounds of dynamic bodies.
+    #[pyo3(get)]
+    sweep_dt: f32,
+    max_radius: f32,
+    kinds: KindRegistry,
+    namespaces: NamespaceRegistry,
+    tick: u64,
+    time: f64,
+    events: Vec<Event>,
+    contacts: HashSet<ContactKey>,
+    /// Ids of the contacts in `contacts`.
+    contact_ids: HashMap<ContactKey, u64>,
+    next_contact_id: u64,
+    sensors: HashSet<usize>,
+    groups: HashMap<String, HashSet<usize>>,
+    inactive_groups: HashSet<String>,
+    frozen: HashSet<usize>,
+    collision_scratch: HashSet<ContactKey>,
+    entity_bounds: HashMap<usize, (f32, f32, f32, f32)>,
+    transforms: HashMap<usize, Transform>,
+    static_exclusions: HashSet<ContactKey>,
+    outside: HashSet<(usize, usize)>,
+    velocities: HashMap<usize, Vector2>,
+    history: CollisionHistory,
+    state_slots: StateSlots,
+    /// Tick at which each entity last had bodies added, moved or removed.
+    changed: HashMap<usize, u64>,
+    subscriptions: HashMap<usize, Subscription>,
+    collision_handlers: Vec<CollisionHandler>,
+    next_handler_id: usize,
+    /// Adding bodies past this many raises `MemoryError`.
+    #[pyo3(get)]
+    max_bodies: Option<usize>,
+    /// Dynamic and static body counts to keep room for, see `reserve`.
+    reserved: (usize, usize),
+    entity_ids: EntityAllocator,
+    debug_server: DebugSlot,
+    logging: Logging,
+    /// Bodies binned into more cells than this are handled by `oversize_policy` and logged
+    /// as oversized.
+    #[pyo3(get, set)]
+    max_cells_per_body: usize,
+    #[pyo3(get, set)]
+    oversize_policy: OversizePolicy,
+    oversized: Option<Oversized>,
+    /// Whether NaN or infinite input raises `RadyxError`. Otherwise it is skipped, leaving
+    /// the grid as it was.
+    #[pyo3(get, set)]
+    strict: bool,
+    invalid: Option<Invalid>,
+    /// Whether `step` moves entities by their stored velocities.
+    #[pyo3(get, set)]
+    integrate: bool,
+    /// Largest distance an entity moves in one substep of `step`, unless overridden per
+    /// entity with `set_entity_max_displacement`.
+    #[pyo3(get, set)]
+    max_displacement: Option<f32>,
+    max_displacements: HashMap<usize, f32>,
+    /// Most substeps one `step` is split into; moves still too long are clamped.
+    #[pyo3(get, set)]
+    max_substeps: usize,
+    /// Acceleration added to the velocity of every dynamic entity in an integrated `step`.
+    #[pyo3(get, set)]
+    gravity: (f32, f32),
+    drags: HashMap<usize, f32>,
+    /// Forces applied since the last `step`.
+    forces: HashMap<usize, Vector2>,
+    masses: HashMap<usize, f32>,
+    frictions: HashMap<(usize, usize), f32>,
+    /// Distance an entity must move within one `step` to count as moving. Entities moving
+    /// less for `sleep_steps` steps in a row fall asleep; `None` turns sleeping off.
+    #[pyo3(get, set)]
+    sleep_threshold: Option<f32>,
+    #[pyo3(get, set)]
+    sleep_steps: usize,
+    asleep: HashSet<usize>,
+    static_layers: HashMap<String, StaticLayer>,
+    /// Name of the static layer last swapped in, `None` until one is or after `reset`.
+    #[pyo3(get)]
+    static_layer: Option<String>,
+    /// Position at the last `step` and how many steps in a row the entity stayed still.
+    stillness: HashMap<usize, (Vector2, usize)>,
+    /// Ticks each entity pair is held back for after being reported.
+    pair_cooldowns: HashMap<(usize, usize), u64>,
+    /// Tick each entity pair with a cooldown was last reported at.
+    cooldown_starts: HashMap<(usize, usize), u64>,
+    /// Passes of overlap push-out `step` runs after moving entities; zero turns it off.
+    #[pyo3(get, set)]
+    push_out_iterations: usize,
+    /// Tick length used by `advance`.
+    #[pyo3(get, set)]
+    fixed_dt: f64,
+    /// Most ticks one `advance` runs; time beyond that is dropped so a slow frame can't
+    /// snowball into ever longer ones.
+    #[pyo3(get, set)]
+    max_steps_per_advance: usize,
+    /// Frame time `advance` has not yet spent on a whole tick.
+    #[pyo3(get)]
+    accumulator: f64,
+    /// Contacts seen in the substeps of the current `step`, reported with its events.
+    substep_contacts: HashSet<ContactKey>,
+    /// Whether mutations are queued for `commit` instead of applied.
+    #[pyo3(get)]
+    deferred: bool,
+    pending: Vec<Mutation>,
+    /// Cells the pending mutations change.
+    dirty: HashSet<usize>,
+    /// Entities the pending mutations change, as they will be once applied.
+    projected: HashMap<usize, Projection>,
 }
 
-#[pymethods]
 impl GridPhysics {
-    #[new]
-    pub fn new(size: usize, cell_size: usize) -> Self {
-        let grid_size = size / cell_size;
-        let mut grid = Vec::with_capacity(grid_size * grid_size);
-        for _ in 0..grid_size * grid_size {
-            grid.push(Vec::new());
+    fn bodies_of(&self, entity_index: usize) -> impl Iterator<Item = &Body> {
+        self.placed_of(entity_index).map(|placed| &placed.body)
+    }
+
+    /// Bodies of an entity along with where they sit on it.
+    fn placed_of(&self, entity_index: usize) -> impl Iterator<Item = &Placed> {
+        self.dynamic_bodies
+            .get(&entity_index)
+            .into_iter()
+            .chain(self.static_bodies.get(&entity_index))
+            .flatten()
+    }
+
+    /// Cells along each side of a grid.
+    fn side_cells(size: f32, cell_size: f32, layout: Layout) -> usize {
+        match layout {
+            // At least one, as a tiny size over a huge cell size rounds the ratio to zero
+            Layout::Square => ((size / cell_size).ceil() as usize).max(1),
+            Layout::Hex => ((size / hex::row_height(cell_size)).ceil() as usize).saturating_add(1),
+        }
+    }
+
+    /// Raises for dimensions that would not make a usable grid, rather than panicking or
+    /// aborting on the allocation.
+    pub(crate) fn check_dimensions(size: f32, cell_size: f32, layout: Layout) -> PyResult<()> {
+        let valid = |value: f32| value.is_finite() && value > 0.0;
+        if !valid(size) || !valid(cell_size) {
+            return Err(PyValueError::new_err(format!(
+                "size and cell_size must be finite and positive, got {size} and {cell_size}"
+            )));
+        }
+        let side = Self::side_cells(size, cell_size, layout);
+        if side.checked_mul(side).is_none_or(|cells| cells > MAX_CELLS) {
+            return Err(PyValueError::new_err(format!(
+                "a world {size} wide needs more than {MAX_CELLS} cells {cell_size} wide"
+            )));
         }
+        Ok(())
+    }
+
+    /// `with_layout` for dimensions from Python or a file.
+    fn try_with_layout(size: f32, cell_size: f32, layout: Layout) -> PyResult<Self> {
+        Self::check_dimensions(size, cell_size, layout)?;
+        Ok(Self::with_layout(size, cell_size, layout))
+    }
+
+    fn with_layout(size: f32, cell_size: f32, layout: Layout) -> Self {
+        let grid_size = Self::side_cells(size, cell_size, layout);
+        // Every cell starts out sharing the same empty list
+        let grid = vec![Cell::default(); grid_size * grid_size];
 
         Self {
             grid,
+            layout,
             dynamic_bodies: HashMap::new(),
+            static_bodies: HashMap::new(),
             size,
             cell_size,
             grid_size,
+            wrap: false,
+            min_overlap: 0.0,
+            max_collisions_per_entity: None,
+            pair_dedup: PairDedup::default(),
+            sweep_dt: 1.0 / 60.0,
+            max_radius: 0.0,
+            kinds: KindRegistry::default(),
+            namespaces: NamespaceRegistry::default(),
+            tick: 0,
+            time: 0.0,
+            events: Vec::new(),
+            contacts: HashSet::new(),
+            contact_ids: HashMap::new(),
+            next_contact_id: 0,
+            sensors: HashSet::new(),
+            groups: HashMap::new(),
+            inactive_groups: HashSet::new(),
+            frozen: HashSet::new(),
+            collision_scratch: HashSet::new(),
+            entity_bounds: HashMap::new(),
+            transforms: HashMap::new(),
+            static_exclusions: HashSet::new(),
+            outside: HashSet::new(),
+            velocities: HashMap::new(),
+            history: CollisionHistory::default(),
+            state_slots: StateSlots::default(),
+            changed: HashMap::new(),
+            subscriptions: HashMap::new(),
+            collision_handlers: Vec::new(),
+            next_handler_id: 0,
+            max_bodies: None,
+            reserved: (0, 0),
+            entity_ids: EntityAllocator::default(),
+            debug_server: DebugSlot::default(),
+            logging: Logging::default(),
+            max_cells_per_body: 64,
+            oversize_policy: OversizePolicy::default(),
+            oversized: None,
+            strict: true,
+            invalid: None,
+            integrate: false,
+            max_displacement: None,
+            max_displacements: HashMap::new(),
+            max_substeps: 16,
+            substep_contacts: HashSet::new(),
+            gravity: (0.0, 0.0),
+            drags: HashMap::new(),
+            forces: HashMap::new(),
+            masses: HashMap::new(),
+            frictions: HashMap::new(),
+            sleep_threshold: None,
+            sleep_steps: 60,
+            asleep: HashSet::new(),
+            static_layers: HashMap::new(),
+            static_layer: None,
+            stillness: HashMap::new(),
+            pair_cooldowns: HashMap::new(),
+            cooldown_starts: HashMap::new(),
+            push_out_iterations: 0,
+            fixed_dt: 1.0 / 60.0,
+            max_steps_per_advance: 8,
+            accumulator: 0.0,
+            deferred: false,
+            pending: Vec::new(),
+            dirty: HashSet::new(),
+            projected: HashMap::new(),
         }
     }
 
-    pub fn reset(&mut self) {
-        self.dynamic_bodies.clear();
+    pub fn new(size: f32, cell_size: f32) -> Self {
+        Self::with_layout(size, cell_size, Layout::Square)
+    }
+
+    pub fn try_new(size: f32, cell_size: f32) -> PyResult<Self> {
+        Self::try_with_layout(size, cell_size, Layout::Square)
+    }
+
+    /// Grows entity lists and cells to hold the `reserved` body counts, spreading the bodies
+    /// evenly over cells.
+    fn reserve_capacity(&mut self) {
+        let (dynamic, statics) = self.reserved;
+        self.dynamic_bodies
+            .reserve(dynamic.saturating_sub(self.dynamic_bodies.len()));
+        self.static_bodies
+            .reserve(statics.saturating_sub(self.static_bodies.len()));
+        self.entity_bounds
+            .reserve((dynamic + statics).saturating_sub(self.entity_bounds.len()));
+        let per_cell = (dynamic + statics).div_ceil(self.grid.len().max(1));
         for cell in self.grid.iter_mut() {
-            cell.clear();
+            if cell.capacity() < per_cell {
+                let len = cell.len();
+                Arc::make_mut(cell).reserve(per_cell - len);
+            }
         }
     }
 
-    pub fn get_grid_bounds(&self, bounds: (f32, f32, f32, f32)) -> (usize, usize, usize, usize) {
-        (
-            (bounds.0 / (self.cell_size as f32)).floor() as usize,
-            (bounds.1 / (self.cell_size as f32)).ceil() as usize,
-            (bounds.2 / (self.cell_size as f32)).floor() as usize,
-            (bounds.3 / (self.cell_size as f32)).ceil() as usize,
-        )
+    /// Fails with `MemoryError` if adding `count` bodies would exceed `max_bodies`.
+    fn ensure_room(&self, count: usize) -> PyResult<()> {
+        let Some(limit) = self.max_bodies else {
+            return Ok(());
+        };
+        let bodies: usize = self
+            .dynamic_bodies
+            .values()
+            .chain(self.static_bodies.values())
+            .map(Vec::len)
+            .sum::<usize>()
+            + self.pending_bodies();
+        if bodies + count > limit {
+            return Err(PyMemoryError::new_err(format!(
+                "adding {count} bodies to {bodies} exceeds the limit of {limit}"
+            )));
+        }
+        Ok(())
     }
 
-    pub fn add_circle(
-        &mut self,
-        entity_index: usize,
-        pos: Vector2,
-        radius: f32,
-        body_index: usize,
-        is_static: bool,
-    ) {
-        let body = Body::new(entity_index, body_index, pos, radius, is_static);
+    /// Normalized `direction` and the distance at which the ray stops, leaving the grid at
+    /// the latest since nothing is binned outside it. `None` for a zero or non-finite
+    /// direction, a non-finite origin or a ray missing the grid.
+    fn clip_ray(
+        &self,
+        origin: Vector2,
+        direction: Vector2,
+        max_distance: Option<f32>,
+    ) -> Option<(Vector2, f32)> {
+        let length = (direction.x * direction.x + direction.y * direction.y).sqrt();
+        if length == 0.0 || !length.is_finite() || !origin.x.is_finite() || !origin.y.is_finite() {
+            return None;
+        }
+        let direction = Vector2::new(direction.x / length, direction.y / length);
+        let extent = self.grid_size as f32 * self.cell_size;
+        let (_, exit) = ray::box_interval(origin, direction, 0.0, extent)?;
+        Some((direction, max_distance.unwrap_or(f32::INFINITY).min(exit)))
+    }
 
-        let (lower_x, upper_x, lower_y, upper_y) = self.get_grid_bounds(body.get_bounds());
+    /// Public coordinates of the cell stored at `index`, axial in hex layout.
+    fn cell_coords(&self, index: usize) -> (isize, isize) {
+        let x = (index / self.grid_size) as isize;
+        let y = (index % self.grid_size) as isize;
+        match self.layout {
+            Layout::Square => (x, y),
+            Layout::Hex => hex::axial_of_offset(x, y),
+        }
+    }
+
+    /// Storage index of a cell given by its public coordinates, which are axial in hex layout.
+    fn cell_index(&self, cell_x: isize, cell_y: isize) -> Option<usize> {
+        let (x, y) = match self.layout {
+            Layout::Square => (cell_x, cell_y),
+            Layout::Hex => hex::offset_of_axial(cell_x, cell_y),
+        };
+        storage_index(self.grid_size, x, y)
+    }
 
-        for x in lower_x..=upper_x {
-            for y in lower_y..=upper_y {
-                let cell = self.grid.get_mut(x * self.grid_size + y);
-                if let Some(cell) = cell {
-                    cell.push(body);
+    fn cell_span(&self, bounds: (f32, f32, f32, f32)) -> CellSpan {
+        match self.layout {
+            Layout::Square if self.wrap => {
+                let cell = |value: f32| value / self.cell_size;
+                let columns = (
+                    cell(bounds.0).floor() as isize,
+                    cell(bounds.1).ceil() as isize,
+                );
+                CellSpan {
+                    grid_size: self.grid_size,
+                    rows: (
+                        cell(bounds.2).floor() as isize,
+                        cell(bounds.3).ceil() as isize,
+                    ),
+                    even_columns: columns,
+                    odd_columns: columns,
+                    wrap: true,
+                }
+            }
+            Layout::Square => {
+                let (lower_x, upper_x, lower_y, upper_y) = self.get_grid_bounds(bounds);
+                // Saturating, since bounds far past the grid give cells beyond `isize::MAX`
+                let cell = |value: usize| isize::try_from(value).unwrap_or(isize::MAX);
+                let columns = (cell(lower_x), cell(upper_x));
+                CellSpan {
+                    grid_size: self.grid_size,
+                    rows: (cell(lower_y), cell(upper_y)),
+                    even_columns: columns,
+                    odd_columns: columns,
+                    wrap: false,
+                }
+            }
+            Layout::Hex => {
+                let (rows, even_columns, odd_columns) = hex::span(bounds, self.cell_size);
+                CellSpan {
+                    grid_size: self.grid_size,
+                    rows,
+                    even_columns,
+                    odd_columns,
+                    wrap: false,
                 }
             }
         }
     }
 
-    pub fn add_static_circle(&mut self, entity_index: usize, pos: Vector2, radius: f32) {
-        self.add_circle(entity_index, pos, radius, 0, true)
+    /// Moves every body of an entity to the world position of its offset under `transform`,
+    /// re-binning them and refreshing the cached entity bounds.
+    fn place_entity(&mut self, entity_index: usize, transform: Transform) -> PyResult<()> {
+        let Some(bounds) = self.entity_bounds.get(&entity_index).copied() else {
+            return Err(PyKeyError::new_err(format!(
+                "unknown entity {entity_index}"
+            )));
+        };
+        if !self.check_move(entity_index, &transform)? {
+            return Ok(());
+        }
+        for index in self.cell_span(self.swept(entity_index, bounds)).indices() {
+            Arc::make_mut(&mut self.grid[index]).retain(|body| body.entity_index() != entity_index);
+        }
+
+        let mut moved = Vec::new();
+        for bodies in [&mut self.dynamic_bodies, &mut self.static_bodies] {
+            for body in bodies.get_mut(&entity_index).into_iter().flatten() {
+                *body = body.transformed(transform);
+                moved.push(body.body);
+            }
+        }
+        let entity_bounds = moved.iter().map(Body::get_bounds).reduce(union_bounds);
+        self.entity_bounds
+            .insert(entity_index, entity_bounds.unwrap_or(bounds));
+        for body in moved {
+            self.insert_into_cells(body);
+        }
+        self.transforms.insert(entity_index, transform);
+        self.changed.insert(entity_index, self.tick);
+        self.track_outside(entity_index);
+        self.wake_entity(entity_index);
+        Ok(())
     }
 
-    pub fn add_static_circles(&mut self, entity_index: usize, bodies: Vec<Vector2>, radius: f32) {
-        for (i, pos) in bodies.iter().enumerate() {
-            self.add_circle(entity_index, *pos, radius, i, true);
+    /// Scales every body of an entity by `factor` like `Body::scaled`, re-binning them.
+    fn scale_bodies(&mut self, entity_index: usize, factor: f32) -> PyResult<()> {
+        let Some(bounds) = self.entity_bounds.get(&entity_index).copied() else {
+            return Err(PyKeyError::new_err(format!(
+                "unknown entity {entity_index}"
+            )));
+        };
+        if !self.check_scale(entity_index, factor)? {
+            return Ok(());
+        }
+        for index in self.cell_span(self.swept(entity_index, bounds)).indices() {
+            Arc::make_mut(&mut self.grid[index]).retain(|body| body.entity_index() != entity_index);
         }
+
+        let transform = self.transforms.get(&entity_index).copied();
+        let mut scaled = Vec::new();
+        for bodies in [&mut self.dynamic_bodies, &mut self.static_bodies] {
+            for body in bodies.get_mut(&entity_index).into_iter().flatten() {
+                *body = body.scaled(factor, transform);
+                scaled.push(body.body);
+            }
+        }
+        for body in scaled {
+            self.max_radius = self.max_radius.max(body.radius);
+            self.insert_into_cells(body);
+        }
+        self.refresh_entity_bounds(entity_index);
+        self.changed.insert(entity_index, self.tick);
+        self.track_outside(entity_index);
+        self.wake_entity(entity_index);
+        Ok(())
     }
 
-    pub fn add_dynamic_circle(&mut self, entity_index: usize, pos: Vector2, radius: f32) {
-        self.add_circle(entity_index, pos, radius, 0, false);
-        self.dynamic_bodies
-            .entry(entity_index)
-            .or_insert_with(Vec::new)
-            .push(Body::new(entity_index, 0, pos, radius, false));
+    /// Removes every body of an entity along with its transform.
+    fn remove_entity(&mut self, entity_index: usize) {
+        if let Some(bounds) = self.entity_bounds.remove(&entity_index) {
+            for index in self.cell_span(self.swept(entity_index, bounds)).indices() {
+                Arc::make_mut(&mut self.grid[index])
+                    .retain(|body| body.entity_index() != entity_index);
+            }
+        }
+        let dynamic = self.dynamic_bodies.remove(&entity_index).is_some();
+        let statics = self.static_bodies.remove(&entity_index).is_some();
+        self.transforms.remove(&entity_index);
+        self.changed.insert(entity_index, self.tick);
+        if dynamic || statics {
+            self.log_removal(entity_index, None);
+        }
     }
 
-    pub fn add_dynamic_circles(&mut self, entity_index: usize, bodies: Vec<Vector2>, radius: f32) {
-        for (i, pos) in bodies.iter().enumerate() {
-            self.add_circle(entity_index, *pos, radius, i, false);
-            self.dynamic_bodies
-                .entry(entity_index)
-                .or_insert_with(Vec::new)
-                .push(Body::new(entity_index, i, *pos, radius, false));
+    /// Removes an entity with its velocity, sensor, freeze and group settings.
+    fn forget_entity(&mut self, entity_index: usize) {
+        self.remove_entity(entity_index);
+        self.velocities.remove(&entity_index);
+        self.max_displacements.remove(&entity_index);
+        self.drags.remove(&entity_index);
+        self.forces.remove(&entity_index);
+        self.masses.remove(&entity_index);
+        self.frictions
+            .retain(|(entity, _), _| *entity != entity_index);
+        self.asleep.remove(&entity_index);
+        self.stillness.remove(&entity_index);
+        self.pair_cooldowns
+            .retain(|pair, _| pair.0 != entity_index && pair.1 != entity_index);
+        self.cooldown_starts
+            .retain(|pair, _| pair.0 != entity_index && pair.1 != entity_index);
+        self.sensors.remove(&entity_index);
+        self.frozen.remove(&entity_index);
+        self.outside.retain(|(entity, _)| *entity != entity_index);
+        for members in self.groups.values_mut() {
+            members.remove(&entity_index);
         }
     }
 
-    pub fn get_collisions(&self) -> HashSet<Collision> {
-        let mut collisions = HashSet::new();
+    /// A copy of one body, failing like `move_body` for an unknown body.
+    fn find_body(&self, entity_index: usize, body_index: usize) -> PyResult<Body> {
+        self.bodies_of(entity_index)
+            .find(|body| body.body_index() == body_index)
+            .copied()
+            .ok_or_else(|| {
+                PyKeyError::new_err(format!(
+                    "unknown body {body_index} of entity {entity_index}"
+                ))
+            })
+    }
 
-        for (entity_index, bodies) in self.dynamic_bodies.iter() {
-            for body in bodies.iter() {
-                let (lower_x, upper_x, lower_y, upper_y) = self.get_grid_bounds(body.get_bounds());
-                for x in lower_x..=upper_x {
-                    for y in lower_y..=upper_y {
-                        let cell = self.grid.get(x * self.grid_size + y);
-                        if let Some(cell) = cell {
-                            for other in cell.iter() {
-                                if body.collided(other) {
-                                    let collision = Collision::new(
-                                        *entity_index,
-                                        other.entity_index,
-                                        body.body_index,
-                                        other.body_index,
-                                    );
-                                    collisions.insert(collision);
-                                }
-                            }
-                        }
-                    }
+    /// Replaces the velocity of an entity with known `bounds`, re-binning it over its new
+    /// sweep.
+    fn store_velocity(
+        &mut self,
+        entity_index: usize,
+        bounds: (f32, f32, f32, f32),
+        velocity: Vector2,
+    ) {
+        let old_bounds = self.swept(entity_index, bounds);
+        if velocity.x == 0.0 && velocity.y == 0.0 {
+            self.velocities.remove(&entity_index);
+        } else {
+            self.velocities.insert(entity_index, velocity);
+            self.wake_entity(entity_index);
+        }
+        self.rebin_entity(entity_index, old_bounds);
+    }
+
+    /// Stores the velocity of a known entity, or queues it while deferred.
+    fn assign_velocity(&mut self, entity_index: usize, velocity: Vector2) {
+        if self.deferred {
+            self.defer(Mutation::SetVelocity(entity_index, velocity));
+        } else {
+            let bounds = self.entity_bounds[&entity_index];
+            self.store_velocity(entity_index, bounds, velocity);
+        }
+    }
+
+    /// Moves one body to `pos` and re-bins it, returning it as it was before the move.
+    fn move_body(
+        &mut self,
+        entity_index: usize,
+        body_index: usize,
+        pos: Vector2,
+    ) -> PyResult<Body> {
+        if !self.check_position(entity_index, body_index, pos)? {
+            return self.find_body(entity_index, body_index);
+        }
+        let transform = self.transforms.get(&entity_index).copied();
+        let body = [&mut self.dynamic_bodies, &mut self.static_bodies]
+            .into_iter()
+            .filter_map(|bodies| bodies.get_mut(&entity_index))
+            .flatten()
+            .find(|body| body.body_index() == body_index)
+            .ok_or_else(|| {
+                PyKeyError::new_err(format!(
+                    "unknown body {body_index} of entity {entity_index}"
+                ))
+            })?;
+        let before = body.body;
+        body.body.pos = pos;
+        body.offset = transform.map_or(pos, |transform| transform.local(pos));
+        let moved = body.body;
+        self.rebin_body(before, moved);
+        Ok(before)
+    }
+
+    /// Resizes one body to a bounding radius of `radius`, scaling shapes other than circles
+    /// about the body position, and re-bins it when the cells it covers change.
+    fn resize_body(&mut self, entity_index: usize, body_index: usize, radius: f32) -> PyResult<()> {
+        let body = [&mut self.dynamic_bodies, &mut self.static_bodies]
+            .into_iter()
+            .filter_map(|bodies| bodies.get_mut(&entity_index))
+            .flatten()
+            .find(|body| body.body_index() == body_index)
+            .ok_or_else(|| {
+                PyKeyError::new_err(format!(
+                    "unknown body {body_index} of entity {entity_index}"
+                ))
+            })?;
+        let before = body.body;
+        *body = body.resized(radius);
+        let resized = body.body;
+        self.max_radius = self.max_radius.max(radius);
+        let cells: Vec<usize> = self.binned_cells(&resized).collect();
+        if self.wrap || self.binned_cells(&before).ne(cells.iter().copied()) {
+            self.rebin_body(before, resized);
+            return Ok(());
+        }
+        // Same cells, so the stored copies are overwritten in place
+        for index in cells {
+            let cell = Arc::make_mut(&mut self.grid[index]);
+            cell.update(|other| {
+                if other.body_index() == body_index
+                    && other.entity_index() == entity_index
+                    && other.is_static() == before.is_static()
+                {
+                    *other = resized;
                 }
+            });
+            if before.is_static() {
+                sort_cell(cell);
             }
         }
-        collisions
+        self.refresh_entity_bounds(entity_index);
+        self.changed.insert(entity_index, self.tick);
+        self.track_outside(entity_index);
+        self.wake_entity(entity_index);
+        Ok(())
     }
 
-    pub fn get_collisions_within_area(&self, position: Vector2, radius: f32) -> HashSet<usize> {
-        let (lower_x, upper_x, lower_y, upper_y) = self.get_grid_bounds((
-            position.x - radius,
-            position.x + radius,
-            position.y - radius,
-            position.y + radius,
-        ));
+    /// Replaces the binned copies of a body that was `before` with `after`, then refreshes
+    /// the entity it belongs to.
+    fn rebin_body(&mut self, before: Body, after: Body) {
+        let entity_index = after.entity_index();
+        let same = |other: &Body| {
+            other.entity_index() == entity_index
+                && other.body_index() == before.body_index()
+                && other.is_static() == before.is_static()
+        };
+        for index in self.cell_span(self.binning_bounds(&before)).indices() {
+            Arc::make_mut(&mut self.grid[index]).retain(|other| !same(other));
+        }
+        self.insert_into_cells(after);
+        self.refresh_entity_bounds(entity_index);
+        self.changed.insert(entity_index, self.tick);
+        self.track_outside(entity_index);
+        self.wake_entity(entity_index);
+    }
 
-        let mut collisions = HashSet::new();
+    /// Queues `Left` and `Reentered` events for dynamic bodies of an entity that moved
+    /// entirely out of the world or back into it since they were last checked.
+    fn track_outside(&mut self, entity_index: usize) {
+        let mut crossings: Vec<(usize, EventKind)> = Vec::new();
+        for body in self.dynamic_bodies.get(&entity_index).into_iter().flatten() {
+            let (min_x, max_x, min_y, max_y) = body.get_bounds();
+            let outside = max_x < 0.0 || max_y < 0.0 || min_x > self.size || min_y > self.size;
+            let key = (entity_index, body.body_index());
+            if outside && !self.outside.contains(&key) {
+                crossings.push((body.body_index(), EventKind::Left));
+            } else if !outside && self.outside.contains(&key) {
+                crossings.push((body.body_index(), EventKind::Reentered));
+            }
+        }
+        crossings.sort_unstable();
+        for (body_index, kind) in crossings {
+            match kind {
+                EventKind::Left => self.outside.insert((entity_index, body_index)),
+                _ => self.outside.remove(&(entity_index, body_index)),
+            };
+            self.events
+                .push(Event::body(kind, self.tick, entity_index, body_index));
+        }
+    }
 
-        for x in lower_x..=upper_x {
-            for y in lower_y..=upper_y {
-                let cell = self.grid.get(x * self.grid_size + y);
-                if let Some(cell) = cell {
-                    for other in cell.iter() {
-                        collisions.insert(other.entity_index);
+    /// Recomputes the cached bounds of an entity from its bodies, dropping them once it has none.
+    fn refresh_entity_bounds(&mut self, entity_index: usize) {
+        match self
+            .bodies_of(entity_index)
+            .map(Body::get_bounds)
+            .reduce(union_bounds)
+        {
+            Some(bounds) => self.entity_bounds.insert(entity_index, bounds),
+            None => self.entity_bounds.remove(&entity_index),
+        };
+    }
+
+    fn remove_static_body(&mut self, body: &Body) {
+        let same = |other: &Body| {
+            other.is_static()
+                && other.entity_index() == body.entity_index()
+                && other.body_index() == body.body_index()
+        };
+        for index in self.cell_span(body.get_bounds()).indices() {
+            Arc::make_mut(&mut self.grid[index]).retain(|other| !same(other));
+        }
+        if let Some(bodies) = self.static_bodies.get_mut(&body.entity_index()) {
+            bodies.retain(|other| !same(other));
+            if bodies.is_empty() {
+                self.static_bodies.remove(&body.entity_index());
+            }
+        }
+        self.refresh_entity_bounds(body.entity_index());
+        self.changed.insert(body.entity_index(), self.tick);
+        self.log_removal(body.entity_index(), Some(body.body_index()));
+    }
+
+    /// Overlapping pairs of static bodies of different entities, each pair once with the
+    /// lower `(entity_index, body_index)` first.
+    fn static_pairs(&self) -> HashSet<ContactKey> {
+        let mut pairs = HashSet::new();
+        for body in self.static_bodies.values().flatten() {
+            for cell in self.cells_within(body.get_bounds()) {
+                for other in cell.iter().filter(|other| other.is_static()) {
+                    let ordered = (body.entity_index(), body.body_index())
+                        < (other.entity_index(), other.body_index());
+                    if ordered && body.entity_index() != other.entity_index() && body.touches(other)
+                    {
+                        pairs.insert((
+                            body.entity_index(),
+                            other.entity_index(),
+                            body.body_index(),
+                            other.body_index(),
+                        ));
                     }
                 }
             }
         }
-        collisions
+        pairs
     }
-}
 
-#[pymodule]
-fn radyx(_py: Python, m: &PyModule) -> PyResult<()> {
-    m.add_class::<Collision>()?;
-    m.add_class::<GridPhysics>()?;
-    m.add("__doc__", "Made in Rust!")?;
-    Ok(())
-}
+    /// `bounds` of an entity stretched over the distance its velocity covers in `sweep_dt`.
+    fn swept(&self, entity_index: usize, bounds: (f32, f32, f32, f32)) -> (f32, f32, f32, f32) {
+        let Some(velocity) = self.velocities.get(&entity_index) else {
+            return bounds;
+        };
+        let (dx, dy) = (velocity.x * self.sweep_dt, velocity.y * self.sweep_dt);
+        let (min_x, max_x, min_y, max_y) = bounds;
+        union_bounds(bounds, (min_x + dx, max_x + dx, min_y + dy, max_y + dy))
+    }
 
-#[cfg(test)]
-mod tests {
+    /// Bounds of the cells a body is binned into, which for moving dynamic bodies include
+    /// those they will cross this frame.
+    fn binning_bounds(&self, body: &Body) -> (f32, f32, f32, f32) {
+        if body.is_static() {
+            body.get_bounds()
+        } else {
+            self.swept(body.entity_index(), body.get_bounds())
+        }
+    }
+
+    /// Removes an entity from the cells and bins it again, after its velocity or the sweep
+    /// time changed. `old_bounds` covers the cells it was binned into.
+    fn rebin_entity(&mut self, entity_index: usize, old_bounds: (f32, f32, f32, f32)) {
+        for index in self.cell_span(old_bounds).indices() {
+            Arc::make_mut(&mut self.grid[index]).retain(|body| body.entity_index() != entity_index);
+        }
+        let bodies: Vec<Body> = self.bodies_of(entity_index).copied().collect();
+        for body in bodies {
+            self.insert_into_cells(body);
+        }
+    }
+
+    /// Storage indices of the cells a body is binned into.
+    fn binned_cells(&self, body: &Body) -> impl Iterator<Item = usize> {
+        let moving = !body.is_static() && self.velocities.contains_key(&body.entity_index());
+        let segment = match (body.shape(), self.layout, moving || self.wrap) {
+            (Shape::Segment { half_length }, Layout::Square, false) => {
+                // Only the cells the segment crosses, not every cell of its bounding box. Both
+                // sides of a sliver around it are walked, so a segment along a cell edge or
+                // through a corner is in the cells on either side, as bounds rounded out are
+                let margin = EDGE_EPSILON * self.cell_size;
+                let reach = half_length + margin;
+                let (sin, cos) = body.angle.sin_cos();
+                let direction = Vector2::new(cos, sin);
+                let mut cells: Vec<(isize, isize)> = [-margin, margin]
+                    .into_iter()
+                    .flat_map(|side| {
+                        let start = Vector2::new(
+                            body.pos.x - cos * reach - sin * side,
+                            body.pos.y - sin * reach + cos * side,
+                        );
+                        let length = 2.0 * reach;
+                        ray::Traversal::clipped(
+                            start,
+                            direction,
+                            self.cell_size,
+                            length,
+                            self.grid_size,
+                        )
+                    })
+                    .flatten()
+                    .map(|(cell_x, cell_y, _)| (cell_x, cell_y))
+                    .collect();
+                cells.sort_unstable();
+                cells.dedup();
+                Some(cells)
+            }
+            _ => None,
+        };
+        let span = self.cell_span(self.binning_bounds(body));
+        let spanned = segment.is_none().then(|| span.indices());
+        let grid_size = self.grid_size;
+        segment
+            .into_iter()
+            .flatten()
+            .filter_map(move |(cell_x, cell_y)| storage_index(grid_size, cell_x, cell_y))
+            // Kept to the span removal clears, which the sliver can cross at a cell edge
+            .filter(move |index| span.covers(*index))
+            .chain(spanned.into_iter().flatten())
+    }
+
+    /// The copies of a body stored in the cells it is binned into. When wrapping, cells
+    /// past an edge of the grid store it shifted by the world width, as seen from there.
+    fn binned_copies(&self, body: &Body) -> impl Iterator<Item = (usize, Body)> {
+        let body = *body;
+        let period = self.wrap_period();
+        let wrapped = self.wrap.then(|| {
+            self.cell_span(self.binning_bounds(&body))
+                .cells()
+                .map(move |(index, wraps)| (index, wrapped(body, wraps, period)))
+        });
+        let plain = (!self.wrap).then(|| self.binned_cells(&body).map(move |index| (index, body)));
+        wrapped
+            .into_iter()
+            .flatten()
+            .chain(plain.into_iter().flatten())
+    }
+
+    /// Width of the world along each axis when wrapping: the grid rounded up to whole cells.
+    fn wrap_period(&self) -> f32 {
+        self.grid_size as f32 * self.cell_size
+    }
+
+    fn insert_into_cells(&mut self, body: Body) {
+        let copies: Vec<(usize, Body)> = self.binned_copies(&body).collect();
+        for (index, copy) in copies {
+            insert_into_cell(Arc::make_mut(&mut self.grid[index]), copy);
+        }
+    }
+
+    /// Cells covered by `bounds`, each once. Bounds reaching past the grid only cover its
+    /// cells. Once they cover `FULL_SCAN` of the grid, every cell is scanned in storage
+    /// order, which is faster than walking the span row by row.
+    fn cells_within(&self, bounds: (f32, f32, f32, f32)) -> impl Iterator<Item = &Vec<Body>> {
+        let span = self.cell_span(bounds);
+        let scan = span.count() as f32 >= FULL_SCAN * self.grid.len() as f32;
+        let scanned = scan.then(|| {
+            self.grid
+                .iter()
+                .enumerate()
+                .filter(move |(index, _)| span.covers(*index))
+                .map(|(_, cell)| &***cell)
+        });
+        let walked = (!scan).then(|| span.indices().map(|index| &**self.grid[index]));
+        scanned
+            .into_iter()
+            .flatten()
+            .chain(walked.into_iter().flatten())
+    }
+
+    /// Colliding body pairs, the set the Python `get_collisions` converts.
+    pub fn get_collisions(
+        &self,
+        with_overlap: bool,
+        limit: Option<usize>,
+        first_only: bool,
+        targets: Targets,
+    ) -> HashSet<Collision> {
+        let limit = result_limit(limit, first_only);
+        if limit == usize::MAX {
+            self.all_collisions_with(with_overlap, targets)
+        } else {
+            self.collisions_of(self.dynamic_in_order(), with_overlap, limit, targets)
+        }
+    }
+
+    /// The `k` bodies nearest to `position` as `(entity_index, body_index, distance)`, nearest
+    /// first, counting only bodies of `kinds` when given.
+    pub fn nearest(
+        &self,
+        position: Vector2,
+        k: usize,
+        max_distance: Option<f32>,
+        metric: Metric,
+        kinds: Option<&HashSet<usize>>,
+    ) -> Vec<(usize, usize, f32)> {
+        let limit = max_distance.unwrap_or(f32::INFINITY);
+        // Past this radius every body of the world has been seen
+        let world_reach = 2.0 * self.size + self.max_radius;
+
+        let mut search = self.cell_size.min(limit);
+        loop {
+            let reach = search + self.max_radius;
+            let bounds = (
+                position.x - reach,
+                position.x + reach,
+                position.y - reach,
+                position.y + reach,
+            );
+
+            let mut found: HashMap<(usize, usize), f32> = HashMap::new();
+            for cell in self.cells_within(bounds) {
+                let matching = cell.iter().filter(|other| {
+                    self.is_active(other.entity_index())
+                        && kinds.is_none_or(|kinds| kinds.contains(&other.kind()))
+                });
+                for other in matching {
+                    let distance = other.metric_distance(position, metric);
+                    if distance <= search {
+                        found.insert((other.entity_index(), other.body_index()), distance);
+                    }
+                }
+            }
+
+            if found.len() >= k || search >= limit || search >= world_reach {
+                let mut nearest: Vec<(usize, usize, f32)> = found
+                    .into_iter()
+                    .map(|((entity_index, body_index), distance)| {
+                        (entity_index, body_index, distance)
+                    })
+                    .collect();
+                nearest.sort_by(|a, b| a.2.total_cmp(&b.2).then((a.0, a.1).cmp(&(b.0, b.1))));
+                nearest.truncate(k);
+                return nearest;
+            }
+            search = (search * 2.0).min(limit);
+        }
+    }
+
+    fn min_distances(
+        &self,
+        entities_a: &[usize],
+        entities_b: &[usize],
+        max_distance: Option<f32>,
+    ) -> Array2<f32> {
+        let mut distances = Array2::from_elem((entities_a.len(), entities_b.len()), f32::INFINITY);
+
+        let mut columns: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (column, entity_index) in entities_b.iter().enumerate() {
+            columns.entry(*entity_index).or_default().push(column);
+        }
+
+        for (row, entity_index) in entities_a.iter().enumerate() {
+            for body in self.bodies_of(*entity_index) {
+                let mut record = |other: &Body| {
+                    if let Some(columns) = columns.get(&other.entity_index()) {
+                        let distance = body.distance(other);
+                        if max_distance.is_some_and(|max| distance > max) {
+                            return;
+                        }
+                        for column in columns {
+                            let cell = &mut distances[[row, *column]];
+                            *cell = cell.min(distance);
+                        }
+                    }
+                };
+
+                match max_distance {
+                    Some(max) => {
+                        let (min_x, max_x, min_y, max_y) = body.get_bounds();
+                        let bounds = (min_x - max, max_x + max, min_y - max, max_y + max);
+                        for cell in self.cells_within(bounds) {
+                            cell.iter().for_each(&mut record);
+                        }
+                    }
+                    None => {
+                        for other_entity in columns.keys() {
+                            self.bodies_of(*other_entity).for_each(&mut record);
+                        }
+                    }
+                }
+            }
+        }
+        distances
+    }
+
+    fn collisions_against(&self, other: &GridPhysics) -> HashSet<Collision> {
+        let mut collisions = HashSet::new();
+        for body in self
+            .awake_bodies()
+            .filter(|body| self.is_active(body.entity_index()))
+        {
+            for cell in other.cells_within(body.get_bounds()) {
+                let touching = cell.iter().filter(|other_body| {
+                    other.is_active(other_body.entity_index())
+                        && body.touches(other_body)
+                        && self.deep_enough(body, other_body)
+                });
+                for other_body in touching {
+                    let collision = Collision::new(
+                        body.entity_index(),
+                        other_body.entity_index(),
+                        body.body_index(),
+                        other_body.body_index(),
+                    );
+                    collisions.insert(collision.at_tick(self.tick));
+                }
+            }
+        }
+        collisions
+    }
+
+    /// Dynamic bodies of awake entities in entity order, so partial results don't depend on
+    /// hash map order.
+    fn dynamic_in_order(&self) -> impl Iterator<Item = &Body> {
+        let mut entities: Vec<&usize> = self
+            .dynamic_bodies
+            .keys()
+            .filter(|entity_index| !self.asleep.contains(entity_index))
+            .collect();
+        entities.sort_unstable();
+        entities
+            .into_iter()
+            .flat_map(|entity_index| &self.dynamic_bodies[entity_index])
+            .map(|placed| &placed.body)
+    }
+
+    /// Every collision ordered by `(self_entity, other_entity, self_body, other_body)`. Each
+    /// entity is checked independently and the results are merged in entity order, so the
+    /// output is identical whatever the number of threads.
+    fn ordered_collisions(&self, with_overlap: bool, parallel: bool) -> Vec<Collision> {
+        let mut entities: Vec<usize> = self
+            .dynamic_bodies
+            .keys()
+            .filter(|entity_index| !self.asleep.contains(entity_index))
+            .copied()
+            .collect();
+        entities.sort_unstable();
+        let detect = |entity_index: &usize| {
+            let mut found: Vec<Collision> = self
+                .collisions_of(
+                    self.dynamic_bodies[entity_index]
+                        .iter()
+                        .map(|placed| &placed.body),
+                    with_overlap,
+                    usize::MAX,
+                    Targets::All,
+                )
+                .into_iter()
+                .collect();
+            found.sort_unstable_by_key(Collision::key);
+            found
+        };
+        if parallel {
+            entities.par_iter().flat_map_iter(detect).collect()
+        } else {
+            entities.iter().flat_map(detect).collect()
+        }
+    }
+
+    fn all_collisions(&self) -> HashSet<Collision> {
+        self.all_collisions_with(false, Targets::All)
+    }
+
+    fn all_collisions_with(&self, with_overlap: bool, targets: Targets) -> HashSet<Collision> {
+        self.collisions_of(self.awake_bodies(), with_overlap, usize::MAX, targets)
+    }
+
+    fn collisions_of<'a>(
+        &self,
+        bodies: impl Iterator<Item = &'a Body>,
+        with_overlap: bool,
+        limit: usize,
+        targets: Targets,
+    ) -> HashSet<Collision> {
+        let mut collisions = HashSet::new();
+        if limit == 0 {
+            return collisions;
+        }
+
+        let mut distances: HashMap<ContactKey, f32> = HashMap::new();
+        self.visit_collisions(bodies, targets, |body, other| {
+            if self.cooling_down(body.entity_index(), other.entity_index()) {
+                return ControlFlow::Continue(());
+            }
+            let mut collision = Collision::new(
+                body.entity_index(),
+                other.entity_index(),
+                body.body_index(),
+                other.body_index(),
+            );
+            if with_overlap {
+                collision = collision.with_overlap(body.overlap(other));
+            }
+            collision.contact_id = self.contact_ids.get(&collision.key()).copied();
+            if self.max_collisions_per_entity.is_some() {
+                let distance = (body.pos.x - other.pos.x).hypot(body.pos.y - other.pos.y);
+                distances.insert(collision.key(), distance);
+            }
+            collisions.insert(collision.at_tick(self.tick));
+            if collisions.len() >= limit {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        match self.max_collisions_per_entity {
+            Some(cap) => nearest_per_entity(collisions, &distances, cap),
+            None => collisions,
+        }
+    }
+
+    fn collision_columns(&self, with_overlap: bool) -> CollisionColumns {
+        let mut columns = CollisionColumns::default();
+        let collisions =
+            self.collisions_of(self.awake_bodies(), with_overlap, usize::MAX, Targets::All);
+        for collision in collisions {
+            columns
+                .self_entity_index
+                .push(collision.self_entity_index as u64);
+            columns
+                .other_entity_index
+                .push(collision.other_entity_index as u64);
+            columns
+                .self_body_index
+                .push(collision.self_body_index as u64);
+            columns
+                .other_body_index
+                .push(collision.other_body_index as u64);
+            columns.overlap.push(collision.overlap);
+            columns.tick.push(collision.tick);
+        }
+        columns
+    }
+
+    /// Sorted entities with a body whose bounding box overlaps `bounds`, found from the cells
+    /// covering it without going through a set.
+    fn visible(&self, bounds: (f32, f32, f32, f32)) -> Vec<i64> {
+        let (min_x, max_x, min_y, max_y) = bounds;
+        let mut entities: Vec<i64> = self
+            .cells_within(bounds)
+            .flat_map(|cell| cell.iter())
+            .filter(|body| {
+                let (body_min_x, body_max_x, body_min_y, body_max_y) = body.get_bounds();
+                body_min_x <= max_x
+                    && body_max_x >= min_x
+                    && body_min_y <= max_y
+                    && body_max_y >= min_y
+                    && self.is_active(body.entity_index())
+            })
+            .map(|body| body.entity_index() as i64)
+            .collect();
+        entities.sort_unstable();
+        entities.dedup();
+        entities
+    }
+
+    /// Calls `visit` with every colliding pair found from `bodies` until it breaks. A pair
+    /// sharing several cells is visited once per shared cell.
+    fn visit_collisions<'a>(
+        &self,
+        bodies: impl Iterator<Item = &'a Body>,
+        targets: Targets,
+        mut visit: impl FnMut(&Body, &Body) -> ControlFlow<()>,
+    ) {
+        let period = self.wrap_period();
+        let mut tested = HashSet::new();
+        for body in bodies.filter(|body| self.is_active(body.entity_index())) {
+            let span = self.cell_span(body.get_bounds());
+            // Only a body in several cells can meet the same one twice
+            let remember = self.pair_dedup == PairDedup::Visited && span.count() > 1;
+            tested.clear();
+            for (index, wraps) in span.cells() {
+                // Cells past an edge hold bodies as seen from there, so look from there too
+                let body = &wrapped(*body, wraps, period);
+                let bounds = body.get_bounds();
+                let cell = &self.grid[index];
+                let statics = cell.partition_point(|other| other.is_static());
+                let reachable = cell[..statics].partition_point(|other| min_x(other) <= bounds.1);
+                for i in (0..reachable).chain(statics..cell.len()) {
+                    // Bounding circles from the columns reject most candidates
+                    let (dx, dy) = (cell.xs[i] - body.pos.x, cell.ys[i] - body.pos.y);
+                    let reach = cell.radii[i] + body.radius;
+                    if cell.entities[i] == body.entity_index || dx * dx + dy * dy > reach * reach {
+                        continue;
+                    }
+                    let other = &cell[i];
+                    // Keyed by where both are seen too, since cells past a wrapping edge hold
+                    // copies a period away, and another image is a different test
+                    if remember
+                        && !tested.insert((
+                            other.entity_index,
+                            other.body_index,
+                            other.is_static(),
+                            wraps,
+                            (other.pos.x.to_bits(), other.pos.y.to_bits()),
+                        ))
+                    {
+                        continue;
+                    }
+                    if targets.admits(other)
+                        && body.collided(other)
+                        && self.is_active(other.entity_index())
+                        && self.deep_enough(body, other)
+                        && visit(body, other).is_break()
+                    {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes collisions as `[self_entity, other_entity, self_body, other_body]` rows,
+    /// returning how many rows were filled. Detection stops once `out` is full.
+    fn write_collisions(&mut self, mut out: ArrayViewMut2<i64>) -> PyResult<usize> {
+        if out.ncols() != 4 {
+            return Err(PyValueError::new_err(format!(
+                "collision buffer needs 4 columns, got {}",
+                out.ncols()
+            )));
+        }
+
+        // Reuse the dedup set between calls so steady-state frames don't allocate
+        let mut seen = std::mem::take(&mut self.collision_scratch);
+        seen.clear();
+        let mut written = 0;
+        if out.nrows() > 0 && self.max_collisions_per_entity.is_some() {
+            // Capping needs every collision of an entity before any can be written
+            let mut keys: Vec<ContactKey> =
+                self.all_collisions().iter().map(Collision::key).collect();
+            keys.sort_unstable();
+            for (mut row, key) in out.rows_mut().into_iter().zip(keys) {
+                row[0] = key.0 as i64;
+                row[1] = key.1 as i64;
+                row[2] = key.2 as i64;
+                row[3] = key.3 as i64;
+                written += 1;
+            }
+        } else if out.nrows() > 0 {
+            self.visit_collisions(self.awake_bodies(), Targets::All, |body, other| {
+                if self.cooling_down(body.entity_index(), other.entity_index()) {
+                    return ControlFlow::Continue(());
+                }
+                let key = (
+                    body.entity_index(),
+                    other.entity_index(),
+                    body.body_index(),
+                    other.body_index(),
+                );
+                if seen.insert(key) {
+                    let mut row = out.row_mut(written);
+                    row[0] = key.0 as i64;
+                    row[1] = key.1 as i64;
+                    row[2] = key.2 as i64;
+                    row[3] = key.3 as i64;
+                    written += 1;
+                }
+                if written == out.nrows() {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            });
+        }
+        self.collision_scratch = seen;
+        Ok(written)
+    }
+
+    /// Whether touching bodies overlap by at least `min_overlap`, filtering grazing contacts.
+    fn deep_enough(&self, body: &Body, other: &Body) -> bool {
+        self.min_overlap <= 0.0 || body.overlap(other) >= self.min_overlap
+    }
+
+    fn is_active(&self, entity_index: usize) -> bool {
+        !self.frozen.contains(&entity_index)
+    }
+
+    fn group(&self, name: &str) -> PyResult<&HashSet<usize>> {
+        self.groups
+            .get(name)
+            .ok_or_else(|| PyKeyError::new_err(format!("no group named {name:?}")))
+    }
+
+    fn update_frozen(&mut self) {
+        self.frozen = self
+            .inactive_groups
+            .iter()
+            .filter_map(|name| self.groups.get(name))
+            .flatten()
+            .copied()
+            .collect();
+    }
+
+    fn queue_events(&mut self) {
+        let tick = self.tick;
+        let mut contacts: Vec<ContactKey> = self
+            .all_collisions()
+            .iter()
+            .map(Collision::key)
+            .chain(self.substep_contacts.drain())
+            .collect();
+        contacts.sort_unstable();
+        contacts.dedup();
+        self.start_cooldowns(&contacts);
+
+        let mut events = Vec::new();
+        let mut ids: HashMap<ContactKey, u64> = HashMap::with_capacity(contacts.len());
+        for key in contacts.iter() {
+            // Both sides of a pair share the id, whichever was seen first
+            let reverse = (key.1, key.0, key.3, key.2);
+            let id = match self
+                .contact_ids
+                .get(key)
+                .or_else(|| ids.get(&reverse))
+                .or_else(|| self.contact_ids.get(&reverse))
+            {
+                Some(id) => *id,
+                None => {
+                    self.next_contact_id += 1;
+                    self.next_contact_id
+                }
+            };
+            ids.insert(*key, id);
+
+            let kind = if self.sensors.contains(&key.0) || self.sensors.contains(&key.1) {
+                EventKind::Sensor
+            } else {
+                EventKind::Collision
+            };
+            events.push(Event::contact(kind, tick, *key, Some(id)));
+            if !self.contacts.contains(key) {
+                events.push(Event::contact(EventKind::Enter, tick, *key, Some(id)));
+            }
+        }
+
+        let current: HashSet<ContactKey> = contacts.into_iter().collect();
+        self.history.record(tick, &current);
+        let mut exits: Vec<ContactKey> = self.contacts.difference(&current).copied().collect();
+        exits.sort_unstable();
+        events.extend(exits.into_iter().map(|key| {
+            let id = self.contact_ids.get(&key).copied();
+            Event::contact(EventKind::Exit, tick, key, id)
+        }));
+
+        let mut boundary: Vec<(usize, usize)> = self
+            .dynamic_bodies
+            .values()
+            .flatten()
+            .filter(|body| {
+                let (min_x, max_x, min_y, max_y) = body.get_bounds();
+                min_x < 0.0 || min_y < 0.0 || max_x > self.size || max_y > self.size
+            })
+            .map(|body| (body.entity_index(), body.body_index()))
+            .collect();
+        boundary.sort_unstable();
+        events.extend(boundary.into_iter().map(|(entity_index, body_index)| {
+            Event::body(EventKind::Boundary, tick, entity_index, body_index)
+        }));
+
+        self.contacts = current;
+        self.contact_ids = ids;
+        self.events.extend(events);
+
+        let mut entities: Vec<usize> = self.dynamic_bodies.keys().copied().collect();
+        entities.sort_unstable();
+        for entity_index in entities {
+            self.track_outside(entity_index);
+        }
+    }
+
+    fn dynamic_counts(&self, kind: Option<usize>) -> Array2<u32> {
+        let mut counts = Array2::zeros((self.grid_size, self.grid_size));
+        let bodies = self.dynamic_bodies.values().flatten();
+        for body in bodies.filter(|body| kind.is_none_or(|kind| body.kind() == kind)) {
+            let (mut cell_x, mut cell_y) = self.world_to_cell(body.pos);
+            if self.wrap {
+                let grid = self.grid_size as isize;
+                (cell_x, cell_y) = (cell_x.rem_euclid(grid), cell_y.rem_euclid(grid));
+            }
+            if let Some(index) = self.cell_index(cell_x, cell_y) {
+                counts[[index / self.grid_size, index % self.grid_size]] += 1;
+            }
+        }
+        counts
+    }
+
+    /// One row per entity: offsets from the entity centroid to its `num_nearest` nearest
+    /// foreign bodies (zero padded), followed by the distance to the first foreign body along
+    /// `num_rays` evenly spaced rays (`max_distance` when nothing is hit).
+    fn observations(
+        &self,
+        entities: &[usize],
+        num_nearest: usize,
+        num_rays: usize,
+        max_distance: f32,
+    ) -> PyResult<Array2<f32>> {
+        if let Some(missing) = entities
+            .iter()
+            .find(|entity_index| self.bodies_of(**entity_index).next().is_none())
+        {
+            return Err(PyKeyError::new_err(format!("unknown entity {missing}")));
+        }
+
+        let features = num_nearest
+            .checked_mul(2)
+            .and_then(|offsets| offsets.checked_add(num_rays))
+            .filter(|features| *features <= MAX_FEATURES)
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "observing {num_nearest} nearest bodies and {num_rays} rays needs more than \
+                     {MAX_FEATURES} features"
+                ))
+            })?;
+        let rows: Vec<Vec<f32>> = entities
+            .par_iter()
+            .map(|entity_index| {
+                let own: Vec<&Body> = self.bodies_of(*entity_index).collect();
+                let count = own.len() as f32;
+                let center = Vector2::new(
+                    own.iter().map(|body| body.pos.x).sum::<f32>() / count,
+                    own.iter().map(|body| body.pos.y).sum::<f32>() / count,
+                );
+
+                let mut row = Vec::with_capacity(features);
+                let nearest = self.nearest(
+                    center,
+                    num_nearest + own.len(),
+                    Some(max_distance),
+                    Metric::Euclidean,
+                    None,
+                );
+                for (other_entity, other_body, _) in nearest
+                    .into_iter()
+                    .filter(|hit| hit.0 != *entity_index)
+                    .take(num_nearest)
+                {
+                    let other = self
+                        .bodies_of(other_entity)
+                        .find(|body| body.body_index() == other_body)
+                        .expect("nearest only reports stored bodies");
+                    row.push(other.pos.x - center.x);
+                    row.push(other.pos.y - center.y);
+                }
+                row.resize(2 * num_nearest, 0.0);
+
+                for ray in 0..num_rays {
+                    let angle = std::f32::consts::TAU * ray as f32 / num_rays as f32;
+                    let direction = Vector2::new(angle.cos(), angle.sin());
+                    // Own bodies can be hit first, so allow enough hits to get past them
+                    let hits = self.raycast(
+                        center,
+                        direction,
+                        Some(max_distance),
+                        Some(own.len() + 1),
+                        false,
+                        false,
+                    );
+                    let distance = hits
+                        .into_iter()
+                        .find(|hit| hit.0 != *entity_index)
+                        .map_or(max_distance, |hit| hit.2);
+                    row.push(distance);
+                }
+                row
+            })
+            .collect();
+
+        let values = rows.into_iter().flatten().collect();
+        Ok(Array2::from_shape_vec((entities.len(), features), values)
+            .expect("every row holds the same number of features"))
+    }
+}
+
+#[pymethods]
+impl GridPhysics {
+    /// With `max_bodies`, adding bodies beyond that many raises `MemoryError` instead of
+    /// growing without bound.
+    #[new]
+    #[pyo3(signature = (size, cell_size, max_bodies=None))]
+    pub fn py_new(size: f32, cell_size: f32, max_bodies: Option<usize>) -> PyResult<Self> {
+        let mut grid = Self::try_new(size, cell_size)?;
+        grid.max_bodies = max_bodies;
+        Ok(grid)
+    }
+
+    /// Builds a grid of static bodies from the object layer `collision_layer` of a Tiled JSON
+    /// map, given as its contents or a path to it. Rectangles, ellipses, polygons and
+    /// polylines are imported, each object as an entity indexed by its Tiled id with its class
+    /// as kind. Objects without an integer id raise `ValueError`. The cell size defaults to
+    /// the tile width.
+    #[staticmethod]
+    #[pyo3(signature = (json_str_or_path, collision_layer="collisions", cell_size=None))]
+    pub fn from_tiled(
+        json_str_or_path: &str,
+        collision_layer: &str,
+        cell_size: Option<f32>,
+    ) -> PyResult<Self> {
+        let json = if json_str_or_path.trim_start().starts_with('{') {
+            json_str_or_path.to_string()
+        } else {
+            std::fs::read_to_string(json_str_or_path)?
+        };
+        if json.trim_start().starts_with('<') {
+            return Err(PyValueError::new_err(
+                "TMX maps are not supported, export the map as JSON",
+            ));
+        }
+        let mut grid = tiled::load(&json, collision_layer, cell_size)?;
+        grid.report_skipped()?;
+        Ok(grid)
+    }
+
+    /// Serializes the grid to a JSON scene (schema documented in `src/scene.rs`) holding its
+    /// `config`, `kinds`, `bodies` with their shapes, entity transforms, `groups` and `sensors`.
+    /// Output is sorted so unchanged worlds produce identical files.
+    #[pyo3(signature = (pretty=true))]
+    pub fn to_json(&self, pretty: bool) -> PyResult<String> {
+        scene::to_json(self, pretty)
+    }
+
+    /// Rebuilds a grid from a JSON scene written by `to_json` or an external tool.
+    #[staticmethod]
+    pub fn from_json(json: &str) -> PyResult<Self> {
+        scene::from_json(json)
+    }
+
+    /// A world binned into pointy-top hexagons `cell_size` wide. Cell coordinates taken and
+    /// returned by its methods are axial `(q, r)`, and neighbors follow hex adjacency.
+    #[staticmethod]
+    #[pyo3(signature = (size, cell_size, max_bodies=None))]
+    pub fn hexagonal(size: f32, cell_size: f32, max_bodies: Option<usize>) -> PyResult<Self> {
+        let mut grid = Self::try_with_layout(size, cell_size, Layout::Hex)?;
+        grid.max_bodies = max_bodies;
+        Ok(grid)
+    }
+
+    pub fn reset(&mut self) {
+        for entity_index in self.entity_bounds.keys() {
+            self.changed.insert(*entity_index, self.tick);
+        }
+        self.dynamic_bodies.clear();
+        self.static_bodies.clear();
+        self.entity_bounds.clear();
+        self.transforms.clear();
+        self.static_exclusions.clear();
+        self.outside.clear();
+        self.velocities.clear();
+        self.max_displacements.clear();
+        self.drags.clear();
+        self.forces.clear();
+        self.masses.clear();
+        self.frictions.clear();
+        self.asleep.clear();
+        self.static_layer = None;
+        self.entity_ids.release_all();
+        self.stillness.clear();
+        self.pair_cooldowns.clear();
+        self.cooldown_starts.clear();
+        self.history.clear();
+        self.pending.clear();
+        self.dirty.clear();
+        self.projected.clear();
+        if self.reserved == (0, 0) {
+            let empty = Cell::default();
+            for cell in self.grid.iter_mut() {
+                *cell = empty.clone();
+            }
+        } else {
+            // Cells not shared with a fork keep their allocation
+            for cell in self.grid.iter_mut() {
+                match Arc::get_mut(cell) {
+                    Some(bodies) => bodies.clear(),
+                    None => *cell = Cell::default(),
+                }
+            }
+            self.reserve_capacity();
+        }
+    }
+
+    /// Allocates an entity index, reusing destroyed ones under a new generation. Indices
+    /// already given bodies directly are skipped.
+    pub fn create_entity(&mut self) -> EntityHandle {
+        let (dynamic, statics) = (&self.dynamic_bodies, &self.static_bodies);
+        self.entity_ids
+            .create(|index| dynamic.contains_key(&index) || statics.contains_key(&index))
+    }
+
+    /// Removes the bodies and settings of the entity and frees its index for `create_entity`.
+    /// Raises `KeyError` for a handle that was already destroyed.
+    pub fn destroy_entity(&mut self, handle: EntityHandle) -> PyResult<()> {
+        self.entity_ids.destroy(handle)?;
+        if self.deferred {
+            self.defer(Mutation::Forget(handle.index));
+        } else {
+            self.forget_entity(handle.index);
+        }
+        Ok(())
+    }
+
+    /// Removes every body of an entity.
+    #[pyo3(name = "remove_entity")]
+    pub fn py_remove_entity(&mut self, entity_index: usize) {
+        if self.deferred {
+            self.defer(Mutation::Remove(entity_index));
+        } else {
+            self.remove_entity(entity_index);
+        }
+    }
+
+    /// While deferred, adds, moves, transforms, velocity changes and removals are queued
+    /// instead of applied, and queries keep seeing the last committed state until `commit`.
+    /// Turning deferral off commits what is pending.
+    pub fn set_deferred(&mut self, deferred: bool) -> PyResult<()> {
+        if !deferred {
+            self.apply_pending()?;
+        }
+        self.deferred = deferred;
+        Ok(())
+    }
+
+    /// Applies the queued mutations together, in the order they were made, and returns how
+    /// many were applied. If some fail, for example moving an entity removed earlier in the
+    /// queue, the rest are still applied and the first error is raised.
+    pub fn commit(&mut self) -> PyResult<usize> {
+        self.apply_pending()
+    }
+
+    /// Drops the queued mutations without applying them, returning how many there were.
+    pub fn discard_pending(&mut self) -> usize {
+        self.dirty.clear();
+        self.projected.clear();
+        std::mem::take(&mut self.pending).len()
+    }
+
+    /// Cells the queued mutations will change, sorted.
+    pub fn dirty_cells(&self) -> Vec<(isize, isize)> {
+        let mut cells: Vec<(isize, isize)> = self
+            .dirty
+            .iter()
+            .map(|index| self.cell_coords(*index))
+            .collect();
+        cells.sort_unstable();
+        cells
+    }
+
+    /// Whether `handle` was created and not destroyed since.
+    pub fn is_alive(&self, handle: EntityHandle) -> bool {
+        self.entity_ids.is_alive(handle)
+    }
+
+    /// Renumbers entities with bodies densely from 0 within each namespace, keeping their
+    /// order, and returns the `{old: new}` indices for remapping arrays kept alongside the
+    /// world. Entities of stored static layers count as having bodies, so swapping a layer
+    /// in afterwards keeps to the new indices. Entities without bodies are forgotten and
+    /// saved states are cleared. Handles keep their generation at the new index. Changes are
+    /// tracked for `encode_delta`, so mirrors follow the renumbering.
+    pub fn compact(&mut self) -> HashMap<usize, usize> {
+        self.compact_entities()
+    }
+
+    /// Preallocates room for this many dynamic and static bodies, kept across `reset` and
+    /// `resize`, so a world refilled to known counts doesn't reallocate while filling its first
+    /// frame.
+    pub fn reserve(&mut self, dynamic_bodies_hint: usize, static_bodies_hint: usize) {
+        self.reserved = (dynamic_bodies_hint, static_bodies_hint);
+        self.reserve_capacity();
+    }
+
+    /// Advances the world clock by one tick of `dt` seconds and returns the new tick. With
+    /// `integrate` set, gravity, applied forces and drag first update the stored velocities,
+    /// then entities are moved by them, in substeps when a move exceeds `max_displacement`.
+    /// `push_out_iterations` passes of `resolve_overlaps` follow, then entities that stopped
+    /// moving fall asleep when `sleep_threshold` is set.
+    /// Collisions are stamped with the tick they were detected in, and the contacts of the
+    /// new tick are queued as events for `drain_events`. Handlers added with `on_collision`
+    /// are called last, once the world can be changed again.
+    #[pyo3(name = "step", signature = (dt=1.0 / 60.0))]
+    pub fn py_step(slf: &PyCell<Self>, dt: f64) -> PyResult<u64> {
+        let (tick, calls) = {
+            let mut grid = slf.borrow_mut();
+            let start = grid.events.len();
+            let tick = grid.step(dt);
+            (tick, grid.collision_calls(start))
+        };
+        callbacks::dispatch(slf.py(), calls)?;
+        Ok(tick)
+    }
+
+    /// Adds `frame_dt` seconds to the accumulator and runs as many `step`s of `fixed_dt` as
+    /// it covers, keeping the remainder for the next frame, so collisions behave the same at
+    /// any frame rate. Returns the queued events, including those of these ticks. Past
+    /// `max_steps_per_advance` ticks the rest of the frame is dropped. Handlers added with
+    /// `on_collision` are called after the last tick.
+    #[pyo3(name = "advance")]
+    pub fn py_advance(slf: &PyCell<Self>, frame_dt: f64) -> PyResult<Vec<Event>> {
+        let (events, calls) = {
+            let mut grid = slf.borrow_mut();
+            let start = grid.events.len();
+            grid.run_fixed_steps(frame_dt)?;
+            let calls = grid.collision_calls(start);
+            (grid.drain_events(), calls)
+        };
+        callbacks::dispatch(slf.py(), calls)?;
+        Ok(events)
+    }
+
+    /// Calls `handler` with every `Collision` and `Sensor` event of later steps between a
+    /// body of kind `layer_a` and one of kind `layer_b`, either way round, with the
+    /// `layer_a` body as its `self` side. A missing layer matches any kind, and a pair is
+    /// passed once per tick even when reported from both sides. Returns an id
+    /// for `remove_collision_handler`.
+    #[pyo3(signature = (handler, layer_a=None, layer_b=None))]
+    pub fn on_collision(
+        &mut self,
+        py: Python,
+        handler: PyObject,
+        layer_a: Option<KindArg>,
+        layer_b: Option<KindArg>,
+    ) -> PyResult<usize> {
+        if !handler.as_ref(py).is_callable() {
+            return Err(PyTypeError::new_err("collision handler must be callable"));
+        }
+        let kinds = (
+            layer_a.map(|kind| self.kinds.intern(kind)),
+            layer_b.map(|kind| self.kinds.intern(kind)),
+        );
+        let id = self.next_handler_id;
+        self.next_handler_id += 1;
+        self.collision_handlers
+            .push(CollisionHandler::new(id, handler, kinds));
+        Ok(id)
+    }
+
+    /// Stops calling a handler added with `on_collision`, returning whether it existed.
+    pub fn remove_collision_handler(&mut self, id: usize) -> bool {
+        let before = self.collision_handlers.len();
+        self.collision_handlers.retain(|handler| handler.id != id);
+        self.collision_handlers.len() != before
+    }
+
+    /// Returns and clears the events queued by `step`.
+    pub fn drain_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Sends structured records of body inserts and removals, and of anomalies (bodies
+    /// inserted outside the grid or into more than `max_cells_per_body` cells), to `hook` at
+    /// each `step`. Records are dicts with `event`, `tick`, `entity_index`, `body_index` and
+    /// `cells`. A `logging.Logger` gets them at DEBUG, or WARNING for anomalies, as
+    /// `extra={"radyx": record}`; any other callable is called with each record. `None`
+    /// turns logging off.
+    #[pyo3(signature = (hook=None))]
+    pub fn set_log_hook(&mut self, py: Python, hook: Option<PyObject>) -> PyResult<()> {
+        self.logging.set_hook(py, hook)
+    }
+
+    /// Serves the world over HTTP on `host:port` until `stop_debug_server`: a page drawing it
+    /// at `/` and a JSON view of bodies, occupied cells and counters at `/state.json`,
+    /// refreshed every `step`. Port 0 picks a free port; the bound port is returned. Bind
+    /// `0.0.0.0` to watch a run on another machine.
+    #[pyo3(signature = (port, host="127.0.0.1"))]
+    pub fn start_debug_server(&mut self, port: u16, host: &str) -> PyResult<u16> {
+        self.debug_server.0 = None;
+        let server = DebugServer::start(host, port)?;
+        server.publish(debug_server::view(self));
+        let port = server.port;
+        self.debug_server.0 = Some(server);
+        Ok(port)
+    }
+
+    pub fn stop_debug_server(&mut self) {
+        self.debug_server.0 = None;
+    }
+
+    /// Draws cells, static and dynamic bodies and the contacts of the last step to an `.svg`
+    /// or `.png` file, `scale` pixels per world unit, to attach a picture of the world to a
+    /// bug report. `scale` must be positive and finite.
+    #[pyo3(signature = (path, scale=1.0))]
+    pub fn export_debug_image(&self, path: &str, scale: f32) -> PyResult<()> {
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "debug image scale must be positive and finite, got {scale}"
+            )));
+        }
+        let extension = std::path::Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        let image = match extension.as_deref() {
+            Some("svg") => debug_image::svg(self, scale).into_bytes(),
+            Some("png") => debug_image::png(&debug_image::rasterize(self, scale)?),
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "expected a .svg or .png path, got {path:?}"
+                )))
+            }
+        };
+        Ok(std::fs::write(path, image)?)
+    }
+
+    /// Approximate bytes used by cells, body lists and caches, with how many body copies the
+    /// cells hold, to track memory across levels and spot a `cell_size` far below body sizes.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.memory()
+    }
+
+    /// A copy of the world that can be changed and queried on its own, for speculative
+    /// queries. Cells are shared with this world until either side changes them, so forking
+    /// doesn't copy the binned bodies up front. Save slots of the fork start out empty.
+    pub fn fork(&self) -> Self {
+        self.clone()
+    }
+
+    /// Bodies added, removed and moved going from `snapshot_a` to `snapshot_b`, typically
+    /// forks of the same world taken at different ticks.
+    #[staticmethod]
+    pub fn diff(snapshot_a: PyRef<GridPhysics>, snapshot_b: PyRef<GridPhysics>) -> WorldDiff {
+        diff::diff(&snapshot_a, &snapshot_b)
+    }
+
+    /// Encodes every entity whose bodies were added, moved or removed at or after `since_tick`
+    /// into a compact binary delta for `apply_delta`, so a server-owned world can stream
+    /// authoritative updates to mirrors on clients.
+    #[pyo3(signature = (since_tick=0))]
+    pub fn encode_delta<'py>(&self, py: Python<'py>, since_tick: u64) -> PyResult<&'py PyBytes> {
+        Ok(PyBytes::new(py, &delta::encode(self, since_tick)?))
+    }
+
+    /// Replaces the entities listed in a delta from `encode_delta` with their encoded bodies,
+    /// removing those left without bodies. Invalid bodies are skipped like when adding them,
+    /// and while deferred the changes wait for `commit`. Returns the tick the delta was
+    /// encoded at.
+    pub fn apply_delta(&mut self, data: &[u8]) -> PyResult<u64> {
+        delta::apply(self, data)
+    }
+
+    /// Sets the number of `save_state` slots, 8 by default. Shrinking drops the states of the
+    /// removed slots.
+    pub fn set_state_slots(&mut self, count: usize) {
+        self.state_slots.resize(count);
+    }
+
+    /// Saves the simulated state into `slot` for `load_state`: dynamic bodies, entity
+    /// transforms and velocities, contacts and the clock. Static bodies are not saved and
+    /// must not change while rolling back. Much cheaper than `to_json` for per-tick rollback.
+    pub fn save_state(&mut self, slot: usize) -> PyResult<()> {
+        self.save_snapshot(slot)
+    }
+
+    /// Restores the state saved in `slot`, which stays saved, and forgets the history of
+    /// later ticks.
+    pub fn load_state(&mut self, slot: usize) -> PyResult<()> {
+        self.load_snapshot(slot)
+    }
+
+    /// Prebuilds a static layer named `name` from a JSON list of static body records, in the
+    /// schema of the `bodies` of `to_json`, replacing any layer with that name. The grid
+    /// doesn't change until the layer is swapped in, so every level can be loaded up front.
+    pub fn load_static_layer(&mut self, name: String, bodies: &str) -> PyResult<()> {
+        let bodies = scene::bodies_from_json(bodies)?;
+        self.load_layer(name, bodies)
+    }
+
+    /// Replaces every static body in the grid with those of a layer from
+    /// `load_static_layer`, keeping the dynamic ones. Costs about as much as binning the
+    /// dynamic bodies again, however much static geometry the levels hold.
+    pub fn swap_static_layer(&mut self, name: &str) -> PyResult<()> {
+        self.swap_layer(name)
+    }
+
+    /// Names of the layers from `load_static_layer`, sorted.
+    pub fn static_layer_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.static_layers.keys().cloned().collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Keeps the collisions of the last `length` steps for `collisions_at` and
+    /// `was_colliding`. `0`, the default, records nothing.
+    pub fn set_history_length(&mut self, length: usize) {
+        self.history.set_capacity(length);
+    }
+
+    /// Collisions detected by the `step` that reached `tick`, while it is still in the
+    /// history.
+    pub fn collisions_at(&self, tick: u64) -> PyResult<HashSet<Collision>> {
+        let contacts = self
+            .history
+            .at(tick)
+            .ok_or_else(|| PyKeyError::new_err(format!("tick {tick} is not in the history")))?;
+        Ok(contacts
+            .iter()
+            .map(|key| Collision::new(key.0, key.1, key.2, key.3).at_tick(tick))
+            .collect())
+    }
+
+    /// Whether any bodies of entities `a` and `b` collided in the last `within_ticks` steps
+    /// before the current tick, or in the current one, as far as the history reaches.
+    #[pyo3(signature = (a, b, within_ticks=0))]
+    pub fn was_colliding(&self, a: usize, b: usize, within_ticks: u64) -> bool {
+        self.history
+            .was_colliding(a, b, self.tick.saturating_sub(within_ticks))
+    }
+
+    /// Contacts involving a sensor entity are reported as `Sensor` events instead of
+    /// `Collision` events.
+    pub fn set_sensor(&mut self, entity_index: usize, is_sensor: bool) {
+        if is_sensor {
+            self.sensors.insert(entity_index);
+        } else {
+            self.sensors.remove(&entity_index);
+        }
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Seconds elapsed over all steps.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Reallocates the grid for a new world size and cell size, re-binning every body. Bodies
+    /// the new size leaves outside, or brings back in, get `Left` and `Reentered` events, and
+    /// cells get the room `reserve` asked for. Raises `ValueError` while mutations are
+    /// deferred.
+    pub fn resize(&mut self, new_size: f32, new_cell_size: f32) -> PyResult<()> {
+        if self.deferred {
+            return Err(PyValueError::new_err(
+                "can't resize the grid while mutations are deferred",
+            ));
+        }
+        let resized = GridPhysics::try_with_layout(new_size, new_cell_size, self.layout)?;
+        self.grid = resized.grid;
+        self.size = resized.size;
+        self.cell_size = resized.cell_size;
+        self.grid_size = resized.grid_size;
+
+        let bodies: Vec<Body> = self
+            .dynamic_bodies
+            .values()
+            .chain(self.static_bodies.values())
+            .flatten()
+            .map(|placed| placed.body)
+            .collect();
+        if self.reserved != (0, 0) {
+            self.reserve_capacity();
+        }
+        for body in bodies {
+            self.insert_into_cells(body);
+        }
+
+        let mut entities: Vec<usize> = self.dynamic_bodies.keys().copied().collect();
+        entities.sort_unstable();
+        for entity_index in entities {
+            self.track_outside(entity_index);
+        }
+        Ok(())
+    }
+
+    /// Cells covered by some bounds as `(min_x, max_x, min_y, max_y)`, inclusive. The upper
+    /// ends round up, so bounds ending exactly on a cell edge reach the cell past it, which
+    /// is where bounds starting on that edge begin.
+    pub fn get_grid_bounds(&self, bounds: (f32, f32, f32, f32)) -> (usize, usize, usize, usize) {
+        (
+            (bounds.0 / self.cell_size).floor() as usize,
+            (bounds.1 / self.cell_size).ceil() as usize,
+            (bounds.2 / self.cell_size).floor() as usize,
+            (bounds.3 / self.cell_size).ceil() as usize,
+        )
+    }
+
+    #[pyo3(
+        name = "add_circle",
+        signature = (entity_index, pos, radius, body_index, is_static, kind=KindArg::default())
+    )]
+    pub fn py_add_circle(
+        &mut self,
+        entity_index: usize,
+        pos: Vector2,
+        radius: f32,
+        body_index: usize,
+        is_static: bool,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        self.ensure_room(1)?;
+        let kind = self.kinds.intern(kind);
+        self.check_body_indices(entity_index, body_index.saturating_add(1), kind)?;
+        self.add_circle(entity_index, pos, radius, body_index, is_static, kind);
+        self.report_skipped()
+    }
+
+    #[pyo3(
+        name = "add_static_circle",
+        signature = (entity_index, pos, radius, kind=KindArg::default())
+    )]
+    pub fn py_add_static_circle(
+        &mut self,
+        entity_index: usize,
+        pos: Vector2,
+        radius: f32,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        self.ensure_room(1)?;
+        let kind = self.kinds.intern(kind);
+        self.check_body_indices(entity_index, 1, kind)?;
+        self.add_static_circle(entity_index, pos, radius, kind);
+        self.report_skipped()
+    }
+
+    /// With an `anchor`, `bodies` are offsets from it and the entity can later be moved as
+    /// a whole with `move_entity`.
+    #[pyo3(
+        name = "add_static_circles",
+        signature = (entity_index, bodies, radius, kind=KindArg::default(), anchor=None)
+    )]
+    pub fn py_add_static_circles(
+        &mut self,
+        entity_index: usize,
+        bodies: Vec<Vector2>,
+        radius: f32,
+        kind: KindArg,
+        anchor: Option<Vector2>,
+    ) -> PyResult<()> {
+        self.ensure_room(bodies.len())?;
+        let kind = self.kinds.intern(kind);
+        self.check_body_indices(entity_index, bodies.len(), kind)?;
+        match anchor {
+            Some(anchor) => {
+                self.add_anchored_circles(entity_index, bodies, radius, anchor, true, kind)
+            }
+            None => self.add_static_circles(entity_index, bodies, radius, kind),
+        }
+        self.report_skipped()
+    }
+
+    /// Adds static circles at the rows of an `(n, 2)` `positions` array with per-circle
+    /// `radii`, float32 or float64, indexed in row order. The GIL is released while the
+    /// circles are binned into cells in parallel, for loading large maps.
+    #[pyo3(signature = (entity_index, positions, radii, kind=KindArg::default()))]
+    pub fn add_static_circles_numpy(
+        &mut self,
+        py: Python,
+        entity_index: usize,
+        positions: bulk::Points,
+        radii: bulk::Values,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        let (positions, radii) = (positions.to_f32(), radii.to_f32());
+        self.add_circles_array(py, entity_index, positions, radii, true, kind)
+    }
+
+    /// Dynamic counterpart of `add_static_circles_numpy`.
+    #[pyo3(signature = (entity_index, positions, radii, kind=KindArg::default()))]
+    pub fn add_dynamic_circles_numpy(
+        &mut self,
+        py: Python,
+        entity_index: usize,
+        positions: bulk::Points,
+        radii: bulk::Values,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        let (positions, radii) = (positions.to_f32(), radii.to_f32());
+        self.add_circles_array(py, entity_index, positions, radii, false, kind)
+    }
+
+    #[pyo3(
+        name = "add_dynamic_circle",
+        signature = (entity_index, pos, radius, kind=KindArg::default())
+    )]
+    pub fn py_add_dynamic_circle(
+        &mut self,
+        entity_index: usize,
+        pos: Vector2,
+        radius: f32,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        self.ensure_room(1)?;
+        let kind = self.kinds.intern(kind);
+        self.check_body_indices(entity_index, 1, kind)?;
+        self.add_dynamic_circle(entity_index, pos, radius, kind);
+        self.report_skipped()
+    }
+
+    /// With an `anchor`, `bodies` are offsets from it and the entity can later be moved as
+    /// a whole with `move_entity`.
+    #[pyo3(
+        name = "add_dynamic_circles",
+        signature = (entity_index, bodies, radius, kind=KindArg::default(), anchor=None)
+    )]
+    pub fn py_add_dynamic_circles(
+        &mut self,
+        entity_index: usize,
+        bodies: Vec<Vector2>,
+        radius: f32,
+        kind: KindArg,
+        anchor: Option<Vector2>,
+    ) -> PyResult<()> {
+        self.ensure_room(bodies.len())?;
+        let kind = self.kinds.intern(kind);
+        self.check_body_indices(entity_index, bodies.len(), kind)?;
+        match anchor {
+            Some(anchor) => {
+                self.add_anchored_circles(entity_index, bodies, radius, anchor, false, kind)
+            }
+            None => self.add_dynamic_circles(entity_index, bodies, radius, kind),
+        }
+        self.report_skipped()
+    }
+
+    /// Adds an ellipse with semi-axes `radius_x` and `radius_y`, rotated by `angle` radians,
+    /// for long thin bodies a circle would bound poorly.
+    #[pyo3(signature = (entity_index, pos, radius_x, radius_y, angle=0.0, kind=KindArg::default()))]
+    pub fn add_static_ellipse(
+        &mut self,
+        entity_index: usize,
+        pos: Vector2,
+        radius_x: f32,
+        radius_y: f32,
+        angle: f32,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        self.ensure_room(1)?;
+        let kind = self.kinds.intern(kind);
+        self.check_body_indices(entity_index, 1, kind)?;
+        self.add_ellipse(entity_index, pos, (radius_x, radius_y), angle, true, kind);
+        self.report_skipped()
+    }
+
+    #[pyo3(signature = (entity_index, pos, radius_x, radius_y, angle=0.0, kind=KindArg::default()))]
+    pub fn add_dynamic_ellipse(
+        &mut self,
+        entity_index: usize,
+        pos: Vector2,
+        radius_x: f32,
+        radius_y: f32,
+        angle: f32,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        self.ensure_room(1)?;
+        let kind = self.kinds.intern(kind);
+        self.check_body_indices(entity_index, 1, kind)?;
+        self.add_ellipse(entity_index, pos, (radius_x, radius_y), angle, false, kind);
+        self.report_skipped()
+    }
+
+    /// Adds a static polyline through `points`, one segment body per consecutive pair with
+    /// body indices in order. Each segment is only stored in the cells it crosses and is
+    /// tested against circles by their distance to it.
+    #[pyo3(
+        name = "add_static_chain",
+        signature = (entity_index, points, kind=KindArg::default())
+    )]
+    pub fn py_add_static_chain(
+        &mut self,
+        entity_index: usize,
+        points: Vec<Vector2>,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        self.ensure_room(points.len().saturating_sub(1))?;
+        let kind = self.kinds.intern(kind);
+        self.check_body_indices(entity_index, points.len().saturating_sub(1), kind)?;
+        self.add_static_chain(entity_index, &points, kind);
+        self.report_skipped()
+    }
+
+    /// Adds static triangles, each given by three indices into `vertices`, as one body per
+    /// triangle in order. Triangles are binned by their bounds and tested exactly against
+    /// circles.
+    #[pyo3(
+        name = "add_static_trimesh",
+        signature = (entity_index, vertices, indices, kind=KindArg::default())
+    )]
+    pub fn py_add_static_trimesh(
+        &mut self,
+        entity_index: usize,
+        vertices: Vec<Vector2>,
+        indices: Vec<(usize, usize, usize)>,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        self.ensure_room(indices.len())?;
+        let kind = self.kinds.intern(kind);
+        self.check_body_indices(entity_index, indices.len(), kind)?;
+        self.add_static_trimesh(entity_index, &vertices, &indices, kind)?;
+        self.report_skipped()
+    }
+
+    /// Adds a rectangle centered on `center` with `half_extents` along its own axes, rotated
+    /// by `angle` radians. It is binned by the box around the rotated rectangle and tested
+    /// with SAT against other rectangles.
+    #[pyo3(signature = (entity_index, center, half_extents, angle=0.0, kind=KindArg::default()))]
+    pub fn add_static_obb(
+        &mut self,
+        entity_index: usize,
+        center: Vector2,
+        half_extents: Vector2,
+        angle: f32,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        self.ensure_room(1)?;
+        let kind = self.kinds.intern(kind);
+        self.check_body_indices(entity_index, 1, kind)?;
+        let half_extents = (half_extents.x, half_extents.y);
+        self.add_obb(entity_index, center, half_extents, angle, true, kind);
+        self.report_skipped()
+    }
+
+    #[pyo3(signature = (entity_index, center, half_extents, angle=0.0, kind=KindArg::default()))]
+    pub fn add_dynamic_obb(
+        &mut self,
+        entity_index: usize,
+        center: Vector2,
+        half_extents: Vector2,
+        angle: f32,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        self.ensure_room(1)?;
+        let kind = self.kinds.intern(kind);
+        self.check_body_indices(entity_index, 1, kind)?;
+        let half_extents = (half_extents.x, half_extents.y);
+        self.add_obb(entity_index, center, half_extents, angle, false, kind);
+        self.report_skipped()
+    }
+
+    /// Places an entity at `position` rotated by `angle` radians counter-clockwise. Body
+    /// positions are the offsets they were added with, relative to the entity origin, so
+    /// rotating a compound entity needs no per-body work in Python.
+    pub fn set_entity_transform(
+        &mut self,
+        entity_index: usize,
+        position: Vector2,
+        angle: f32,
+    ) -> PyResult<()> {
+        let transform = Transform { position, angle };
+        if self.deferred {
+            self.defer(Mutation::SetTransform(entity_index, transform));
+            return Ok(());
+        }
+        self.place_entity(entity_index, transform)
+    }
+
+    /// Moves an entity so its anchor is at `anchor`, keeping its rotation, and re-bins all
+    /// of its bodies.
+    pub fn move_entity(&mut self, entity_index: usize, anchor: Vector2) -> PyResult<()> {
+        if self.deferred {
+            self.defer(Mutation::MoveEntity(entity_index, anchor));
+            return Ok(());
+        }
+        let angle = self
+            .transforms
+            .get(&entity_index)
+            .map_or(0.0, |transform| transform.angle);
+        self.place_entity(
+            entity_index,
+            Transform {
+                position: anchor,
+                angle,
+            },
+        )
+    }
+
+    /// Moves a single body to `pos` in world space and re-bins it. Moving a dynamic body
+    /// entirely out of the world, or back in, queues a `Left` or `Reentered` event.
+    pub fn update_position(
+        &mut self,
+        entity_index: usize,
+        body_index: usize,
+        pos: Vector2,
+    ) -> PyResult<()> {
+        if self.deferred {
+            self.pending_body(entity_index, body_index)?;
+            self.defer(Mutation::UpdatePosition(entity_index, body_index, pos));
+            return Ok(());
+        }
+        self.move_body(entity_index, body_index, pos).map(|_| ())
+    }
+
+    /// Changes the radius of a single body, re-binning it if it now covers other cells.
+    /// Shapes other than circles are scaled about their position so their bounding radius
+    /// is `new_radius`.
+    pub fn set_radius(
+        &mut self,
+        entity_index: usize,
+        body_index: usize,
+        new_radius: f32,
+    ) -> PyResult<()> {
+        if !new_radius.is_finite() || new_radius < 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "radius must be finite and non-negative, got {new_radius}"
+            )));
+        }
+        if self.deferred {
+            self.pending_body(entity_index, body_index)?;
+            self.defer(Mutation::SetRadius(entity_index, body_index, new_radius));
+            return Ok(());
+        }
+        self.resize_body(entity_index, body_index, new_radius)
+    }
+
+    /// Scales every body of an entity by `factor` and re-bins them. Offsets scale with the
+    /// radii once the entity has been placed with `move_entity` or a transform, so a
+    /// compound entity grows about its anchor; otherwise bodies keep their positions.
+    pub fn scale_entity(&mut self, entity_index: usize, factor: f32) -> PyResult<()> {
+        if !factor.is_finite() || factor <= 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "scale factor must be finite and positive, got {factor}"
+            )));
+        }
+        if self.deferred {
+            if !self.pending_entity(entity_index) {
+                return Err(PyKeyError::new_err(format!(
+                    "unknown entity {entity_index}"
+                )));
+            }
+            self.defer(Mutation::Scale(entity_index, factor));
+            return Ok(());
+        }
+        self.scale_bodies(entity_index, factor)
+    }
+
+    /// Moves a single body to `new_pos` like `update_position`, returning its collisions at
+    /// the destination. With `sweep`, bodies crossed on the straight path from the old
+    /// position are reported too, so a blink through a tripwire still hits it. The path is
+    /// checked along its center line and at steps of the body's bounding radius.
+    #[pyo3(signature = (entity_index, body_index, new_pos, sweep=false))]
+    pub fn teleport(
+        &mut self,
+        entity_index: usize,
+        body_index: usize,
+        new_pos: Vector2,
+        sweep: bool,
+    ) -> PyResult<HashSet<Collision>> {
+        let before = if self.deferred {
+            let before = self.pending_body(entity_index, body_index)?;
+            self.defer(Mutation::UpdatePosition(entity_index, body_index, new_pos));
+            before
+        } else {
+            self.move_body(entity_index, body_index, new_pos)?
+        };
+        let mut moved = before;
+        moved.pos = new_pos;
+
+        let (dx, dy) = (new_pos.x - before.pos.x, new_pos.y - before.pos.y);
+        let distance = dx.hypot(dy);
+        let steps = if sweep && distance > 0.0 {
+            (distance / before.radius.max(f32::EPSILON)).ceil().min(1e6) as usize
+        } else {
+            0
+        };
+        let direction = Vector2::new(dx / distance, dy / distance);
+        let bounds = if sweep {
+            union_bounds(before.get_bounds(), moved.get_bounds())
+        } else {
+            moved.get_bounds()
+        };
+
+        let mut collisions = HashSet::new();
+        let mut checked = HashSet::new();
+        for cell in self.cells_within(bounds) {
+            for other in cell.iter() {
+                if other.entity_index() == entity_index || !self.is_active(other.entity_index()) {
+                    continue;
+                }
+                // A body spanning several cells is only walked along the path once
+                if !checked.insert((other.entity_index(), other.body_index())) {
+                    continue;
+                }
+                let crossed = steps > 0
+                    && (other
+                        .ray_hit(before.pos, direction)
+                        .is_some_and(|t| t <= distance)
+                        || (0..steps).any(|step| {
+                            let t = step as f32 / steps as f32;
+                            let mut sample = before;
+                            sample.pos = Vector2::new(before.pos.x + dx * t, before.pos.y + dy * t);
+                            sample.touches(other)
+                        }));
+                if crossed || (moved.touches(other) && self.deep_enough(&moved, other)) {
+                    let collision = Collision::new(
+                        entity_index,
+                        other.entity_index(),
+                        body_index,
+                        other.body_index(),
+                    );
+                    collisions.insert(collision.at_tick(self.tick));
+                }
+            }
+        }
+        Ok(collisions)
+    }
+
+    /// Stores the velocity of an entity. Its dynamic bodies are then binned into every cell
+    /// they cross within `sweep_dt` as well, so fast movers show up as broad phase candidates
+    /// along their path. A zero velocity clears it.
+    pub fn set_velocity(&mut self, entity_index: usize, velocity: Vector2) -> PyResult<()> {
+        let known = if self.deferred {
+            self.pending_entity(entity_index)
+        } else {
+            self.entity_bounds.contains_key(&entity_index)
+        };
+        if !known {
+            return Err(PyKeyError::new_err(format!(
+                "unknown entity {entity_index}"
+            )));
+        }
+        if self.check_vector("velocity", entity_index, velocity)? {
+            self.assign_velocity(entity_index, velocity);
+        }
+        Ok(())
+    }
+
+    /// Changes the velocity of the entity owning a body by `impulse` divided by its mass, at
+    /// once.
+    pub fn apply_impulse(
+        &mut self,
+        entity_index: usize,
+        body_index: usize,
+        impulse: Vector2,
+    ) -> PyResult<()> {
+        if self.deferred {
+            self.pending_body(entity_index, body_index)?;
+        } else {
+            self.find_body(entity_index, body_index)?;
+        }
+        if !self.check_vector("impulse", entity_index, impulse)? {
+            return Ok(());
+        }
+        let velocity = self.pending_velocity(entity_index);
+        let mass = self.mass_of(entity_index);
+        self.assign_velocity(
+            entity_index,
+            Vector2::new(velocity.x + impulse.x / mass, velocity.y + impulse.y / mass),
+        );
+        Ok(())
+    }
+
+    /// Adds `force` to the entity owning a body for the next `step`, which turns it into a
+    /// velocity change of `force * dt / mass` when integrating. Forces add up until then and are
+    /// cleared by every `step`.
+    pub fn apply_force(
+        &mut self,
+        entity_index: usize,
+        body_index: usize,
+        force: Vector2,
+    ) -> PyResult<()> {
+        self.find_body(entity_index, body_index)?;
+        if !self.check_vector("force", entity_index, force)? {
+            return Ok(());
+        }
+        let total = self
+            .forces
+            .entry(entity_index)
+            .or_insert(Vector2::new(0.0, 0.0));
+        *total = Vector2::new(total.x + force.x, total.y + force.y);
+        Ok(())
+    }
+
+    /// Pushes every dynamic entity with a body within `radius` of `center` away from it with
+    /// an impulse of `strength`, weakened by the distance to its nearest body's surface under
+    /// `falloff` (`"constant"`, `"linear"` or `"quadratic"`). Returns the applied
+    /// `(entity_index, (x, y))` impulses in entity order.
+    #[pyo3(signature = (center, radius, strength, falloff=explosion::Falloff::Linear))]
+    pub fn radial_impulse(
+        &mut self,
+        center: Vector2,
+        radius: f32,
+        strength: f32,
+        falloff: explosion::Falloff,
+    ) -> PyResult<Vec<(usize, (f32, f32))>> {
+        if ![center.x, center.y, radius, strength]
+            .iter()
+            .all(|value| value.is_finite())
+        {
+            return Err(PyErr::new::<RadyxError, _>(format!(
+                "radial_impulse needs finite values, got center ({}, {}), radius {radius} and \
+                 strength {strength}",
+                center.x, center.y
+            )));
+        }
+        let impulses = self.radial_impulses(center, radius, strength, falloff);
+        for (entity_index, impulse) in &impulses {
+            let velocity = self.pending_velocity(*entity_index);
+            let mass = self.mass_of(*entity_index);
+            self.assign_velocity(
+                *entity_index,
+                Vector2::new(velocity.x + impulse.x / mass, velocity.y + impulse.y / mass),
+            );
+        }
+        Ok(impulses
+            .into_iter()
+            .map(|(entity_index, impulse)| (entity_index, (impulse.x, impulse.y)))
+            .collect())
+    }
+
+    /// Sets the mass of an entity, shared by all its bodies. Impulses and forces change its
+    /// velocity less the heavier it is, and overlap push-out moves it less than a lighter
+    /// entity it overlaps. Statics count as infinitely heavy.
+    pub fn set_mass(&mut self, entity_index: usize, mass: f32) -> PyResult<()> {
+        if !mass.is_finite() || mass <= 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "mass must be finite and positive, got {mass}"
+            )));
+        }
+        if mass == 1.0 {
+            self.masses.remove(&entity_index);
+        } else {
+            self.masses.insert(entity_index, mass);
+        }
+        Ok(())
+    }
+
+    /// Mass of an entity, one unless set with `set_mass`.
+    pub fn get_mass(&self, entity_index: usize) -> f32 {
+        self.mass_of(entity_index)
+    }
+
+    /// Sets the friction coefficient of one body. When overlap push-out stops two bodies
+    /// moving into each other it also slows their sliding along each other, by at most the
+    /// geometric mean of their coefficients times the stopped speed. Zero, the default, lets
+    /// bodies slide freely.
+    pub fn set_friction(
+        &mut self,
+        entity_index: usize,
+        body_index: usize,
+        friction: f32,
+    ) -> PyResult<()> {
+        self.find_body(entity_index, body_index)?;
+        if !friction.is_finite() || friction < 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "friction must be finite and non-negative, got {friction}"
+            )));
+        }
+        if friction == 0.0 {
+            self.frictions.remove(&(entity_index, body_index));
+        } else {
+            self.frictions.insert((entity_index, body_index), friction);
+        }
+        Ok(())
+    }
+
+    /// Friction coefficient of one body, zero unless set with `set_friction`.
+    pub fn get_friction(&self, entity_index: usize, body_index: usize) -> f32 {
+        self.friction_of(entity_index, body_index)
+    }
+
+    /// Holds a pair of entities back for `ticks` ticks after a collision between them is
+    /// reported by `step`: until then they are left out of collision queries and events, as
+    /// if apart, so leaving the cooldown still touching reports them entering again. Zero
+    /// removes the cooldown.
+    pub fn set_pair_cooldown(&mut self, entity_a: usize, entity_b: usize, ticks: u64) {
+        let pair = cooldown::entity_pair(entity_a, entity_b);
+        if ticks == 0 {
+            self.pair_cooldowns.remove(&pair);
+            self.cooldown_starts.remove(&pair);
+        } else {
+            self.pair_cooldowns.insert(pair, ticks);
+        }
+    }
+
+    /// Moves overlapping entities apart, in up to `iterations` passes. Each contact involving
+    /// a dynamic body is corrected along the line between the body centers, and the
+    /// correction is split by inverse mass: the lighter entity moves further, and statics
+    /// never move. Velocity into the contact is removed, and friction set with
+    /// `set_friction` slows sliding along it. Sensors and frozen entities are left alone.
+    /// Returns how many contacts were corrected.
+    #[pyo3(signature = (iterations=1))]
+    pub fn resolve_overlaps(&mut self, iterations: usize) -> usize {
+        self.push_out(iterations)
+    }
+
+    /// Whether an entity is asleep. Sleeping entities aren't sources of whole-world collision
+    /// queries, though awake bodies still collide with them, and wake when moved, given a
+    /// velocity or force, or touched by an awake dynamic body in a `step`.
+    pub fn is_sleeping(&self, entity_index: usize) -> bool {
+        self.asleep.contains(&entity_index)
+    }
+
+    /// Sorted indices of sleeping entities.
+    pub fn sleeping_entities(&self) -> Vec<usize> {
+        let mut entities: Vec<usize> = self.asleep.iter().copied().collect();
+        entities.sort_unstable();
+        entities
+    }
+
+    /// Wakes a sleeping entity and restarts its stillness count.
+    pub fn wake(&mut self, entity_index: usize) {
+        self.wake_entity(entity_index);
+    }
+
+    /// Sets the linear drag of an entity: its velocity decays by `exp(-drag * dt)` every
+    /// integrated `step`. Zero turns drag off.
+    pub fn set_drag(&mut self, entity_index: usize, drag: f32) -> PyResult<()> {
+        if !drag.is_finite() || drag < 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "drag must be finite and non-negative, got {drag}"
+            )));
+        }
+        if drag == 0.0 {
+            self.drags.remove(&entity_index);
+        } else {
+            self.drags.insert(entity_index, drag);
+        }
+        Ok(())
+    }
+
+    /// Overrides `max_displacement` for one entity; `None` goes back to the world setting.
+    #[pyo3(signature = (entity_index, max_displacement=None))]
+    pub fn set_entity_max_displacement(
+        &mut self,
+        entity_index: usize,
+        max_displacement: Option<f32>,
+    ) -> PyResult<()> {
+        if let Some(limit) = max_displacement.filter(|limit| limit.is_nan() || *limit <= 0.0) {
+            return Err(PyValueError::new_err(format!(
+                "max_displacement must be positive, got {limit}"
+            )));
+        }
+        match max_displacement {
+            Some(limit) => self.max_displacements.insert(entity_index, limit),
+            None => self.max_displacements.remove(&entity_index),
+        };
+        Ok(())
+    }
+
+    /// Stored velocity of an entity, zero if none was set.
+    pub fn get_velocity(&self, entity_index: usize) -> (f32, f32) {
+        self.velocities
+            .get(&entity_index)
+            .map_or((0.0, 0.0), |velocity| (velocity.x, velocity.y))
+    }
+
+    /// Dead-reckoned `(x, y)` positions of an entity's bodies `dt` seconds ahead under its
+    /// stored velocity, dynamic bodies first and each in body index order. Nothing is moved.
+    pub fn extrapolate(&self, entity_index: usize, dt: f32) -> PyResult<Vec<(f32, f32)>> {
+        if !self.entity_bounds.contains_key(&entity_index) {
+            return Err(PyKeyError::new_err(format!(
+                "unknown entity {entity_index}"
+            )));
+        }
+        let (vx, vy) = self.get_velocity(entity_index);
+        let mut bodies: Vec<&Body> = self.bodies_of(entity_index).collect();
+        bodies.sort_unstable_by_key(|body| (body.is_static(), body.body_index()));
+        Ok(bodies
+            .into_iter()
+            .map(|body| (body.pos.x + vx * dt, body.pos.y + vy * dt))
+            .collect())
+    }
+
+    /// Earliest time within `dt` (the sweep time step by default) at which a body of
+    /// `entity_a` touches a body of `entity_b` as both translate, or `None` if they don't meet.
+    /// `velocities` overrides the stored velocities of the two entities as `(a, b)`. Circles
+    /// are solved exactly and other shapes by conservative advancement; bodies touching at the
+    /// start give zero.
+    #[pyo3(signature = (entity_a, entity_b, velocities=None, dt=None))]
+    pub fn time_of_impact(
+        &self,
+        entity_a: usize,
+        entity_b: usize,
+        velocities: Option<(Vector2, Vector2)>,
+        dt: Option<f32>,
+    ) -> PyResult<Option<f32>> {
+        let velocities = velocities.unwrap_or_else(|| {
+            let stored = |entity_index| {
+                let (x, y) = self.get_velocity(entity_index);
+                Vector2::new(x, y)
+            };
+            (stored(entity_a), stored(entity_b))
+        });
+        self.first_contact(entity_a, entity_b, velocities, dt.unwrap_or(self.sweep_dt))
+    }
+
+    /// A fork of the world with every entity that has a velocity moved `dt` seconds ahead,
+    /// so any query run on it sees extrapolated positions while this world stays
+    /// authoritative.
+    pub fn extrapolated(&self, dt: f32) -> PyResult<Self> {
+        let mut fork = self.clone();
+        let mut moving: Vec<(usize, Vector2)> = self
+            .velocities
+            .iter()
+            .map(|(entity_index, velocity)| (*entity_index, *velocity))
+            .collect();
+        moving.sort_unstable_by_key(|(entity_index, _)| *entity_index);
+        for (entity_index, velocity) in moving {
+            let mut transform = fork
+                .transforms
+                .get(&entity_index)
+                .copied()
+                .unwrap_or_default();
+            transform.position.x += velocity.x * dt;
+            transform.position.y += velocity.y * dt;
+            fork.place_entity(entity_index, transform)?;
+        }
+        Ok(fork)
+    }
+
+    /// Makes the world a torus, or a plain square again, re-binning every body. When
+    /// wrapping, bodies crossing an edge are binned into the cells at the opposite edge as
+    /// well, so they collide with bodies there; the world repeats every `grid_size` cells,
+    /// which is `size` when `cell_size` divides it. Positions are kept as given. Collision
+    /// detection and push-out see across the seam, while area queries and rays only find
+    /// bodies binned into the cells they cover. Hex grids can't wrap, and wrapping can't
+    /// change while mutations are deferred.
+    pub fn set_wrap(&mut self, wrap: bool) -> PyResult<()> {
+        if wrap && self.layout == Layout::Hex {
+            return Err(PyValueError::new_err("hex grids can't wrap"));
+        }
+        if self.deferred {
+            return Err(PyValueError::new_err(
+                "can't change wrapping while mutations are deferred",
+            ));
+        }
+        if wrap != self.wrap {
+            self.wrap = wrap;
+            self.resize(self.size, self.cell_size)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the time step stored velocities are swept over when binning, re-binning moving
+    /// entities. `sweep_dt` must be finite and non-negative, and can't change while
+    /// mutations are deferred.
+    pub fn set_sweep_dt(&mut self, sweep_dt: f32) -> PyResult<()> {
+        if !sweep_dt.is_finite() || sweep_dt < 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "sweep_dt must be finite and non-negative, got {sweep_dt}"
+            )));
+        }
+        if self.deferred {
+            return Err(PyValueError::new_err(
+                "can't change sweep_dt while mutations are deferred",
+            ));
+        }
+        let mut moving: Vec<(usize, (f32, f32, f32, f32))> = self
+            .velocities
+            .keys()
+            .filter_map(|entity_index| {
+                let bounds = self.entity_bounds.get(entity_index)?;
+                Some((*entity_index, self.swept(*entity_index, *bounds)))
+            })
+            .collect();
+        moving.sort_unstable_by_key(|(entity_index, _)| *entity_index);
+        self.sweep_dt = sweep_dt;
+        for (entity_index, old_bounds) in moving {
+            self.rebin_entity(entity_index, old_bounds);
+        }
+        Ok(())
+    }
+
+    /// Current `((x, y), angle)` of an entity, the identity until it is first transformed.
+    pub fn get_entity_transform(&self, entity_index: usize) -> PyResult<((f32, f32), f32)> {
+        if !self.entity_bounds.contains_key(&entity_index) {
+            return Err(PyKeyError::new_err(format!(
+                "unknown entity {entity_index}"
+            )));
+        }
+        let transform = self
+            .transforms
+            .get(&entity_index)
+            .copied()
+            .unwrap_or_default();
+        Ok((
+            (transform.position.x, transform.position.y),
+            transform.angle,
+        ))
+    }
+
+    /// Interns a kind name, returning the integer used for it in add and query calls.
+    /// Registering a name twice returns the same integer.
+    pub fn register_kind(&mut self, name: &str) -> usize {
+        self.kinds.register(name)
+    }
+
+    pub fn kind_name(&self, kind: usize) -> Option<&str> {
+        self.kinds.name_of(kind)
+    }
+
+    /// Interns a namespace name, returning its integer from 1 up. Entity indices made with
+    /// `namespaced` carry it, and `Collision.self_entity` and `other_entity` report it.
+    pub fn register_namespace(&mut self, name: &str) -> PyResult<usize> {
+        self.namespaces.register(name)
+    }
+
+    /// The entity index to pass to add and query calls for `entity` in a namespace, so
+    /// systems numbering their entities independently don't collide. Entities must be below
+    /// `2**24`, and plain indices below that belong to namespace 0. Adding bodies under a
+    /// larger index raises `OverflowError` unless its namespace is registered.
+    pub fn namespaced(&mut self, namespace: &str, entity: usize) -> PyResult<usize> {
+        self.namespaces.entity(namespace, entity)
+    }
+
+    pub fn namespace_name(&self, namespace: usize) -> Option<&str> {
+        self.namespaces.name_of(namespace)
+    }
+
+    /// Colliding body pairs. `overlap` is only measured when `with_overlap` is set, as it
+    /// costs a square root per collision.
+    /// Detection stops as soon as `limit` collisions (one with `first_only`) are found,
+    /// walking entities in index order so the same collisions are returned on every run.
+    /// `targets` of `"static"` or `"dynamic"` only tests against bodies of that kind.
+    /// `result_type` of `"list"` returns them sorted by key rather than as a set, which is
+    /// cheaper to build, and `"numpy"` as an `(n, 4)` int64 array of index rows.
+    #[pyo3(
+        name = "get_collisions",
+        signature = (
+            with_overlap=false, limit=None, first_only=false, targets=Targets::All,
+            result_type=ResultType::Set
+        )
+    )]
+    pub fn py_get_collisions(
+        &self,
+        py: Python,
+        with_overlap: bool,
+        limit: Option<usize>,
+        first_only: bool,
+        targets: Targets,
+        result_type: ResultType,
+    ) -> PyResult<PyObject> {
+        let collisions = self.get_collisions(with_overlap, limit, first_only, targets);
+        result_type.convert(py, collisions, with_overlap)
+    }
+
+    /// Colliding body pairs as a list sorted by
+    /// `(self_entity_index, other_entity_index, self_body_index, other_body_index)`. With
+    /// `parallel` entities are checked on `num_threads` threads, or the default set with
+    /// `set_num_threads`; contents and order are the same as the sequential result for any
+    /// number of threads.
+    #[pyo3(signature = (with_overlap=false, parallel=false, num_threads=None))]
+    pub fn get_sorted_collisions(
+        &self,
+        with_overlap: bool,
+        parallel: bool,
+        num_threads: Option<usize>,
+    ) -> PyResult<Vec<Collision>> {
+        if parallel {
+            threads::install(num_threads, || self.ordered_collisions(with_overlap, true))
+        } else {
+            Ok(self.ordered_collisions(with_overlap, false))
+        }
+    }
+
+    /// Groups the dynamic entities into islands that cannot influence each other this frame,
+    /// as no body of one island shares a cell with a body of another. Static bodies don't join
+    /// islands. Islands are sorted lists, ordered by their lowest entity, so they can be
+    /// processed independently, for example by separate workers.
+    pub fn simulation_islands(&self) -> Vec<Vec<usize>> {
+        self.islands()
+    }
+
+    /// Fills a preallocated `(n, 4)` int64 array with collisions as
+    /// `[self_entity_index, other_entity_index, self_body_index, other_body_index]` rows
+    /// instead of allocating a set of `Collision` objects, and returns the number of rows
+    /// filled. Detection stops once the buffer is full.
+    pub fn get_collisions_into(&mut self, buffer: &PyArray2<i64>) -> PyResult<usize> {
+        let mut buffer = buffer.try_readwrite()?;
+        self.write_collisions(buffer.as_array_mut())
+    }
+
+    /// Collisions as a `pyarrow.RecordBatch` with one column per `Collision` field, so no
+    /// per-collision Python objects are created. The columns are copied into numpy arrays and
+    /// passed to `pyarrow.RecordBatch.from_arrays`, so `pyarrow` is imported on every call and
+    /// this is not a zero-copy Arrow export.
+    #[pyo3(signature = (with_overlap=false))]
+    pub fn get_collisions_arrow(&self, py: Python, with_overlap: bool) -> PyResult<PyObject> {
+        let columns = self.collision_columns(with_overlap);
+        let arrays = vec![
+            columns.self_entity_index.into_pyarray(py).to_object(py),
+            columns.other_entity_index.into_pyarray(py).to_object(py),
+            columns.self_body_index.into_pyarray(py).to_object(py),
+            columns.other_body_index.into_pyarray(py).to_object(py),
+            columns.overlap.into_pyarray(py).to_object(py),
+            columns.tick.into_pyarray(py).to_object(py),
+        ];
+        let names = [
+            "self_entity_index",
+            "other_entity_index",
+            "self_body_index",
+            "other_body_index",
+            "overlap",
+            "tick",
+        ];
+
+        let pyarrow = py.import("pyarrow")?;
+        let batch = pyarrow.getattr("RecordBatch")?.call_method(
+            "from_arrays",
+            (arrays,),
+            Some([("names", names.to_object(py))].into_py_dict(py)),
+        )?;
+        Ok(batch.into())
+    }
+
+    /// Like `get_collisions`, but only for the dynamic bodies of the group's entities.
+    #[pyo3(signature = (name, with_overlap=false, limit=None, first_only=false))]
+    pub fn get_group_collisions(
+        &self,
+        name: &str,
+        with_overlap: bool,
+        limit: Option<usize>,
+        first_only: bool,
+    ) -> PyResult<HashSet<Collision>> {
+        let group = self.group(name)?;
+        let bodies = group
+            .iter()
+            .filter_map(|entity_index| self.dynamic_bodies.get(entity_index))
+            .flatten()
+            .map(|placed| &placed.body);
+        let limit = result_limit(limit, first_only);
+        Ok(self.collisions_of(bodies, with_overlap, limit, Targets::All))
+    }
+
+    /// Creates (or replaces) a named group of entities. New groups are active.
+    pub fn create_group(&mut self, name: String, entities: Vec<usize>) {
+        self.groups
+            .insert(name.clone(), entities.into_iter().collect());
+        self.inactive_groups.remove(&name);
+        self.update_frozen();
+    }
+
+    /// Entities of inactive groups are left out of collisions and queries, both as sources
+    /// and as targets, until the group is activated again.
+    pub fn set_group_active(&mut self, name: &str, active: bool) -> PyResult<()> {
+        self.group(name)?;
+        if active {
+            self.inactive_groups.remove(name);
+        } else {
+            self.inactive_groups.insert(name.to_string());
+        }
+        self.update_frozen();
+        Ok(())
+    }
+
+    /// Number of colliding body pairs for each `(self_entity_index, other_entity_index)` pair.
+    pub fn get_collision_counts(&self) -> HashMap<(usize, usize), usize> {
+        let mut counts = HashMap::new();
+        for collision in self.all_collisions() {
+            *counts
+                .entry((collision.self_entity_index, collision.other_entity_index))
+                .or_default() += 1;
+        }
+        counts
+    }
+
+    /// Body pairs whose gap is at most `margin`, including those already touching. The gap is
+    /// reported as a negative `overlap`.
+    pub fn get_near_collisions(&self, margin: f32) -> HashSet<Collision> {
+        let mut collisions = HashSet::new();
+
+        for (entity_index, bodies) in self.dynamic_bodies.iter() {
+            if !self.is_active(*entity_index) {
+                continue;
+            }
+            for body in bodies.iter() {
+                let (min_x, max_x, min_y, max_y) = body.get_bounds();
+                let bounds = (
+                    min_x - margin,
+                    max_x + margin,
+                    min_y - margin,
+                    max_y + margin,
+                );
+                for cell in self.cells_within(bounds) {
+                    for other in cell.iter() {
+                        if other.entity_index() == *entity_index
+                            || !self.is_active(other.entity_index())
+                        {
+                            continue;
+                        }
+                        let overlap = body.overlap(other);
+                        if overlap >= -margin {
+                            let collision = Collision::new(
+                                *entity_index,
+                                other.entity_index(),
+                                body.body_index(),
+                                other.body_index(),
+                            );
+                            collisions.insert(collision.with_overlap(overlap).at_tick(self.tick));
+                        }
+                    }
+                }
+            }
+        }
+        collisions
+    }
+
+    /// Static bodies of different entities that overlap, for validating map layouts, leaving
+    /// out the pairs recorded by `bake_static_exclusions`.
+    pub fn get_static_overlaps(&self) -> HashSet<Collision> {
+        self.static_pairs()
+            .difference(&self.static_exclusions)
+            .map(|key| Collision::new(key.0, key.1, key.2, key.3).at_tick(self.tick))
+            .collect()
+    }
+
+    /// Bakes the static layout of a map: static bodies lying entirely inside another static
+    /// body are removed, as anything touching them touches their container, and the pairs of
+    /// statics still overlapping are recorded as permanent and left out of
+    /// `get_static_overlaps`. Returns `(excluded_pairs, merged_bodies)`.
+    pub fn bake_static_exclusions(&mut self) -> (usize, usize) {
+        // Highest indices first, so of two identical bodies the lower entity is kept
+        let mut statics: Vec<Body> = self
+            .static_bodies
+            .values()
+            .flatten()
+            .map(|placed| placed.body)
+            .collect();
+        statics.sort_unstable_by_key(|body| {
+            std::cmp::Reverse((body.entity_index(), body.body_index()))
+        });
+
+        let key = |body: &Body| (body.entity_index(), body.body_index());
+        let mut merged: HashSet<(usize, usize)> = HashSet::new();
+        for body in &statics {
+            let contained = self.cells_within(body.get_bounds()).any(|cell| {
+                cell.iter().any(|other| {
+                    other.is_static()
+                        && key(other) != key(body)
+                        && !merged.contains(&key(other))
+                        && other.contains(body)
+                })
+            });
+            if contained {
+                merged.insert(key(body));
+            }
+        }
+        for body in statics.iter().filter(|body| merged.contains(&key(body))) {
+            self.remove_static_body(body);
+        }
+
+        self.static_exclusions = self.static_pairs();
+        (self.static_exclusions.len(), merged.len())
+    }
+
+    /// Merges static bodies into fewer, larger ones to cut broad phase candidates, mainly for
+    /// imported tilemaps: axis-aligned boxes of the same kind sharing edges become one box and
+    /// consecutive collinear segments of a chain become one segment. Boxes are only merged
+    /// within an entity unless `across_entities` is set, in which case the merged box belongs
+    /// to the lowest entity. Returns the number of bodies removed.
+    #[pyo3(signature = (across_entities=false))]
+    pub fn optimize_statics(&mut self, across_entities: bool) -> usize {
+        let removed = self.merge_statics(across_entities);
+        if removed > 0 {
+            let pairs = self.static_pairs();
+            self.static_exclusions.retain(|key| pairs.contains(key));
+        }
+        removed
+    }
+
+    /// Collisions of this world's dynamic bodies against every body of `other`, which must
+    /// share this world's coordinate system but may use a different cell size. `self_*` fields
+    /// refer to this world and `other_*` fields to `other`.
+    pub fn cross_collisions(&self, other: PyRef<GridPhysics>) -> HashSet<Collision> {
+        self.collisions_against(&other)
+    }
+
+    /// Entities with bodies in the cells covering the area. With a `metric`, only entities
+    /// with a body within `radius` of `position` under that metric are returned. The scan stops
+    /// once `limit` entities (one with `first_only`) are found.
+    #[pyo3(signature = (
+        position, radius, metric=None, limit=None, first_only=false, containment=Containment::Intersects
+    ))]
+    pub fn get_collisions_within_area(
+        &self,
+        position: Vector2,
+        radius: f32,
+        metric: Option<Metric>,
+        limit: Option<usize>,
+        first_only: bool,
+        containment: Containment,
+    ) -> HashSet<usize> {
+        let limit = result_limit(limit, first_only);
+        let bounds = (
+            position.x - radius,
+            position.x + radius,
+            position.y - radius,
+            position.y + radius,
+        );
+
+        let mut collisions = HashSet::new();
+
+        for cell in self.cells_within(bounds) {
+            for other in cell.iter() {
+                let within =
+                    metric.is_none_or(|metric| other.metric_distance(position, metric) <= radius);
+                let inside = containment == Containment::Intersects
+                    || !collisions.contains(&other.entity_index())
+                        && self.entity_within(other.entity_index(), |body| {
+                            body.within_circle(position, radius)
+                        });
+                if within && inside && self.is_active(other.entity_index()) {
+                    if collisions.len() >= limit {
+                        return collisions;
+                    }
+                    collisions.insert(other.entity_index());
+                }
+            }
+        }
+        collisions
+    }
+
+    /// Entities with a body overlapping the convex polygon through `points`, given in either
+    /// winding, or with `containment="contains"` those with every body inside it. Raises
+    /// `ValueError` for fewer than three points or a polygon that isn't convex.
+    #[pyo3(signature = (points, containment=Containment::Intersects))]
+    pub fn query_polygon(
+        &self,
+        points: Vec<Vector2>,
+        containment: Containment,
+    ) -> PyResult<HashSet<usize>> {
+        let polygon = polygon::ConvexPolygon::new(points)?;
+        Ok(self.polygon_entities(&polygon, containment))
+    }
+
+    /// Subscribes `id` to the entities within `radius` of `center`, for `poll_subscription`.
+    /// Subscribing an existing id moves its area, keeping what it has already reported.
+    pub fn subscribe(&mut self, center: Vector2, radius: f32, id: usize) {
+        self.subscriptions
+            .entry(id)
+            .and_modify(|subscription| {
+                subscription.center = center;
+                subscription.radius = radius;
+            })
+            .or_insert_with(|| Subscription::new(center, radius));
+    }
+
+    pub fn unsubscribe(&mut self, id: usize) -> PyResult<()> {
+        self.subscriptions
+            .remove(&id)
+            .map(|_| ())
+            .ok_or_else(|| PyKeyError::new_err(format!("unknown subscription {id}")))
+    }
+
+    /// Sorted `(entered, left)` entities of the area of subscription `id` since it was last
+    /// polled. The first poll reports everything inside as entered.
+    pub fn poll_subscription(&mut self, id: usize) -> PyResult<(Vec<usize>, Vec<usize>)> {
+        self.poll_interest(id)
+    }
+
+    /// Entities with a body overlapping the box from `min` to `max`, in index order, or with
+    /// `containment="contains"` those with every body inside it. Cached entity bounds reject
+    /// whole entities before their bodies are tested.
+    #[pyo3(signature = (min, max, containment=Containment::Intersects))]
+    pub fn entities_overlapping_aabb(
+        &self,
+        min: Vector2,
+        max: Vector2,
+        containment: Containment,
+    ) -> Vec<usize> {
+        let mut entities: Vec<usize> = self
+            .entity_bounds
+            .iter()
+            .filter(|(entity_index, bounds)| {
+                bounds.0 <= max.x
+                    && bounds.1 >= min.x
+                    && bounds.2 <= max.y
+                    && bounds.3 >= min.y
+                    && self.is_active(**entity_index)
+            })
+            .filter(|(entity_index, _)| match containment {
+                Containment::Intersects => self
+                    .bodies_of(**entity_index)
+                    .any(|body| body.overlaps_box(min, max)),
+                Containment::Contains => {
+                    self.entity_within(**entity_index, |body| body.within_box(min, max))
+                }
+            })
+            .map(|(entity_index, _)| *entity_index)
+            .collect();
+        entities.sort_unstable();
+        entities
+    }
+
+    /// Entities with a body whose bounding box overlaps the camera rectangle from
+    /// `camera_min` to `camera_max` grown by `margin` on every side, as a sorted int64 numpy
+    /// array. Meant for render culling every frame, so bodies are only tested by their
+    /// bounds and the GIL is released while searching.
+    #[pyo3(signature = (camera_min, camera_max, margin=0.0))]
+    pub fn visible_entities<'py>(
+        &self,
+        py: Python<'py>,
+        camera_min: Vector2,
+        camera_max: Vector2,
+        margin: f32,
+    ) -> &'py PyArray1<i64> {
+        let bounds = (
+            camera_min.x - margin,
+            camera_max.x + margin,
+            camera_min.y - margin,
+            camera_max.y + margin,
+        );
+        py.allow_threads(|| self.visible(bounds)).into_pyarray(py)
+    }
+
+    /// Bodies hit by the ray from `origin` along `direction` within `max_distance`, as
+    /// `(entity_index, body_index, distance)` nearest first. Cells are walked along the ray so
+    /// the search stops after `limit` hits (one with `first_only`). `supercover` also walks
+    /// the cells beside corners the ray passes through exactly.
+    #[pyo3(signature = (
+        origin, direction, max_distance=None, limit=None, first_only=false, supercover=false
+    ))]
+    pub fn raycast(
+        &self,
+        origin: Vector2,
+        direction: Vector2,
+        max_distance: Option<f32>,
+        limit: Option<usize>,
+        first_only: bool,
+        supercover: bool,
+    ) -> Vec<(usize, usize, f32)> {
+        let limit = result_limit(limit, first_only);
+        let mut hits = Vec::new();
+        if limit == 0 {
+            return hits;
+        }
+        let Some((direction, max_distance)) = self.clip_ray(origin, direction, max_distance) else {
+            return hits;
+        };
+
+        let mut seen = HashSet::new();
+        let mut pending: Vec<(f32, usize, usize)> = Vec::new();
+        let mut visit = |cell: &Vec<Body>, pending: &mut Vec<(f32, usize, usize)>| {
+            for body in cell
+                .iter()
+                .filter(|body| self.is_active(body.entity_index()))
+            {
+                if !seen.insert((body.entity_index(), body.body_index())) {
+                    continue;
+                }
+                if let Some(t) = body.ray_hit(origin, direction) {
+                    if t <= max_distance {
+                        pending.push((t, body.entity_index(), body.body_index()));
+                    }
+                }
+            }
+        };
+
+        match self.layout {
+            Layout::Square => {
+                let cells = ray::Traversal::clipped(
+                    origin,
+                    direction,
+                    self.cell_size,
+                    max_distance,
+                    self.grid_size,
+                )
+                .into_iter()
+                .flat_map(|cells| cells.supercover(supercover));
+                for (cell_x, cell_y, t_exit) in cells {
+                    if let Some(index) = storage_index(self.grid_size, cell_x, cell_y) {
+                        visit(&self.grid[index], &mut pending);
+                    }
+                    // Hits before the ray leaves this cell can't be beaten by later cells
+                    pending.sort_by(|a, b| b.0.total_cmp(&a.0));
+                    while pending.last().is_some_and(|hit| hit.0 <= t_exit) {
+                        let (t, entity_index, body_index) = pending.pop().unwrap();
+                        hits.push((entity_index, body_index, t));
+                        if hits.len() >= limit {
+                            return hits;
+                        }
+                    }
+                }
+            }
+            Layout::Hex => {
+                let end = Vector2::new(
+                    origin.x + direction.x * max_distance,
+                    origin.y + direction.y * max_distance,
+                );
+                let bounds = (
+                    origin.x.min(end.x),
+                    origin.x.max(end.x),
+                    origin.y.min(end.y),
+                    origin.y.max(end.y),
+                );
+                for cell in self.cells_within(bounds) {
+                    visit(cell, &mut pending);
+                }
+            }
+        }
+
+        pending.sort_by(|a, b| a.0.total_cmp(&b.0));
+        hits.extend(
+            pending
+                .into_iter()
+                .map(|(t, entity_index, body_index)| (entity_index, body_index, t)),
+        );
+        hits.truncate(limit);
+        hits
+    }
+
+    /// Casts one ray per row of the `(n, 2)` `origins` and `directions` arrays in parallel
+    /// without holding the GIL, returning `(distances, entity_indices, body_indices)` arrays
+    /// of each ray's nearest hit. Misses have an infinite distance and indices of `-1`.
+    /// `max_distances` is one length for every ray or an `(n,)` array.
+    #[pyo3(signature = (origins, directions, max_distances=None, num_threads=None))]
+    pub fn raycast_batch<'py>(
+        &self,
+        py: Python<'py>,
+        origins: bulk::Points,
+        directions: bulk::Points,
+        max_distances: Option<raybatch::MaxDistances>,
+        num_threads: Option<usize>,
+    ) -> PyResult<(&'py PyArray1<f32>, &'py PyArray1<i64>, &'py PyArray1<i64>)> {
+        let (origins, directions) = (origins.to_f32(), directions.to_f32());
+        let max_distances = max_distances.map(|distances| match distances {
+            raybatch::MaxDistances::All(distance) => Array1::from_elem(origins.nrows(), distance),
+            raybatch::MaxDistances::PerRay(distances) => distances.to_f32(),
+        });
+        let hits = py.allow_threads(|| {
+            self.raycast_many(
+                origins.view(),
+                directions.view(),
+                max_distances.as_ref().map(|distances| distances.view()),
+                num_threads,
+            )
+        })?;
+        Ok((
+            hits.distances.into_pyarray(py),
+            hits.entities.into_pyarray(py),
+            hits.bodies.into_pyarray(py),
+        ))
+    }
+
+    /// Grid cells the ray from `origin` along `direction` crosses within `max_distance`, in
+    /// order. Plain traversal steps one axis at a time, so a ray through a corner skips one
+    /// of the two cells beside it; `supercover` includes both. Only square grids are walked.
+    #[pyo3(signature = (origin, direction, max_distance=None, supercover=false))]
+    pub fn ray_cells(
+        &self,
+        origin: Vector2,
+        direction: Vector2,
+        max_distance: Option<f32>,
+        supercover: bool,
+    ) -> PyResult<Vec<(isize, isize)>> {
+        if self.layout == Layout::Hex {
+            return Err(PyValueError::new_err("ray_cells needs a square grid"));
+        }
+        let Some((direction, max_distance)) = self.clip_ray(origin, direction, max_distance) else {
+            return Ok(Vec::new());
+        };
+        Ok(ray::Traversal::clipped(
+            origin,
+            direction,
+            self.cell_size,
+            max_distance,
+            self.grid_size,
+        )
+        .into_iter()
+        .flat_map(|cells| cells.supercover(supercover))
+        .filter(|(cell_x, cell_y, _)| self.cell_index(*cell_x, *cell_y).is_some())
+        .map(|(cell_x, cell_y, _)| (cell_x, cell_y))
+        .collect())
+    }
+
+    /// Starts a `Query` on this world, for area queries combining several filters.
+    pub fn query(slf: PyRef<'_, Self>) -> Query {
+        Query::new(slf.into())
+    }
+
+    /// Bodies touching the circle as `(entity_index, body_index, distance)` ordered by entity
+    /// and body, with the distance from `center` to their surface. Each body is tested
+    /// exactly, so an area clipping one limb of a compound entity reports only that limb.
+    pub fn query_circle_bodies(&self, center: Vector2, radius: f32) -> Vec<(usize, usize, f32)> {
+        let spec = query::QuerySpec {
+            area: Some(query::Area::Circle { center, radius }),
+            ..Default::default()
+        };
+        self.run_query(&spec).expect("the query has an area")
+    }
+
+    /// Bodies touching the circle as `(entity_index, body_index, distance)` ordered by the
+    /// distance from `center` to their surface. With a `limit` only the nearest bodies are
+    /// fully sorted.
+    #[pyo3(signature = (center, radius, limit=None))]
+    pub fn query_circle_sorted(
+        &self,
+        center: Vector2,
+        radius: f32,
+        limit: Option<usize>,
+    ) -> Vec<(usize, usize, f32)> {
+        let bounds = (
+            center.x - radius,
+            center.x + radius,
+            center.y - radius,
+            center.y + radius,
+        );
+
+        let mut seen = HashSet::new();
+        let mut results = Vec::new();
+        for cell in self.cells_within(bounds) {
+            for body in cell
+                .iter()
+                .filter(|body| self.is_active(body.entity_index()))
+            {
+                let distance = body.metric_distance(center, Metric::Euclidean);
+                if distance <= radius && seen.insert((body.entity_index(), body.body_index())) {
+                    results.push((body.entity_index(), body.body_index(), distance));
+                }
+            }
+        }
+
+        let by_distance = |a: &(usize, usize, f32), b: &(usize, usize, f32)| {
+            a.2.total_cmp(&b.2).then((a.0, a.1).cmp(&(b.0, b.1)))
+        };
+        if let Some(limit) = limit.filter(|limit| *limit < results.len()) {
+            if limit == 0 {
+                return Vec::new();
+            }
+            results.select_nth_unstable_by(limit - 1, by_distance);
+            results.truncate(limit);
+        }
+        results.sort_unstable_by(by_distance);
+        results
+    }
+
+    /// The `k` bodies nearest to `position` as `(entity_index, body_index, distance)`, nearest
+    /// first. The search widens until `k` bodies are found or `max_distance` is reached. With
+    /// `kind` or `mask`, a list of kinds, only bodies of those kinds are searched for, so
+    /// other bodies nearby don't use up `k`.
+    #[pyo3(
+        name = "nearest",
+        signature = (position, k=1, max_distance=None, metric=Metric::Euclidean, kind=None, mask=None)
+    )]
+    pub fn py_nearest(
+        &self,
+        position: Vector2,
+        k: usize,
+        max_distance: Option<f32>,
+        metric: Metric,
+        kind: Option<KindArg>,
+        mask: Option<Vec<KindArg>>,
+    ) -> PyResult<Vec<(usize, usize, f32)>> {
+        let kinds = match (kind, mask) {
+            (None, None) => None,
+            (kind, mask) => Some(
+                kind.into_iter()
+                    .chain(mask.into_iter().flatten())
+                    .map(|kind| self.kinds.resolve(&kind))
+                    .collect::<PyResult<HashSet<usize>>>()?,
+            ),
+        };
+        Ok(self.nearest(position, k, max_distance, metric, kinds.as_ref()))
+    }
+
+    /// Minimum body-to-body gap between every entity of `entities_a` and every entity of
+    /// `entities_b`. Pairs further apart than `max_distance` are skipped using the grid and
+    /// reported as infinity.
+    #[pyo3(signature = (entities_a, entities_b, max_distance=None))]
+    pub fn distance_matrix<'py>(
+        &self,
+        py: Python<'py>,
+        entities_a: Vec<usize>,
+        entities_b: Vec<usize>,
+        max_distance: Option<f32>,
+    ) -> &'py PyArray2<f32> {
+        self.min_distances(&entities_a, &entities_b, max_distance)
+            .into_pyarray(py)
+    }
+
+    /// Number of dynamic bodies in each cell, indexed as `[cell_x, cell_y]` (odd-r
+    /// `[column, row]` in hex layout). Each body is counted once, in the cell holding its
+    /// position, however many cells it spans; bodies outside the grid aren't counted.
+    #[pyo3(signature = (kind=None))]
+    pub fn density_map<'py>(
+        &self,
+        py: Python<'py>,
+        kind: Option<KindArg>,
+    ) -> PyResult<&'py PyArray2<u32>> {
+        let kind = kind.map(|kind| self.kinds.resolve(&kind)).transpose()?;
+        Ok(self.dynamic_counts(kind).into_pyarray(py))
+    }
+
+    /// Batched agent observations as a `(len(entity_indices), 2 * num_nearest + num_rays)`
+    /// float32 array: offsets to the nearest foreign bodies, then raycast distances on evenly
+    /// spaced rays. Rows are computed in parallel on `num_threads` threads without holding the
+    /// GIL. More than `2**16` features per row raise `ValueError`.
+    #[pyo3(signature = (entity_indices, num_nearest, num_rays, max_distance=None, num_threads=None))]
+    pub fn observe<'py>(
+        &self,
+        py: Python<'py>,
+        entity_indices: Vec<usize>,
+        num_nearest: usize,
+        num_rays: usize,
+        max_distance: Option<f32>,
+        num_threads: Option<usize>,
+    ) -> PyResult<&'py PyArray2<f32>> {
+        let max_distance = max_distance.unwrap_or(self.size);
+        let observations = py.allow_threads(|| {
+            threads::install(num_threads, || {
+                self.observations(&entity_indices, num_nearest, num_rays, max_distance)
+            })
+        })??;
+        Ok(observations.into_pyarray(py))
+    }
+
+    /// Boolean `(2n + 1, 2n + 1)` field of view around `origin`, indexed `[x, y]` like
+    /// `density_map`, over square tiles of side `resolution` (the cell size by default), with
+    /// `n` the radius in tiles and `origin` in the middle tile. Static bodies block sight
+    /// when they come within half a tile of a tile's center, and blocking tiles in view are
+    /// visible themselves. Computed by recursive shadowcasting.
+    #[pyo3(signature = (origin, radius, resolution=None))]
+    pub fn light_mask<'py>(
+        &self,
+        py: Python<'py>,
+        origin: Vector2,
+        radius: f32,
+        resolution: Option<f32>,
+    ) -> PyResult<&'py PyArray2<bool>> {
+        let resolution = resolution.unwrap_or(self.cell_size);
+        let mask = py.allow_threads(|| self.shadowcast(origin, radius, resolution))?;
+        Ok(mask.into_pyarray(py))
+    }
+
+    /// Body positions, ordered by entity and body index, in a contiguous buffer exposed
+    /// through `__dlpack__`. Static bodies are only included with `include_static`.
+    #[pyo3(signature = (include_static=false))]
+    pub fn positions(&self, include_static: bool) -> Positions {
+        let statics = self.static_bodies.values().filter(|_| include_static);
+        let mut rows: Vec<(usize, usize, f32, f32)> = self
+            .dynamic_bodies
+            .values()
+            .chain(statics)
+            .flatten()
+            .map(|body| {
+                (
+                    body.entity_index(),
+                    body.body_index(),
+                    body.pos.x,
+                    body.pos.y,
+                )
+            })
+            .collect();
+        rows.sort_unstable_by_key(|row| (row.0, row.1));
+        Positions::new(rows)
+    }
+
+    /// `(entity_index, body_index)` of every body stored in the given cell, static bodies
+    /// first.
+    pub fn cell_contents(&self, cell_x: isize, cell_y: isize) -> PyResult<Vec<(usize, usize)>> {
+        let index = self.cell_index(cell_x, cell_y).ok_or_else(|| {
+            PyIndexError::new_err(format!("cell ({cell_x}, {cell_y}) is outside the grid"))
+        })?;
+        Ok(self.grid[index]
+            .iter()
+            .map(|body| (body.entity_index(), body.body_index()))
+            .collect())
+    }
+
+    /// Cell containing `position`, or `None` when it lies outside the grid.
+    pub fn cell_of(&self, position: Vector2) -> Option<(isize, isize)> {
+        let (cell_x, cell_y) = self.world_to_cell(position);
+        self.cell_index(cell_x, cell_y).map(|_| (cell_x, cell_y))
+    }
+
+    /// Coordinates of the cell containing `position`, continuing the grid's pattern past
+    /// its edges. Cells are axial `(q, r)` in hex layout.
+    pub fn world_to_cell(&self, position: Vector2) -> (isize, isize) {
+        match self.layout {
+            Layout::Square => (
+                (position.x / self.cell_size).floor() as isize,
+                (position.y / self.cell_size).floor() as isize,
+            ),
+            Layout::Hex => hex::axial_of(position.x, position.y, self.cell_size),
+        }
+    }
+
+    /// World position of the center of a cell, which needn't lie inside the grid.
+    pub fn cell_to_world(&self, cell_x: isize, cell_y: isize) -> (f32, f32) {
+        match self.layout {
+            Layout::Square => (
+                (cell_x as f32 + 0.5) * self.cell_size,
+                (cell_y as f32 + 0.5) * self.cell_size,
+            ),
+            Layout::Hex => hex::center_of(cell_x, cell_y, self.cell_size),
+        }
+    }
+
+    /// `(min_x, max_x, min_y, max_y)` of a cell, ordered like body bounds. In hex layout this
+    /// is the rectangle around the hexagon, so neighbouring rectangles overlap.
+    pub fn cell_rect(&self, cell_x: isize, cell_y: isize) -> (f32, f32, f32, f32) {
+        let (x, y) = self.cell_to_world(cell_x, cell_y);
+        let (half_x, half_y) = match self.layout {
+            Layout::Square => (self.cell_size / 2.0, self.cell_size / 2.0),
+            Layout::Hex => (self.cell_size / 2.0, hex::circumradius(self.cell_size)),
+        };
+        (x - half_x, x + half_x, y - half_y, y + half_y)
+    }
+
+    /// `(columns, rows, column_width, row_height)` of the grid. Hex rows are closer together
+    /// than the cell width, and odd rows are shifted right by half a cell.
+    pub fn grid_dimensions(&self) -> (usize, usize, f32, f32) {
+        let row_height = match self.layout {
+            Layout::Square => self.cell_size,
+            Layout::Hex => hex::row_height(self.cell_size),
+        };
+        (self.grid_size, self.grid_size, self.cell_size, row_height)
+    }
+
+    /// Cells adjacent to the given cell that lie inside the grid: the eight surrounding cells
+    /// in square layout, or the six edge-sharing cells in hex layout.
+    pub fn cell_neighbors(&self, cell_x: isize, cell_y: isize) -> Vec<(isize, isize)> {
+        let offsets: Vec<(isize, isize)> = match self.layout {
+            Layout::Square => (-1..=1)
+                .flat_map(|x| (-1..=1).map(move |y| (x, y)))
+                .filter(|offset| *offset != (0, 0))
+                .collect(),
+            Layout::Hex => hex::NEIGHBORS.to_vec(),
+        };
+        offsets
+            .into_iter()
+            .filter_map(|(x, y)| Some((cell_x.checked_add(x)?, cell_y.checked_add(y)?)))
+            .filter(|(x, y)| self.cell_index(*x, *y).is_some())
+            .collect()
+    }
+}
+
+/// The clock for Rust callers, which have no collision handlers to call.
+impl GridPhysics {
+    /// Advances the world clock by one tick of `dt` seconds and returns the new tick, like the
+    /// Python `step` without calling collision handlers.
+    pub fn step(&mut self, dt: f64) -> u64 {
+        self.tick += 1;
+        self.time += dt;
+        if self.integrate {
+            self.apply_forces(dt as f32);
+            self.integrate_velocities(dt as f32);
+        }
+        self.push_out(self.push_out_iterations);
+        self.update_sleep();
+        self.forces.clear();
+        self.queue_events();
+        if let Some(server) = &self.debug_server.0 {
+            server.publish(debug_server::view(self));
+        }
+        self.logging.flush();
+        self.tick
+    }
+
+    /// Like the Python `advance` without calling collision handlers.
+    pub fn advance(&mut self, frame_dt: f64) -> PyResult<Vec<Event>> {
+        self.run_fixed_steps(frame_dt)?;
+        Ok(self.drain_events())
+    }
+
+    /// Runs the `step`s of `fixed_dt` that the accumulator covers once `frame_dt` is added.
+    fn run_fixed_steps(&mut self, frame_dt: f64) -> PyResult<()> {
+        if self.fixed_dt.is_nan() || self.fixed_dt <= 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "fixed_dt must be positive, got {}",
+                self.fixed_dt
+            )));
+        }
+        if !frame_dt.is_finite() || frame_dt < 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "frame_dt must be finite and non-negative, got {frame_dt}"
+            )));
+        }
+        self.accumulator += frame_dt;
+        let mut steps = 0;
+        while self.accumulator >= self.fixed_dt {
+            if steps == self.max_steps_per_advance {
+                self.accumulator %= self.fixed_dt;
+                break;
+            }
+            self.step(self.fixed_dt);
+            self.accumulator -= self.fixed_dt;
+            steps += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Insertion with already interned kinds, shared by the Python methods and `World`.
+impl GridPhysics {
+    pub fn add_circle(
+        &mut self,
+        entity_index: usize,
+        pos: Vector2,
+        radius: f32,
+        body_index: usize,
+        is_static: bool,
+        kind: usize,
+    ) {
+        let body = Body::new(entity_index, body_index, pos, radius, is_static, kind);
+        self.add_body(body);
+    }
+
+    pub fn add_ellipse(
+        &mut self,
+        entity_index: usize,
+        pos: Vector2,
+        radii: (f32, f32),
+        angle: f32,
+        is_static: bool,
+        kind: usize,
+    ) {
+        let body = Body::new(entity_index, 0, pos, 0.0, is_static, kind);
+        self.add_body(body.ellipse(radii.0, radii.1, angle));
+    }
+
+    pub fn add_static_chain(&mut self, entity_index: usize, points: &[Vector2], kind: usize) {
+        for (i, pair) in points.windows(2).enumerate() {
+            let body = Body::new(entity_index, i, pair[0], 0.0, true, kind);
+            self.add_body(body.segment(pair[0], pair[1]));
+        }
+    }
+
+    pub fn add_static_trimesh(
+        &mut self,
+        entity_index: usize,
+        vertices: &[Vector2],
+        indices: &[(usize, usize, usize)],
+        kind: usize,
+    ) -> PyResult<()> {
+        if let Some(index) = indices
+            .iter()
+            .flat_map(|&(a, b, c)| [a, b, c])
+            .find(|index| *index >= vertices.len())
+        {
+            return Err(PyIndexError::new_err(format!(
+                "vertex index {index} out of range for {} vertices",
+                vertices.len()
+            )));
+        }
+        for (i, &(a, b, c)) in indices.iter().enumerate() {
+            let body = Body::new(entity_index, i, vertices[a], 0.0, true, kind);
+            self.add_body(body.triangle([vertices[a], vertices[b], vertices[c]]));
+        }
+        Ok(())
+    }
+
+    pub fn add_obb(
+        &mut self,
+        entity_index: usize,
+        center: Vector2,
+        half_extents: (f32, f32),
+        angle: f32,
+        is_static: bool,
+        kind: usize,
+    ) {
+        let body = Body::new(entity_index, 0, center, 0.0, is_static, kind);
+        self.add_body(body.oriented_box(half_extents.0, half_extents.1, angle));
+    }
+
+    /// Adds a body placed in world space, deriving its entity-local offset and turn from the
+    /// entity transform. Bodies spanning too many cells are subject to `oversize_policy`.
+    fn add_body(&mut self, body: Body) {
+        self.add_placed(Placed::new(body));
+    }
+
+    /// Adds a body like `add_body`, keeping the offset and turn it comes with when its entity
+    /// has no transform by the time it is added.
+    fn add_placed(&mut self, placed: Placed) {
+        if self.screen(&placed) {
+            return;
+        }
+        if self.deferred {
+            self.defer(Mutation::Add(placed));
+        } else {
+            self.push_body(self.localized(placed));
+        }
+    }
+
+    /// A body placed in world space with its offset and turn relative to its entity.
+    fn localized(&self, mut placed: Placed) -> Placed {
+        if let Some(transform) = self.transforms.get(&placed.entity_index()) {
+            placed.offset = transform.local(placed.pos);
+            placed.turn = placed.angle - transform.angle;
+        }
+        placed
+    }
+
+    /// Adds bodies at `offsets` from `anchor`, which becomes the entity position for
+    /// `move_entity`. Bodies the entity already has keep their world positions.
+    pub fn add_anchored_circles(
+        &mut self,
+        entity_index: usize,
+        offsets: Vec<Vector2>,
+        radius: f32,
+        anchor: Vector2,
+        is_static: bool,
+        kind: usize,
+    ) {
+        if self.deferred {
+            self.defer(Mutation::AddAnchored {
+                entity_index,
+                offsets,
+                radius,
+                anchor,
+                is_static,
+                kind,
+            });
+            return;
+        }
+        let angle = self
+            .transforms
+            .get(&entity_index)
+            .map_or(0.0, |transform| transform.angle);
+        let transform = Transform {
+            position: anchor,
+            angle,
+        };
+        if !anchor.x.is_finite() || !anchor.y.is_finite() {
+            // Recorded against the first body, since none can be placed
+            let body = Body::new(entity_index, 0, anchor, radius, is_static, kind);
+            self.screen_invalid(&Placed::new(body));
+            return;
+        }
+        for bodies in [&mut self.dynamic_bodies, &mut self.static_bodies] {
+            for body in bodies.get_mut(&entity_index).into_iter().flatten() {
+                body.offset = transform.local(body.pos);
+            }
+        }
+        self.transforms.insert(entity_index, transform);
+
+        for (i, offset) in offsets.into_iter().enumerate() {
+            let pos = transform.apply(offset);
+            let body = Body::new(entity_index, i, pos, radius, is_static, kind);
+            let placed = Placed {
+                offset,
+                ..Placed::new(body)
+            };
+            if !self.screen(&placed) {
+                self.push_body(placed);
+            }
+        }
+    }
+
+    fn push_body(&mut self, placed: Placed) {
+        self.insert_into_cells(placed.body);
+        self.register_body(placed);
+    }
+
+    /// Records a body already binned into its cells.
+    fn register_body(&mut self, placed: Placed) {
+        let body = placed.body;
+        self.log_insert(&body);
+        self.changed.insert(body.entity_index(), self.tick);
+        self.max_radius = self.max_radius.max(body.radius);
+        let bounds = body.get_bounds();
+        self.entity_bounds
+            .entry(body.entity_index())
+            .and_modify(|entity| *entity = union_bounds(*entity, bounds))
+            .or_insert(bounds);
+
+        let bodies = if body.is_static() {
+            &mut self.static_bodies
+        } else {
+            &mut self.dynamic_bodies
+        };
+        bodies.entry(body.entity_index()).or_default().push(placed);
+    }
+
+    pub fn add_static_circle(
+        &mut self,
+        entity_index: usize,
+        pos: Vector2,
+        radius: f32,
+        kind: usize,
+    ) {
+        self.add_circle(entity_index, pos, radius, 0, true, kind)
+    }
+
+    pub fn add_static_circles(
+        &mut self,
+        entity_index: usize,
+        bodies: Vec<Vector2>,
+        radius: f32,
+        kind: usize,
+    ) {
+        for (i, pos) in bodies.iter().enumerate() {
+            self.add_circle(entity_index, *pos, radius, i, true, kind);
+        }
+    }
+
+    pub fn add_dynamic_circle(
+        &mut self,
+        entity_index: usize,
+        pos: Vector2,
+        radius: f32,
+        kind: usize,
+    ) {
+        self.add_circle(entity_index, pos, radius, 0, false, kind);
+    }
+
+    pub fn add_dynamic_circles(
+        &mut self,
+        entity_index: usize,
+        bodies: Vec<Vector2>,
+        radius: f32,
+        kind: usize,
+    ) {
+        for (i, pos) in bodies.iter().enumerate() {
+            self.add_circle(entity_index, *pos, radius, i, false, kind);
+        }
+    }
+}
+
+#[pymodule]
+fn radyx(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<Collision>()?;
+    m.add_class::<GridPhysics>()?;
+    m.add_class::<World>()?;
+    m.add_class::<Event>()?;
+    m.add_class::<EventKind>()?;
+    m.add_class::<Positions>()?;
+    m.add_class::<WorldDiff>()?;
+    m.add_class::<MemoryUsage>()?;
+    m.add_class::<EntityHandle>()?;
+    m.add_class::<Query>()?;
+    m.add_class::<benchmark::BenchmarkReport>()?;
+    m.add("RadyxError", py.get_type::<RadyxError>())?;
+    m.add_function(wrap_pyfunction!(threads::set_num_threads, m)?)?;
+    m.add_function(wrap_pyfunction!(threads::get_num_threads, m)?)?;
+    m.add_function(wrap_pyfunction!(benchmark::benchmark, m)?)?;
+    m.add("__doc__", "Made in Rust!")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
     use super::*;
     #[test]
-    fn check_dynamic_collisions() {
-        let mut grid = GridPhysics::new(100, 10);
-        grid.add_dynamic_circles(
-            0,
-            vec![
-                Vector2::new(5.0, 5.0),
-                Vector2::new(5.0, 5.5),
-                Vector2::new(5.0, 6.0),
-                Vector2::new(5.0, 6.5),
-            ],
-            1.0,
+    fn check_dynamic_collisions() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circles(
+            0,
+            vec![
+                Vector2::new(5.0, 5.0),
+                Vector2::new(5.0, 5.5),
+                Vector2::new(5.0, 6.0),
+                Vector2::new(5.0, 6.5),
+            ],
+            1.0,
+            0,
+        );
+        grid.add_dynamic_circles(
+            1,
+            vec![
+                Vector2::new(5.0, 8.5),
+                Vector2::new(5.0, 9.0),
+                Vector2::new(5.0, 9.5),
+                Vector2::new(5.0, 10.0),
+            ],
+            1.0,
+            0,
+        );
+
+        let collisions = grid.get_collisions(false, None, false, Targets::All);
+        assert_eq!(collisions.len(), 2);
+        for collision in collisions.iter() {
+            println!(
+                "Self: {}, Other: {}, Self Body: {}, Other Body: {}",
+                collision.self_entity_index,
+                collision.other_entity_index,
+                collision.self_body_index,
+                collision.other_body_index
+            );
+        }
+        assert!(collisions.contains(&Collision::new(1, 0, 0, 3)));
+        assert!(collisions.contains(&Collision::new(0, 1, 3, 0)));
+    }
+
+    #[test]
+    fn check_distance_matrix() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(5.0, 5.0), 1.0, 0);
+        grid.add_dynamic_circles(
+            1,
+            vec![Vector2::new(5.0, 10.0), Vector2::new(5.0, 8.0)],
+            1.0,
+            0,
+        );
+        grid.add_static_circle(2, Vector2::new(50.0, 50.0), 1.0, 0);
+
+        let distances = grid.min_distances(&[0], &[1, 2], None);
+        assert_eq!(distances[[0, 0]], 1.0);
+        assert!((distances[[0, 1]] - 61.63).abs() < 0.01);
+
+        let distances = grid.min_distances(&[0], &[1, 2], Some(5.0));
+        assert_eq!(distances[[0, 0]], 1.0);
+        assert_eq!(distances[[0, 1]], f32::INFINITY);
+    }
+
+    #[test]
+    fn check_density_map() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(25.0, 35.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(25.0, 35.0), 1.0, 1);
+        grid.add_static_circle(2, Vector2::new(25.0, 35.0), 1.0, 0);
+
+        let counts = grid.dynamic_counts(None);
+        assert_eq!(counts.dim(), (10, 10));
+        assert_eq!(counts[[2, 3]], 2);
+        // One body is a single 1, not one in every cell its rounded out bounds reach
+        assert_eq!(grid.dynamic_counts(Some(1))[[2, 3]], 1);
+        assert_eq!(grid.dynamic_counts(Some(1)).sum(), 1);
+        assert_eq!(counts.sum(), 2);
+    }
+
+    #[test]
+    fn check_cell_contents() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circles(
+            4,
+            vec![Vector2::new(15.0, 15.0), Vector2::new(45.0, 15.0)],
+            1.0,
+            0,
+        );
+
+        assert_eq!(grid.cell_of(Vector2::new(45.0, 15.0)), Some((4, 1)));
+        assert_eq!(grid.cell_of(Vector2::new(-1.0, 15.0)), None);
+        assert_eq!(grid.cell_of(Vector2::new(15.0, 100.0)), None);
+        assert_eq!(grid.cell_contents(4, 1).unwrap(), vec![(4, 1)]);
+        assert_eq!(grid.cell_contents(1, 1).unwrap(), vec![(4, 0)]);
+        assert!(grid.cell_contents(0, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn check_collision_counts() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circles(
+            0,
+            vec![Vector2::new(5.0, 5.0), Vector2::new(5.0, 6.0)],
+            1.0,
+            0,
+        );
+        grid.add_static_circle(1, Vector2::new(5.0, 7.0), 1.0, 0);
+
+        let counts = grid.get_collision_counts();
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts[&(0, 1)], 2);
+    }
+
+    #[test]
+    fn check_collision_overlap() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(5.0, 5.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(5.0, 6.5), 1.0, 0);
+
+        let collision = grid
+            .get_collisions(true, None, false, Targets::All)
+            .into_iter()
+            .next()
+            .unwrap();
+        assert!((collision.overlap - 0.5).abs() < 1e-6);
+        let collision = grid
+            .get_collisions(false, None, false, Targets::All)
+            .into_iter()
+            .next()
+            .unwrap();
+        assert_eq!(collision.overlap, 0.0);
+    }
+
+    #[test]
+    fn check_near_collisions() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(5.0, 5.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(5.0, 7.5), 1.0, 0);
+        grid.add_static_circle(2, Vector2::new(5.0, 9.0), 1.0, 0);
+
+        assert!(grid
+            .get_collisions(false, None, false, Targets::All)
+            .is_empty());
+        let collisions = grid.get_near_collisions(1.0);
+        assert_eq!(collisions.len(), 1);
+        let collision = collisions.into_iter().next().unwrap();
+        assert_eq!(collision.other_entity_index, 1);
+        assert!((collision.overlap + 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn check_cross_collisions() {
+        let mut projectiles = GridPhysics::new(100.0, 5.0);
+        projectiles.add_dynamic_circle(0, Vector2::new(12.0, 12.0), 0.5, 0);
+        projectiles.add_dynamic_circle(1, Vector2::new(80.0, 80.0), 0.5, 0);
+        let mut creatures = GridPhysics::new(100.0, 20.0);
+        creatures.add_dynamic_circle(0, Vector2::new(13.0, 12.0), 1.0, 0);
+
+        let collisions = projectiles.collisions_against(&creatures);
+        assert_eq!(collisions.len(), 1);
+        assert!(collisions.contains(&Collision::new(0, 0, 0, 0)));
+        assert!(creatures
+            .collisions_against(&projectiles)
+            .contains(&Collision::new(0, 0, 0, 0)));
+    }
+
+    #[test]
+    fn check_float_cell_size() {
+        let mut grid = GridPhysics::new(10.0, 0.5);
+        assert_eq!(grid.grid_size, 20);
+        assert_eq!(grid.cell_of(Vector2::new(1.2, 9.9)), Some((2, 19)));
+
+        grid.add_dynamic_circle(0, Vector2::new(1.0, 1.0), 0.3, 0);
+        grid.add_dynamic_circle(1, Vector2::new(1.5, 1.0), 0.3, 0);
+        assert_eq!(
+            grid.get_collisions(false, None, false, Targets::All).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn check_resize() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(150.0, 5.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(150.0, 6.0), 1.0, 0);
+        assert!(grid
+            .get_collisions(false, None, false, Targets::All)
+            .is_empty());
+
+        grid.reserve(400, 0);
+        grid.resize(200.0, 20.0).unwrap();
+        assert_eq!(grid.grid_size, 10);
+        assert_eq!(grid.cell_contents(7, 0).unwrap().len(), 2);
+        assert!(grid
+            .get_collisions(false, None, false, Targets::All)
+            .contains(&Collision::new(0, 1, 0, 0)));
+        assert!(grid.grid.iter().all(|cell| cell.capacity() >= 4));
+
+        // Shrinking the world past a body reports it leaving, and growing it back returning
+        grid.resize(100.0, 20.0).unwrap();
+        let events = grid.drain_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, EventKind::Left);
+        assert_eq!(events[0].self_entity_index, 0);
+        grid.resize(200.0, 20.0).unwrap();
+        let events = grid.drain_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, EventKind::Reentered);
+
+        // A cell far wider than the world still makes one cell, which wrapping walks
+        grid.resize(f32::MIN_POSITIVE, 1e30).unwrap();
+        assert_eq!(grid.grid_size, 1);
+        grid.set_wrap(true).unwrap();
+    }
+
+    #[test]
+    fn check_world_clock() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(5.0, 5.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(5.0, 6.0), 1.0, 0);
+
+        assert_eq!(grid.step(0.5), 1);
+        assert_eq!(grid.step(0.25), 2);
+        grid.reset();
+        assert_eq!(grid.tick(), 2);
+        assert_eq!(grid.time(), 0.75);
+
+        grid.add_dynamic_circle(0, Vector2::new(5.0, 5.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(5.0, 6.0), 1.0, 0);
+        let collision = grid
+            .get_collisions(false, None, false, Targets::All)
+            .into_iter()
+            .next()
+            .unwrap();
+        assert_eq!(collision.tick, 2);
+    }
+
+    #[test]
+    fn check_step_events() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.set_sensor(2, true);
+        grid.add_dynamic_circle(0, Vector2::new(5.0, 5.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(5.0, 6.0), 1.0, 0);
+        grid.add_dynamic_circle(2, Vector2::new(0.5, 50.0), 1.0, 0);
+        grid.add_static_circle(3, Vector2::new(1.0, 50.0), 1.0, 0);
+        grid.step(1.0 / 60.0);
+
+        let kinds: Vec<EventKind> = grid.drain_events().iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EventKind::Collision,
+                EventKind::Enter,
+                EventKind::Sensor,
+                EventKind::Enter,
+                EventKind::Boundary,
+            ]
+        );
+        assert!(grid.drain_events().is_empty());
+
+        grid.reset();
+        grid.add_dynamic_circle(0, Vector2::new(5.0, 5.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(5.0, 6.0), 1.0, 0);
+        grid.step(1.0 / 60.0);
+
+        let events = grid.drain_events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, EventKind::Collision);
+        assert_eq!(events[1].kind, EventKind::Exit);
+        assert_eq!(events[1].self_entity_index, 2);
+        assert_eq!(events[1].tick, 2);
+    }
+
+    #[test]
+    fn check_groups() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(5.0, 5.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(5.0, 6.0), 1.0, 0);
+        grid.add_dynamic_circle(2, Vector2::new(6.0, 5.0), 1.0, 0);
+        grid.create_group("particles".to_string(), vec![2]);
+
+        assert_eq!(
+            grid.get_collisions(false, None, false, Targets::All).len(),
+            6
+        );
+        assert_eq!(
+            grid.get_group_collisions("particles", false, None, false)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(grid
+            .get_group_collisions("missing", false, None, false)
+            .is_err());
+
+        grid.set_group_active("particles", false).unwrap();
+        assert_eq!(
+            grid.get_collisions(false, None, false, Targets::All).len(),
+            2
+        );
+        assert!(grid
+            .get_group_collisions("particles", false, None, false)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            grid.get_collisions_within_area(
+                Vector2::new(5.0, 5.0),
+                1.0,
+                None,
+                None,
+                false,
+                Containment::Intersects
+            ),
+            HashSet::from([0, 1])
+        );
+
+        grid.set_group_active("particles", true).unwrap();
+        assert_eq!(
+            grid.get_collisions(false, None, false, Targets::All).len(),
+            6
+        );
+    }
+
+    #[test]
+    fn check_hex_layout() {
+        let mut grid = GridPhysics::hexagonal(100.0, 10.0, None).unwrap();
+        assert_eq!(grid.cell_of(Vector2::new(0.0, 0.0)), Some((0, 0)));
+        assert_eq!(grid.cell_of(Vector2::new(10.0, 0.0)), Some((1, 0)));
+        // Half a cell to the right on the next row
+        assert_eq!(grid.cell_of(Vector2::new(5.0, 8.66)), Some((0, 1)));
+        assert_eq!(grid.cell_of(Vector2::new(-6.0, 0.0)), None);
+        assert_eq!(grid.cell_neighbors(1, 1).len(), 6);
+        assert_eq!(grid.cell_neighbors(0, 0).len(), 2);
+
+        grid.add_dynamic_circle(0, Vector2::new(5.0, 8.66), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(6.0, 8.66), 1.0, 0);
+        grid.add_static_circle(2, Vector2::new(50.0, 50.0), 1.0, 0);
+        assert_eq!(grid.cell_contents(0, 1).unwrap(), vec![(1, 0), (0, 0)]);
+        assert_eq!(
+            grid.get_collisions(false, None, false, Targets::All).len(),
+            1
+        );
+        assert_eq!(
+            grid.get_collisions_within_area(
+                Vector2::new(5.0, 8.66),
+                2.0,
+                None,
+                None,
+                false,
+                Containment::Intersects
+            ),
+            HashSet::from([0, 1])
+        );
+    }
+
+    #[test]
+    fn check_query_metrics() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(50.0, 50.0), 0.5, 0);
+        grid.add_dynamic_circle(1, Vector2::new(53.5, 53.5), 0.5, 0);
+        grid.add_dynamic_circle(2, Vector2::new(54.5, 50.0), 0.5, 0);
+        grid.add_static_circle(3, Vector2::new(90.0, 90.0), 0.5, 0);
+
+        let center = Vector2::new(50.0, 50.0);
+        let within = |metric| {
+            grid.get_collisions_within_area(
+                center,
+                4.0,
+                Some(metric),
+                None,
+                false,
+                Containment::Intersects,
+            )
+        };
+        assert_eq!(within(Metric::Chebyshev), HashSet::from([0, 1, 2]));
+        assert_eq!(within(Metric::Euclidean), HashSet::from([0, 2]));
+        assert_eq!(within(Metric::Manhattan), HashSet::from([0, 2]));
+
+        let nearest = grid.nearest(center, 2, None, Metric::Euclidean, None);
+        assert_eq!(nearest, vec![(0, 0, 0.0), (2, 0, 4.0)]);
+        let nearest = grid.nearest(center, 3, None, Metric::Chebyshev, None);
+        assert_eq!(nearest[1], (1, 0, 3.0));
+        let nearest = grid.nearest(Vector2::new(10.0, 10.0), 1, None, Metric::Manhattan, None);
+        assert_eq!(nearest, vec![(0, 0, 79.5)]);
+        assert!(grid
+            .nearest(center, 10, Some(5.0), Metric::Euclidean, None)
+            .iter()
+            .all(|(_, _, distance)| *distance <= 5.0));
+    }
+
+    #[test]
+    fn check_query_limits() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        for i in 0..5 {
+            grid.add_dynamic_circle(i, Vector2::new(5.0 + 10.0 * i as f32, 5.0), 1.0, 0);
+            grid.add_static_circle(10 + i, Vector2::new(5.0 + 10.0 * i as f32, 6.0), 1.0, 0);
+        }
+
+        assert_eq!(
+            grid.get_collisions(false, None, false, Targets::All).len(),
+            5
+        );
+        assert_eq!(
+            grid.get_collisions(false, Some(3), false, Targets::All)
+                .len(),
+            3
+        );
+        assert_eq!(
+            grid.get_collisions(false, Some(3), true, Targets::All)
+                .len(),
+            1
+        );
+        let center = Vector2::new(25.0, 5.0);
+        assert_eq!(
+            grid.get_collisions_within_area(
+                center,
+                30.0,
+                None,
+                None,
+                false,
+                Containment::Intersects
+            )
+            .len(),
+            10
+        );
+        assert_eq!(
+            grid.get_collisions_within_area(
+                center,
+                30.0,
+                None,
+                Some(4),
+                false,
+                Containment::Intersects
+            )
+            .len(),
+            4
+        );
+
+        let origin = Vector2::new(0.0, 4.5);
+        let hits = grid.raycast(origin, Vector2::new(1.0, 0.0), None, None, false, false);
+        let entities: Vec<usize> = hits.iter().map(|hit| hit.0).collect();
+        assert_eq!(entities, vec![0, 1, 2, 3, 4]);
+        assert!((hits[0].2 - 4.134).abs() < 0.001);
+        let hits = grid.raycast(
+            origin,
+            Vector2::new(1.0, 0.0),
+            Some(20.0),
+            None,
+            false,
+            false,
+        );
+        assert_eq!(hits.len(), 2);
+        let hits = grid.raycast(origin, Vector2::new(1.0, 0.0), None, None, true, false);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0, 0);
+        let hits = grid.raycast(origin, Vector2::new(-1.0, 0.0), None, None, false, false);
+        assert!(hits.is_empty());
+    }
+
+    #[test]
+    fn check_sorted_circle_query() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circles(
+            0,
+            vec![Vector2::new(58.0, 50.0), Vector2::new(52.0, 50.0)],
+            1.0,
+            0,
+        );
+        grid.add_static_circle(1, Vector2::new(50.0, 45.0), 1.0, 0);
+        grid.add_static_circle(2, Vector2::new(50.0, 70.0), 1.0, 0);
+
+        let center = Vector2::new(50.0, 50.0);
+        let results = grid.query_circle_sorted(center, 10.0, None);
+        assert_eq!(results, vec![(0, 1, 1.0), (1, 0, 4.0), (0, 0, 7.0)]);
+        assert_eq!(
+            grid.query_circle_sorted(center, 10.0, Some(2)),
+            results[..2]
+        );
+    }
+
+    #[test]
+    fn check_kind_registry() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let enemy = grid.register_kind("enemy");
+        assert_eq!(enemy, 1);
+        assert_eq!(grid.register_kind("enemy"), enemy);
+        assert_eq!(grid.kind_name(enemy), Some("enemy"));
+
+        grid.py_add_dynamic_circle(
+            0,
+            Vector2::new(5.0, 5.0),
+            1.0,
+            KindArg::Name("enemy".into()),
+        )
+        .unwrap();
+        grid.py_add_dynamic_circle(1, Vector2::new(5.0, 5.0), 1.0, KindArg::Name("ally".into()))
+            .unwrap();
+        assert_eq!(
+            grid.kinds.resolve(&KindArg::Name("ally".into())).unwrap(),
+            2
+        );
+        assert!(grid.kinds.resolve(&KindArg::Name("boss".into())).is_err());
+        assert_eq!(grid.dynamic_counts(Some(enemy))[[0, 0]], 1);
+    }
+
+    #[test]
+    fn check_collisions_into_buffer() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(9.5, 5.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(10.5, 5.0), 1.0, 0);
+        grid.add_static_circle(2, Vector2::new(8.5, 5.0), 1.0, 0);
+
+        let mut buffer = Array2::<i64>::from_elem((4, 4), -1);
+        assert_eq!(grid.write_collisions(buffer.view_mut()).unwrap(), 2);
+        let mut rows: Vec<Vec<i64>> = buffer
+            .outer_iter()
+            .take(2)
+            .map(|row| row.to_vec())
+            .collect();
+        rows.sort();
+        assert_eq!(rows, vec![vec![0, 1, 0, 0], vec![0, 2, 0, 0]]);
+        assert_eq!(buffer[[2, 0]], -1);
+
+        let mut small = Array2::<i64>::zeros((1, 4));
+        assert_eq!(grid.write_collisions(small.view_mut()).unwrap(), 1);
+        assert!(grid
+            .write_collisions(Array2::zeros((4, 3)).view_mut())
+            .is_err());
+    }
+
+    #[test]
+    fn check_collision_columns() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(3, Vector2::new(5.0, 5.0), 1.0, 0);
+        grid.add_static_circle(7, Vector2::new(5.0, 6.5), 1.0, 0);
+        grid.step(1.0 / 60.0);
+
+        let columns = grid.collision_columns(true);
+        assert_eq!(columns.self_entity_index, vec![3]);
+        assert_eq!(columns.other_entity_index, vec![7]);
+        assert_eq!(columns.self_body_index, vec![0]);
+        assert_eq!(columns.tick, vec![1]);
+        assert!((columns.overlap[0] - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn check_positions() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circles(
+            2,
+            vec![Vector2::new(1.0, 2.0), Vector2::new(3.0, 4.0)],
+            1.0,
+            0,
+        );
+        grid.add_dynamic_circle(1, Vector2::new(5.0, 6.0), 1.0, 0);
+        grid.add_static_circle(0, Vector2::new(7.0, 8.0), 1.0, 0);
+
+        let positions = grid.positions(false);
+        assert_eq!(positions.indices(), &[(1, 0), (2, 0), (2, 1)]);
+        assert_eq!(positions.data(), &[5.0, 6.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(grid.positions(true).indices()[0], (0, 0));
+    }
+
+    #[test]
+    fn check_observations() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(50.0, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(60.0, 50.0), 1.0, 0);
+        grid.add_static_circle(2, Vector2::new(50.0, 30.0), 2.0, 0);
+
+        let observations = grid.observations(&[0, 1], 2, 4, 40.0).unwrap();
+        assert_eq!(observations.dim(), (2, 8));
+        // Nearest offsets, then rays along +x, +y, -x, -y
+        let row: Vec<f32> = observations.row(0).to_vec();
+        assert_eq!(&row[..4], &[10.0, 0.0, 0.0, -20.0]);
+        assert!((row[4] - 9.0).abs() < 1e-4);
+        assert_eq!(row[5], 40.0);
+        assert_eq!(row[6], 40.0);
+        assert!((row[7] - 18.0).abs() < 1e-4);
+
+        assert!(grid.observations(&[7], 1, 1, 10.0).is_err());
+        assert!(grid
+            .observations(&[0], usize::MAX / 2 + 1, 0, 10.0)
+            .is_err());
+        assert!(grid.observations(&[0], usize::MAX, 1, 10.0).is_err());
+    }
+
+    #[test]
+    fn check_deterministic_collisions() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        for entity_index in 0..40 {
+            let x = 5.0 + (entity_index % 8) as f32 * 1.5;
+            let y = 5.0 + (entity_index / 8) as f32 * 1.5;
+            grid.add_dynamic_circle(entity_index, Vector2::new(x, y), 1.0, 0);
+        }
+
+        let keys = |collisions: Vec<Collision>| -> Vec<ContactKey> {
+            collisions.iter().map(Collision::key).collect()
+        };
+        let sequential = keys(grid.ordered_collisions(false, false));
+        assert!(sequential.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(
+            sequential.len(),
+            grid.get_collisions(false, None, false, Targets::All).len()
+        );
+        for threads in [1, 2, 7] {
+            let parallel =
+                threads::install(Some(threads), || keys(grid.ordered_collisions(false, true)))
+                    .unwrap();
+            assert_eq!(parallel, sequential);
+        }
+
+        // Limited queries take the collisions of the lowest entities
+        let limited = grid.get_collisions(false, Some(1), false, Targets::All);
+        assert!(limited
+            .iter()
+            .all(|collision| collision.self_entity_index == 0));
+    }
+
+    #[test]
+    fn check_entity_bounds() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circles(
+            0,
+            vec![Vector2::new(10.0, 10.0), Vector2::new(30.0, 10.0)],
+            2.0,
+            0,
+        );
+        grid.add_static_circle(1, Vector2::new(50.0, 50.0), 5.0, 0);
+        assert_eq!(grid.entity_bounds[&0], (8.0, 32.0, 8.0, 12.0));
+
+        let query = |grid: &GridPhysics, min: (f32, f32), max: (f32, f32)| {
+            grid.entities_overlapping_aabb(
+                Vector2::new(min.0, min.1),
+                Vector2::new(max.0, max.1),
+                Containment::Intersects,
+            )
+        };
+        // Inside the entity bounds but between its circles
+        assert!(query(&grid, (18.0, 8.0), (22.0, 12.0)).is_empty());
+        assert_eq!(query(&grid, (0.0, 0.0), (100.0, 100.0)), vec![0, 1]);
+        // Touches the bounding box corner of the static circle but not the circle itself
+        assert!(query(&grid, (53.9, 53.9), (60.0, 60.0)).is_empty());
+        assert_eq!(query(&grid, (29.0, 9.0), (31.0, 11.0)), vec![0]);
+
+        grid.reset();
+        assert!(grid.entity_bounds.is_empty());
+    }
+
+    #[test]
+    fn check_entity_transform() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circles(
+            0,
+            vec![Vector2::new(0.0, 0.0), Vector2::new(10.0, 0.0)],
+            1.0,
+            0,
+        );
+        grid.add_static_circle(1, Vector2::new(50.0, 60.0), 1.0, 0);
+        assert!(grid
+            .get_collisions(false, None, false, Targets::All)
+            .is_empty());
+
+        // A quarter turn swings the second circle from +x to +y onto the static one
+        grid.set_entity_transform(0, Vector2::new(50.0, 50.0), std::f32::consts::FRAC_PI_2)
+            .unwrap();
+        let collisions = grid.get_collisions(false, None, false, Targets::All);
+        assert_eq!(collisions.len(), 1);
+        assert!(collisions.contains(&Collision::new(0, 1, 1, 0)));
+        assert_eq!(grid.cell_contents(4, 4).unwrap(), vec![(0, 0)]);
+        assert!(grid.cell_contents(0, 0).unwrap().is_empty());
+
+        let bounds = grid.entity_bounds[&0];
+        assert!((bounds.2 - 49.0).abs() < 1e-4 && (bounds.3 - 61.0).abs() < 1e-4);
+        assert!(grid
+            .set_entity_transform(9, Vector2::new(0.0, 0.0), 0.0)
+            .is_err());
+    }
+
+    #[test]
+    fn check_anchored_entity() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let offsets = vec![Vector2::new(-5.0, 0.0), Vector2::new(5.0, 0.0)];
+        grid.add_anchored_circles(0, offsets, 1.0, Vector2::new(20.0, 20.0), false, 0);
+        grid.add_static_circle(1, Vector2::new(75.0, 40.0), 1.0, 0);
+        let positions = grid.positions(false);
+        assert_eq!(positions.data(), &[15.0, 20.0, 25.0, 20.0]);
+
+        grid.move_entity(0, Vector2::new(70.0, 40.0)).unwrap();
+        assert_eq!(grid.positions(false).data(), &[65.0, 40.0, 75.0, 40.0]);
+        assert!(grid
+            .get_collisions(false, None, false, Targets::All)
+            .contains(&Collision::new(0, 1, 1, 0)));
+        assert!(grid.cell_contents(1, 2).unwrap().is_empty());
+
+        // Bodies added later without an anchor keep their world position through moves
+        grid.add_circle(0, Vector2::new(70.0, 45.0), 1.0, 2, false, 0);
+        grid.move_entity(0, Vector2::new(70.0, 50.0)).unwrap();
+        let moved = grid.positions(false);
+        assert_eq!(&moved.data()[4..], &[70.0, 55.0]);
+        assert!(grid.move_entity(5, Vector2::new(0.0, 0.0)).is_err());
+    }
+
+    #[test]
+    fn check_ellipse_bodies() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_ellipse(0, Vector2::new(0.0, 0.0), (20.0, 2.0), 0.0, false, 0);
+        grid.set_entity_transform(0, Vector2::new(50.0, 50.0), 0.0)
+            .unwrap();
+        grid.add_static_circle(1, Vector2::new(68.0, 50.0), 1.0, 0);
+        grid.add_static_circle(2, Vector2::new(50.0, 60.0), 1.0, 0);
+        let collisions = grid.get_collisions(false, None, false, Targets::All);
+        assert_eq!(collisions.len(), 1);
+        assert!(collisions.contains(&Collision::new(0, 1, 0, 0)));
+        assert!(grid.cell_contents(3, 4).unwrap().contains(&(0, 0)));
+        assert!(!grid.cell_contents(5, 3).unwrap().contains(&(0, 0)));
+
+        // Turning the entity turns the ellipse with it
+        grid.set_entity_transform(0, Vector2::new(50.0, 50.0), std::f32::consts::FRAC_PI_2)
+            .unwrap();
+        let collisions = grid.get_collisions(false, None, false, Targets::All);
+        assert_eq!(collisions.len(), 1);
+        assert!(collisions.contains(&Collision::new(0, 2, 0, 0)));
+    }
+
+    #[test]
+    fn check_obb_bodies() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let diagonal = std::f32::consts::FRAC_PI_4;
+        grid.add_obb(0, Vector2::new(50.0, 50.0), (10.0, 1.0), diagonal, false, 0);
+        grid.add_static_circle(1, Vector2::new(56.0, 56.0), 1.0, 0);
+        grid.add_static_circle(2, Vector2::new(56.0, 50.0), 1.0, 0);
+        grid.add_obb(3, Vector2::new(44.0, 56.0), (3.0, 3.0), 0.0, true, 0);
+
+        let collisions = grid.get_collisions(false, None, false, Targets::All);
+        assert_eq!(collisions.len(), 1);
+        assert!(collisions.contains(&Collision::new(0, 1, 0, 0)));
+        // The rotated box reaches about 7.8 along each axis
+        assert!(grid.cell_contents(4, 4).unwrap().contains(&(0, 0)));
+        assert!(!grid.cell_contents(3, 5).unwrap().contains(&(0, 0)));
+    }
+
+    #[test]
+    fn check_static_chain() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let hills = [
+            Vector2::new(0.0, 20.0),
+            Vector2::new(30.0, 50.0),
+            Vector2::new(60.0, 20.0),
+        ];
+        grid.add_static_chain(0, &hills, 0);
+        grid.add_dynamic_circle(1, Vector2::new(40.0, 42.0), 1.5, 0);
+        grid.add_dynamic_circle(2, Vector2::new(30.0, 45.0), 1.0, 0);
+
+        let collisions = grid.get_collisions(false, None, false, Targets::All);
+        assert_eq!(collisions.len(), 1);
+        assert!(collisions.contains(&Collision::new(1, 0, 0, 1)));
+        // The diagonal crosses cell (1, 3) but not the (1, 2) corner of its bounding box
+        assert!(grid.cell_contents(1, 3).unwrap().contains(&(0, 0)));
+        assert!(!grid.cell_contents(2, 2).unwrap().contains(&(0, 0)));
+
+        let hits = grid.raycast(
+            Vector2::new(10.0, 0.0),
+            Vector2::new(0.0, 1.0),
+            None,
+            Some(1),
+            false,
+            false,
+        );
+        assert_eq!((hits[0].0, hits[0].1), (0, 0));
+        assert!((hits[0].2 - 30.0).abs() < 1e-3);
+
+        // A chain along a cell edge is only in cells its removal clears
+        grid.add_static_chain(3, &[Vector2::new(5.0, 70.0), Vector2::new(35.0, 70.0)], 0);
+        grid.py_remove_entity(3);
+        assert!(grid
+            .grid
+            .iter()
+            .flat_map(|cell| cell.iter())
+            .all(|body| body.entity_index() != 3));
+    }
+
+    #[test]
+    fn check_static_trimesh() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let vertices = [
+            Vector2::new(20.0, 20.0),
+            Vector2::new(40.0, 20.0),
+            Vector2::new(40.0, 40.0),
+            Vector2::new(20.0, 40.0),
+        ];
+        grid.add_static_trimesh(0, &vertices, &[(0, 1, 2), (0, 2, 3)], 0)
+            .unwrap();
+        grid.add_dynamic_circle(1, Vector2::new(38.0, 25.0), 1.0, 0);
+        grid.add_dynamic_circle(2, Vector2::new(19.5, 30.0), 1.0, 0);
+        grid.add_dynamic_circle(3, Vector2::new(45.0, 45.0), 1.0, 0);
+
+        let collisions = grid.get_collisions(false, None, false, Targets::All);
+        assert_eq!(collisions.len(), 2);
+        assert!(collisions.contains(&Collision::new(1, 0, 0, 0)));
+        assert!(collisions.contains(&Collision::new(2, 0, 0, 1)));
+        assert!(grid.cell_contents(3, 3).unwrap().contains(&(0, 0)));
+        assert!(!grid.cell_contents(5, 5).unwrap().contains(&(0, 0)));
+
+        assert!(grid
+            .add_static_trimesh(4, &vertices, &[(0, 1, 4)], 0)
+            .is_err());
+    }
+
+    #[test]
+    fn check_tiled_import() {
+        let map = r#"{
+            "width": 10, "height": 8, "tilewidth": 16, "tileheight": 16,
+            "layers": [
+                {"type": "tilelayer", "name": "ground"},
+                {"type": "group", "name": "level", "layers": [
+                    {"type": "objectgroup", "name": "collisions", "objects": [
+                        {"id": 1, "x": 0, "y": 0, "width": 32, "height": 16, "class": "wall"},
+                        {"id": 2, "x": 64, "y": 0, "width": 20, "height": 10, "ellipse": true},
+                        {"id": 3, "x": 100, "y": 100, "polygon": [
+                            {"x": 0, "y": 0}, {"x": 20, "y": 0}, {"x": 20, "y": 10},
+                            {"x": 10, "y": 10}, {"x": 10, "y": 20}, {"x": 0, "y": 20}
+                        ]},
+                        {"id": 4, "x": 0, "y": 64, "polyline": [
+                            {"x": 0, "y": 0}, {"x": 40, "y": 0}
+                        ]},
+                        {"id": 5, "x": 5, "y": 5, "point": true}
+                    ]}
+                ]}
+            ]
+        }"#;
+        let mut grid = GridPhysics::from_tiled(map, "collisions", None).unwrap();
+        assert_eq!(grid.size, 160.0);
+        assert_eq!(grid.cell_size, 16.0);
+        assert_eq!(grid.kind_name(1), Some("wall"));
+        assert_eq!(grid.static_bodies[&3].len(), 4);
+        assert_eq!(grid.static_bodies[&4].len(), 1);
+        assert!(!grid.static_bodies.contains_key(&5));
+
+        let probes = [
+            (30.0, 8.0),
+            (74.0, 9.0),
+            (115.0, 115.0),
+            (20.0, 65.0),
+            (50.0, 50.0),
+        ];
+        for (i, (x, y)) in probes.into_iter().enumerate() {
+            grid.add_dynamic_circle(10 + i, Vector2::new(x, y), 1.5, 0);
+        }
+        let mut hit: Vec<(usize, usize)> = grid
+            .get_collisions(false, None, false, Targets::All)
+            .iter()
+            .map(|collision| (collision.self_entity_index, collision.other_entity_index))
+            .collect();
+        hit.sort_unstable();
+        assert_eq!(hit, vec![(10, 1), (11, 2), (13, 4)]);
+
+        assert!(GridPhysics::from_tiled(map, "missing", None).is_err());
+        assert!(GridPhysics::from_tiled("<map/>", "collisions", None).is_err());
+
+        // Ids must be integers fitting an entity index, not defaulted or truncated
+        for id in ["", r#""id": "7","#, r#""id": 4294967296,"#] {
+            let map = format!(
+                r#"{{"width": 4, "height": 4, "tilewidth": 16, "tileheight": 16, "layers": [
+                    {{"type": "objectgroup", "name": "collisions", "objects": [
+                        {{{id} "x": 0, "y": 0, "width": 16, "height": 16}}
+                    ]}}
+                ]}}"#
+            );
+            assert!(GridPhysics::from_tiled(&map, "collisions", None).is_err());
+        }
+    }
+
+    #[test]
+    fn check_json_scene() {
+        let mut grid = GridPhysics::hexagonal(100.0, 10.0, None).unwrap();
+        let wall = grid.register_kind("wall");
+        grid.add_anchored_circles(
+            0,
+            vec![Vector2::new(-2.0, 0.0), Vector2::new(2.0, 0.0)],
+            1.0,
+            Vector2::new(20.0, 20.0),
+            false,
+            0,
+        );
+        grid.set_entity_transform(0, Vector2::new(20.0, 20.0), 0.5)
+            .unwrap();
+        grid.add_obb(1, Vector2::new(50.0, 50.0), (4.0, 1.0), 0.3, true, wall);
+        grid.add_static_trimesh(
+            2,
+            &[
+                Vector2::new(70.0, 70.0),
+                Vector2::new(80.0, 70.0),
+                Vector2::new(75.0, 80.0),
+            ],
+            &[(0, 1, 2)],
+            wall,
+        )
+        .unwrap();
+        grid.create_group("ships".to_string(), vec![0]);
+        grid.set_sensor(1, true);
+
+        let json = grid.to_json(true).unwrap();
+        let restored = GridPhysics::from_json(&json).unwrap();
+        assert_eq!(restored.to_json(true).unwrap(), json);
+        assert!(matches!(restored.layout, Layout::Hex));
+        assert_eq!(restored.kind_name(wall), Some("wall"));
+        assert_eq!(restored.positions(true).data(), grid.positions(true).data());
+        assert_eq!(restored.entity_bounds[&2], grid.entity_bounds[&2]);
+
+        // Moves keep working from the restored entity transforms
+        let mut moved = GridPhysics::from_json(&json).unwrap();
+        moved.move_entity(0, Vector2::new(30.0, 20.0)).unwrap();
+        grid.move_entity(0, Vector2::new(30.0, 20.0)).unwrap();
+        assert_eq!(moved.positions(false).data(), grid.positions(false).data());
+
+        assert!(GridPhysics::from_json("{}").is_err());
+        let future = json.replacen("\"version\": 1", "\"version\": 2", 1);
+        assert!(GridPhysics::from_json(&future).is_err());
+    }
+
+    #[test]
+    fn check_static_baking() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_static_circle(0, Vector2::new(50.0, 50.0), 10.0, 0);
+        grid.add_static_circle(1, Vector2::new(52.0, 50.0), 3.0, 0);
+        grid.add_obb(2, Vector2::new(45.0, 45.0), (1.0, 1.0), 0.0, true, 0);
+        grid.add_static_circle(3, Vector2::new(62.0, 50.0), 4.0, 0);
+        // Identical bodies contain each other, only one of them goes
+        grid.add_static_circle(4, Vector2::new(20.0, 20.0), 2.0, 0);
+        grid.add_static_circle(5, Vector2::new(20.0, 20.0), 2.0, 0);
+        assert_eq!(grid.get_static_overlaps().len(), 4);
+
+        assert_eq!(grid.bake_static_exclusions(), (1, 3));
+        assert!(!grid.static_bodies.contains_key(&1) && !grid.static_bodies.contains_key(&2));
+        assert!(grid.static_bodies.contains_key(&4) && !grid.static_bodies.contains_key(&5));
+        assert!(!grid.entity_bounds.contains_key(&1));
+        assert!(grid.get_static_overlaps().is_empty());
+
+        // Newly placed statics are reported again
+        grid.add_static_circle(6, Vector2::new(20.0, 23.0), 2.0, 0);
+        let overlaps = grid.get_static_overlaps();
+        assert_eq!(overlaps.len(), 1);
+        assert!(overlaps.contains(&Collision::new(4, 6, 0, 0)));
+    }
+
+    #[test]
+    fn check_optimize_statics() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        // A 3x2 block of unit tiles, each its own entity, and one tile of another kind
+        for (entity, (x, y)) in [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]
+            .into_iter()
+            .enumerate()
+        {
+            let center = Vector2::new(x as f32 + 10.5, y as f32 + 10.5);
+            grid.add_obb(entity, center, (0.5, 0.5), 0.0, true, 0);
+        }
+        let water = grid.register_kind("water");
+        grid.add_obb(6, Vector2::new(13.5, 10.5), (0.5, 0.5), 0.0, true, water);
+        // A chain with a straight run and a corner
+        let chain = [
+            Vector2::new(50.0, 50.0),
+            Vector2::new(52.0, 50.0),
+            Vector2::new(54.0, 50.0),
+            Vector2::new(54.0, 53.0),
+        ];
+        grid.add_static_chain(7, &chain, 0);
+
+        // Within entities only the chain gets shorter
+        assert_eq!(grid.optimize_statics(false), 1);
+        assert_eq!(grid.optimize_statics(true), 5);
+        assert_eq!(grid.static_bodies[&0].len(), 1);
+        assert!(!grid.static_bodies.contains_key(&5));
+        assert_eq!(grid.static_bodies[&6].len(), 1);
+        assert_eq!(grid.static_bodies[&7].len(), 2);
+
+        let block = grid.static_bodies[&0][0];
+        assert_eq!(block.get_bounds(), (10.0, 13.0, 10.0, 12.0));
+        assert_eq!(
+            grid.entities_overlapping_aabb(
+                Vector2::new(12.2, 11.2),
+                Vector2::new(12.4, 11.4),
+                Containment::Intersects
+            ),
+            vec![0]
+        );
+        assert_eq!(
+            grid.entities_overlapping_aabb(
+                Vector2::new(51.0, 49.0),
+                Vector2::new(53.5, 49.9),
+                Containment::Intersects
+            ),
+            Vec::<usize>::new()
+        );
+        assert_eq!(
+            grid.entities_overlapping_aabb(
+                Vector2::new(53.0, 49.9),
+                Vector2::new(53.5, 50.1),
+                Containment::Intersects
+            ),
+            vec![7]
+        );
+        assert_eq!(grid.optimize_statics(true), 0);
+    }
+
+    #[test]
+    fn check_simulation_islands() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(3, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(17.0, 15.0), 1.0, 0);
+        // Sharing a cell is enough, even without touching
+        grid.add_dynamic_circle(5, Vector2::new(11.0, 11.0), 1.0, 0);
+        grid.add_dynamic_circle(2, Vector2::new(85.0, 85.0), 1.0, 0);
+        // A static body spanning both groups doesn't join them
+        grid.add_static_circle(0, Vector2::new(50.0, 50.0), 50.0, 0);
+
+        assert_eq!(grid.simulation_islands(), vec![vec![1, 3, 5], vec![2]]);
+    }
+
+    #[test]
+    fn check_min_overlap() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        // Grazing by 0.05 and overlapping by 0.5
+        grid.add_dynamic_circle(1, Vector2::new(16.95, 15.0), 1.0, 0);
+        grid.add_dynamic_circle(2, Vector2::new(15.0, 13.5), 1.0, 0);
+        assert_eq!(
+            grid.get_collisions(false, None, false, Targets::All).len(),
+            4
+        );
+
+        grid.min_overlap = 0.1;
+        let collisions = grid.get_collisions(true, None, false, Targets::All);
+        assert_eq!(collisions.len(), 2);
+        assert!(collisions
+            .iter()
+            .all(|collision| collision.self_entity_index != 1 && collision.overlap >= 0.1));
+    }
+
+    #[test]
+    fn check_world_exit_events() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(50.0, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(98.0, 50.0), 1.0, 0);
+
+        // Crossing the edge isn't leaving yet
+        grid.update_position(1, 0, Vector2::new(100.5, 50.0))
+            .unwrap();
+        assert!(grid.drain_events().is_empty());
+        grid.update_position(1, 0, Vector2::new(102.0, 50.0))
+            .unwrap();
+        let events = grid.drain_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, EventKind::Left);
+        assert_eq!(events[0].self_entity_index, 1);
+        assert!(grid.cell_contents(9, 5).unwrap().is_empty());
+
+        // Stepping reports the crossing once, and the body is still tracked while outside
+        grid.move_entity(0, Vector2::new(-60.0, 0.0)).unwrap();
+        grid.step(1.0 / 60.0);
+        let kinds: Vec<EventKind> = grid.drain_events().iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![EventKind::Left, EventKind::Boundary, EventKind::Boundary]
+        );
+        grid.step(1.0 / 60.0);
+        assert!(grid
+            .drain_events()
+            .iter()
+            .all(|event| event.kind == EventKind::Boundary));
+
+        grid.update_position(1, 0, Vector2::new(95.0, 50.0))
+            .unwrap();
+        let events = grid.drain_events();
+        assert_eq!(events[0].kind, EventKind::Reentered);
+        assert_eq!(events[0].tick, 2);
+        assert_eq!(grid.cell_contents(9, 5).unwrap(), vec![(1, 0)]);
+        assert!(grid.update_position(1, 3, Vector2::new(0.0, 0.0)).is_err());
+    }
+
+    #[test]
+    fn check_teleport_sweep() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(10.0, 50.0), 1.0, 0);
+        // A tripwire and a sensor pad on the path, and a target at the destination
+        grid.add_static_chain(1, &[Vector2::new(30.0, 40.0), Vector2::new(30.0, 60.0)], 0);
+        grid.add_static_circle(2, Vector2::new(50.0, 51.5), 1.0, 0);
+        grid.add_dynamic_circle(3, Vector2::new(80.0, 50.0), 1.0, 0);
+        grid.add_static_circle(4, Vector2::new(50.0, 56.0), 1.0, 0);
+
+        let entities = |collisions: HashSet<Collision>| {
+            let mut entities: Vec<usize> = collisions
+                .iter()
+                .map(|collision| collision.other_entity_index)
+                .collect();
+            entities.sort_unstable();
+            entities
+        };
+        let hits = grid
+            .teleport(0, 0, Vector2::new(79.0, 50.0), false)
+            .unwrap();
+        assert_eq!(entities(hits), vec![3]);
+        assert_eq!(grid.cell_contents(7, 5).unwrap(), vec![(3, 0), (0, 0)]);
+
+        let back = grid.teleport(0, 0, Vector2::new(10.0, 50.0), true).unwrap();
+        assert_eq!(entities(back), vec![1, 2, 3]);
+        assert!(grid
+            .teleport(0, 0, Vector2::new(10.0, 50.0), true)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn check_velocity_binning() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(45.0, 13.0), 1.0, 0);
+        assert!(grid.cell_contents(3, 1).unwrap().is_empty());
+
+        // 24 units per second over half a second reaches x = 28
+        grid.set_sweep_dt(0.5).unwrap();
+        grid.set_velocity(0, Vector2::new(24.0, 0.0)).unwrap();
+        assert_eq!(grid.get_velocity(0), (24.0, 0.0));
+        for cell_x in 1..=3 {
+            assert_eq!(grid.cell_contents(cell_x, 1).unwrap(), vec![(0, 0)]);
+        }
+        assert_eq!(grid.cell_contents(4, 1).unwrap(), vec![(1, 0)]);
+        // Binning doesn't change the narrow phase
+        assert!(grid
+            .get_collisions(false, None, false, Targets::All)
+            .is_empty());
+
+        grid.move_entity(0, Vector2::new(10.0, 0.0)).unwrap();
+        assert_eq!(grid.cell_contents(4, 1).unwrap(), vec![(1, 0), (0, 0)]);
+        assert!(grid.cell_contents(1, 1).unwrap().is_empty());
+
+        grid.set_sweep_dt(0.0).unwrap();
+        assert_eq!(grid.cell_contents(4, 1).unwrap(), vec![(1, 0)]);
+        for bad in [f32::NAN, -0.5, f32::INFINITY] {
+            assert!(grid.set_sweep_dt(bad).is_err());
+        }
+        assert_eq!(grid.sweep_dt, 0.0);
+        grid.set_sweep_dt(0.5).unwrap();
+        assert_eq!(grid.cell_contents(4, 1).unwrap(), vec![(1, 0), (0, 0)]);
+        grid.set_velocity(0, Vector2::new(0.0, 0.0)).unwrap();
+        assert_eq!(grid.cell_contents(4, 1).unwrap(), vec![(1, 0)]);
+        assert_eq!(grid.cell_contents(2, 1).unwrap(), vec![(0, 0)]);
+        assert!(grid.set_velocity(7, Vector2::new(1.0, 0.0)).is_err());
+    }
+
+    #[test]
+    fn check_collision_history() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.set_history_length(3);
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(16.0, 15.0), 1.0, 0);
+        grid.step(1.0 / 60.0);
+        grid.move_entity(0, Vector2::new(30.0, 0.0)).unwrap();
+        for _ in 0..2 {
+            grid.step(1.0 / 60.0);
+        }
+
+        let first = grid.collisions_at(1).unwrap();
+        assert_eq!(first.len(), 1);
+        assert!(first.iter().all(|collision| collision.tick == 1));
+        assert!(grid.collisions_at(3).unwrap().is_empty());
+        assert!(grid.was_colliding(1, 0, 2));
+        assert!(!grid.was_colliding(0, 1, 1));
+
+        // Older ticks fall out of the buffer
+        grid.step(1.0 / 60.0);
+        assert!(grid.collisions_at(1).is_err());
+        assert!(!grid.was_colliding(0, 1, 10));
+    }
+
+    #[test]
+    fn check_rollback() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.set_history_length(8);
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(35.0, 15.0), 1.0, 0);
+        grid.step(1.0 / 60.0);
+        grid.save_state(2).unwrap();
+
+        for _ in 0..3 {
+            grid.move_entity(0, Vector2::new(20.0, 0.0)).unwrap();
+            grid.set_velocity(0, Vector2::new(60.0, 0.0)).unwrap();
+            grid.step(1.0 / 60.0);
+        }
+        assert_eq!(grid.tick(), 4);
+        assert!(grid.was_colliding(0, 1, 0));
+
+        for _ in 0..2 {
+            grid.load_state(2).unwrap();
+            assert_eq!(grid.tick(), 1);
+            assert_eq!(grid.get_entity_transform(0).unwrap(), ((0.0, 0.0), 0.0));
+            assert_eq!(grid.get_velocity(0), (0.0, 0.0));
+            assert_eq!(grid.cell_contents(1, 1).unwrap(), vec![(0, 0)]);
+            assert_eq!(grid.cell_contents(3, 1).unwrap(), vec![(1, 0)]);
+            assert!(grid.collisions_at(2).is_err());
+            grid.step(1.0 / 60.0);
+            assert!(grid
+                .get_collisions(false, None, false, Targets::All)
+                .is_empty());
+        }
+
+        assert!(grid.load_state(3).is_err());
+        assert!(grid.save_state(8).is_err());
+        grid.set_state_slots(2);
+        assert!(grid.load_state(2).is_err());
+    }
+
+    #[test]
+    fn check_fork() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_obb(1, Vector2::new(30.0, 15.0), (1.0, 5.0), 0.0, true, 0);
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.save_state(0).unwrap();
+
+        let mut fork = grid.fork();
+        assert!(Arc::ptr_eq(&grid.grid[35], &fork.grid[35]));
+        assert!(fork.load_state(0).is_err());
+
+        // Would the player fit there once the door is gone?
+        let door = fork.static_bodies[&1][0];
+        fork.remove_static_body(&door);
+        fork.move_entity(0, Vector2::new(15.0, 0.0)).unwrap();
+        assert!(fork
+            .get_collisions(false, None, false, Targets::All)
+            .is_empty());
+        assert!(!Arc::ptr_eq(&grid.grid[31], &fork.grid[31]));
+        assert!(Arc::ptr_eq(&grid.grid[55], &fork.grid[55]));
+
+        grid.move_entity(0, Vector2::new(15.0, 0.0)).unwrap();
+        assert_eq!(
+            grid.get_collisions(false, None, false, Targets::All).len(),
+            1
+        );
+        assert_eq!(grid.cell_contents(3, 1).unwrap(), vec![(1, 0), (0, 0)]);
+    }
+
+    #[test]
+    fn check_delta_mirror() {
+        let mut server = GridPhysics::new(100.0, 10.0);
+        server.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        server.add_ellipse(1, Vector2::new(40.0, 40.0), (3.0, 1.0), 0.5, false, 2);
+        server.add_static_chain(2, &[Vector2::new(60.0, 60.0), Vector2::new(70.0, 65.0)], 0);
+        let mut client = GridPhysics::new(100.0, 10.0);
+        let full = delta::encode(&server, 0).unwrap();
+        assert_eq!(client.apply_delta(&full).unwrap(), 0);
+        assert!(diff::diff(&server, &client).is_empty());
+
+        server.step(1.0 / 60.0);
+        server
+            .set_entity_transform(1, Vector2::new(5.0, 0.0), 0.25)
+            .unwrap();
+        server.remove_entity(2);
+        let update = delta::encode(&server, 1).unwrap();
+        assert!(update.len() < full.len());
+        assert_eq!(client.apply_delta(&update).unwrap(), 1);
+        assert!(diff::diff(&server, &client).is_empty());
+        assert_eq!(client.get_entity_transform(1).unwrap(), ((5.0, 0.0), 0.25));
+        assert!(client.cell_contents(6, 6).unwrap().is_empty());
+
+        assert!(client.apply_delta(&update[..update.len() - 1]).is_err());
+        assert!(client.apply_delta(b"nope").is_err());
+        assert!(diff::diff(&server, &client).is_empty());
+
+        // Deferred clients keep seeing the committed state until `commit`
+        let mut deferred = GridPhysics::new(100.0, 10.0);
+        deferred.set_deferred(true).unwrap();
+        deferred.apply_delta(&full).unwrap();
+        assert!(deferred.cell_contents(1, 1).unwrap().is_empty());
+        deferred.commit().unwrap();
+        deferred.apply_delta(&update).unwrap();
+        assert_eq!(deferred.cell_contents(6, 6).unwrap().len(), 1);
+        deferred.commit().unwrap();
+        assert!(diff::diff(&server, &deferred).is_empty());
+
+        // A NaN position in the delta is screened out, and raises in strict mode
+        let mut corrupt = delta::encode(&server, 0).unwrap();
+        corrupt[46..50].copy_from_slice(&f32::NAN.to_le_bytes());
+        client.strict = true;
+        assert!(client.apply_delta(&corrupt).is_err());
+        assert!(diff::diff(&server, &client).is_empty());
+        client.strict = false;
+        client.apply_delta(&corrupt).unwrap();
+        assert!(client.find_body(0, 0).is_err());
+    }
+
+    #[test]
+    fn check_subscriptions() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(25.0, 15.0), 1.0, 0);
+        grid.add_static_circle(2, Vector2::new(80.0, 80.0), 1.0, 0);
+        grid.subscribe(Vector2::new(20.0, 15.0), 5.0, 7);
+
+        assert_eq!(grid.poll_subscription(7).unwrap(), (vec![0, 1], vec![]));
+        assert_eq!(grid.poll_subscription(7).unwrap(), (vec![], vec![]));
+
+        grid.move_entity(1, Vector2::new(30.0, 0.0)).unwrap();
+        assert_eq!(grid.poll_subscription(7).unwrap(), (vec![], vec![1]));
+
+        // Moving the area keeps the members it already reported
+        grid.subscribe(Vector2::new(70.0, 70.0), 20.0, 7);
+        assert_eq!(grid.poll_subscription(7).unwrap(), (vec![2], vec![0]));
+
+        grid.unsubscribe(7).unwrap();
+        assert!(grid.poll_subscription(7).is_err());
+        assert!(grid.unsubscribe(7).is_err());
+    }
+
+    #[test]
+    fn check_extrapolation() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circles(
+            0,
+            vec![Vector2::new(15.0, 15.0), Vector2::new(17.0, 15.0)],
+            1.0,
+            0,
+        );
+        grid.add_static_circle(1, Vector2::new(44.0, 10.0), 1.0, 0);
+        grid.set_velocity(0, Vector2::new(10.0, -2.0)).unwrap();
+
+        assert_eq!(
+            grid.extrapolate(0, 2.5).unwrap(),
+            vec![(40.0, 10.0), (42.0, 10.0)]
+        );
+        assert_eq!(grid.extrapolate(1, 2.5).unwrap(), vec![(44.0, 10.0)]);
+        assert!(grid.extrapolate(5, 1.0).is_err());
+
+        let ahead = grid.extrapolated(2.8).unwrap();
+        assert_eq!(
+            ahead.get_collisions(false, None, false, Targets::All).len(),
+            2
+        );
+        assert!(grid
+            .get_collisions(false, None, false, Targets::All)
+            .is_empty());
+        assert_eq!(grid.get_entity_transform(0).unwrap(), ((0.0, 0.0), 0.0));
+    }
+
+    #[test]
+    fn check_batch_static_circles() {
+        use numpy::ndarray::{array, Array1};
+
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let positions = array![[15.0, 15.0], [55.0, 55.0], [57.0, 55.0]];
+        let radii = array![1.0, 3.0, 0.5];
+        grid.add_circles_batch(4, positions.view(), radii.view(), true, 2)
+            .unwrap();
+
+        let bodies = &grid.static_bodies[&4];
+        assert_eq!(bodies.len(), 3);
+        assert_eq!(bodies[1].radius, 3.0);
+        assert_eq!(bodies[2].body_index(), 2);
+        assert_eq!(grid.cell_contents(5, 5).unwrap(), vec![(4, 1), (4, 2)]);
+        assert_eq!(
+            grid.entities_overlapping_aabb(
+                Vector2::new(14.0, 14.0),
+                Vector2::new(15.0, 15.0),
+                Containment::Intersects
+            ),
+            vec![4]
+        );
+
+        let short: Array1<f32> = array![1.0];
+        assert!(grid
+            .add_circles_batch(5, positions.view(), short.view(), true, 0)
+            .is_err());
+    }
+
+    #[test]
+    fn check_parallel_bulk_insert() {
+        use numpy::ndarray::Array;
+
+        let positions = Array::from_shape_fn((500, 2), |(i, axis)| {
+            ((i * 37 + axis * 11) % 97) as f32 + 1.5
+        });
+        let radii = Array::from_shape_fn(500, |i| 0.5 + (i % 7) as f32);
+        let mut sequential = GridPhysics::new(100.0, 10.0);
+        for (i, row) in positions.rows().into_iter().enumerate() {
+            let pos = Vector2::new(row[0], row[1]);
+            sequential.add_circle(3, pos, radii[i], i, false, 0);
+        }
+        let mut parallel = GridPhysics::new(100.0, 10.0);
+        parallel
+            .add_circles_batch(3, positions.view(), radii.view(), false, 0)
+            .unwrap();
+
+        let keys = |cell: &Vec<Body>| -> Vec<(usize, usize)> {
+            cell.iter()
+                .map(|body| (body.entity_index(), body.body_index()))
+                .collect()
+        };
+        for (a, b) in sequential.grid.iter().zip(&parallel.grid) {
+            assert_eq!(keys(a), keys(b));
+        }
+        assert_eq!(sequential.entity_bounds, parallel.entity_bounds);
+        assert_eq!(sequential.max_radius, parallel.max_radius);
+    }
+
+    #[test]
+    fn check_memory_usage() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let empty = grid.memory_usage();
+        assert_eq!(empty.cell_copies, 0);
+        assert_eq!(empty.total, empty.cells + empty.bodies + empty.caches);
+
+        // Bodies are binned into 2 x 2 and 5 x 5 cells
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(50.0, 50.0), 12.0, 0);
+        let usage = grid.memory_usage();
+        assert_eq!(usage.cell_copies, 4 + 25);
+        assert_eq!(usage.duplicated_copies, 27);
+        assert!(usage.cells >= 29 * std::mem::size_of::<Body>());
+        assert!(usage.bodies > empty.bodies);
+        assert!(usage.caches > empty.caches);
+    }
+
+    #[test]
+    fn check_max_bodies() {
+        let mut grid = GridPhysics::py_new(100.0, 10.0, Some(3)).unwrap();
+        let points = vec![Vector2::new(5.0, 5.0), Vector2::new(15.0, 5.0)];
+        grid.py_add_dynamic_circles(0, points, 1.0, KindArg::default(), None)
+            .unwrap();
+
+        // A chain of two segments would make four bodies, so none of it is added
+        let chain = vec![
+            Vector2::new(0.0, 50.0),
+            Vector2::new(50.0, 50.0),
+            Vector2::new(90.0, 50.0),
+        ];
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let err = grid
+                .py_add_static_chain(1, chain, KindArg::default())
+                .unwrap_err();
+            assert!(err.is_instance_of::<PyMemoryError>(py));
+        });
+        assert!(!grid.static_bodies.contains_key(&1));
+
+        grid.py_add_static_circle(2, Vector2::new(50.0, 50.0), 2.0, KindArg::default())
+            .unwrap();
+        assert!(grid
+            .py_add_static_circle(3, Vector2::new(70.0, 70.0), 2.0, KindArg::default())
+            .is_err());
+
+        // Deltas replacing an entity only count the bodies they add
+        let mut server = GridPhysics::new(100.0, 10.0);
+        server.add_dynamic_circle(0, Vector2::new(30.0, 30.0), 1.0, 0);
+        assert!(grid
+            .apply_delta(&delta::encode(&server, 0).unwrap())
+            .is_ok());
+        let pair = vec![Vector2::new(60.0, 60.0), Vector2::new(70.0, 60.0)];
+        server.add_dynamic_circles(4, pair, 1.0, 0);
+        assert!(grid
+            .apply_delta(&delta::encode(&server, 0).unwrap())
+            .is_err());
+        assert!(!grid.dynamic_bodies.contains_key(&4));
+    }
+
+    #[test]
+    fn check_reserve() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.reserve(300, 200);
+        assert!(grid.dynamic_bodies.capacity() >= 300);
+        assert!(grid.static_bodies.capacity() >= 200);
+        assert!(grid.grid.iter().all(|cell| cell.capacity() >= 5));
+
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        let snapshot = grid.fork();
+        grid.reset();
+        assert!(grid.grid.iter().all(|cell| cell.is_empty()));
+        assert!(grid.grid.iter().all(|cell| cell.capacity() >= 5));
+        assert!(grid.dynamic_bodies.capacity() >= 300);
+        assert_eq!(snapshot.dynamic_bodies[&0].len(), 1);
+    }
+
+    #[test]
+    fn check_entity_handles() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_static_circle(1, Vector2::new(50.0, 50.0), 2.0, 0);
+        let first = grid.create_entity();
+        let second = grid.create_entity();
+        // Index 1 already has bodies
+        assert_eq!((first.index, second.index), (0, 2));
+
+        grid.add_dynamic_circle(first.index, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.set_sensor(first.index, true);
+        grid.destroy_entity(first).unwrap();
+        assert!(!grid.is_alive(first));
+        assert!(grid.is_alive(second));
+        assert!(!grid.dynamic_bodies.contains_key(&0));
+        assert!(!grid.sensors.contains(&0));
+        assert!(grid.destroy_entity(first).is_err());
+
+        let recycled = grid.create_entity();
+        assert_eq!(recycled, EntityHandle::new(0, 1));
+        assert!(grid.destroy_entity(first).is_err());
+        assert!(grid.destroy_entity(recycled).is_ok());
+
+        // An index skipped while a deferred destroy keeps its bodies is handed out after
+        let pending = grid.create_entity();
+        grid.add_dynamic_circle(pending.index, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.set_deferred(true).unwrap();
+        grid.destroy_entity(pending).unwrap();
+        let during = grid.create_entity();
+        assert_eq!(during.index, 3);
+        grid.commit().unwrap();
+        assert_eq!(grid.create_entity(), EntityHandle::new(0, 3));
+
+        grid.reset();
+        assert!(!grid.is_alive(second));
+        assert!(!grid.is_alive(during));
+        assert_eq!(grid.create_entity(), EntityHandle::new(0, 4));
+    }
+
+    #[test]
+    fn check_log_hook() {
+        use pyo3::types::PyDict;
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let locals = PyDict::new(py);
+            py.run("records = []\nhook = records.append", None, Some(locals))
+                .unwrap();
+            let mut grid = GridPhysics::new(100.0, 10.0);
+            grid.max_cells_per_body = 16;
+            grid.set_log_hook(py, Some(locals.get_item("hook").unwrap().into()))
+                .unwrap();
+            grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+            grid.add_static_circle(1, Vector2::new(50.0, 50.0), 20.0, 0);
+            grid.add_dynamic_circle(2, Vector2::new(500.0, 500.0), 1.0, 0);
+            grid.remove_entity(0);
+            grid.step(1.0 / 60.0);
+
+            let records: Vec<&PyDict> = locals.get_item("records").unwrap().extract().unwrap();
+            let events: Vec<String> = records
+                .iter()
+                .map(|record| record.get_item("event").unwrap().extract().unwrap())
+                .collect();
+            assert_eq!(
+                events,
+                [
+                    "insert",
+                    "insert",
+                    "oversized",
+                    "insert",
+                    "out_of_bounds",
+                    "remove"
+                ]
+            );
+            let cells: usize = records[1].get_item("cells").unwrap().extract().unwrap();
+            assert_eq!(cells, 25);
+
+            // Records are delivered once
+            grid.step(1.0 / 60.0);
+            assert_eq!(locals.get_item("records").unwrap().len().unwrap(), 6);
+            assert!(grid.set_log_hook(py, Some(1.into_py(py))).is_err());
+        });
+    }
+
+    #[test]
+    fn check_oversize_policy() {
+        use pyo3::exceptions::PyUserWarning;
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut grid = GridPhysics::new(100.0, 10.0);
+            grid.max_cells_per_body = 16;
+            py.run("import warnings", None, None).unwrap();
+            py.run("warnings.simplefilter('error')", None, None)
+                .unwrap();
+            // Spans 5 x 5 cells
+            let huge =
+                grid.py_add_static_circle(0, Vector2::new(50.0, 50.0), 20.0, KindArg::default());
+            let err = huge.unwrap_err();
+            assert!(err.is_instance_of::<PyUserWarning>(py));
+            assert!(grid.static_bodies.contains_key(&0));
+            py.run("warnings.resetwarnings()", None, None).unwrap();
+
+            grid.oversize_policy = OversizePolicy::Error;
+            let points = vec![Vector2::new(30.0, 30.0), Vector2::new(60.0, 60.0)];
+            let err = grid
+                .py_add_dynamic_circles(1, points, 16.0, KindArg::default(), None)
+                .unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+            assert!(err.to_string().contains("2 bodies"));
+            assert!(!grid.dynamic_bodies.contains_key(&1));
+
+            grid.py_add_dynamic_circle(2, Vector2::new(15.0, 15.0), 1.0, KindArg::default())
+                .unwrap();
+            grid.oversize_policy = OversizePolicy::Ignore;
+            grid.py_add_dynamic_circle(3, Vector2::new(50.0, 50.0), 30.0, KindArg::default())
+                .unwrap();
+            assert!(grid.dynamic_bodies.contains_key(&3));
+        });
+    }
+
+    #[test]
+    fn check_collision_targets() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(16.0, 15.0), 1.0, 0);
+        grid.add_static_circle(2, Vector2::new(15.0, 16.0), 1.0, 0);
+
+        let others = |targets| -> HashSet<(usize, usize)> {
+            grid.get_collisions(false, None, false, targets)
+                .iter()
+                .map(|collision| (collision.self_entity_index, collision.other_entity_index))
+                .collect()
+        };
+        assert_eq!(others(Targets::All).len(), 4);
+        assert_eq!(others(Targets::Static), HashSet::from([(0, 2), (1, 2)]));
+        assert_eq!(others(Targets::Dynamic), HashSet::from([(0, 1), (1, 0)]));
+        assert_eq!(
+            grid.get_collisions(false, Some(1), false, Targets::Static)
+                .len(),
+            1
         );
-        grid.add_dynamic_circles(
-            1,
+    }
+
+    #[test]
+    fn check_sorted_statics() {
+        let mut grid = GridPhysics::new(100.0, 100.0);
+        for (i, x) in [70.0, 10.0, 50.0, 30.0, 90.0].into_iter().enumerate() {
+            grid.add_static_circle(i, Vector2::new(x, 50.0), 4.0, 0);
+        }
+        grid.add_dynamic_circle(10, Vector2::new(32.0, 50.0), 1.0, 0);
+        let statics = vec![
+            Vector2::new(20.0, 20.0),
+            Vector2::new(5.0, 20.0),
+            Vector2::new(60.0, 20.0),
+        ];
+        grid.add_bodies(
+            statics
+                .into_iter()
+                .enumerate()
+                .map(|(i, pos)| Body::new(20, i, pos, 1.0, true, 0))
+                .collect(),
+        )
+        .unwrap();
+
+        let cell = &grid.grid[0];
+        let edges: Vec<f32> = cell
+            .iter()
+            .filter(|body| body.is_static())
+            .map(min_x)
+            .collect();
+        assert!(edges.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(cell.iter().position(|body| !body.is_static()), Some(8));
+
+        let collisions = grid.get_collisions(false, None, false, Targets::All);
+        let others: Vec<usize> = collisions
+            .iter()
+            .map(|collision| collision.other_entity_index)
+            .collect();
+        assert_eq!(others, vec![3]);
+    }
+
+    #[test]
+    fn check_deferred_commit() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.set_deferred(true).unwrap();
+        grid.add_dynamic_circle(1, Vector2::new(55.0, 55.0), 1.0, 0);
+        grid.move_entity(0, Vector2::new(60.0, 0.0)).unwrap();
+        let dirty = grid.dirty_cells();
+        assert!([(1, 1), (5, 5), (7, 1)]
+            .iter()
+            .all(|cell| dirty.contains(cell)));
+        assert!(!dirty.contains(&(3, 3)));
+
+        let area = |grid: &GridPhysics, x: f32, y: f32| {
+            grid.get_collisions_within_area(
+                Vector2::new(x, y),
+                3.0,
+                None,
+                None,
+                false,
+                Containment::Intersects,
+            )
+        };
+        assert!(area(&grid, 55.0, 55.0).is_empty());
+        assert_eq!(area(&grid, 15.0, 15.0), HashSet::from([0]));
+
+        assert!(grid.resize(200.0, 20.0).is_err());
+        assert!(grid.set_wrap(true).is_err());
+        assert!(grid.set_sweep_dt(0.5).is_err());
+        assert_eq!(
+            (grid.grid_size, grid.wrap, grid.sweep_dt),
+            (10, false, 1.0 / 60.0)
+        );
+
+        assert_eq!(grid.commit().unwrap(), 2);
+        assert!(grid.dirty_cells().is_empty());
+        assert_eq!(area(&grid, 55.0, 55.0), HashSet::from([1]));
+        assert!(area(&grid, 15.0, 15.0).is_empty());
+
+        grid.py_remove_entity(1);
+        grid.update_position(1, 0, Vector2::new(5.0, 5.0)).unwrap();
+        assert!(grid.commit().is_err());
+        assert!(area(&grid, 55.0, 55.0).is_empty());
+        grid.py_remove_entity(0);
+        assert_eq!(grid.discard_pending(), 1);
+        grid.set_deferred(false).unwrap();
+        assert!(!grid.deferred);
+        assert_eq!(area(&grid, 75.0, 15.0), HashSet::from([0]));
+
+        // Velocities re-bin the swept cells only once committed, and impulses add up
+        grid.set_deferred(true).unwrap();
+        grid.set_velocity(0, Vector2::new(300.0, 0.0)).unwrap();
+        grid.apply_impulse(0, 0, Vector2::new(60.0, 0.0)).unwrap();
+        assert_eq!(grid.get_velocity(0), (0.0, 0.0));
+        assert!(grid.cell_contents(9, 1).unwrap().is_empty());
+        assert!(grid.dirty_cells().contains(&(9, 1)));
+        assert_eq!(grid.commit().unwrap(), 2);
+        assert_eq!(grid.get_velocity(0), (360.0, 0.0));
+        assert_eq!(grid.cell_contents(9, 1).unwrap(), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn check_deferred_chains() {
+        // Commits only change cells reported dirty, also when mutations of one entity follow
+        // each other in the queue
+        let commit = |grid: &mut GridPhysics| {
+            let dirty = grid.dirty_cells();
+            let before = grid.grid.clone();
+            grid.commit().unwrap();
+            for (index, cell) in before.iter().enumerate() {
+                let key = |body: &Body| (body.entity_index(), body.body_index(), body.pos);
+                if cell.iter().map(key).ne(grid.grid[index].iter().map(key)) {
+                    assert!(dirty.contains(&grid.cell_coords(index)));
+                }
+            }
+            dirty
+        };
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.set_deferred(true).unwrap();
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.update_position(0, 0, Vector2::new(75.0, 75.0))
+            .unwrap();
+        grid.add_dynamic_circle(1, Vector2::new(15.0, 55.0), 1.0, 0);
+        grid.set_radius(1, 0, 8.0).unwrap();
+        grid.add_dynamic_circle(2, Vector2::new(15.0, 85.0), 1.0, 0);
+        grid.set_velocity(2, Vector2::new(3000.0, 0.0)).unwrap();
+        let dirty = commit(&mut grid);
+        assert!([(7, 7), (0, 5), (6, 8)]
+            .iter()
+            .all(|cell| dirty.contains(cell)));
+
+        // Two scales mark the cells of both
+        grid.add_dynamic_circle(3, Vector2::new(55.0, 55.0), 4.0, 0);
+        grid.commit().unwrap();
+        grid.scale_entity(3, 2.0).unwrap();
+        grid.scale_entity(3, 2.0).unwrap();
+        let dirty = commit(&mut grid);
+        assert!(dirty.contains(&(3, 5)) && dirty.contains(&(8, 5)));
+        assert_eq!(grid.find_body(3, 0).unwrap().radius, 16.0);
+    }
+
+    #[test]
+    fn check_cell_coordinates() {
+        let grid = GridPhysics::new(100.0, 10.0);
+        assert_eq!(grid.world_to_cell(Vector2::new(45.0, 15.0)), (4, 1));
+        assert_eq!(grid.world_to_cell(Vector2::new(-1.0, 105.0)), (-1, 10));
+        assert_eq!(grid.cell_to_world(4, 1), (45.0, 15.0));
+        assert_eq!(grid.cell_rect(4, 1), (40.0, 50.0, 10.0, 20.0));
+        assert_eq!(grid.grid_dimensions(), (10, 10, 10.0, 10.0));
+
+        let hex = GridPhysics::hexagonal(100.0, 10.0, None).unwrap();
+        for cell in [(0, 0), (3, 1), (-1, 4)] {
+            let (x, y) = hex.cell_to_world(cell.0, cell.1);
+            assert_eq!(hex.world_to_cell(Vector2::new(x, y)), cell);
+            let (min_x, max_x, min_y, max_y) = hex.cell_rect(cell.0, cell.1);
+            assert!((max_x - min_x - 10.0).abs() < 1e-4);
+            assert!(min_y < y && y < max_y);
+        }
+        let (x, y) = hex.cell_to_world(0, 1);
+        assert!((x - 5.0).abs() < 1e-4 && (y - 8.66).abs() < 1e-2);
+        let (columns, rows, width, height) = hex.grid_dimensions();
+        assert_eq!((columns, rows, width), (hex.grid_size, hex.grid_size, 10.0));
+        assert!((height - 8.66).abs() < 1e-2);
+    }
+
+    #[test]
+    fn check_supercover_ray_cells() {
+        let grid = GridPhysics::new(100.0, 10.0);
+        let origin = Vector2::new(5.0, 5.0);
+        let diagonal = Vector2::new(1.0, 1.0);
+        let plain = grid.ray_cells(origin, diagonal, Some(25.0), false).unwrap();
+        assert_eq!(plain, vec![(0, 0), (0, 1), (1, 1), (1, 2), (2, 2)]);
+        let cover = grid.ray_cells(origin, diagonal, Some(25.0), true).unwrap();
+        assert_eq!(
+            cover,
+            vec![(0, 0), (1, 0), (0, 1), (1, 1), (2, 1), (1, 2), (2, 2)]
+        );
+
+        // Away from corners both modes agree
+        let shallow = Vector2::new(1.0, 0.3);
+        assert_eq!(
+            grid.ray_cells(origin, shallow, Some(40.0), false).unwrap(),
+            grid.ray_cells(origin, shallow, Some(40.0), true).unwrap()
+        );
+        assert!(grid
+            .ray_cells(Vector2::new(95.0, 5.0), Vector2::new(1.0, 0.0), None, true)
+            .unwrap()
+            .eq(&vec![(9, 0)]));
+        assert!(GridPhysics::hexagonal(100.0, 10.0, None)
+            .unwrap()
+            .ray_cells(origin, diagonal, None, true)
+            .is_err());
+    }
+
+    #[test]
+    fn check_nearest_by_kind() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let enemy = grid.register_kind("enemy");
+        let ally = grid.register_kind("ally");
+        for i in 0..5 {
+            grid.add_dynamic_circle(i, Vector2::new(52.0 + i as f32, 50.0), 0.5, ally);
+        }
+        grid.add_dynamic_circle(10, Vector2::new(90.0, 50.0), 0.5, enemy);
+        grid.add_static_circle(11, Vector2::new(50.0, 20.0), 0.5, 0);
+
+        let center = Vector2::new(50.0, 50.0);
+        let nearest = grid
+            .py_nearest(
+                center,
+                1,
+                None,
+                Metric::Euclidean,
+                Some(KindArg::Name("enemy".into())),
+                None,
+            )
+            .unwrap();
+        assert_eq!((nearest[0].0, nearest[0].1), (10, 0));
+        assert!((nearest[0].2 - 39.5).abs() < 1e-4);
+
+        let mask = Some(vec![KindArg::Id(enemy), KindArg::Id(0)]);
+        let nearest = grid
+            .py_nearest(center, 2, None, Metric::Euclidean, None, mask)
+            .unwrap();
+        let entities: Vec<usize> = nearest.iter().map(|hit| hit.0).collect();
+        assert_eq!(entities, vec![11, 10]);
+        assert!(grid
+            .py_nearest(
+                center,
+                1,
+                Some(30.0),
+                Metric::Euclidean,
+                Some(KindArg::Id(enemy)),
+                None
+            )
+            .unwrap()
+            .is_empty());
+        assert!(grid
+            .py_nearest(
+                center,
+                1,
+                None,
+                Metric::Euclidean,
+                Some(KindArg::Name("boss".into())),
+                None
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn check_substepped_integration() {
+        let mut grid = GridPhysics::new(200.0, 10.0);
+        grid.integrate = true;
+        grid.add_dynamic_circle(0, Vector2::new(10.0, 50.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(60.0, 50.0), 2.0, 0);
+        grid.set_velocity(0, Vector2::new(200.0, 0.0)).unwrap();
+
+        // One 100 unit move jumps over the wall
+        let mut unlimited = grid.fork();
+        unlimited.step(0.5);
+        assert_eq!(unlimited.get_entity_transform(0).unwrap().0, (100.0, 0.0));
+        assert!(unlimited.drain_events().is_empty());
+
+        grid.max_substeps = 64;
+        grid.max_displacement = Some(2.0);
+        grid.step(0.5);
+        assert_eq!(grid.get_entity_transform(0).unwrap().0, (100.0, 0.0));
+        let kinds: Vec<EventKind> = grid.drain_events().iter().map(|event| event.kind).collect();
+        assert!(kinds.contains(&EventKind::Enter));
+
+        // Hitting max_substeps clamps the move instead
+        grid.max_substeps = 10;
+        grid.set_entity_max_displacement(0, Some(1.0)).unwrap();
+        grid.step(0.5);
+        assert_eq!(grid.get_entity_transform(0).unwrap().0, (110.0, 0.0));
+        assert!(grid.set_entity_max_displacement(0, Some(0.0)).is_err());
+
+        assert!(grid.set_velocity(0, Vector2::new(f32::NAN, 0.0)).is_err());
+    }
+
+    #[test]
+    fn check_non_finite_input() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let nan = Vector2::new(f32::NAN, 5.0);
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let err = grid
+                .py_add_dynamic_circles(
+                    0,
+                    vec![Vector2::new(5.0, 5.0), nan, nan],
+                    1.0,
+                    KindArg::default(),
+                    None,
+                )
+                .unwrap_err();
+            assert!(err.is_instance_of::<RadyxError>(py));
+            assert!(err.is_instance_of::<PyValueError>(py));
+            assert!(err.to_string().contains("2 bodies"));
+            assert!(err.to_string().contains("body 1 of entity 0"));
+        });
+        assert_eq!(grid.dynamic_bodies[&0].len(), 1);
+        assert!(grid.grid.iter().map(|cell| cell.len()).sum::<usize>() > 0);
+
+        assert!(grid.move_entity(0, nan).is_err());
+        assert!(grid
+            .update_position(0, 0, Vector2::new(1.0, f32::INFINITY))
+            .is_err());
+        assert_eq!(grid.get_entity_transform(0).unwrap().0, (0.0, 0.0));
+
+        // A finite move refused all the same when it carries a body past f32
+        grid.add_dynamic_circle(2, Vector2::new(-3e38, 50.0), 1.0, 0);
+        assert!(grid.move_entity(2, Vector2::new(-3e38, 0.0)).is_err());
+        assert_eq!(grid.find_body(2, 0).unwrap().pos, Vector2::new(-3e38, 50.0));
+        let huge = Vector2::new(3e38, 50.0);
+        assert!(grid
+            .py_add_static_circle(3, huge, 3e38, KindArg::default())
+            .is_err());
+        assert!(!grid.static_bodies.contains_key(&3));
+
+        grid.strict = false;
+        grid.py_add_static_circle(1, nan, 1.0, KindArg::default())
+            .unwrap();
+        assert!(!grid.static_bodies.contains_key(&1));
+        grid.move_entity(0, nan).unwrap();
+        grid.set_velocity(0, nan).unwrap();
+        assert_eq!(grid.get_entity_transform(0).unwrap().0, (0.0, 0.0));
+        assert_eq!(grid.get_velocity(0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn check_advance_accumulator() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.fixed_dt = 0.1;
+        grid.add_dynamic_circle(0, Vector2::new(50.0, 50.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(51.0, 50.0), 1.0, 0);
+
+        assert!(grid.advance(0.05).unwrap().is_empty());
+        assert_eq!(grid.tick, 0);
+        let events = grid.advance(0.17).unwrap();
+        assert_eq!(grid.tick, 2);
+        let ticks: Vec<u64> = events
+            .iter()
+            .filter(|event| event.kind == EventKind::Collision)
+            .map(|event| event.tick)
+            .collect();
+        assert_eq!(ticks, vec![1, 2]);
+        assert!((grid.accumulator - 0.02).abs() < 1e-9);
+
+        // A long stall runs at most max_steps_per_advance ticks
+        grid.max_steps_per_advance = 3;
+        grid.advance(10.0).unwrap();
+        assert_eq!(grid.tick, 5);
+        assert!(grid.accumulator < grid.fixed_dt);
+        // Both arguments raise a plain ValueError, not the strict mode error
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let frame = grid.advance(f64::NAN).unwrap_err();
+            grid.fixed_dt = 0.0;
+            let fixed = grid.advance(0.1).unwrap_err();
+            for err in [frame, fixed] {
+                assert!(err.is_instance_of::<PyValueError>(py));
+                assert!(!err.is_instance_of::<RadyxError>(py));
+            }
+        });
+    }
+
+    #[test]
+    fn check_gravity_and_drag() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.integrate = true;
+        grid.gravity = (0.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(50.0, 10.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(20.0, 10.0), 1.0, 0);
+        grid.add_static_circle(2, Vector2::new(80.0, 10.0), 1.0, 0);
+        grid.set_drag(1, 2.0).unwrap();
+
+        grid.step(0.5);
+        assert_eq!(grid.get_velocity(0), (0.0, 5.0));
+        assert_eq!(grid.get_entity_transform(0).unwrap().0, (0.0, 2.5));
+        let (_, vy) = grid.get_velocity(1);
+        assert!((vy - 5.0 * (-1.0_f32).exp()).abs() < 1e-5);
+        assert_eq!(grid.get_velocity(2), (0.0, 0.0));
+
+        // Binning follows the updated velocity's sweep
+        grid.step(0.5);
+        let swept = grid.swept(0, grid.entity_bounds[&0]);
+        let expected: usize = grid.cell_span(swept).indices().count();
+        let stored = grid
+            .grid
+            .iter()
+            .filter(|cell| cell.iter().any(|body| body.entity_index() == 0))
+            .count();
+        assert_eq!(stored, expected);
+        assert!(grid.set_drag(1, -1.0).is_err());
+    }
+
+    #[test]
+    fn check_impulses_and_forces() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.integrate = true;
+        let circles = vec![Vector2::new(50.0, 50.0), Vector2::new(52.0, 50.0)];
+        grid.add_dynamic_circles(0, circles, 1.0, 0);
+
+        grid.apply_impulse(0, 1, Vector2::new(3.0, 0.0)).unwrap();
+        grid.apply_impulse(0, 0, Vector2::new(1.0, -2.0)).unwrap();
+        assert_eq!(grid.get_velocity(0), (4.0, -2.0));
+        assert!(grid.apply_impulse(0, 5, Vector2::new(1.0, 0.0)).is_err());
+        assert!(grid
+            .apply_impulse(0, 0, Vector2::new(f32::NAN, 0.0))
+            .is_err());
+
+        grid.apply_force(0, 0, Vector2::new(0.0, 4.0)).unwrap();
+        grid.apply_force(0, 1, Vector2::new(0.0, 4.0)).unwrap();
+        grid.step(0.5);
+        assert_eq!(grid.get_velocity(0), (4.0, 2.0));
+        assert_eq!(grid.get_entity_transform(0).unwrap().0, (2.0, 1.0));
+        // Forces only last one step
+        grid.step(0.5);
+        assert_eq!(grid.get_velocity(0), (4.0, 2.0));
+    }
+
+    #[test]
+    fn check_radial_impulse() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(60.0, 50.0), 1.0, 0);
+        grid.set_velocity(0, Vector2::new(0.0, 1.0)).unwrap();
+        let applied = grid
+            .radial_impulse(
+                Vector2::new(50.0, 50.0),
+                18.0,
+                10.0,
+                explosion::Falloff::Linear,
+            )
+            .unwrap();
+        assert_eq!(applied, vec![(0, (5.0, 0.0))]);
+        assert_eq!(grid.get_velocity(0), (5.0, 1.0));
+        assert!(grid
+            .radial_impulse(
+                Vector2::new(50.0, 50.0),
+                f32::NAN,
+                1.0,
+                explosion::Falloff::Linear
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn check_mass_weighted_push_out() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(50.0, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(51.5, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(2, Vector2::new(20.0, 20.0), 1.0, 0);
+        grid.add_static_circle(3, Vector2::new(21.0, 20.0), 1.0, 0);
+        grid.set_mass(0, 9.0).unwrap();
+        assert_eq!(grid.get_mass(1), 1.0);
+        assert!(grid.set_mass(1, 0.0).is_err());
+
+        assert_eq!(grid.resolve_overlaps(1), 2);
+        let (tank, _) = grid.get_entity_transform(0).unwrap();
+        let (crate_, _) = grid.get_entity_transform(1).unwrap();
+        assert!((tank.0 + 0.05).abs() < 1e-4 && tank.1 == 0.0);
+        assert!((crate_.0 - 0.45).abs() < 1e-4);
+        // Statics don't budge, so the crate takes the whole correction
+        assert!((grid.get_entity_transform(2).unwrap().0 .0 + 1.0).abs() < 1e-4);
+        assert_eq!(grid.get_entity_transform(3).unwrap().0, (0.0, 0.0));
+
+        grid.apply_impulse(0, 0, Vector2::new(9.0, 0.0)).unwrap();
+        assert_eq!(grid.get_velocity(0), (1.0, 0.0));
+    }
+
+    #[test]
+    fn check_push_out_friction() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_static_circle(0, Vector2::new(30.0, 40.0), 10.0, 0);
+        grid.add_static_circle(1, Vector2::new(70.0, 40.0), 10.0, 0);
+        grid.add_dynamic_circle(2, Vector2::new(30.0, 50.5), 1.0, 0);
+        grid.add_dynamic_circle(3, Vector2::new(70.0, 50.5), 1.0, 0);
+        for entity_index in [2, 3] {
+            grid.set_velocity(entity_index, Vector2::new(4.0, -2.0))
+                .unwrap();
+        }
+        grid.set_friction(0, 0, 0.5).unwrap();
+        grid.set_friction(2, 0, 0.5).unwrap();
+        assert_eq!(grid.get_friction(3, 0), 0.0);
+        assert!(grid.set_friction(2, 1, 0.5).is_err());
+
+        assert_eq!(grid.resolve_overlaps(1), 2);
+        // Friction takes half the stopped speed off the slide, a frictionless body keeps it
+        assert_eq!(grid.get_velocity(2), (3.0, 0.0));
+        assert_eq!(grid.get_velocity(3), (4.0, 0.0));
+        assert_eq!(grid.get_entity_transform(2).unwrap().0, (0.0, 0.5));
+    }
+
+    #[test]
+    fn check_sleep_and_wake() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.integrate = true;
+        grid.sleep_threshold = Some(0.01);
+        grid.sleep_steps = 3;
+        grid.add_dynamic_circle(0, Vector2::new(50.0, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(51.5, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(2, Vector2::new(20.0, 50.0), 1.0, 0);
+        grid.set_velocity(2, Vector2::new(10.0, 0.0)).unwrap();
+        assert_eq!(
+            grid.get_collisions(false, None, false, Targets::All).len(),
+            2
+        );
+
+        for _ in 0..4 {
+            grid.step(0.1);
+        }
+        assert_eq!(grid.sleeping_entities(), vec![0, 1]);
+        // Neither sleeper is a source, so their contact stops being reported
+        assert!(grid
+            .get_collisions(false, None, false, Targets::All)
+            .is_empty());
+
+        // The mover wakes the pair by running into them
+        let mut steps = 0;
+        while grid.is_sleeping(0) {
+            grid.step(0.1);
+            steps += 1;
+            assert!(steps < 40);
+        }
+        assert!(!grid.is_sleeping(0));
+
+        grid.set_velocity(2, Vector2::new(0.0, 0.0)).unwrap();
+        grid.wake(1);
+        grid.move_entity(1, Vector2::new(0.0, 20.0)).unwrap();
+        assert!(!grid.is_sleeping(1));
+    }
+
+    #[test]
+    fn check_collision_handlers_by_layer() {
+        use pyo3::types::PyDict;
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut grid = GridPhysics::new(100.0, 10.0);
+            let player = grid.register_kind("player");
+            let pickup = grid.register_kind("pickup");
+            let wall = grid.register_kind("wall");
+            grid.add_dynamic_circle(0, Vector2::new(50.0, 50.0), 1.0, player);
+            grid.add_dynamic_circle(1, Vector2::new(51.0, 50.0), 1.0, pickup);
+            grid.add_static_circle(2, Vector2::new(49.0, 50.0), 1.0, wall);
+            let grid = PyCell::new(py, grid).unwrap();
+
+            let locals = PyDict::new(py);
+            locals.set_item("grid", grid).unwrap();
+            py.run(
+                "hits = []\n\
+                 def collect(event):\n    \
+                     hits.append((event.self_entity_index, event.other_entity_index))\n    \
+                     grid.remove_entity(event.self_entity_index)",
+                Some(locals),
+                None,
+            )
+            .unwrap();
+            let collect: PyObject = locals.get_item("collect").unwrap().into();
+            let id = grid
+                .borrow_mut()
+                .on_collision(
+                    py,
+                    collect,
+                    Some(KindArg::Name("pickup".into())),
+                    Some(KindArg::Name("player".into())),
+                )
+                .unwrap();
+            assert!(grid
+                .borrow_mut()
+                .on_collision(py, py.None(), None, None)
+                .is_err());
+
+            // Only the pickup pair reaches Python, from the pickup's side, and the handler
+            // may change the world
+            GridPhysics::py_step(grid, 0.1).unwrap();
+            let hits: Vec<(usize, usize)> = locals.get_item("hits").unwrap().extract().unwrap();
+            assert_eq!(hits, vec![(1, 0)]);
+            assert!(!grid.borrow().dynamic_bodies.contains_key(&1));
+
+            assert!(grid.borrow_mut().remove_collision_handler(id));
+            assert!(!grid.borrow_mut().remove_collision_handler(id));
+        });
+    }
+
+    #[test]
+    fn check_contact_ids() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(50.0, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(51.0, 50.0), 1.0, 0);
+        let ids = |events: &[Event]| -> Vec<Option<u64>> {
+            events.iter().map(|event| event.contact_id).collect()
+        };
+
+        grid.step(1.0);
+        let first = grid.drain_events();
+        let id = first[0].contact_id;
+        assert!(id.is_some());
+        assert_eq!(ids(&first), vec![id; first.len()]);
+        let collisions = grid.get_collisions(false, None, false, Targets::All);
+        assert!(collisions
+            .iter()
+            .all(|collision| collision.contact_id == id));
+
+        grid.step(1.0);
+        assert_eq!(ids(&grid.drain_events()), vec![id; 2]);
+
+        grid.move_entity(1, Vector2::new(10.0, 0.0)).unwrap();
+        grid.step(1.0);
+        let exits = grid.drain_events();
+        assert!(exits.iter().all(|event| event.kind == EventKind::Exit));
+        assert_eq!(ids(&exits), vec![id; 2]);
+
+        // Touching again is a new contact
+        grid.move_entity(1, Vector2::new(0.0, 0.0)).unwrap();
+        grid.step(1.0);
+        let again = grid.drain_events()[0].contact_id;
+        assert!(again.is_some() && again != id);
+    }
+
+    #[test]
+    fn check_wrap_seam() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(99.5, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(0.5, 50.0), 1.0, 0);
+        grid.add_static_circle(2, Vector2::new(30.0, 0.2), 1.0, 0);
+        grid.add_dynamic_circle(3, Vector2::new(30.0, 99.0), 1.0, 0);
+        assert!(grid.all_collisions().is_empty());
+
+        grid.set_wrap(true).unwrap();
+        let keys: HashSet<ContactKey> = grid.all_collisions().iter().map(Collision::key).collect();
+        assert_eq!(
+            keys,
+            HashSet::from([(0, 1, 0, 0), (1, 0, 0, 0), (3, 2, 0, 0)])
+        );
+
+        // Copies past the seam leave with the body
+        grid.move_entity(1, Vector2::new(50.0, 0.0)).unwrap();
+        assert_eq!(grid.all_collisions().len(), 1);
+        assert!(grid
+            .grid
+            .iter()
+            .flat_map(|cell| cell.iter())
+            .all(|body| { body.entity_index() != 1 || body.pos.x == 50.5 }));
+
+        grid.set_wrap(false).unwrap();
+        assert!(grid.all_collisions().is_empty());
+        let mut hex = GridPhysics::hexagonal(100.0, 10.0, None).unwrap();
+        assert!(hex.set_wrap(true).is_err());
+    }
+
+    #[test]
+    fn check_set_radius() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(15.0, 15.0), 1.0, 0);
+        grid.add_static_circle(1, Vector2::new(25.0, 15.0), 1.0, 0);
+        let cells_of = |grid: &GridPhysics| {
+            (0..grid.grid.len())
+                .filter(|index| {
+                    grid.grid[*index]
+                        .iter()
+                        .any(|body| body.entity_index() == 0)
+                })
+                .count()
+        };
+        assert!(grid.all_collisions().is_empty());
+        assert_eq!(cells_of(&grid), 4);
+
+        // Growing within the same cells keeps the copies in place
+        grid.set_radius(0, 0, 4.0).unwrap();
+        assert_eq!(cells_of(&grid), 4);
+        assert!(grid.all_collisions().is_empty());
+
+        grid.set_radius(0, 0, 9.5).unwrap();
+        assert_eq!(cells_of(&grid), 16);
+        assert_eq!(grid.all_collisions().len(), 1);
+        assert_eq!(grid.entity_bounds[&0], (5.5, 24.5, 5.5, 24.5));
+
+        grid.set_radius(0, 0, 1.0).unwrap();
+        assert_eq!(cells_of(&grid), 4);
+        assert!(grid.all_collisions().is_empty());
+        assert!(grid.set_radius(0, 1, 2.0).is_err());
+        assert!(grid.set_radius(0, 0, f32::NAN).is_err());
+
+        let mut boxes = GridPhysics::new(100.0, 10.0);
+        boxes.add_obb(0, Vector2::new(50.0, 50.0), (3.0, 4.0), 0.0, false, 0);
+        boxes.set_radius(0, 0, 10.0).unwrap();
+        let body = boxes.find_body(0, 0).unwrap();
+        assert_eq!(
+            body.shape(),
+            Shape::Box {
+                half_x: 6.0,
+                half_y: 8.0
+            }
+        );
+    }
+
+    #[test]
+    fn check_scale_entity() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let offsets = vec![Vector2::new(-2.0, 0.0), Vector2::new(2.0, 0.0)];
+        grid.add_anchored_circles(0, offsets, 1.0, Vector2::new(50.0, 50.0), false, 0);
+        grid.add_dynamic_circle(1, Vector2::new(57.0, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(2, Vector2::new(20.0, 20.0), 1.0, 0);
+        assert!(grid.all_collisions().is_empty());
+
+        grid.scale_entity(0, 2.0).unwrap();
+        let positions: Vec<(f32, f32)> = grid
+            .bodies_of(0)
+            .map(|body| (body.pos.x, body.radius))
+            .collect();
+        assert_eq!(positions, vec![(46.0, 2.0), (54.0, 2.0)]);
+        assert_eq!(grid.entity_bounds[&0], (44.0, 56.0, 48.0, 52.0));
+        let keys: HashSet<ContactKey> = grid.all_collisions().iter().map(Collision::key).collect();
+        assert!(keys.contains(&(0, 1, 1, 0)));
+
+        // Without an anchor only the radii grow
+        grid.scale_entity(2, 3.0).unwrap();
+        let body = grid.find_body(2, 0).unwrap();
+        assert_eq!((body.pos.x, body.radius), (20.0, 3.0));
+        assert!(grid.scale_entity(2, 0.0).is_err());
+        assert!(grid.scale_entity(9, 2.0).is_err());
+    }
+
+    #[test]
+    fn check_pair_cooldown() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(50.0, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(51.0, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(2, Vector2::new(49.0, 50.0), 1.0, 0);
+        grid.set_pair_cooldown(1, 0, 2);
+
+        let touching = |grid: &GridPhysics| {
+            let mut pairs: Vec<(usize, usize)> = grid
+                .all_collisions()
+                .iter()
+                .map(|collision| {
+                    cooldown::entity_pair(collision.self_entity_index, collision.other_entity_index)
+                })
+                .collect();
+            pairs.sort_unstable();
+            pairs.dedup();
+            pairs
+        };
+        grid.step(1.0);
+        assert_eq!(touching(&grid), vec![(0, 1), (0, 2), (1, 2)]);
+        grid.step(1.0);
+        assert_eq!(touching(&grid), vec![(0, 2), (1, 2)]);
+        grid.step(1.0);
+        assert_eq!(touching(&grid), vec![(0, 2), (1, 2)]);
+        grid.events.clear();
+        grid.step(1.0);
+        assert_eq!(touching(&grid), vec![(0, 1), (0, 2), (1, 2)]);
+        assert!(grid
+            .events
+            .iter()
+            .any(|event| event.kind == EventKind::Enter && event.self_entity_index == 0));
+
+        grid.set_pair_cooldown(0, 1, 0);
+        grid.step(1.0);
+        assert_eq!(touching(&grid).len(), 3);
+
+        // A cooldown too long to end saturates instead of wrapping to none
+        grid.set_pair_cooldown(1, 2, u64::MAX);
+        grid.step(1.0);
+        grid.step(1.0);
+        assert_eq!(touching(&grid), vec![(0, 1), (0, 2)]);
+    }
+
+    #[test]
+    fn check_collision_cap() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(50.0, 50.0), 5.0, 0);
+        for (entity_index, x) in [(1, 53.5), (2, 51.0), (3, 47.0), (4, 56.0)] {
+            grid.add_dynamic_circle(entity_index, Vector2::new(x, 50.0), 1.0, 0);
+        }
+        grid.max_collisions_per_entity = Some(2);
+        let mut others: Vec<usize> = grid
+            .all_collisions()
+            .iter()
+            .filter(|collision| collision.self_entity_index == 0)
+            .map(|collision| collision.other_entity_index)
+            .collect();
+        others.sort_unstable();
+        assert_eq!(others, vec![2, 3]);
+        // Each bullet still reports the boss
+        assert_eq!(grid.all_collisions().len(), 6);
+
+        let mut buffer = Array2::zeros((10, 4));
+        assert_eq!(grid.write_collisions(buffer.view_mut()).unwrap(), 6);
+        grid.max_collisions_per_entity = None;
+        assert_eq!(grid.all_collisions().len(), 8);
+    }
+
+    #[test]
+    fn check_query_circle_bodies() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let limbs = vec![
+            Vector2::new(-3.0, 0.0),
+            Vector2::new(0.0, 0.0),
+            Vector2::new(3.0, 0.0),
+        ];
+        grid.add_anchored_circles(0, limbs, 1.0, Vector2::new(50.0, 50.0), false, 0);
+        grid.add_static_circle(1, Vector2::new(58.0, 50.0), 1.0, 0);
+
+        let hits = grid.query_circle_bodies(Vector2::new(56.0, 50.0), 2.5);
+        assert_eq!(hits, vec![(0, 2, 2.0), (1, 0, 1.0)]);
+        assert!(grid
+            .query_circle_bodies(Vector2::new(20.0, 20.0), 5.0)
+            .is_empty());
+    }
+
+    #[test]
+    fn check_query_polygon() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(50.0, 50.0), 1.0, 0);
+        // Near the hypotenuse but outside it
+        grid.add_dynamic_circle(1, Vector2::new(62.0, 62.0), 1.0, 0);
+        grid.add_dynamic_circle(2, Vector2::new(61.0, 40.0), 1.5, 0);
+        grid.add_obb(3, Vector2::new(45.0, 75.0), (1.0, 2.0), 0.0, true, 0);
+
+        let triangle = vec![
+            Vector2::new(40.0, 40.0),
+            Vector2::new(80.0, 40.0),
+            Vector2::new(40.0, 80.0),
+        ];
+        let found = grid
+            .query_polygon(triangle.clone(), Containment::Intersects)
+            .unwrap();
+        assert_eq!(found, HashSet::from([0, 2, 3]));
+        let reversed: Vec<Vector2> = triangle.into_iter().rev().collect();
+        assert_eq!(
+            grid.query_polygon(reversed, Containment::Intersects)
+                .unwrap(),
+            found
+        );
+
+        let bowtie = vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(10.0, 10.0),
+            Vector2::new(10.0, 0.0),
+            Vector2::new(0.0, 10.0),
+        ];
+        assert!(grid.query_polygon(bowtie, Containment::Intersects).is_err());
+        assert!(grid
+            .query_polygon(vec![Vector2::new(0.0, 0.0)], Containment::Intersects)
+            .is_err());
+    }
+
+    #[test]
+    fn check_containment() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(0, Vector2::new(50.0, 50.0), 1.0, 0);
+        // One limb pokes out of every area
+        let limbs = vec![Vector2::new(0.0, 0.0), Vector2::new(4.5, 0.0)];
+        grid.add_anchored_circles(1, limbs, 1.0, Vector2::new(52.0, 50.0), false, 0);
+        grid.add_obb(2, Vector2::new(47.0, 50.0), (1.0, 1.0), 0.3, true, 0);
+
+        let center = Vector2::new(50.0, 50.0);
+        let contained =
+            grid.get_collisions_within_area(center, 5.0, None, None, false, Containment::Contains);
+        assert_eq!(contained, HashSet::from([0, 2]));
+        let touching = grid.get_collisions_within_area(
+            center,
+            5.0,
+            None,
+            None,
+            false,
+            Containment::Intersects,
+        );
+        assert_eq!(touching, HashSet::from([0, 1, 2]));
+
+        let (min, max) = (Vector2::new(45.0, 45.0), Vector2::new(55.0, 55.0));
+        assert_eq!(
+            grid.entities_overlapping_aabb(min, max, Containment::Contains),
+            vec![0, 2]
+        );
+        let square = vec![
+            Vector2::new(45.0, 45.0),
+            Vector2::new(55.0, 45.0),
+            Vector2::new(55.0, 55.0),
+            Vector2::new(45.0, 55.0),
+        ];
+        assert_eq!(
+            grid.query_polygon(square, Containment::Contains).unwrap(),
+            HashSet::from([0, 2])
+        );
+
+        let spec = query::QuerySpec {
+            area: Some(query::Area::Rect { min, max }),
+            containment: Containment::Contains,
+            ..Default::default()
+        };
+        let bodies: Vec<(usize, usize)> = grid
+            .run_query(&spec)
+            .unwrap()
+            .into_iter()
+            .map(|hit| (hit.0, hit.1))
+            .collect();
+        assert_eq!(bodies, vec![(0, 0), (1, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn check_visible_entities() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(3, Vector2::new(20.0, 20.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(25.0, 22.0), 1.0, 0);
+        grid.add_dynamic_circle(1, Vector2::new(27.0, 22.0), 1.0, 0);
+        grid.add_static_circle(2, Vector2::new(31.5, 20.0), 1.0, 0);
+        grid.add_dynamic_circle(4, Vector2::new(80.0, 80.0), 1.0, 0);
+
+        let bounds = |margin: f32| (15.0 - margin, 30.0 + margin, 15.0 - margin, 25.0 + margin);
+        assert_eq!(grid.visible(bounds(0.0)), vec![1, 3]);
+        assert_eq!(grid.visible(bounds(1.0)), vec![1, 2, 3]);
+        assert!(grid.visible((50.0, 60.0, 50.0, 60.0)).is_empty());
+    }
+
+    #[test]
+    fn check_collision_result_types() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        grid.add_dynamic_circle(1, Vector2::new(50.0, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(0, Vector2::new(51.0, 50.0), 1.0, 0);
+        grid.add_static_circle(2, Vector2::new(52.5, 50.0), 1.0, 0);
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let get = |result_type, with_overlap| {
+                grid.py_get_collisions(py, with_overlap, None, false, Targets::All, result_type)
+            };
+            let set = get(ResultType::Set, false).unwrap();
+            assert_eq!(set.as_ref(py).len().unwrap(), 3);
+
+            let list = get(ResultType::List, true).unwrap();
+            let list: Vec<PyRef<Collision>> = list.extract(py).unwrap();
+            let keys: Vec<ContactKey> = list.iter().map(|collision| collision.key()).collect();
+            assert_eq!(keys, vec![(0, 1, 0, 0), (0, 2, 0, 0), (1, 0, 0, 0)]);
+
+            assert!(get(ResultType::Numpy, true).is_err());
+        });
+        let mut sorted: Vec<Collision> = grid.all_collisions().into_iter().collect();
+        sorted.sort_unstable_by_key(Collision::key);
+        assert_eq!(
+            GridPhysics::collision_rows(&sorted),
+            Array2::from_shape_vec((3, 4), vec![0, 1, 0, 0, 0, 2, 0, 0, 1, 0, 0, 0]).unwrap()
+        );
+    }
+
+    #[test]
+    fn check_index_overflow() {
+        assert_eq!(std::mem::size_of::<Body>(), 40);
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut grid = GridPhysics::new(100.0, 10.0);
+            let pos = Vector2::new(5.0, 5.0);
+            let too_big = u32::MAX as usize + 1;
+            let err = grid
+                .py_add_circle(too_big, pos, 1.0, 0, false, KindArg::default())
+                .unwrap_err();
+            assert!(err.is_instance_of::<pyo3::exceptions::PyOverflowError>(py));
+            assert!(grid
+                .py_add_circle(0, pos, 1.0, too_big, false, KindArg::default())
+                .is_err());
+            assert!(grid.dynamic_bodies.is_empty());
+
+            // Entities past the plain indices need a namespace
+            let last = u32::MAX as usize;
+            assert!(grid
+                .py_add_circle(last, pos, 1.0, 0, false, KindArg::default())
+                .is_err());
+            let entity = (1 << namespaces::ENTITY_BITS) - 1;
+            grid.py_add_circle(entity, pos, 1.0, last, false, KindArg::default())
+                .unwrap();
+            let body = grid.find_body(entity, last).unwrap();
+            assert_eq!((body.entity_index(), body.body_index()), (entity, last));
+        });
+    }
+
+    #[test]
+    fn check_edge_aligned_contacts() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let tile = |entity_index, x| {
+            Body::new(entity_index, 0, Vector2::new(x, 25.0), 0.0, false, 0)
+                .oriented_box(5.0, 5.0, 0.0)
+        };
+        // A wall on the edge between columns 1 and 2, with a tile either side of it
+        grid.add_static_chain(0, &[Vector2::new(20.0, 10.0), Vector2::new(20.0, 40.0)], 0);
+        grid.add_body(tile(1, 15.0));
+        grid.add_body(tile(2, 25.0));
+        // A ledge ending on the edge of column 1, and a circle reaching it from column 1
+        grid.add_static_chain(3, &[Vector2::new(2.0, 55.0), Vector2::new(10.0, 55.0)], 0);
+        grid.add_dynamic_circle(4, Vector2::new(12.0, 55.0), 2.0, 0);
+
+        let mut pairs: Vec<(usize, usize)> = grid
+            .all_collisions()
+            .iter()
+            .map(|collision| (collision.self_entity_index, collision.other_entity_index))
+            .collect();
+        pairs.sort_unstable();
+        assert_eq!(pairs, vec![(1, 0), (1, 2), (2, 0), (2, 1), (4, 3)]);
+    }
+
+    #[test]
+    fn check_huge_query_radius() {
+        let mut wrapping = GridPhysics::new(100.0, 10.0);
+        wrapping.set_wrap(true).unwrap();
+        let grids = [
+            GridPhysics::new(100.0, 10.0),
+            wrapping,
+            GridPhysics::hexagonal(100.0, 10.0, None).unwrap(),
+        ];
+        for mut grid in grids {
+            // Scanning and walking a span must cover the same cells
+            for bounds in [
+                (12.0, 38.0, 55.0, 71.0),
+                (-30.0, 45.0, 85.0, 140.0),
+                (-1e9, 1e9, -1e9, 1e9),
+            ] {
+                let span = grid.cell_span(bounds);
+                let mut walked: Vec<usize> = span.indices().collect();
+                walked.sort_unstable();
+                let scanned: Vec<usize> = (0..grid.grid.len())
+                    .filter(|index| span.covers(*index))
+                    .collect();
+                assert_eq!(walked, scanned);
+                assert_eq!(span.count(), walked.len());
+            }
+
+            for (entity_index, x) in [5.0, 50.0, 95.0].into_iter().enumerate() {
+                grid.add_dynamic_circle(entity_index, Vector2::new(x, x), 1.0, 0);
+            }
+            let found = grid.query_circle_sorted(Vector2::new(50.0, 50.0), 1e9, None);
+            let entities: Vec<usize> = found.iter().map(|hit| hit.0).collect();
+            assert_eq!(entities, vec![1, 0, 2]);
+        }
+    }
+
+    #[test]
+    fn check_namespaces() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let projectile = grid.namespaced("projectiles", 0).unwrap();
+        let creature = grid.namespaced("creatures", 0).unwrap();
+        assert_eq!(grid.register_namespace("creatures").unwrap(), 2);
+        assert_eq!(grid.namespace_name(1), Some("projectiles"));
+        assert!(grid.namespaced("creatures", 1 << 24).is_err());
+        // Plain indices can't reach into a namespace that isn't registered
+        let alias = 3 << 24;
+        assert!(grid
+            .py_add_dynamic_circle(alias, Vector2::new(5.0, 5.0), 1.0, KindArg::default())
+            .is_err());
+        assert!(!grid.dynamic_bodies.contains_key(&alias));
+        assert_eq!(grid.register_namespace("bullets").unwrap(), 3);
+        assert_eq!(grid.namespaced("bullets", 0).unwrap(), alias);
+        grid.add_dynamic_circle(projectile, Vector2::new(50.0, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(creature, Vector2::new(51.0, 50.0), 1.0, 0);
+        grid.add_dynamic_circle(0, Vector2::new(52.5, 50.0), 1.0, 0);
+
+        let mut pairs: Vec<((usize, usize), (usize, usize))> = grid
+            .all_collisions()
+            .iter()
+            .map(|collision| (collision.self_entity(), collision.other_entity()))
+            .collect();
+        pairs.sort_unstable();
+        assert_eq!(
+            pairs,
             vec![
-                Vector2::new(5.0, 8.5),
-                Vector2::new(5.0, 9.0),
-                Vector2::new(5.0, 9.5),
-                Vector2::new(5.0, 10.0),
-            ],
-            1.0,
+                ((0, 0), (2, 0)),
+                ((1, 0), (2, 0)),
+                ((2, 0), (0, 0)),
+                ((2, 0), (1, 0))
+            ]
         );
 
-        let collisions = grid.get_collisions();
-        assert_eq!(collisions.len(), 2);
-        for collision in collisions.iter() {
-            println!(
-                "Self: {}, Other: {}, Self Body: {}, Other Body: {}",
-                collision.self_entity_index,
-                collision.other_entity_index,
-                collision.self_body_index,
-                collision.other_body_index
-            );
+        // Compaction renumbers within each namespace
+        grid.remove_entity(0);
+        let later = grid.namespaced("creatures", 7).unwrap();
+        grid.add_dynamic_circle(later, Vector2::new(20.0, 20.0), 1.0, 0);
+        let map = grid.compact_entities();
+        assert_eq!(map[&later], grid.namespaced("creatures", 1).unwrap());
+        assert_eq!(map[&projectile], projectile);
+
+        let restored = GridPhysics::from_json(&grid.to_json(false).unwrap()).unwrap();
+        assert_eq!(restored.namespace_name(2), Some("creatures"));
+    }
+
+    #[test]
+    fn check_pair_dedup() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        // Bodies spanning a few cells each, overlapping their neighbours in all of them
+        for entity_index in 0..6 {
+            let pos = Vector2::new(20.0 + entity_index as f32 * 12.0, 50.0);
+            grid.add_dynamic_circle(entity_index, pos, 14.0, 0);
         }
-        assert!(collisions.contains(&Collision::new(1, 0, 0, 3)));
-        assert!(collisions.contains(&Collision::new(0, 1, 3, 0)));
+        grid.add_static_chain(6, &[Vector2::new(0.0, 45.0), Vector2::new(100.0, 55.0)], 0);
+        let keys = |grid: &GridPhysics| {
+            let mut keys: Vec<ContactKey> =
+                grid.all_collisions().iter().map(Collision::key).collect();
+            keys.sort_unstable();
+            keys
+        };
+        let visited = keys(&grid);
+        grid.pair_dedup = PairDedup::After;
+        assert_eq!(keys(&grid), visited);
+        assert_eq!(visited.len(), 6 + 2 * 5 + 2 * 4);
     }
 }
@@ -0,0 +1,206 @@
+//! Field of view by recursive shadowcasting over a square raster of the static bodies around
+//! a light, for roguelike visibility and light masks.
+
+use crate::{GridPhysics, Metric, Vector2, MAX_CELLS};
+use numpy::ndarray::Array2;
+use pyo3::{exceptions::PyValueError, PyResult};
+
+/// Octant transforms from `(dx, dy)` in the scanned octant to raster offsets.
+const OCTANTS: [(isize, isize, isize, isize); 8] = [
+    (1, 0, 0, 1),
+    (0, 1, 1, 0),
+    (0, -1, 1, 0),
+    (-1, 0, 0, 1),
+    (-1, 0, 0, -1),
+    (0, -1, -1, 0),
+    (0, 1, -1, 0),
+    (1, 0, 0, -1),
+];
+
+struct Caster {
+    opaque: Array2<bool>,
+    visible: Array2<bool>,
+    /// Raster index of the tile holding the light.
+    center: isize,
+    radius: isize,
+}
+
+impl Caster {
+    fn tile(&self, x: isize, y: isize) -> Option<(usize, usize)> {
+        let side = self.visible.nrows() as isize;
+        ((0..side).contains(&x) && (0..side).contains(&y)).then_some((x as usize, y as usize))
+    }
+
+    /// Scans rows of one octant from `row` outwards between the `start` and `end` slopes,
+    /// recursing past each run of opaque tiles.
+    fn cast(&mut self, row: isize, mut start: f32, end: f32, octant: (isize, isize, isize, isize)) {
+        if start < end {
+            return;
+        }
+        let (xx, xy, yx, yy) = octant;
+        let mut next_start = start;
+        for distance in row..=self.radius {
+            let dy = -distance;
+            let mut blocked = false;
+            for dx in -distance..=0 {
+                let left = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+                let right = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+                if start < right {
+                    continue;
+                }
+                if end > left {
+                    break;
+                }
+                let x = self.center + dx * xx + dy * xy;
+                let y = self.center + dx * yx + dy * yy;
+                let Some(tile) = self.tile(x, y) else {
+                    continue;
+                };
+                if dx * dx + dy * dy <= self.radius * self.radius {
+                    self.visible[tile] = true;
+                }
+                let opaque = self.opaque[tile];
+                if blocked {
+                    if opaque {
+                        next_start = right;
+                    } else {
+                        blocked = false;
+                        start = next_start;
+                    }
+                } else if opaque && distance < self.radius {
+                    blocked = true;
+                    self.cast(distance + 1, start, left, octant);
+                    next_start = right;
+                }
+            }
+            if blocked {
+                break;
+            }
+        }
+    }
+}
+
+impl GridPhysics {
+    /// `(2n + 1, 2n + 1)` tiles of side `resolution` centered on `origin`, with `n` the
+    /// radius in tiles, marking those visible from the tile holding `origin`. A tile is opaque
+    /// when a static body comes within half a tile of its center; opaque tiles in view are
+    /// visible themselves.
+    pub(crate) fn shadowcast(
+        &self,
+        origin: Vector2,
+        radius: f32,
+        resolution: f32,
+    ) -> PyResult<Array2<bool>> {
+        if !resolution.is_finite() || resolution <= 0.0 || !radius.is_finite() || radius < 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "light_mask needs a finite non-negative radius and positive resolution, got \
+                 {radius} and {resolution}"
+            )));
+        }
+        if !origin.x.is_finite() || !origin.y.is_finite() {
+            return Err(PyValueError::new_err(format!(
+                "light_mask needs a finite origin, got ({}, {})",
+                origin.x, origin.y
+            )));
+        }
+        // Saturating, so a tiny resolution fails the size check below instead of wrapping
+        let tiles = (radius / resolution).ceil() as isize;
+        let side = tiles
+            .checked_mul(2)
+            .and_then(|double| double.checked_add(1))
+            .map(|side| side as usize)
+            .filter(|side| {
+                side.checked_mul(*side)
+                    .is_some_and(|area| area <= MAX_CELLS)
+            })
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "a light mask {radius} in radius needs more than {MAX_CELLS} tiles \
+                     {resolution} wide"
+                ))
+            })?;
+
+        let mut opaque = Array2::from_elem((side, side), false);
+        let reach = (tiles as f32 + 0.5) * resolution;
+        let bounds = (
+            origin.x - reach,
+            origin.x + reach,
+            origin.y - reach,
+            origin.y + reach,
+        );
+        // Saturating, as a huge body can reach more tiles past the mask than an isize counts
+        let tile_of = |value: f32, from: f32| {
+            (((value - from) / resolution).round() as isize).saturating_add(tiles)
+        };
+        let tile_center = |tile: isize, from: f32| from + (tile - tiles) as f32 * resolution;
+        let last = side as isize - 1;
+        for body in self
+            .cells_within(bounds)
+            .flatten()
+            .filter(|body| body.is_static())
+        {
+            let (min_x, max_x, min_y, max_y) = body.get_bounds();
+            let columns = tile_of(min_x, origin.x).max(0)..=tile_of(max_x, origin.x).min(last);
+            for x in columns {
+                let rows = tile_of(min_y, origin.y).max(0)..=tile_of(max_y, origin.y).min(last);
+                for y in rows {
+                    let center = Vector2::new(tile_center(x, origin.x), tile_center(y, origin.y));
+                    if body.metric_distance(center, Metric::Euclidean) <= resolution / 2.0 {
+                        opaque[(x as usize, y as usize)] = true;
+                    }
+                }
+            }
+        }
+
+        let mut caster = Caster {
+            opaque,
+            visible: Array2::from_elem((side, side), false),
+            center: tiles,
+            radius: tiles,
+        };
+        caster.visible[(tiles as usize, tiles as usize)] = true;
+        for octant in OCTANTS {
+            caster.cast(1, 1.0, 0.0, octant);
+        }
+        Ok(caster.visible)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_light_mask() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        // A wall three tiles to the right of the light, spanning five tiles vertically
+        for (i, y) in [40.0, 45.0, 50.0, 55.0, 60.0].into_iter().enumerate() {
+            grid.add_static_circle(i, Vector2::new(65.0, y), 1.0, 0);
+        }
+        let mask = grid
+            .shadowcast(Vector2::new(50.0, 50.0), 30.0, 5.0)
+            .unwrap();
+        assert_eq!(mask.dim(), (13, 13));
+        let at = |dx: isize, dy: isize| mask[((6 + dx) as usize, (6 + dy) as usize)];
+
+        assert!(at(0, 0));
+        assert!(at(2, 0) && at(3, 0), "the wall itself is lit");
+        assert!(!at(4, 0) && !at(6, 0), "tiles behind the wall are dark");
+        assert!(at(2, 5), "light passes beside the wall");
+        assert!(at(-6, 0) && at(0, 6) && at(0, -6));
+        assert!(!at(6, 6), "corners lie outside the radius");
+
+        let open = GridPhysics::new(100.0, 10.0)
+            .shadowcast(Vector2::new(50.0, 50.0), 10.0, 5.0)
+            .unwrap();
+        assert_eq!(open.iter().filter(|lit| **lit).count(), 13);
+        assert!(grid.shadowcast(Vector2::new(0.0, 0.0), 5.0, 0.0).is_err());
+        assert!(grid
+            .shadowcast(Vector2::new(0.0, 0.0), f32::INFINITY, 5.0)
+            .is_err());
+        assert!(grid.shadowcast(Vector2::new(0.0, 0.0), 1e5, 1e-3).is_err());
+        assert!(grid
+            .shadowcast(Vector2::new(f32::NAN, 0.0), 5.0, 5.0)
+            .is_err());
+    }
+}
@@ -0,0 +1,263 @@
+//! Structured records of inserts, removals and anomalies, delivered to a Python hook once per
+//! step.
+
+use crate::{Body, GridPhysics, Placed};
+use pyo3::{
+    exceptions::{PyTypeError, PyUserWarning, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
+
+/// Level of `logging` records for inserts and removals.
+const DEBUG: u8 = 10;
+/// Level of `logging` records for anomalies.
+const WARNING: u8 = 30;
+
+/// What adding a body binned into more than `max_cells_per_body` cells does, passed from
+/// Python by name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum OversizePolicy {
+    Ignore,
+    /// Adds the body and emits a `UserWarning`.
+    #[default]
+    Warn,
+    /// Skips the body and raises `ValueError`.
+    Error,
+}
+
+impl<'source> FromPyObject<'source> for OversizePolicy {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract::<&str>()? {
+            "ignore" => Ok(OversizePolicy::Ignore),
+            "warn" => Ok(OversizePolicy::Warn),
+            "error" => Ok(OversizePolicy::Error),
+            name => Err(PyValueError::new_err(format!(
+                "unknown policy {name:?}, expected \"ignore\", \"warn\" or \"error\""
+            ))),
+        }
+    }
+}
+
+impl IntoPy<PyObject> for OversizePolicy {
+    fn into_py(self, py: Python) -> PyObject {
+        match self {
+            OversizePolicy::Ignore => "ignore",
+            OversizePolicy::Warn => "warn",
+            OversizePolicy::Error => "error",
+        }
+        .into_py(py)
+    }
+}
+
+/// Oversized bodies met since the last report, with the first of them.
+#[derive(Clone, Copy, Debug)]
+pub struct Oversized {
+    count: usize,
+    entity_index: usize,
+    body_index: usize,
+    cells: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LogRecord {
+    pub event: &'static str,
+    pub tick: u64,
+    pub entity_index: usize,
+    pub body_index: Option<usize>,
+    /// Cells an inserted body was binned into.
+    pub cells: Option<usize>,
+}
+
+impl LogRecord {
+    fn level(&self) -> u8 {
+        match self.event {
+            "out_of_bounds" | "oversized" => WARNING,
+            _ => DEBUG,
+        }
+    }
+
+    fn to_dict<'py>(self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("event", self.event)?;
+        dict.set_item("tick", self.tick)?;
+        dict.set_item("entity_index", self.entity_index)?;
+        dict.set_item("body_index", self.body_index)?;
+        dict.set_item("cells", self.cells)?;
+        Ok(dict)
+    }
+}
+
+#[derive(Clone)]
+enum Hook {
+    /// Called with each record as a dict.
+    Callable(PyObject),
+    /// A `logging.Logger`, given each record as `extra={"radyx": record}`.
+    Logger(PyObject),
+}
+
+#[derive(Clone, Default)]
+pub struct Logging {
+    hook: Option<Hook>,
+    records: Vec<LogRecord>,
+}
+
+impl Logging {
+    pub fn set_hook(&mut self, py: Python, hook: Option<PyObject>) -> PyResult<()> {
+        self.records.clear();
+        self.hook = match hook {
+            None => None,
+            Some(hook) if hook.as_ref(py).hasattr("isEnabledFor")? => Some(Hook::Logger(hook)),
+            Some(hook) if hook.as_ref(py).is_callable() => Some(Hook::Callable(hook)),
+            Some(_) => {
+                return Err(PyTypeError::new_err(
+                    "expected a callable or a logging.Logger",
+                ))
+            }
+        };
+        Ok(())
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.hook.is_some()
+    }
+
+    pub fn push(&mut self, record: LogRecord) {
+        if self.enabled() {
+            self.records.push(record);
+        }
+    }
+
+    /// Hands the queued records to the hook. Exceptions raised by the hook are reported as
+    /// unraisable, like errors inside `logging` handlers, and don't stop the simulation.
+    pub fn flush(&mut self) {
+        let Some(hook) = &self.hook else {
+            return;
+        };
+        if self.records.is_empty() {
+            return;
+        }
+        let records = std::mem::take(&mut self.records);
+        Python::with_gil(|py| {
+            for record in records {
+                let delivered = record.to_dict(py).and_then(|dict| match hook {
+                    Hook::Callable(hook) => hook.call1(py, (dict,)).map(drop),
+                    Hook::Logger(logger) => {
+                        let extra = PyDict::new(py);
+                        extra.set_item("radyx", dict)?;
+                        let kwargs = PyDict::new(py);
+                        kwargs.set_item("extra", extra)?;
+                        let message = format!(
+                            "radyx {} of entity {} at tick {}",
+                            record.event, record.entity_index, record.tick
+                        );
+                        logger
+                            .call_method(py, "log", (record.level(), message), Some(kwargs))
+                            .map(drop)
+                    }
+                });
+                if let Err(err) = delivered {
+                    let hook = match hook {
+                        Hook::Callable(hook) | Hook::Logger(hook) => hook.as_ref(py),
+                    };
+                    err.write_unraisable(py, Some(hook));
+                }
+            }
+        });
+    }
+}
+
+impl GridPhysics {
+    /// Notes a body about to be inserted if it spans more than `max_cells_per_body` cells,
+    /// returning whether the policy skips it.
+    fn screen_oversized(&mut self, body: &Body) -> bool {
+        if self.oversize_policy == OversizePolicy::Ignore {
+            return false;
+        }
+        let cells = self.binned_cells(body).count();
+        if cells <= self.max_cells_per_body {
+            return false;
+        }
+        let first = Oversized {
+            count: 0,
+            entity_index: body.entity_index(),
+            body_index: body.body_index(),
+            cells,
+        };
+        self.oversized.get_or_insert(first).count += 1;
+        self.oversize_policy == OversizePolicy::Error
+    }
+
+    /// Screens a body about to be inserted, returning whether it is skipped for NaN or
+    /// infinite values or for spanning too many cells.
+    pub(crate) fn screen(&mut self, body: &Placed) -> bool {
+        self.screen_invalid(body) || self.screen_oversized(body)
+    }
+
+    /// Raises for the invalid bodies of the last add call, then warns or raises for its
+    /// oversized ones.
+    pub(crate) fn report_skipped(&mut self) -> PyResult<()> {
+        if let Err(err) = self.report_invalid() {
+            self.oversized = None;
+            return Err(err);
+        }
+        let Some(oversized) = self.oversized.take() else {
+            return Ok(());
+        };
+        let Oversized {
+            count,
+            entity_index,
+            body_index,
+            cells,
+        } = oversized;
+        let message = format!(
+            "{count} bodies span more than {} cells, body {body_index} of entity \
+             {entity_index} spans {cells}; raise cell_size or split the body",
+            self.max_cells_per_body
+        );
+        match self.oversize_policy {
+            OversizePolicy::Error => Err(PyValueError::new_err(format!(
+                "{message}, they were not added"
+            ))),
+            _ => {
+                Python::with_gil(|py| PyErr::warn(py, py.get_type::<PyUserWarning>(), &message, 1))
+            }
+        }
+    }
+
+    /// Logs an inserted body, and whether it landed outside the grid or in too many cells.
+    pub(crate) fn log_insert(&mut self, body: &Body) {
+        if !self.logging.enabled() {
+            return;
+        }
+        let cells = self.binned_cells(body).count();
+        let record = LogRecord {
+            event: "insert",
+            tick: self.tick,
+            entity_index: body.entity_index(),
+            body_index: Some(body.body_index()),
+            cells: Some(cells),
+        };
+        self.logging.push(record);
+        if cells == 0 {
+            self.logging.push(LogRecord {
+                event: "out_of_bounds",
+                ..record
+            });
+        } else if cells > self.max_cells_per_body {
+            self.logging.push(LogRecord {
+                event: "oversized",
+                ..record
+            });
+        }
+    }
+
+    pub(crate) fn log_removal(&mut self, entity_index: usize, body_index: Option<usize>) {
+        self.logging.push(LogRecord {
+            event: "remove",
+            tick: self.tick,
+            entity_index,
+            body_index,
+            cells: None,
+        });
+    }
+}
@@ -0,0 +1,78 @@
+//! Estimates of the heap memory a world holds.
+
+use crate::GridPhysics;
+use pyo3::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    mem::size_of,
+};
+
+/// Approximate bytes held by a world, from allocated capacities. Hash tables count one
+/// control byte per slot. Cells shared with forks are counted in full by each world.
+#[pyclass(module = "radyx", get_all)]
+#[derive(Clone, Debug)]
+pub struct MemoryUsage {
+    /// Cell lists and the bodies binned into them.
+    pub cells: usize,
+    /// Per-entity lists of dynamic and static bodies.
+    pub bodies: usize,
+    /// Entity bounds, transforms, velocities, contacts, history and other bookkeeping.
+    pub caches: usize,
+    pub total: usize,
+    /// Bodies binned into cells, counting each cell a body is in.
+    pub cell_copies: usize,
+    /// Cell copies beyond one per body, which grow as `cell_size` shrinks below body sizes.
+    pub duplicated_copies: usize,
+}
+
+fn map_bytes<K, V>(map: &HashMap<K, V>) -> usize {
+    map.capacity() * (size_of::<(K, V)>() + 1)
+}
+
+fn set_bytes<T>(set: &HashSet<T>) -> usize {
+    set.capacity() * (size_of::<T>() + 1)
+}
+
+fn lists_bytes<T>(lists: &HashMap<usize, Vec<T>>) -> usize {
+    map_bytes(lists)
+        + lists
+            .values()
+            .map(|bodies| bodies.capacity() * size_of::<T>())
+            .sum::<usize>()
+}
+
+impl GridPhysics {
+    pub(crate) fn memory(&self) -> MemoryUsage {
+        let cells = self.grid.capacity() * size_of::<crate::Cell>()
+            + self.grid.iter().map(|cell| cell.bytes()).sum::<usize>();
+        let bodies = lists_bytes(&self.dynamic_bodies) + lists_bytes(&self.static_bodies);
+        let caches = map_bytes(&self.entity_bounds)
+            + map_bytes(&self.transforms)
+            + map_bytes(&self.velocities)
+            + map_bytes(&self.changed)
+            + set_bytes(&self.contacts)
+            + map_bytes(&self.contact_ids)
+            + map_bytes(&self.cooldown_starts)
+            + set_bytes(&self.collision_scratch)
+            + set_bytes(&self.static_exclusions)
+            + set_bytes(&self.outside)
+            + self.events.capacity() * size_of::<crate::Event>()
+            + self.history.bytes();
+
+        let cell_copies: usize = self.grid.iter().map(|cell| cell.len()).sum();
+        let body_count: usize = self
+            .dynamic_bodies
+            .values()
+            .chain(self.static_bodies.values())
+            .map(Vec::len)
+            .sum();
+        MemoryUsage {
+            cells,
+            bodies,
+            caches,
+            total: cells + bodies + caches,
+            cell_copies,
+            duplicated_copies: cell_copies.saturating_sub(body_count),
+        }
+    }
+}
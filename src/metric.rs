@@ -0,0 +1,33 @@
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+/// Distance metric used by area and nearest queries, passed from Python by name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Metric {
+    #[default]
+    Euclidean,
+    Chebyshev,
+    Manhattan,
+}
+
+impl Metric {
+    pub fn length(self, dx: f32, dy: f32) -> f32 {
+        match self {
+            Metric::Euclidean => (dx * dx + dy * dy).sqrt(),
+            Metric::Chebyshev => dx.abs().max(dy.abs()),
+            Metric::Manhattan => dx.abs() + dy.abs(),
+        }
+    }
+}
+
+impl<'source> FromPyObject<'source> for Metric {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract::<&str>()? {
+            "euclidean" => Ok(Metric::Euclidean),
+            "chebyshev" => Ok(Metric::Chebyshev),
+            "manhattan" => Ok(Metric::Manhattan),
+            name => Err(PyValueError::new_err(format!(
+                "unknown metric {name:?}, expected \"euclidean\", \"chebyshev\" or \"manhattan\""
+            ))),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Entity namespaces, so systems counting their entities from zero on their own can share a
+//! grid. A namespaced entity index carries its namespace in the bits above `ENTITY_BITS`,
+//! leaving plain indices below `1 << ENTITY_BITS` in the default namespace 0.
+
+use pyo3::{exceptions::PyOverflowError, PyResult};
+use std::collections::HashMap;
+
+/// Bits of an entity index left to the entity within its namespace.
+pub const ENTITY_BITS: u32 = 24;
+
+/// Namespaces that fit the rest of the 32 bits a body stores the entity index in, besides
+/// the default one.
+const MAX_NAMESPACES: usize = (1 << (32 - ENTITY_BITS)) - 1;
+
+/// The namespace and the entity within it an entity index stands for.
+pub fn split(entity_index: usize) -> (usize, usize) {
+    (
+        entity_index >> ENTITY_BITS,
+        entity_index & ((1 << ENTITY_BITS) - 1),
+    )
+}
+
+/// The entity index of an entity within a namespace.
+pub fn join(namespace: usize, entity: usize) -> usize {
+    (namespace << ENTITY_BITS) | entity
+}
+
+/// Interns namespace names to the integers in their entity indices, counting up from 1.
+#[derive(Clone, Default)]
+pub struct NamespaceRegistry {
+    ids: HashMap<String, usize>,
+}
+
+impl NamespaceRegistry {
+    /// The id of a namespace, registering names seen for the first time.
+    pub fn register(&mut self, name: &str) -> PyResult<usize> {
+        if let Some(id) = self.ids.get(name) {
+            return Ok(*id);
+        }
+        if self.ids.len() == MAX_NAMESPACES {
+            return Err(PyOverflowError::new_err(format!(
+                "can't register namespace {name:?}, all {MAX_NAMESPACES} are taken"
+            )));
+        }
+        let id = self.ids.len() + 1;
+        self.ids.insert(name.to_string(), id);
+        Ok(id)
+    }
+
+    /// The entity index of `entity` in the namespace called `name`.
+    pub fn entity(&mut self, name: &str, entity: usize) -> PyResult<usize> {
+        if entity >> ENTITY_BITS != 0 {
+            return Err(PyOverflowError::new_err(format!(
+                "entity {entity} doesn't fit in the {ENTITY_BITS} bits of a namespace"
+            )));
+        }
+        Ok(join(self.register(name)?, entity))
+    }
+
+    /// Raises for an entity index past the plain ones whose namespace isn't registered,
+    /// which would alias an entity of the namespace registered under that id later.
+    pub fn check(&self, entity_index: usize) -> PyResult<()> {
+        let namespace = split(entity_index).0;
+        // Ids count up from 1, so the registered ones are exactly those up to the count
+        if namespace <= self.ids.len() {
+            return Ok(());
+        }
+        Err(PyOverflowError::new_err(format!(
+            "entity index {entity_index} is past the {} plain indices and namespace \
+             {namespace} isn't registered, make namespaced indices with `namespaced`",
+            1usize << ENTITY_BITS
+        )))
+    }
+
+    /// Registered `(name, id)` pairs in id order.
+    pub fn entries(&self) -> Vec<(&str, usize)> {
+        let mut entries: Vec<(&str, usize)> = self
+            .ids
+            .iter()
+            .map(|(name, id)| (name.as_str(), *id))
+            .collect();
+        entries.sort_unstable_by_key(|entry| entry.1);
+        entries
+    }
+
+    pub fn name_of(&self, id: usize) -> Option<&str> {
+        self.ids
+            .iter()
+            .find(|(_, namespace)| **namespace == id)
+            .map(|(name, _)| name.as_str())
+    }
+}
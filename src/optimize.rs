@@ -0,0 +1,194 @@
+//! Merging of static bodies into fewer, larger ones to cut broad phase candidates.
+
+use crate::{shape::Shape, Body, GridPhysics, Placed, Vector2};
+use std::collections::BTreeMap;
+
+/// Tolerance for edges and angles to count as aligned, absorbing float noise of tile
+/// coordinates.
+const EPSILON: f32 = 1e-4;
+
+#[derive(Clone, Copy)]
+struct Rect {
+    min_x: f32,
+    max_x: f32,
+    min_y: f32,
+    max_y: f32,
+}
+
+/// Greedy merge of rectangles into rows of equal height, then columns of equal width.
+fn merge_rects(mut rects: Vec<Rect>) -> Vec<Rect> {
+    let close = |a: f32, b: f32| (a - b).abs() <= EPSILON;
+    let merge = |rects: &mut Vec<Rect>, horizontal: bool| {
+        let span = |rect: &Rect| {
+            if horizontal {
+                (rect.min_y, rect.max_y, rect.min_x, rect.max_x)
+            } else {
+                (rect.min_x, rect.max_x, rect.min_y, rect.max_y)
+            }
+        };
+        rects.sort_by(|a, b| {
+            let (a, b) = (span(a), span(b));
+            a.0.total_cmp(&b.0)
+                .then(a.1.total_cmp(&b.1))
+                .then(a.2.total_cmp(&b.2))
+        });
+        let mut merged: Vec<Rect> = Vec::new();
+        for rect in rects.drain(..) {
+            if let Some(last) = merged.last_mut() {
+                let (a, b) = (span(last), span(&rect));
+                if close(a.0, b.0) && close(a.1, b.1) && b.2 <= a.3 + EPSILON {
+                    if horizontal {
+                        last.max_x = last.max_x.max(rect.max_x);
+                    } else {
+                        last.max_y = last.max_y.max(rect.max_y);
+                    }
+                    continue;
+                }
+            }
+            merged.push(rect);
+        }
+        *rects = merged;
+    };
+    merge(&mut rects, true);
+    merge(&mut rects, false);
+    rects
+}
+
+impl GridPhysics {
+    /// Merges axis-aligned static boxes sharing edges into larger boxes, and consecutive
+    /// collinear segments of static chains into single segments. Only bodies of the same kind
+    /// are merged, and of the same entity unless `across_entities` is set, in which case
+    /// merged boxes belong to the lowest entity. Returns how many bodies were removed.
+    pub(crate) fn merge_statics(&mut self, across_entities: bool) -> usize {
+        let statics: Vec<Body> = self
+            .static_bodies
+            .values()
+            .flatten()
+            .map(|placed| placed.body)
+            .collect();
+        let before = statics.len();
+
+        let mut boxes: BTreeMap<(usize, usize), Vec<Body>> = BTreeMap::new();
+        let mut chains: BTreeMap<usize, Vec<Body>> = BTreeMap::new();
+        for body in statics {
+            match body.shape() {
+                Shape::Box { .. } if body.angle.abs() <= EPSILON => {
+                    let owner = if across_entities {
+                        0
+                    } else {
+                        body.entity_index()
+                    };
+                    boxes.entry((body.kind(), owner)).or_default().push(body);
+                }
+                Shape::Segment { .. } => chains.entry(body.entity_index()).or_default().push(body),
+                _ => {}
+            }
+        }
+
+        for mut group in boxes.into_values() {
+            if group.len() < 2 {
+                continue;
+            }
+            group.sort_unstable_by_key(|body| (body.entity_index(), body.body_index()));
+            let rects = group
+                .iter()
+                .map(|body| {
+                    let (min_x, max_x, min_y, max_y) = body.get_bounds();
+                    Rect {
+                        min_x,
+                        max_x,
+                        min_y,
+                        max_y,
+                    }
+                })
+                .collect();
+            let merged = merge_rects(rects);
+            if merged.len() == group.len() {
+                continue;
+            }
+
+            for body in &group {
+                self.remove_static_body(body);
+            }
+            // Reuse the identities of the first bodies of the group for the merged boxes
+            for (template, rect) in group.iter().zip(merged) {
+                let center = Vector2::new(
+                    (rect.min_x + rect.max_x) / 2.0,
+                    (rect.min_y + rect.max_y) / 2.0,
+                );
+                let half_x = (rect.max_x - rect.min_x) / 2.0;
+                let half_y = (rect.max_y - rect.min_y) / 2.0;
+                let body = Body::new(
+                    template.entity_index(),
+                    template.body_index(),
+                    center,
+                    0.0,
+                    true,
+                    template.kind(),
+                );
+                // Merged boxes are large by design, so they bypass the oversize policy
+                self.push_body(self.localized(Placed::new(body.oriented_box(half_x, half_y, 0.0))));
+            }
+        }
+
+        for mut chain in chains.into_values() {
+            chain.sort_unstable_by_key(|body| body.body_index());
+            let endpoints = |body: &Body| {
+                let Shape::Segment { half_length } = body.shape() else {
+                    unreachable!("chains only hold segments");
+                };
+                let (sin, cos) = body.angle.sin_cos();
+                let (dx, dy) = (cos * half_length, sin * half_length);
+                (
+                    Vector2::new(body.pos.x - dx, body.pos.y - dy),
+                    Vector2::new(body.pos.x + dx, body.pos.y + dy),
+                )
+            };
+
+            let mut runs: Vec<(Body, Vector2, Vector2, usize)> = Vec::new();
+            for body in chain {
+                let (start, end) = endpoints(&body);
+                if let Some((first, _, run_end, count)) = runs.last_mut() {
+                    let joined = (run_end.x - start.x).abs() <= EPSILON
+                        && (run_end.y - start.y).abs() <= EPSILON;
+                    let turn = (body.angle - first.angle).sin().abs();
+                    if joined && turn <= EPSILON && body.kind() == first.kind() {
+                        *run_end = end;
+                        *count += 1;
+                        continue;
+                    }
+                }
+                runs.push((body, start, end, 1));
+            }
+
+            for (first, start, end, count) in runs {
+                if count < 2 {
+                    continue;
+                }
+                let removed: Vec<Body> = self.static_bodies[&first.entity_index()]
+                    .iter()
+                    .filter(|body| {
+                        matches!(body.shape(), Shape::Segment { .. })
+                            && (first.body_index()..first.body_index() + count)
+                                .contains(&body.body_index())
+                    })
+                    .map(|placed| placed.body)
+                    .collect();
+                for body in &removed {
+                    self.remove_static_body(body);
+                }
+                let body = Body::new(
+                    first.entity_index(),
+                    first.body_index(),
+                    start,
+                    0.0,
+                    true,
+                    first.kind(),
+                );
+                self.push_body(self.localized(Placed::new(body.segment(start, end))));
+            }
+        }
+
+        before - self.static_bodies.values().map(Vec::len).sum::<usize>()
+    }
+}
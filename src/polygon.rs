@@ -0,0 +1,90 @@
+//! Convex polygons given from Python, for area queries of shapes other than circles and
+//! rectangles.
+
+use crate::{Containment, GridPhysics, Vector2};
+use pyo3::{exceptions::PyValueError, PyResult};
+use std::collections::HashSet;
+
+/// A convex polygon with finite corners, in either winding.
+pub struct ConvexPolygon(Vec<Vector2>);
+
+impl ConvexPolygon {
+    pub fn new(points: Vec<Vector2>) -> PyResult<Self> {
+        if points.len() < 3 {
+            return Err(PyValueError::new_err(format!(
+                "polygon needs at least 3 points, got {}",
+                points.len()
+            )));
+        }
+        if points.iter().any(|p| !p.x.is_finite() || !p.y.is_finite()) {
+            return Err(PyValueError::new_err("polygon points must be finite"));
+        }
+        let turns: Vec<f32> = (0..points.len())
+            .map(|i| {
+                let (a, b, c) = (
+                    points[i],
+                    points[(i + 1) % points.len()],
+                    points[(i + 2) % points.len()],
+                );
+                (b.x - a.x) * (c.y - b.y) - (b.y - a.y) * (c.x - b.x)
+            })
+            .collect();
+        if turns.iter().any(|turn| *turn > 0.0) && turns.iter().any(|turn| *turn < 0.0) {
+            return Err(PyValueError::new_err("polygon must be convex"));
+        }
+        Ok(Self(points))
+    }
+
+    pub fn corners(&self) -> &[Vector2] {
+        &self.0
+    }
+
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        self.0.iter().fold(
+            (
+                f32::INFINITY,
+                f32::NEG_INFINITY,
+                f32::INFINITY,
+                f32::NEG_INFINITY,
+            ),
+            |(min_x, max_x, min_y, max_y), p| {
+                (
+                    min_x.min(p.x),
+                    max_x.max(p.x),
+                    min_y.min(p.y),
+                    max_y.max(p.y),
+                )
+            },
+        )
+    }
+}
+
+impl GridPhysics {
+    /// Entities with an active body overlapping the polygon, or with all their bodies inside
+    /// it, culled by the cells covering its bounds.
+    pub(crate) fn polygon_entities(
+        &self,
+        polygon: &ConvexPolygon,
+        containment: Containment,
+    ) -> HashSet<usize> {
+        let corners = polygon.corners();
+        let mut entities = HashSet::new();
+        for cell in self.cells_within(polygon.bounds()) {
+            for body in cell.iter() {
+                let passes = match containment {
+                    Containment::Intersects => body.overlaps_polygon(corners),
+                    Containment::Contains => {
+                        self.entity_within(body.entity_index(), |body| body.within_polygon(corners))
+                    }
+                };
+                if !entities.contains(&body.entity_index())
+                    && self.is_active(body.entity_index())
+                    && passes
+                {
+                    entities.insert(body.entity_index());
+                }
+            }
+        }
+        entities
+    }
+}
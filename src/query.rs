@@ -0,0 +1,227 @@
+//! A fluent builder for area queries, so filters can grow without adding arguments to every
+//! query method.
+
+use crate::{Containment, GridPhysics, KindArg, Metric, Vector2};
+use pyo3::{exceptions::PyValueError, prelude::*};
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Area {
+    Circle { center: Vector2, radius: f32 },
+    Rect { min: Vector2, max: Vector2 },
+}
+
+impl Area {
+    fn bounds(self) -> (f32, f32, f32, f32) {
+        match self {
+            Area::Circle { center, radius } => (
+                center.x - radius,
+                center.x + radius,
+                center.y - radius,
+                center.y + radius,
+            ),
+            Area::Rect { min, max } => (min.x, max.x, min.y, max.y),
+        }
+    }
+
+    fn center(self) -> Vector2 {
+        match self {
+            Area::Circle { center, .. } => center,
+            Area::Rect { min, max } => Vector2::new((min.x + max.x) / 2.0, (min.y + max.y) / 2.0),
+        }
+    }
+}
+
+/// Filters of a query with kinds resolved.
+#[derive(Clone, Debug, Default)]
+pub struct QuerySpec {
+    pub area: Option<Area>,
+    pub kinds: Option<HashSet<usize>>,
+    pub exclude: HashSet<usize>,
+    pub limit: Option<usize>,
+    pub nearest_first: bool,
+    pub metric: Metric,
+    pub containment: Containment,
+}
+
+impl GridPhysics {
+    /// Bodies in the area passing the filters as `(entity_index, body_index, distance)`, with
+    /// the distance measured from the area center to the body surface.
+    pub(crate) fn run_query(&self, spec: &QuerySpec) -> PyResult<Vec<(usize, usize, f32)>> {
+        let Some(area) = spec.area else {
+            return Err(PyValueError::new_err(
+                "query needs an area, set one with circle() or rect()",
+            ));
+        };
+        let center = area.center();
+        let mut seen = HashSet::new();
+        let mut hits = Vec::new();
+        for cell in self.cells_within(area.bounds()) {
+            for body in cell.iter() {
+                let passes = self.is_active(body.entity_index())
+                    && !spec.exclude.contains(&body.entity_index())
+                    && spec
+                        .kinds
+                        .as_ref()
+                        .is_none_or(|kinds| kinds.contains(&body.kind()));
+                if !passes
+                    || !seen.insert((body.entity_index(), body.is_static(), body.body_index()))
+                {
+                    continue;
+                }
+                let distance = body.metric_distance(center, spec.metric);
+                let inside = match (area, spec.containment) {
+                    (Area::Circle { radius, .. }, Containment::Intersects) => distance <= radius,
+                    (Area::Circle { center, radius }, Containment::Contains) => {
+                        body.within_circle(center, radius)
+                    }
+                    (Area::Rect { min, max }, Containment::Intersects) => {
+                        body.overlaps_box(min, max)
+                    }
+                    (Area::Rect { min, max }, Containment::Contains) => body.within_box(min, max),
+                };
+                if inside {
+                    hits.push((body.entity_index(), body.body_index(), distance));
+                }
+            }
+        }
+
+        if spec.nearest_first {
+            hits.sort_unstable_by(|a, b| a.2.total_cmp(&b.2).then((a.0, a.1).cmp(&(b.0, b.1))));
+        } else {
+            hits.sort_unstable_by_key(|hit| (hit.0, hit.1));
+        }
+        if let Some(limit) = spec.limit {
+            hits.truncate(limit);
+        }
+        Ok(hits)
+    }
+}
+
+/// A query built step by step, such as
+/// `grid.query().circle(pos, 5.0).mask("enemy").exclude(3).limit(10).nearest_first().execute()`.
+/// Each filter method returns the query itself.
+#[pyclass(module = "radyx")]
+pub struct Query {
+    grid: Py<GridPhysics>,
+    spec: QuerySpec,
+    kinds: Vec<KindArg>,
+}
+
+impl Query {
+    pub fn new(grid: Py<GridPhysics>) -> Self {
+        Self {
+            grid,
+            spec: QuerySpec::default(),
+            kinds: Vec::new(),
+        }
+    }
+}
+
+#[pymethods]
+impl Query {
+    /// Bodies touching the circle, replacing any area set before.
+    pub fn circle(mut slf: PyRefMut<'_, Self>, center: Vector2, radius: f32) -> PyRefMut<'_, Self> {
+        slf.spec.area = Some(Area::Circle { center, radius });
+        slf
+    }
+
+    /// Bodies overlapping the axis-aligned rectangle, replacing any area set before.
+    pub fn rect(mut slf: PyRefMut<'_, Self>, min: Vector2, max: Vector2) -> PyRefMut<'_, Self> {
+        slf.spec.area = Some(Area::Rect { min, max });
+        slf
+    }
+
+    /// Only bodies of this kind; calling it again allows further kinds.
+    pub fn mask(mut slf: PyRefMut<'_, Self>, kind: KindArg) -> PyRefMut<'_, Self> {
+        slf.kinds.push(kind);
+        slf
+    }
+
+    pub fn exclude(mut slf: PyRefMut<'_, Self>, entity_index: usize) -> PyRefMut<'_, Self> {
+        slf.spec.exclude.insert(entity_index);
+        slf
+    }
+
+    pub fn limit(mut slf: PyRefMut<'_, Self>, limit: usize) -> PyRefMut<'_, Self> {
+        slf.spec.limit = Some(limit);
+        slf
+    }
+
+    /// Orders results by distance instead of by index, so `limit` keeps the nearest.
+    pub fn nearest_first(mut slf: PyRefMut<'_, Self>) -> PyRefMut<'_, Self> {
+        slf.spec.nearest_first = true;
+        slf
+    }
+
+    pub fn metric(mut slf: PyRefMut<'_, Self>, metric: Metric) -> PyRefMut<'_, Self> {
+        slf.spec.metric = metric;
+        slf
+    }
+
+    /// With `"contains"`, only bodies entirely inside the area, the circle measured
+    /// euclidean whatever the metric.
+    pub fn containment(
+        mut slf: PyRefMut<'_, Self>,
+        containment: Containment,
+    ) -> PyRefMut<'_, Self> {
+        slf.spec.containment = containment;
+        slf
+    }
+
+    /// Runs the query on the current state of the world, returning
+    /// `(entity_index, body_index, distance)` sorted by index, or by distance with
+    /// `nearest_first`. The query can be executed again later.
+    pub fn execute(&self, py: Python) -> PyResult<Vec<(usize, usize, f32)>> {
+        let grid = self.grid.as_ref(py).try_borrow()?;
+        let mut spec = self.spec.clone();
+        if !self.kinds.is_empty() {
+            let kinds = self.kinds.iter().map(|kind| grid.kinds.resolve(kind));
+            spec.kinds = Some(kinds.collect::<PyResult<_>>()?);
+        }
+        grid.run_query(&spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_query_builder() {
+        let mut grid = GridPhysics::new(100.0, 10.0);
+        let enemy = grid.register_kind("enemy");
+        grid.add_dynamic_circle(0, Vector2::new(50.0, 50.0), 1.0, enemy);
+        grid.add_dynamic_circle(1, Vector2::new(54.0, 50.0), 1.0, enemy);
+        grid.add_dynamic_circle(2, Vector2::new(52.0, 50.0), 1.0, enemy);
+        grid.add_dynamic_circle(3, Vector2::new(51.0, 51.0), 1.0, 0);
+        grid.add_static_circle(4, Vector2::new(80.0, 80.0), 1.0, enemy);
+
+        let mut spec = QuerySpec {
+            area: Some(Area::Circle {
+                center: Vector2::new(50.0, 50.0),
+                radius: 5.0,
+            }),
+            ..QuerySpec::default()
+        };
+        let entities = |hits: Vec<(usize, usize, f32)>| -> Vec<usize> {
+            hits.into_iter().map(|hit| hit.0).collect()
+        };
+        assert_eq!(entities(grid.run_query(&spec).unwrap()), vec![0, 1, 2, 3]);
+
+        spec.kinds = Some(HashSet::from([enemy]));
+        spec.exclude.insert(0);
+        spec.nearest_first = true;
+        assert_eq!(entities(grid.run_query(&spec).unwrap()), vec![2, 1]);
+        spec.limit = Some(1);
+        assert_eq!(entities(grid.run_query(&spec).unwrap()), vec![2]);
+
+        spec.area = Some(Area::Rect {
+            min: Vector2::new(70.0, 70.0),
+            max: Vector2::new(90.0, 90.0),
+        });
+        assert_eq!(entities(grid.run_query(&spec).unwrap()), vec![4]);
+        spec.area = None;
+        assert!(grid.run_query(&spec).is_err());
+    }
+}
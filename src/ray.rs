@@ -0,0 +1,185 @@
+use crate::Vector2;
+
+/// Distance along the ray `origin + t * direction` (with `direction` normalized) at which it
+/// first touches the circle, or zero when `origin` is inside it.
+pub fn circle_hit(
+    origin: Vector2,
+    direction: Vector2,
+    center: Vector2,
+    radius: f32,
+) -> Option<f32> {
+    let (ox, oy) = (origin.x - center.x, origin.y - center.y);
+    let b = ox * direction.x + oy * direction.y;
+    let c = ox * ox + oy * oy - radius * radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    let discriminant = b * b - c;
+    if discriminant < 0.0 || b > 0.0 {
+        return None;
+    }
+    Some(-b - discriminant.sqrt())
+}
+
+/// Interval of `t` over which the ray is inside the box `[min, max]` on both axes.
+pub fn box_interval(origin: Vector2, direction: Vector2, min: f32, max: f32) -> Option<(f32, f32)> {
+    let mut enter = 0.0_f32;
+    let mut exit = f32::INFINITY;
+    for (start, delta) in [(origin.x, direction.x), (origin.y, direction.y)] {
+        if delta == 0.0 {
+            if start < min || start > max {
+                return None;
+            }
+            continue;
+        }
+        let (a, b) = ((min - start) / delta, (max - start) / delta);
+        enter = enter.max(a.min(b));
+        exit = exit.min(a.max(b));
+    }
+    (enter <= exit).then_some((enter, exit))
+}
+
+/// Relative difference below which the ray is taken to cross both axes at once.
+const CORNER_EPSILON: f32 = 1e-5;
+
+/// Square cells crossed by a ray in order (Amanatides & Woo), as `(cell_x, cell_y, t_exit)`
+/// where `t_exit` is the distance at which the ray leaves the cell.
+pub struct Traversal {
+    cell: (isize, isize),
+    step: (isize, isize),
+    t_max: (f32, f32),
+    t_delta: (f32, f32),
+    max_distance: f32,
+    /// Distance from the ray origin to where the walk starts, added to reported distances.
+    offset: f32,
+    /// Cells a side of the grid the walk is clipped to, ending once it leaves the grid.
+    grid_size: Option<isize>,
+    done: bool,
+    supercover: bool,
+    /// Cells only touched at a corner, reported before the cell diagonal to the last one.
+    corners: Vec<(isize, isize, f32)>,
+}
+
+impl Traversal {
+    pub fn new(origin: Vector2, direction: Vector2, cell_size: f32, max_distance: f32) -> Self {
+        let axis = |start: f32, delta: f32| {
+            let cell = (start / cell_size).floor();
+            if delta > 0.0 {
+                (
+                    1,
+                    ((cell + 1.0) * cell_size - start) / delta,
+                    cell_size / delta,
+                )
+            } else if delta < 0.0 {
+                (-1, (cell * cell_size - start) / delta, -cell_size / delta)
+            } else {
+                (0, f32::INFINITY, f32::INFINITY)
+            }
+        };
+        let (step_x, t_max_x, t_delta_x) = axis(origin.x, direction.x);
+        let (step_y, t_max_y, t_delta_y) = axis(origin.y, direction.y);
+        Self {
+            cell: (
+                (origin.x / cell_size).floor() as isize,
+                (origin.y / cell_size).floor() as isize,
+            ),
+            step: (step_x, step_y),
+            t_max: (t_max_x, t_max_y),
+            t_delta: (t_delta_x, t_delta_y),
+            max_distance,
+            offset: 0.0,
+            grid_size: None,
+            done: false,
+            supercover: false,
+            corners: Vec::new(),
+        }
+    }
+
+    /// Like `new`, but only walking the part of the ray inside a square grid `grid_size` cells
+    /// a side, so a ray from far outside starts where it enters and stops where it leaves
+    /// however long it is. `None` if the ray misses the grid within `max_distance`.
+    pub fn clipped(
+        origin: Vector2,
+        direction: Vector2,
+        cell_size: f32,
+        max_distance: f32,
+        grid_size: usize,
+    ) -> Option<Self> {
+        // NaN slips through the interval's `min` and `max`, and would never finish the walk
+        let values = [origin.x, origin.y, direction.x, direction.y];
+        if !values.iter().all(|value| value.is_finite()) || max_distance.is_nan() {
+            return None;
+        }
+        let extent = grid_size as f32 * cell_size;
+        let (enter, exit) = box_interval(origin, direction, 0.0, extent)?;
+        if enter > max_distance {
+            return None;
+        }
+        let start = Vector2::new(
+            origin.x + direction.x * enter,
+            origin.y + direction.y * enter,
+        );
+        let mut traversal = Self::new(start, direction, cell_size, max_distance.min(exit) - enter);
+        traversal.offset = enter;
+        traversal.grid_size = Some(grid_size as isize);
+        Some(traversal)
+    }
+
+    /// Whether the current cell is outside the grid and the walk heads further away from it.
+    fn left_grid(&self) -> bool {
+        let Some(size) = self.grid_size else {
+            return false;
+        };
+        let away =
+            |cell: isize, step: isize| (cell < 0 && step <= 0) || (cell >= size && step >= 0);
+        away(self.cell.0, self.step.0) || away(self.cell.1, self.step.1)
+    }
+
+    /// Also reports the two cells beside a corner the ray passes through exactly, which plain
+    /// traversal skips by stepping one axis ahead of the other.
+    pub fn supercover(mut self, enabled: bool) -> Self {
+        self.supercover = enabled;
+        self
+    }
+}
+
+impl Iterator for Traversal {
+    type Item = (isize, isize, f32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(corner) = self.corners.pop() {
+            return Some(corner);
+        }
+        if self.done || self.left_grid() {
+            return None;
+        }
+        let exit = self.t_max.0.min(self.t_max.1);
+        let item = (
+            self.cell.0,
+            self.cell.1,
+            exit.min(self.max_distance) + self.offset,
+        );
+        let at_corner = self.supercover
+            && self.step.0 != 0
+            && self.step.1 != 0
+            && (self.t_max.0 - self.t_max.1).abs() <= CORNER_EPSILON * exit.max(1.0);
+        if exit >= self.max_distance {
+            self.done = true;
+        } else if at_corner {
+            // Popped in reverse, so the x neighbour comes first
+            self.corners.push((item.0, item.1 + self.step.1, item.2));
+            self.corners.push((item.0 + self.step.0, item.1, item.2));
+            self.cell.0 += self.step.0;
+            self.cell.1 += self.step.1;
+            self.t_max.0 += self.t_delta.0;
+            self.t_max.1 += self.t_delta.1;
+        } else if self.t_max.0 < self.t_max.1 {
+            self.cell.0 += self.step.0;
+            self.t_max.0 += self.t_delta.0;
+        } else {
+            self.cell.1 += self.step.1;
+            self.t_max.1 += self.t_delta.1;
+        }
+        Some(item)
+    }
+}
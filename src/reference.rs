@@ -0,0 +1,232 @@
+//! A brute force collider checking every pair of bodies, and property tests holding the
+//! grid's collisions to it on random worlds. Positions cluster on cell borders, where a body
+//! binned into too few cells misses the bodies of its neighbours.
+//!
+//! `cargo test reference` runs a fixed set of seeds. Set `RADYX_REFERENCE_SEEDS` to run more,
+//! and `RADYX_REFERENCE_SEED` to replay the one a failure names. proptest isn't among the
+//! crate's dependencies, so the worlds come from `benchmark::Rng` and a failing one is shrunk
+//! here instead: bodies are dropped one at a time while the mismatch persists, and the
+//! failure lists the few that are left.
+
+use crate::{
+    benchmark::Rng, dedup::PairDedup, targets::Targets, Body, Collision, ContactKey, GridPhysics,
+    Layout, Vector2,
+};
+use std::collections::HashSet;
+
+/// Bodies in each random world.
+const BODIES: usize = 60;
+
+impl GridPhysics {
+    /// Collisions of the awake dynamic bodies found by testing each against every body in
+    /// the world, under the same filters as `get_collisions` but without the grid. Wrapping
+    /// worlds test each pair at the neighbouring periods as well.
+    pub(crate) fn brute_force_collisions(&self, targets: Targets) -> HashSet<Collision> {
+        let shifts = self.shifts();
+        let everything: Vec<&Body> = self
+            .dynamic_bodies
+            .values()
+            .chain(self.static_bodies.values())
+            .flatten()
+            .map(|placed| &placed.body)
+            .collect();
+        let mut collisions = HashSet::new();
+        for body in self.awake_bodies() {
+            for other in &everything {
+                let seen = shifts.iter().any(|shift| {
+                    let other = shifted(**other, *shift);
+                    body.collided(&other) && self.deep_enough(body, &other)
+                });
+                if seen
+                    && targets.admits(other)
+                    && self.is_active(body.entity_index())
+                    && self.is_active(other.entity_index())
+                    && !self.cooling_down(body.entity_index(), other.entity_index())
+                {
+                    let collision = Collision::new(
+                        body.entity_index(),
+                        other.entity_index(),
+                        body.body_index(),
+                        other.body_index(),
+                    );
+                    collisions.insert(collision.at_tick(self.tick));
+                }
+            }
+        }
+        collisions
+    }
+
+    /// Offsets another body is seen at, one per neighbouring period when wrapping.
+    fn shifts(&self) -> Vec<Vector2> {
+        let period = self.wrap_period();
+        let steps: &[f32] = if self.wrap { &[-1.0, 0.0, 1.0] } else { &[0.0] };
+        steps
+            .iter()
+            .flat_map(|x| {
+                steps
+                    .iter()
+                    .map(move |y| Vector2::new(x * period, y * period))
+            })
+            .collect()
+    }
+}
+
+fn shifted(mut body: Body, shift: Vector2) -> Body {
+    body.pos = Vector2::new(body.pos.x + shift.x, body.pos.y + shift.y);
+    body
+}
+
+/// A coordinate in the world, on or just either side of a cell border half the time. Only
+/// worlds that don't wrap put coordinates exactly on a border, since shifting bodies by the
+/// period rounds their positions, which decides exact ties either way.
+fn coordinate(rng: &mut Rng, grid: &GridPhysics) -> f32 {
+    let value = rng.next() * grid.size;
+    if rng.next() < 0.5 {
+        return value;
+    }
+    let border = (value / grid.cell_size).round() * grid.cell_size;
+    // Varied off the border, so bodies rarely share a coordinate there by chance
+    let nudge = if rng.next() < 0.5 && !grid.wrap {
+        0.0
+    } else {
+        (rng.next() - 0.5) * 4e-3
+    };
+    let nudged = border + nudge * grid.cell_size;
+    if (0.0..=grid.size).contains(&nudged) {
+        nudged
+    } else {
+        value
+    }
+}
+
+/// A random body of a random shape, mostly smaller than a cell but sometimes spanning
+/// several.
+fn random_body(rng: &mut Rng, grid: &GridPhysics, entity_index: usize) -> Body {
+    let point = |rng: &mut Rng| Vector2::new(coordinate(rng, grid), coordinate(rng, grid));
+    let pos = point(rng);
+    let extent = if rng.next() < 0.2 { 2.5 } else { 0.6 };
+    let length = |rng: &mut Rng| grid.cell_size * extent * rng.next().max(0.01);
+    let radius = length(rng);
+    let is_static = rng.next() < 0.3;
+    let body = Body::new(entity_index, 0, pos, radius, is_static, 0);
+    let angle = rng.next() * std::f32::consts::TAU;
+    match (rng.next() * 5.0) as usize {
+        0 => body,
+        1 => body.ellipse(radius, length(rng), angle),
+        2 => body.oriented_box(radius, length(rng), angle),
+        3 => body.segment(pos, point(rng)),
+        _ => body.triangle([pos, point(rng), point(rng)]),
+    }
+}
+
+/// An empty world of random dimensions, with the bodies to add to it.
+fn random_world(seed: u64) -> (GridPhysics, Vec<Body>) {
+    // Spread over the state, since xorshift starts out near zero from small seeds
+    let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1);
+    let cell_size = 5.0 + rng.next() * 20.0;
+    let size = cell_size * (2.0 + rng.next() * 8.0);
+    let layout = if rng.next() < 0.25 {
+        Layout::Hex
+    } else {
+        Layout::Square
+    };
+    let mut grid = GridPhysics::try_with_layout(size, cell_size, layout).expect("valid dimensions");
+    if layout == Layout::Square && rng.next() < 0.3 {
+        grid.set_wrap(true).expect("square grids wrap");
+    }
+    let bodies = (0..BODIES)
+        .map(|entity_index| loop {
+            // Inside the grid, since nothing is binned past its edges
+            let body = random_body(&mut rng, &grid, entity_index);
+            let (min_x, max_x, min_y, max_y) = body.get_bounds();
+            if min_x >= 0.0 && min_y >= 0.0 && max_x <= size && max_y <= size {
+                break body;
+            }
+        })
+        .collect();
+    (grid, bodies)
+}
+
+fn keys(collisions: &HashSet<Collision>) -> Vec<ContactKey> {
+    let mut keys: Vec<ContactKey> = collisions.iter().map(Collision::key).collect();
+    keys.sort_unstable();
+    keys
+}
+
+/// Describes how the grid's collisions differ from the brute force ones once `bodies` are
+/// added to `empty`, under either way of suppressing duplicates.
+fn mismatch(empty: &GridPhysics, bodies: &[Body]) -> Option<String> {
+    let mut grid = empty.clone();
+    for body in bodies {
+        grid.add_body(*body);
+    }
+    for (targets, dedup) in [Targets::All, Targets::Static, Targets::Dynamic]
+        .into_iter()
+        .flat_map(|targets| [(targets, PairDedup::Visited), (targets, PairDedup::After)])
+    {
+        grid.pair_dedup = dedup;
+        let expected = keys(&grid.brute_force_collisions(targets));
+        let found = keys(&grid.get_collisions(false, None, false, targets));
+        if found == expected {
+            continue;
+        }
+        let missed: Vec<_> = expected.iter().filter(|key| !found.contains(key)).collect();
+        let extra: Vec<_> = found.iter().filter(|key| !expected.contains(key)).collect();
+        return Some(format!(
+            "{targets:?} {dedup:?} hex {} wrap {}: missed {missed:?}, extra {extra:?}",
+            grid.layout == Layout::Hex,
+            grid.wrap
+        ));
+    }
+    None
+}
+
+/// The mismatch of a seed's world, reported for the fewest of its bodies that still show it.
+fn shrunk_mismatch(seed: u64) -> Option<String> {
+    let (empty, mut bodies) = random_world(seed);
+    let mut message = mismatch(&empty, &bodies)?;
+    for index in (0..bodies.len()).rev() {
+        let mut fewer = bodies.clone();
+        fewer.remove(index);
+        if let Some(fewer_message) = mismatch(&empty, &fewer) {
+            (bodies, message) = (fewer, fewer_message);
+        }
+    }
+    let described: Vec<String> = bodies
+        .iter()
+        .map(|body| {
+            format!(
+                "entity {} static {} at ({}, {}) radius {} angle {} {:?}",
+                body.entity_index(),
+                body.is_static(),
+                body.pos.x,
+                body.pos.y,
+                body.radius,
+                body.angle,
+                body.shape()
+            )
+        })
+        .collect();
+    Some(format!(
+        "{message}\n  size {} cell size {} with {}",
+        empty.size,
+        empty.cell_size,
+        described.join("\n    ")
+    ))
+}
+
+fn env_number(name: &str) -> Option<u64> {
+    std::env::var(name).ok()?.parse().ok()
+}
+
+#[test]
+fn check_grid_matches_brute_force() {
+    let seeds = match env_number("RADYX_REFERENCE_SEED") {
+        Some(seed) => seed..seed + 1,
+        None => 0..env_number("RADYX_REFERENCE_SEEDS").unwrap_or(64),
+    };
+    let failures: Vec<String> = seeds
+        .filter_map(|seed| shrunk_mismatch(seed).map(|message| format!("seed {seed}: {message}")))
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,71 @@
+//! The Python container collision results are returned in, chosen by name.
+
+use crate::{Collision, GridPhysics};
+use numpy::{ndarray::Array2, IntoPyArray};
+use pyo3::{exceptions::PyValueError, prelude::*};
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ResultType {
+    /// A set of `Collision` objects.
+    #[default]
+    Set,
+    /// A list of `Collision` objects sorted by their key.
+    List,
+    /// An `(n, 4)` int64 array of `[self_entity, other_entity, self_body, other_body]` rows
+    /// sorted like the list.
+    Numpy,
+}
+
+impl<'source> FromPyObject<'source> for ResultType {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract::<&str>()? {
+            "set" => Ok(ResultType::Set),
+            "list" => Ok(ResultType::List),
+            "numpy" => Ok(ResultType::Numpy),
+            name => Err(PyValueError::new_err(format!(
+                "unknown result type {name:?}, expected \"set\", \"list\" or \"numpy\""
+            ))),
+        }
+    }
+}
+
+impl ResultType {
+    /// Converts collisions to the container, failing for overlaps asked of an array.
+    pub fn convert(
+        self,
+        py: Python,
+        collisions: HashSet<Collision>,
+        with_overlap: bool,
+    ) -> PyResult<PyObject> {
+        if self == ResultType::Set {
+            return Ok(collisions.into_py(py));
+        }
+        let mut collisions: Vec<Collision> = collisions.into_iter().collect();
+        collisions.sort_unstable_by_key(Collision::key);
+        if self == ResultType::List {
+            return Ok(collisions.into_py(py));
+        }
+        if with_overlap {
+            return Err(PyValueError::new_err(
+                "result_type=\"numpy\" has no overlap column, use get_collisions_arrow",
+            ));
+        }
+        let rows = GridPhysics::collision_rows(&collisions);
+        Ok(rows.into_pyarray(py).to_object(py))
+    }
+}
+
+impl GridPhysics {
+    pub(crate) fn collision_rows(collisions: &[Collision]) -> Array2<i64> {
+        let mut rows = Array2::zeros((collisions.len(), 4));
+        for (mut row, collision) in rows.rows_mut().into_iter().zip(collisions) {
+            let key = collision.key();
+            row[0] = key.0 as i64;
+            row[1] = key.1 as i64;
+            row[2] = key.2 as i64;
+            row[3] = key.3 as i64;
+        }
+        rows
+    }
+}
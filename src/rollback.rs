@@ -0,0 +1,137 @@
+//! In-memory snapshots of the simulated state for per-tick rollback.
+//!
+//! Only what moves between ticks is kept: dynamic bodies with their entity transforms,
+//! bounds and velocities, the contacts events are derived from, and the clock. Static bodies,
+//! groups and settings are assumed to stay put while rolling back. Saving into a slot that
+//! was used before reuses its allocations.
+
+use crate::{events::ContactKey, Body, GridPhysics, Placed, Transform, Vector2};
+use pyo3::{
+    exceptions::{PyIndexError, PyKeyError},
+    PyResult,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+#[derive(Default)]
+pub struct Snapshot {
+    dynamic_bodies: HashMap<usize, Vec<Placed>>,
+    entity_bounds: HashMap<usize, (f32, f32, f32, f32)>,
+    transforms: HashMap<usize, Transform>,
+    velocities: HashMap<usize, Vector2>,
+    contacts: HashSet<ContactKey>,
+    contact_ids: HashMap<ContactKey, u64>,
+    cooldown_starts: HashMap<(usize, usize), u64>,
+    outside: HashSet<(usize, usize)>,
+    tick: u64,
+    time: f64,
+}
+
+/// Save slots of a world. A fork gets as many slots as its origin, all empty.
+pub struct StateSlots(Vec<Option<Snapshot>>);
+
+impl StateSlots {
+    pub fn resize(&mut self, count: usize) {
+        self.0.resize_with(count, || None);
+    }
+
+    pub fn clear(&mut self) {
+        self.0.fill_with(|| None);
+    }
+
+    fn slot(&mut self, slot: usize) -> PyResult<&mut Option<Snapshot>> {
+        let slots = self.0.len();
+        self.0
+            .get_mut(slot)
+            .ok_or_else(|| PyIndexError::new_err(format!("slot {slot} out of {slots} slots")))
+    }
+}
+
+impl Default for StateSlots {
+    fn default() -> Self {
+        Self((0..8).map(|_| None).collect())
+    }
+}
+
+impl Clone for StateSlots {
+    fn clone(&self) -> Self {
+        Self((0..self.0.len()).map(|_| None).collect())
+    }
+}
+
+impl GridPhysics {
+    pub(crate) fn save_snapshot(&mut self, slot: usize) -> PyResult<()> {
+        let mut snapshot = self.state_slots.slot(slot)?.take().unwrap_or_default();
+        snapshot.dynamic_bodies.clone_from(&self.dynamic_bodies);
+        snapshot.entity_bounds.clone_from(&self.entity_bounds);
+        snapshot.transforms.clone_from(&self.transforms);
+        snapshot.velocities.clone_from(&self.velocities);
+        snapshot.contacts.clone_from(&self.contacts);
+        snapshot.contact_ids.clone_from(&self.contact_ids);
+        snapshot.cooldown_starts.clone_from(&self.cooldown_starts);
+        snapshot.outside.clone_from(&self.outside);
+        snapshot.tick = self.tick;
+        snapshot.time = self.time;
+        *self.state_slots.slot(slot)? = Some(snapshot);
+        Ok(())
+    }
+
+    pub(crate) fn load_snapshot(&mut self, slot: usize) -> PyResult<()> {
+        let snapshot = self
+            .state_slots
+            .slot(slot)?
+            .take()
+            .ok_or_else(|| PyKeyError::new_err(format!("slot {slot} is empty")))?;
+
+        let current: Vec<Body> = self
+            .dynamic_bodies
+            .values()
+            .flatten()
+            .map(|placed| placed.body)
+            .collect();
+        let entities = self
+            .dynamic_bodies
+            .keys()
+            .chain(snapshot.dynamic_bodies.keys());
+        for entity_index in entities {
+            self.changed.insert(*entity_index, self.tick);
+        }
+        for body in &current {
+            let same = |other: &Body| {
+                !other.is_static()
+                    && other.entity_index() == body.entity_index()
+                    && other.body_index() == body.body_index()
+            };
+            for index in self.cell_span(self.binning_bounds(body)).indices() {
+                Arc::make_mut(&mut self.grid[index]).retain(|other| !same(other));
+            }
+        }
+
+        self.dynamic_bodies.clone_from(&snapshot.dynamic_bodies);
+        self.entity_bounds.clone_from(&snapshot.entity_bounds);
+        self.transforms.clone_from(&snapshot.transforms);
+        self.velocities.clone_from(&snapshot.velocities);
+        self.contacts.clone_from(&snapshot.contacts);
+        self.contact_ids.clone_from(&snapshot.contact_ids);
+        self.cooldown_starts.clone_from(&snapshot.cooldown_starts);
+        self.outside.clone_from(&snapshot.outside);
+        self.tick = snapshot.tick;
+        self.time = snapshot.time;
+        self.history.truncate_after(snapshot.tick);
+        let restored: Vec<Body> = self
+            .dynamic_bodies
+            .values()
+            .flatten()
+            .map(|placed| placed.body)
+            .collect();
+        for body in restored {
+            self.insert_into_cells(body);
+        }
+
+        // Keep the snapshot, a state is usually loaded more than once while resimulating
+        *self.state_slots.slot(slot)? = Some(snapshot);
+        Ok(())
+    }
+}
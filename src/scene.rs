@@ -0,0 +1,368 @@
+//! JSON scene descriptions of a grid, for authoring worlds in external tools and keeping them
+//! in version control.
+//!
+//! The schema, at `"version": 1`:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "config": {"layout": "square", "size": 100.0, "cell_size": 10.0, "min_overlap": 0.0},
+//!   "kinds": {"wall": 1},
+//!   "namespaces": {"projectiles": 1},
+//!   "bodies": [
+//!     {"entity": 0, "body": 0, "static": false, "kind": 1, "position": [5.0, 5.0],
+//!      "angle": 0.0, "offset": [5.0, 5.0], "turn": 0.0,
+//!      "shape": {"type": "circle", "radius": 1.0}}
+//!   ],
+//!   "entities": [{"entity": 0, "position": [0.0, 0.0], "angle": 0.0}],
+//!   "groups": {"enemies": {"entities": [0], "active": true}},
+//!   "sensors": [0]
+//! }
+//! ```
+//!
+//! `layout` is `"square"` or `"hex"`, and `config` may also cap the body count with
+//! `max_bodies`. Shapes are `circle` (`radius`), `ellipse` (`radius_x`,
+//! `radius_y`), `box` (`half_x`, `half_y`), `segment` (`half_length`) or `triangle`
+//! (`corners`, three points relative to `position`). `offset` and `turn` place a body relative
+//! to the transform of its entity listed in `entities`; both default to the world placement.
+//! When written, bodies are sorted by entity and body index and maps by key so files diff well.
+
+use crate::{shape::Shape, validate, Body, GridPhysics, Layout, Placed, Vector2};
+use pyo3::{exceptions::PyValueError, PyResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+type Point = [f32; 2];
+
+fn point(v: Vector2) -> Point {
+    [v.x, v.y]
+}
+
+fn vector(p: Point) -> Vector2 {
+    Vector2::new(p[0], p[1])
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum LayoutRecord {
+    Square,
+    Hex,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Config {
+    layout: LayoutRecord,
+    size: f32,
+    cell_size: f32,
+    #[serde(default)]
+    min_overlap: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_bodies: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ShapeRecord {
+    Circle { radius: f32 },
+    Ellipse { radius_x: f32, radius_y: f32 },
+    Box { half_x: f32, half_y: f32 },
+    Segment { half_length: f32 },
+    Triangle { corners: [Point; 3] },
+}
+
+#[derive(Serialize, Deserialize)]
+struct BodyRecord {
+    entity: usize,
+    body: usize,
+    #[serde(rename = "static")]
+    is_static: bool,
+    #[serde(default)]
+    kind: usize,
+    position: Point,
+    #[serde(default)]
+    angle: f32,
+    #[serde(default)]
+    offset: Option<Point>,
+    #[serde(default)]
+    turn: Option<f32>,
+    shape: ShapeRecord,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EntityRecord {
+    entity: usize,
+    position: Point,
+    angle: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GroupRecord {
+    entities: Vec<usize>,
+    active: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Scene {
+    version: u32,
+    config: Config,
+    #[serde(default)]
+    kinds: BTreeMap<String, usize>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    namespaces: BTreeMap<String, usize>,
+    #[serde(default)]
+    bodies: Vec<BodyRecord>,
+    #[serde(default)]
+    entities: Vec<EntityRecord>,
+    #[serde(default)]
+    groups: BTreeMap<String, GroupRecord>,
+    #[serde(default)]
+    sensors: Vec<usize>,
+}
+
+fn body_record(body: &Placed) -> BodyRecord {
+    let shape = match body.shape() {
+        Shape::Circle => ShapeRecord::Circle {
+            radius: body.radius,
+        },
+        Shape::Ellipse { radius_x, radius_y } => ShapeRecord::Ellipse { radius_x, radius_y },
+        Shape::Box { half_x, half_y } => ShapeRecord::Box { half_x, half_y },
+        Shape::Segment { half_length } => ShapeRecord::Segment { half_length },
+        Shape::Triangle { corners } => ShapeRecord::Triangle {
+            corners: corners.map(point),
+        },
+    };
+    BodyRecord {
+        entity: body.entity_index(),
+        body: body.body_index(),
+        is_static: body.is_static(),
+        kind: body.kind(),
+        position: point(body.pos),
+        angle: body.angle,
+        offset: Some(point(body.offset)),
+        turn: Some(body.turn),
+        shape,
+    }
+}
+
+fn body_of(record: BodyRecord) -> Placed {
+    let pos = vector(record.position);
+    let (shape, radius) = match record.shape {
+        ShapeRecord::Circle { radius } => (Shape::Circle, radius),
+        ShapeRecord::Ellipse { radius_x, radius_y } => (
+            Shape::Ellipse { radius_x, radius_y },
+            radius_x.max(radius_y),
+        ),
+        ShapeRecord::Box { half_x, half_y } => {
+            (Shape::Box { half_x, half_y }, half_x.hypot(half_y))
+        }
+        ShapeRecord::Segment { half_length } => (Shape::Segment { half_length }, half_length),
+        ShapeRecord::Triangle { corners } => {
+            let corners = corners.map(vector);
+            let radius = corners
+                .iter()
+                .map(|corner| corner.x.hypot(corner.y))
+                .fold(0.0, f32::max);
+            (Shape::Triangle { corners }, radius)
+        }
+    };
+    let mut body = Body::new(
+        record.entity,
+        record.body,
+        pos,
+        radius,
+        record.is_static,
+        record.kind,
+    );
+    body.angle = record.angle;
+    let mut placed = Placed {
+        body,
+        offset: record.offset.map_or(pos, vector),
+        turn: record.turn.unwrap_or(record.angle),
+    };
+    placed.set_shape(shape);
+    placed
+}
+
+fn scene(grid: &GridPhysics) -> Scene {
+    let mut bodies: Vec<&Placed> = grid
+        .dynamic_bodies
+        .values()
+        .chain(grid.static_bodies.values())
+        .flatten()
+        .collect();
+    bodies.sort_unstable_by_key(|body| (body.entity_index(), body.is_static(), body.body_index()));
+
+    let mut entities: Vec<EntityRecord> = grid
+        .transforms
+        .iter()
+        .map(|(entity, transform)| EntityRecord {
+            entity: *entity,
+            position: point(transform.position),
+            angle: transform.angle,
+        })
+        .collect();
+    entities.sort_unstable_by_key(|entity| entity.entity);
+
+    let mut sensors: Vec<usize> = grid.sensors.iter().copied().collect();
+    sensors.sort_unstable();
+
+    Scene {
+        version: 1,
+        config: Config {
+            layout: match grid.layout {
+                Layout::Square => LayoutRecord::Square,
+                Layout::Hex => LayoutRecord::Hex,
+            },
+            size: grid.size,
+            cell_size: grid.cell_size,
+            min_overlap: grid.min_overlap,
+            max_bodies: grid.max_bodies,
+        },
+        kinds: grid
+            .kinds
+            .entries()
+            .into_iter()
+            .map(|(name, id)| (name.to_string(), id))
+            .collect(),
+        namespaces: grid
+            .namespaces
+            .entries()
+            .into_iter()
+            .map(|(name, id)| (name.to_string(), id))
+            .collect(),
+        bodies: bodies.into_iter().map(body_record).collect(),
+        entities,
+        groups: grid
+            .groups
+            .iter()
+            .map(|(name, members)| {
+                let mut entities: Vec<usize> = members.iter().copied().collect();
+                entities.sort_unstable();
+                let active = !grid.inactive_groups.contains(name);
+                (name.clone(), GroupRecord { entities, active })
+            })
+            .collect(),
+        sensors,
+    }
+}
+
+fn write<T: Serialize>(value: &T, pretty: bool) -> PyResult<String> {
+    let json = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+    json.map_err(|error| PyValueError::new_err(error.to_string()))
+}
+
+fn read<'a, T: Deserialize<'a>>(json: &'a str) -> PyResult<T> {
+    serde_json::from_str(json)
+        .map_err(|error| PyValueError::new_err(format!("invalid scene: {error}")))
+}
+
+/// Bodies from a JSON list of body records, as in the `bodies` of a scene.
+pub fn bodies_from_json(json: &str) -> PyResult<Vec<Placed>> {
+    let records: Vec<BodyRecord> = read(json)?;
+    records
+        .into_iter()
+        .map(|record| {
+            validate::check_indices(record.entity, record.body.saturating_add(1), record.kind)?;
+            Ok(body_of(record))
+        })
+        .collect()
+}
+
+pub fn to_json(grid: &GridPhysics, pretty: bool) -> PyResult<String> {
+    write(&scene(grid), pretty)
+}
+
+pub fn from_json(json: &str) -> PyResult<GridPhysics> {
+    build(read(json)?)
+}
+
+/// A world is written as `{"version": 1, "layers": {name: scene}}`.
+#[derive(Serialize, Deserialize)]
+struct WorldScene {
+    version: u32,
+    layers: BTreeMap<String, Scene>,
+}
+
+pub fn world_to_json<'a>(
+    layers: impl Iterator<Item = (&'a str, &'a GridPhysics)>,
+    pretty: bool,
+) -> PyResult<String> {
+    let world = WorldScene {
+        version: 1,
+        layers: layers
+            .map(|(name, grid)| (name.to_string(), scene(grid)))
+            .collect(),
+    };
+    write(&world, pretty)
+}
+
+pub fn world_from_json(json: &str) -> PyResult<Vec<(String, GridPhysics)>> {
+    let world: WorldScene = read(json)?;
+    world
+        .layers
+        .into_iter()
+        .map(|(name, scene)| Ok((name, build(scene)?)))
+        .collect()
+}
+
+fn build(scene: Scene) -> PyResult<GridPhysics> {
+    if scene.version != 1 {
+        return Err(PyValueError::new_err(format!(
+            "unsupported scene version {}",
+            scene.version
+        )));
+    }
+
+    let layout = match scene.config.layout {
+        LayoutRecord::Square => Layout::Square,
+        LayoutRecord::Hex => Layout::Hex,
+    };
+    let mut grid = GridPhysics::try_with_layout(scene.config.size, scene.config.cell_size, layout)?;
+    grid.min_overlap = scene.config.min_overlap;
+    grid.max_bodies = scene.config.max_bodies;
+
+    let mut kinds: Vec<(String, usize)> = scene.kinds.into_iter().collect();
+    kinds.sort_unstable_by_key(|kind| kind.1);
+    for (name, id) in kinds {
+        if grid.kinds.register(&name) != id {
+            return Err(PyValueError::new_err(format!(
+                "kind ids must count up from 1, got {id} for {name:?}"
+            )));
+        }
+    }
+    let mut namespaces: Vec<(String, usize)> = scene.namespaces.into_iter().collect();
+    namespaces.sort_unstable_by_key(|namespace| namespace.1);
+    for (name, id) in namespaces {
+        if grid.namespaces.register(&name)? != id {
+            return Err(PyValueError::new_err(format!(
+                "namespace ids must count up from 1, got {id} for {name:?}"
+            )));
+        }
+    }
+
+    for entity in scene.entities {
+        let transform = crate::Transform {
+            position: vector(entity.position),
+            angle: entity.angle,
+        };
+        grid.transforms.insert(entity.entity, transform);
+    }
+    for record in scene.bodies {
+        grid.check_body_indices(record.entity, record.body.saturating_add(1), record.kind)?;
+        grid.push_body(body_of(record));
+    }
+    for (name, group) in scene.groups {
+        if !group.active {
+            grid.inactive_groups.insert(name.clone());
+        }
+        grid.groups
+            .insert(name, group.entities.into_iter().collect());
+    }
+    grid.update_frozen();
+    grid.sensors = scene.sensors.into_iter().collect();
+    Ok(grid)
+}
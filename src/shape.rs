@@ -0,0 +1,761 @@
+//! Body shapes other than circles and the narrow phase between them.
+//!
+//! Every shape is placed at its body position and rotated by the body angle, and the body
+//! radius is that of its bounding circle. Pairs involving a circle are tested against the exact
+//! closest point of the other shape; other convex pairs go through GJK on their support functions.
+
+use crate::{Body, Vector2};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Shape {
+    /// Circle of the body radius.
+    Circle,
+    /// Ellipse with semi-axes along the local x and y axes.
+    Ellipse { radius_x: f32, radius_y: f32 },
+    /// Rectangle with half extents along the local x and y axes.
+    Box { half_x: f32, half_y: f32 },
+    /// Line segment along the local x axis, as used by static chains.
+    Segment { half_length: f32 },
+    /// Triangle with corners relative to the body position, as used by static meshes.
+    Triangle { corners: [Vector2; 3] },
+}
+
+impl Shape {
+    /// Half extents of boxes, treating a segment as a box without thickness.
+    fn box_halves(&self) -> Option<(f32, f32)> {
+        match *self {
+            Shape::Box { half_x, half_y } => Some((half_x, half_y)),
+            Shape::Segment { half_length } => Some((half_length, 0.0)),
+            _ => None,
+        }
+    }
+}
+
+/// Shape tags in the low bits of `Body::flags`.
+const CIRCLE: u8 = 0;
+const ELLIPSE: u8 = 1;
+const BOX: u8 = 2;
+const SEGMENT: u8 = 3;
+const TRIANGLE: u8 = 4;
+/// Flag bit marking static bodies, above the shape tag.
+pub(crate) const STATIC: u8 = 1 << 7;
+
+fn dot(a: Vector2, b: Vector2) -> f32 {
+    a.x * b.x + a.y * b.y
+}
+
+fn sub(a: Vector2, b: Vector2) -> Vector2 {
+    Vector2::new(a.x - b.x, a.y - b.y)
+}
+
+fn length(v: Vector2) -> f32 {
+    dot(v, v).sqrt()
+}
+
+fn cross(a: Vector2, b: Vector2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn segment_distance(p: Vector2, a: Vector2, b: Vector2) -> f32 {
+    let (ab, ap) = (sub(b, a), sub(p, a));
+    let t = (dot(ap, ab) / dot(ab, ab).max(f32::EPSILON)).clamp(0.0, 1.0);
+    length(Vector2::new(ap.x - ab.x * t, ap.y - ab.y * t))
+}
+
+fn bounds_touch(a: (f32, f32, f32, f32), b: (f32, f32, f32, f32)) -> bool {
+    a.0 <= b.1 && b.0 <= a.1 && a.2 <= b.3 && b.2 <= a.3
+}
+
+/// Consecutive corners of a polygon, closing it.
+fn edges(corners: &[Vector2]) -> impl Iterator<Item = (Vector2, Vector2)> + '_ {
+    corners
+        .iter()
+        .copied()
+        .zip(corners.iter().copied().cycle().skip(1))
+}
+
+/// Whether `p` is inside or on the convex polygon through `corners`, in either winding.
+fn inside_polygon(p: Vector2, corners: &[Vector2]) -> bool {
+    let sides: Vec<f32> = edges(corners)
+        .map(|(s, e)| cross(sub(e, s), sub(p, s)))
+        .collect();
+    sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0)
+}
+
+/// Smallest overlap of two boxes over the four separating axes of SAT, negative when an axis
+/// separates them. For intersecting boxes it is their penetration depth.
+fn box_overlap(a: &Body, b: &Body, (ax, ay): (f32, f32), (bx, by): (f32, f32)) -> f32 {
+    let axes = |body: &Body| {
+        let (sin, cos) = body.angle.sin_cos();
+        [Vector2::new(cos, sin), Vector2::new(-sin, cos)]
+    };
+    let (a_axes, b_axes) = (axes(a), axes(b));
+    let centers = sub(b.pos, a.pos);
+    a_axes
+        .iter()
+        .chain(b_axes.iter())
+        .map(|axis| {
+            let a_reach = ax * dot(*axis, a_axes[0]).abs() + ay * dot(*axis, a_axes[1]).abs();
+            let b_reach = bx * dot(*axis, b_axes[0]).abs() + by * dot(*axis, b_axes[1]).abs();
+            a_reach + b_reach - dot(centers, *axis).abs()
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// Closest point to `p` on the ellipse with semi-axes `a` and `b` centered at the origin,
+/// using a few iterations of the evolute approximation, which converges for any eccentricity.
+fn closest_on_ellipse(p: Vector2, a: f32, b: f32) -> Vector2 {
+    let (px, py) = (p.x.abs(), p.y.abs());
+    let (mut tx, mut ty) = (
+        std::f32::consts::FRAC_1_SQRT_2,
+        std::f32::consts::FRAC_1_SQRT_2,
+    );
+    for _ in 0..4 {
+        let (x, y) = (a * tx, b * ty);
+        let ex = (a * a - b * b) * tx.powi(3) / a;
+        let ey = (b * b - a * a) * ty.powi(3) / b;
+        let (rx, ry) = (x - ex, y - ey);
+        let (qx, qy) = (px - ex, py - ey);
+        let r = rx.hypot(ry);
+        let q = qx.hypot(qy).max(f32::EPSILON);
+        tx = ((qx * r / q + ex) / a).clamp(0.0, 1.0);
+        ty = ((qy * r / q + ey) / b).clamp(0.0, 1.0);
+        let t = tx.hypot(ty);
+        tx /= t;
+        ty /= t;
+    }
+    Vector2::new((a * tx).copysign(p.x), (b * ty).copysign(p.y))
+}
+
+impl Body {
+    /// The shape, unpacked. A triangle is stored centered on the position and turned so its
+    /// farthest corner lies on the local x axis at the bounding radius, which leaves only
+    /// the second corner to store, as the third is the negated sum of the other two.
+    pub(crate) fn shape(&self) -> Shape {
+        let [x, y] = self.params;
+        match self.flags & !STATIC {
+            ELLIPSE => Shape::Ellipse {
+                radius_x: x,
+                radius_y: y,
+            },
+            BOX => Shape::Box {
+                half_x: x,
+                half_y: y,
+            },
+            SEGMENT => Shape::Segment { half_length: x },
+            TRIANGLE => Shape::Triangle {
+                corners: [
+                    Vector2::new(self.radius, 0.0),
+                    Vector2::new(x, y),
+                    Vector2::new(-self.radius - x, -y),
+                ],
+            },
+            _ => Shape::Circle,
+        }
+    }
+
+    /// Packs `shape`, returning how far the position moved in the body's frame and the
+    /// angle the body turned by to store it. Only triangles move, onto their centroid, and
+    /// turn, and they also take their bounding radius from their corners.
+    pub(crate) fn set_shape(&mut self, shape: Shape) -> (Vector2, f32) {
+        let (tag, params) = match shape {
+            Shape::Circle => (CIRCLE, [0.0; 2]),
+            Shape::Ellipse { radius_x, radius_y } => (ELLIPSE, [radius_x, radius_y]),
+            Shape::Box { half_x, half_y } => (BOX, [half_x, half_y]),
+            Shape::Segment { half_length } => (SEGMENT, [half_length, 0.0]),
+            Shape::Triangle { corners } => return self.set_triangle(corners),
+        };
+        self.flags = self.flags & STATIC | tag;
+        self.params = params;
+        (Vector2::new(0.0, 0.0), 0.0)
+    }
+
+    fn set_triangle(&mut self, corners: [Vector2; 3]) -> (Vector2, f32) {
+        let centroid = Vector2::new(
+            corners.iter().map(|corner| corner.x).sum::<f32>() / 3.0,
+            corners.iter().map(|corner| corner.y).sum::<f32>() / 3.0,
+        );
+        let corners = corners.map(|corner| sub(corner, centroid));
+        let lengths = corners.map(length);
+        let farthest = lengths.into_iter().fold(0.0, f32::max);
+        // With some slack, so a triangle already stored this way isn't turned again
+        let first = (0..3)
+            .find(|&i| lengths[i] >= farthest * (1.0 - 1e-5))
+            .unwrap_or(0);
+        let turn = corners[first].y.atan2(corners[first].x);
+        let (sin, cos) = turn.sin_cos();
+        let second = corners[(first + 1) % 3];
+        self.pos = self.world_point(centroid);
+        self.radius = lengths[first];
+        self.angle += turn;
+        self.flags = self.flags & STATIC | TRIANGLE;
+        self.params = [
+            second.x * cos + second.y * sin,
+            second.y * cos - second.x * sin,
+        ];
+        (centroid, turn)
+    }
+
+    fn local_point(&self, p: Vector2) -> Vector2 {
+        let (sin, cos) = self.angle.sin_cos();
+        let d = sub(p, self.pos);
+        Vector2::new(d.x * cos + d.y * sin, d.y * cos - d.x * sin)
+    }
+
+    fn world_point(&self, p: Vector2) -> Vector2 {
+        let (sin, cos) = self.angle.sin_cos();
+        Vector2::new(
+            self.pos.x + p.x * cos - p.y * sin,
+            self.pos.y + p.x * sin + p.y * cos,
+        )
+    }
+
+    fn rotate_to_local(&self, d: Vector2) -> Vector2 {
+        let (sin, cos) = self.angle.sin_cos();
+        Vector2::new(d.x * cos + d.y * sin, d.y * cos - d.x * sin)
+    }
+
+    /// The body with its shape scaled about its position so its bounding radius is `radius`.
+    pub(crate) fn resized(mut self, radius: f32) -> Self {
+        let factor = if self.radius > 0.0 {
+            radius / self.radius
+        } else {
+            0.0
+        };
+        // Every shape parameter is a length, triangles included as they scale with the radius
+        self.params = self.params.map(|param| param * factor);
+        self.radius = radius;
+        self
+    }
+
+    /// World positions of the corners of a triangle.
+    pub(crate) fn corners(&self) -> Option<[Vector2; 3]> {
+        match self.shape() {
+            Shape::Triangle { corners } => Some(corners.map(|corner| self.world_point(corner))),
+            _ => None,
+        }
+    }
+
+    /// `(min_x, max_x, min_y, max_y)` of the axis-aligned box around the shape.
+    pub(crate) fn aabb(&self) -> (f32, f32, f32, f32) {
+        if let Some(corners) = self.corners() {
+            let xs = corners.map(|corner| corner.x);
+            let ys = corners.map(|corner| corner.y);
+            return (
+                xs.into_iter().fold(f32::INFINITY, f32::min),
+                xs.into_iter().fold(f32::NEG_INFINITY, f32::max),
+                ys.into_iter().fold(f32::INFINITY, f32::min),
+                ys.into_iter().fold(f32::NEG_INFINITY, f32::max),
+            );
+        }
+        let (half_width, half_height) = self.half_extents();
+        (
+            self.pos.x - half_width,
+            self.pos.x + half_width,
+            self.pos.y - half_height,
+            self.pos.y + half_height,
+        )
+    }
+
+    /// Half width and height of the axis-aligned box around shapes symmetric about their
+    /// position.
+    fn half_extents(&self) -> (f32, f32) {
+        match self.shape() {
+            Shape::Circle => (self.radius, self.radius),
+            Shape::Ellipse { radius_x, radius_y } => {
+                let (sin, cos) = self.angle.sin_cos();
+                (
+                    (radius_x * radius_x * cos * cos + radius_y * radius_y * sin * sin).sqrt(),
+                    (radius_x * radius_x * sin * sin + radius_y * radius_y * cos * cos).sqrt(),
+                )
+            }
+            Shape::Box { .. } | Shape::Segment { .. } => {
+                let (half_x, half_y) = self.shape().box_halves().unwrap();
+                let (sin, cos) = self.angle.sin_cos();
+                (
+                    half_x * cos.abs() + half_y * sin.abs(),
+                    half_x * sin.abs() + half_y * cos.abs(),
+                )
+            }
+            Shape::Triangle { .. } => (self.radius, self.radius),
+        }
+    }
+
+    /// Point of the shape furthest along `d`.
+    fn support(&self, d: Vector2) -> Vector2 {
+        match self.shape() {
+            Shape::Circle => {
+                let l = length(d).max(f32::EPSILON);
+                Vector2::new(
+                    self.pos.x + d.x * self.radius / l,
+                    self.pos.y + d.y * self.radius / l,
+                )
+            }
+            Shape::Ellipse { radius_x, radius_y } => {
+                let local = self.rotate_to_local(d);
+                let (sx, sy) = (radius_x * radius_x * local.x, radius_y * radius_y * local.y);
+                let l = (sx * local.x + sy * local.y).sqrt().max(f32::EPSILON);
+                self.world_point(Vector2::new(sx / l, sy / l))
+            }
+            Shape::Box { .. } | Shape::Segment { .. } => {
+                let (half_x, half_y) = self.shape().box_halves().unwrap();
+                let local = self.rotate_to_local(d);
+                self.world_point(Vector2::new(
+                    half_x.copysign(local.x),
+                    half_y.copysign(local.y),
+                ))
+            }
+            Shape::Triangle { .. } => self
+                .corners()
+                .unwrap()
+                .into_iter()
+                .max_by(|a, b| dot(*a, d).total_cmp(&dot(*b, d)))
+                .unwrap(),
+        }
+    }
+
+    /// Distance from `p` to the surface of the shape, negative inside it.
+    pub(crate) fn surface_distance(&self, p: Vector2) -> f32 {
+        match self.shape() {
+            Shape::Circle => length(sub(p, self.pos)) - self.radius,
+            Shape::Ellipse { radius_x, radius_y } => {
+                let local = self.local_point(p);
+                let closest = closest_on_ellipse(local, radius_x, radius_y);
+                let gap = length(sub(local, closest));
+                let inside = (local.x / radius_x).powi(2) + (local.y / radius_y).powi(2) <= 1.0;
+                if inside {
+                    -gap
+                } else {
+                    gap
+                }
+            }
+            Shape::Box { .. } | Shape::Segment { .. } => {
+                let (half_x, half_y) = self.shape().box_halves().unwrap();
+                let local = self.local_point(p);
+                let (dx, dy) = (local.x.abs() - half_x, local.y.abs() - half_y);
+                length(Vector2::new(dx.max(0.0), dy.max(0.0))) + dx.max(dy).min(0.0)
+            }
+            Shape::Triangle { .. } => {
+                let [a, b, c] = self.corners().unwrap();
+                let gap = segment_distance(p, a, b)
+                    .min(segment_distance(p, b, c))
+                    .min(segment_distance(p, c, a));
+                let sides = [(a, b), (b, c), (c, a)].map(|(s, e)| cross(sub(e, s), sub(p, s)));
+                let inside =
+                    sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0);
+                if inside {
+                    -gap
+                } else {
+                    gap
+                }
+            }
+        }
+    }
+
+    /// Whether `other` lies entirely inside this shape. Exact for circles and polygonal
+    /// shapes; ellipses are tested by their bounding circle, so containment can be missed but
+    /// is never reported wrongly.
+    pub(crate) fn contains(&self, other: &Body) -> bool {
+        match other.outline() {
+            // Every shape is convex, so holding the corners means holding the polygon
+            Some(corners) => corners
+                .into_iter()
+                .all(|corner| self.surface_distance(corner) <= 0.0),
+            None => self.surface_distance(other.pos) <= -other.radius,
+        }
+    }
+
+    /// World positions of the corners of polygonal shapes.
+    fn outline(&self) -> Option<Vec<Vector2>> {
+        match self.shape() {
+            Shape::Triangle { .. } => self.corners().map(|corners| corners.to_vec()),
+            Shape::Box { .. } | Shape::Segment { .. } => {
+                let (half_x, half_y) = self.shape().box_halves().unwrap();
+                let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+                Some(
+                    corners
+                        .map(|(x, y)| self.world_point(Vector2::new(x * half_x, y * half_y)))
+                        .to_vec(),
+                )
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the shapes of two bodies intersect.
+    pub(crate) fn shapes_touch(&self, other: &Body) -> bool {
+        // Rounding in the exact tests can accept a hairline gap, which binning by bounds
+        // would never bring together
+        if !bounds_touch(self.aabb(), other.aabb()) {
+            return false;
+        }
+        match (self.shape(), other.shape()) {
+            (Shape::Circle, Shape::Circle) => {
+                let d = sub(self.pos, other.pos);
+                dot(d, d) <= (self.radius + other.radius).powi(2)
+            }
+            (Shape::Circle, _) => other.surface_distance(self.pos) <= self.radius,
+            (_, Shape::Circle) => self.surface_distance(other.pos) <= other.radius,
+            _ => match (self.shape().box_halves(), other.shape().box_halves()) {
+                (Some(a), Some(b)) => box_overlap(self, other, a, b) >= 0.0,
+                _ => gjk(|d| self.support(d), |d| other.support(d)),
+            },
+        }
+    }
+
+    /// Penetration depth, negative when apart. Exact when either body is a circle or both are
+    /// boxes or segments, otherwise measured between the bounding circles.
+    pub(crate) fn penetration(&self, other: &Body) -> f32 {
+        match (self.shape(), other.shape()) {
+            (Shape::Circle, Shape::Circle) => {
+                self.radius + other.radius - length(sub(self.pos, other.pos))
+            }
+            (Shape::Circle, _) => self.radius - other.surface_distance(self.pos),
+            (_, Shape::Circle) => other.radius - self.surface_distance(other.pos),
+            _ => match (self.shape().box_halves(), other.shape().box_halves()) {
+                (Some(a), Some(b)) => box_overlap(self, other, a, b),
+                _ => self.radius + other.radius - length(sub(self.pos, other.pos)),
+            },
+        }
+    }
+
+    /// Whether the shape overlaps the axis-aligned box from `min` to `max`.
+    pub(crate) fn overlaps_box(&self, min: Vector2, max: Vector2) -> bool {
+        match self.shape() {
+            Shape::Circle => {
+                // Not `clamp`, which panics on NaN or inverted bounds
+                let dx = self.pos.x - self.pos.x.max(min.x).min(max.x);
+                let dy = self.pos.y - self.pos.y.max(min.y).min(max.y);
+                dx * dx + dy * dy <= self.radius * self.radius
+            }
+            _ => gjk(
+                |d| self.support(d),
+                |d| {
+                    Vector2::new(
+                        if d.x >= 0.0 { max.x } else { min.x },
+                        if d.y >= 0.0 { max.y } else { min.y },
+                    )
+                },
+            ),
+        }
+    }
+
+    /// Whether the shape overlaps the convex polygon through `corners`, in either winding.
+    pub(crate) fn overlaps_polygon(&self, corners: &[Vector2]) -> bool {
+        match self.shape() {
+            Shape::Circle => {
+                inside_polygon(self.pos, corners)
+                    || edges(corners).any(|(s, e)| segment_distance(self.pos, s, e) <= self.radius)
+            }
+            _ => gjk(
+                |d| self.support(d),
+                |d| {
+                    *corners
+                        .iter()
+                        .max_by(|a, b| dot(**a, d).total_cmp(&dot(**b, d)))
+                        .unwrap()
+                },
+            ),
+        }
+    }
+
+    /// Whether the shape lies entirely inside the convex polygon through `corners`. Ellipses
+    /// are tested by their bounding circle, like `contains`.
+    pub(crate) fn within_polygon(&self, corners: &[Vector2]) -> bool {
+        match self.outline() {
+            Some(outline) => outline
+                .into_iter()
+                .all(|corner| inside_polygon(corner, corners)),
+            None => {
+                inside_polygon(self.pos, corners)
+                    && edges(corners).all(|(s, e)| segment_distance(self.pos, s, e) >= self.radius)
+            }
+        }
+    }
+
+    /// Distance along the ray `origin + t * direction` (`direction` normalized) at which it
+    /// first touches the shape, zero when `origin` is inside it.
+    pub(crate) fn ray_hit(&self, origin: Vector2, direction: Vector2) -> Option<f32> {
+        match self.shape() {
+            Shape::Circle => crate::ray::circle_hit(origin, direction, self.pos, self.radius),
+            Shape::Ellipse { radius_x, radius_y } => {
+                // Scaling the ellipse to a unit circle keeps `t` unchanged
+                let o = self.local_point(origin);
+                let d = self.rotate_to_local(direction);
+                let o = Vector2::new(o.x / radius_x, o.y / radius_y);
+                let d = Vector2::new(d.x / radius_x, d.y / radius_y);
+                let (a, b, c) = (dot(d, d), dot(o, d), dot(o, o) - 1.0);
+                if c <= 0.0 {
+                    return Some(0.0);
+                }
+                let discriminant = b * b - a * c;
+                if discriminant < 0.0 || b > 0.0 {
+                    return None;
+                }
+                Some((-b - discriminant.sqrt()) / a)
+            }
+            Shape::Box { .. } | Shape::Segment { .. } => {
+                let (half_x, half_y) = self.shape().box_halves().unwrap();
+                let o = self.local_point(origin);
+                let d = self.rotate_to_local(direction);
+                let mut enter = 0.0_f32;
+                let mut exit = f32::INFINITY;
+                for (start, delta, half) in [(o.x, d.x, half_x), (o.y, d.y, half_y)] {
+                    if delta == 0.0 {
+                        if start.abs() > half {
+                            return None;
+                        }
+                        continue;
+                    }
+                    let (a, b) = ((-half - start) / delta, (half - start) / delta);
+                    enter = enter.max(a.min(b));
+                    exit = exit.min(a.max(b));
+                }
+                (enter <= exit).then_some(enter)
+            }
+            Shape::Triangle { .. } => {
+                if self.surface_distance(origin) <= 0.0 {
+                    return Some(0.0);
+                }
+                let [a, b, c] = self.corners().unwrap();
+                [(a, b), (b, c), (c, a)]
+                    .into_iter()
+                    .filter_map(|(start, end)| {
+                        let edge = sub(end, start);
+                        let denominator = cross(direction, edge);
+                        if denominator == 0.0 {
+                            return None;
+                        }
+                        let to_start = sub(start, origin);
+                        let t = cross(to_start, edge) / denominator;
+                        let s = cross(to_start, direction) / denominator;
+                        (t >= 0.0 && (0.0..=1.0).contains(&s)).then_some(t)
+                    })
+                    .reduce(f32::min)
+            }
+        }
+    }
+}
+
+/// Boolean GJK: whether the convex shapes given by their support functions intersect.
+fn gjk(a: impl Fn(Vector2) -> Vector2, b: impl Fn(Vector2) -> Vector2) -> bool {
+    let support = |d: Vector2| sub(a(d), b(Vector2::new(-d.x, -d.y)));
+    // (a x b) x c, the part of b perpendicular to a pointing towards c. In f64, since the
+    // terms cancel for long thin differences and f32 would tilt the direction enough to
+    // misjudge a near miss
+    let triple = |a: Vector2, b: Vector2, c: Vector2| {
+        let (a, b, c) = (
+            [a.x as f64, a.y as f64],
+            [b.x as f64, b.y as f64],
+            [c.x as f64, c.y as f64],
+        );
+        let (ac, bc) = (a[0] * c[0] + a[1] * c[1], b[0] * c[0] + b[1] * c[1]);
+        Vector2::new(
+            (b[0] * ac - a[0] * bc) as f32,
+            (b[1] * ac - a[1] * bc) as f32,
+        )
+    };
+
+    let first = support(Vector2::new(1.0, 0.0));
+    let mut simplex = vec![first];
+    let mut d = Vector2::new(-first.x, -first.y);
+    for _ in 0..32 {
+        if dot(d, d) <= f32::EPSILON {
+            return true;
+        }
+        let p = support(d);
+        if dot(p, d) < 0.0 {
+            return false;
+        }
+        simplex.push(p);
+
+        let a = *simplex.last().unwrap();
+        let ao = Vector2::new(-a.x, -a.y);
+        if simplex.len() == 2 {
+            let ab = sub(simplex[0], a);
+            d = triple(ab, ao, ab);
+            if dot(d, d) <= f32::EPSILON {
+                // The origin lies on the segment
+                return dot(ab, ao) >= 0.0 && dot(ab, ao) <= dot(ab, ab);
+            }
+        } else {
+            let (b, c) = (simplex[1], simplex[0]);
+            let (ab, ac) = (sub(b, a), sub(c, a));
+            let ab_perp = triple(ac, ab, ab);
+            let ac_perp = triple(ab, ac, ac);
+            if dot(ab_perp, ao) > 0.0 {
+                simplex.remove(0);
+                d = ab_perp;
+            } else if dot(ac_perp, ao) > 0.0 {
+                simplex.remove(1);
+                d = ac_perp;
+            } else {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_triangle_narrow_phase() {
+        let mut ramp = Body::new(0, 0, Vector2::new(0.0, 0.0), 0.0, true, 0);
+        ramp.set_shape(Shape::Triangle {
+            corners: [
+                Vector2::new(0.0, 0.0),
+                Vector2::new(10.0, 0.0),
+                Vector2::new(10.0, 5.0),
+            ],
+        });
+        let circle = |x: f32, y: f32| Body::new(1, 0, Vector2::new(x, y), 1.0, false, 0);
+
+        let (min_x, max_x, min_y, max_y) = ramp.aabb();
+        // Stored turned, so the corners come back within rounding
+        for (got, expected) in [(min_x, 0.0), (max_x, 10.0), (min_y, 0.0), (max_y, 5.0)] {
+            assert!((got - expected).abs() < 1e-5);
+        }
+        assert!(circle(8.0, 2.0).shapes_touch(&ramp));
+        assert!(circle(10.5, 5.5).shapes_touch(&ramp));
+        assert!(!circle(2.0, 3.0).shapes_touch(&ramp));
+        assert!(ramp.surface_distance(Vector2::new(8.0, 1.0)) < 0.0);
+
+        let t = ramp
+            .ray_hit(Vector2::new(5.0, 10.0), Vector2::new(0.0, -1.0))
+            .unwrap();
+        assert!((t - 7.5).abs() < 1e-5);
+        assert!(ramp
+            .ray_hit(Vector2::new(-1.0, 1.0), Vector2::new(-1.0, 0.0))
+            .is_none());
+    }
+
+    #[test]
+    fn check_packed_shapes() {
+        let mut wall = Body::new(0, 0, Vector2::new(3.0, 4.0), 1.0, true, 0);
+        wall.set_shape(Shape::Box {
+            half_x: 2.0,
+            half_y: 0.5,
+        });
+        assert!(wall.is_static());
+        assert_eq!(
+            wall.shape(),
+            Shape::Box {
+                half_x: 2.0,
+                half_y: 0.5
+            }
+        );
+
+        let mut tile = Body::new(0, 0, Vector2::new(5.0, 5.0), 0.0, false, 0);
+        let (shift, turn) = tile.set_shape(Shape::Triangle {
+            corners: [
+                Vector2::new(0.0, 0.0),
+                Vector2::new(3.0, 0.0),
+                Vector2::new(0.0, 3.0),
+            ],
+        });
+        assert_eq!(
+            (shift, tile.pos),
+            (Vector2::new(1.0, 1.0), Vector2::new(6.0, 6.0))
+        );
+        assert!(!tile.is_static() && tile.angle == turn);
+        // A triangle stored once is stored again as it is, as deltas and scenes do
+        let (pos, angle, radius, shape) = (tile.pos, tile.angle, tile.radius, tile.shape());
+        assert_eq!(tile.set_shape(shape), (Vector2::new(0.0, 0.0), 0.0));
+        assert_eq!((tile.pos, tile.angle, tile.radius), (pos, angle, radius));
+        assert_eq!(tile.shape(), shape);
+    }
+
+    #[test]
+    fn check_ellipse_narrow_phase() {
+        let ellipse = |x: f32, y: f32, angle: f32| {
+            let mut body = Body::new(0, 0, Vector2::new(x, y), 10.0, false, 0);
+            body.set_shape(Shape::Ellipse {
+                radius_x: 10.0,
+                radius_y: 2.0,
+            });
+            body.angle = angle;
+            body
+        };
+        let circle = |x: f32, y: f32| Body::new(1, 0, Vector2::new(x, y), 1.0, false, 0);
+
+        let car = ellipse(0.0, 0.0, 0.0);
+        assert!(car.shapes_touch(&circle(10.5, 0.0)));
+        assert!(!car.shapes_touch(&circle(0.0, 3.5)));
+        assert!(car.shapes_touch(&circle(0.0, 2.5)));
+        // The bounding circle would report a hit here
+        assert!(!car.shapes_touch(&circle(7.0, 6.0)));
+        assert!((car.surface_distance(Vector2::new(0.0, 5.0)) - 3.0).abs() < 1e-3);
+
+        let turned = ellipse(0.0, 0.0, std::f32::consts::FRAC_PI_2);
+        assert!(turned.shapes_touch(&circle(0.0, 10.5)));
+        assert!(!turned.shapes_touch(&circle(10.5, 0.0)));
+        let (hx, hy) = turned.half_extents();
+        assert!((hx - 2.0).abs() < 1e-4 && (hy - 10.0).abs() < 1e-4);
+
+        // Crossed ellipses intersect, parallel ones side by side don't
+        assert!(car.shapes_touch(&ellipse(0.0, 5.0, std::f32::consts::FRAC_PI_2)));
+        assert!(!car.shapes_touch(&ellipse(0.0, 5.0, 0.0)));
+
+        let t = car
+            .ray_hit(Vector2::new(-20.0, 0.0), Vector2::new(1.0, 0.0))
+            .unwrap();
+        assert!((t - 10.0).abs() < 1e-4);
+        assert!(car
+            .ray_hit(Vector2::new(-20.0, 3.0), Vector2::new(1.0, 0.0))
+            .is_none());
+        assert!(car.overlaps_box(Vector2::new(8.0, -1.0), Vector2::new(9.0, 1.0)));
+        assert!(!car.overlaps_box(Vector2::new(8.0, 1.5), Vector2::new(9.0, 3.0)));
+    }
+
+    #[test]
+    fn check_box_narrow_phase() {
+        let obb = |x: f32, y: f32, angle: f32| {
+            let mut body = Body::new(0, 0, Vector2::new(x, y), 0.0, false, 0);
+            body.set_shape(Shape::Box {
+                half_x: 4.0,
+                half_y: 1.0,
+            });
+            body.radius = 17.0_f32.sqrt();
+            body.angle = angle;
+            body
+        };
+        let circle = |x: f32, y: f32| Body::new(1, 0, Vector2::new(x, y), 1.0, false, 0);
+        let quarter = std::f32::consts::FRAC_PI_2;
+
+        let sword = obb(0.0, 0.0, 0.0);
+        assert!(sword.shapes_touch(&circle(4.5, 0.0)));
+        assert!(!sword.shapes_touch(&circle(4.8, 1.8)));
+        assert!((sword.surface_distance(Vector2::new(0.0, 3.0)) - 2.0).abs() < 1e-5);
+        assert!((sword.surface_distance(Vector2::new(0.0, 0.5)) + 0.5).abs() < 1e-5);
+
+        // A crossing box touches, one turned past it along its diagonal doesn't
+        assert!(sword.shapes_touch(&obb(0.0, 4.0, quarter)));
+        assert!(!sword.shapes_touch(&obb(5.5, 5.5, std::f32::consts::FRAC_PI_4)));
+        assert!((sword.penetration(&obb(7.0, 0.0, 0.0)) - 1.0).abs() < 1e-5);
+        let (hx, hy) = obb(0.0, 0.0, quarter).half_extents();
+        assert!((hx - 1.0).abs() < 1e-5 && (hy - 4.0).abs() < 1e-5);
+
+        let t = sword
+            .ray_hit(Vector2::new(0.0, -10.0), Vector2::new(0.0, 1.0))
+            .unwrap();
+        assert!((t - 9.0).abs() < 1e-5);
+        let mut ground = obb(0.0, -3.0, 0.0);
+        ground.set_shape(Shape::Segment { half_length: 10.0 });
+        assert!(ground.shapes_touch(&circle(9.0, -2.0)));
+        assert!(!ground.shapes_touch(&circle(11.0, -2.0)));
+        assert!(!sword.shapes_touch(&ground));
+        assert!(obb(0.0, -1.5, 0.3).shapes_touch(&ground));
+        assert!(sword.shapes_touch(&{
+            let mut ellipse = circle(0.0, 2.5);
+            ellipse.set_shape(Shape::Ellipse {
+                radius_x: 3.0,
+                radius_y: 2.0,
+            });
+            ellipse.radius = 3.0;
+            ellipse
+        }));
+    }
+}
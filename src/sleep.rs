@@ -0,0 +1,76 @@
+//! Putting dynamic entities that have stopped moving to sleep, so whole-world collision
+//! queries skip them as sources until something wakes them.
+
+use crate::{targets::Targets, Body, GridPhysics, Vector2};
+use std::{collections::HashSet, ops::ControlFlow};
+
+impl GridPhysics {
+    /// Dynamic bodies of awake entities, the sources of whole-world collision queries.
+    pub(crate) fn awake_bodies(&self) -> impl Iterator<Item = &Body> {
+        self.dynamic_bodies
+            .iter()
+            .filter(|(entity_index, _)| !self.asleep.contains(entity_index))
+            .flat_map(|(_, bodies)| bodies)
+            .map(|placed| &placed.body)
+    }
+
+    pub(crate) fn wake_entity(&mut self, entity_index: usize) {
+        if self.asleep.remove(&entity_index) {
+            self.stillness.remove(&entity_index);
+        }
+    }
+
+    /// Wakes sleepers touched by an awake dynamic body, then counts how many steps each
+    /// awake dynamic entity has moved less than `sleep_threshold` in a row. Entities still
+    /// for `sleep_steps` steps fall asleep and lose their velocity.
+    pub(crate) fn update_sleep(&mut self) {
+        let Some(threshold) = self.sleep_threshold else {
+            return;
+        };
+        if !self.asleep.is_empty() {
+            let mut touched = HashSet::new();
+            self.visit_collisions(self.awake_bodies(), Targets::Dynamic, |_, other| {
+                if self.asleep.contains(&other.entity_index()) {
+                    touched.insert(other.entity_index());
+                }
+                ControlFlow::Continue(())
+            });
+            for entity_index in touched {
+                self.wake_entity(entity_index);
+            }
+        }
+
+        let mut entities: Vec<usize> = self
+            .dynamic_bodies
+            .keys()
+            .filter(|entity_index| !self.asleep.contains(entity_index))
+            .copied()
+            .collect();
+        entities.sort_unstable();
+        for entity_index in entities {
+            let position = self
+                .transforms
+                .get(&entity_index)
+                .copied()
+                .unwrap_or_default()
+                .position;
+            let (last, steps) = self
+                .stillness
+                .get(&entity_index)
+                .copied()
+                .unwrap_or((Vector2::new(f32::NAN, f32::NAN), 0));
+            let moved = (position.x - last.x).hypot(position.y - last.y);
+            // A first sighting compares against NaN and starts the count at zero
+            let steps = if moved < threshold { steps + 1 } else { 0 };
+            if steps < self.sleep_steps.max(1) {
+                self.stillness.insert(entity_index, (position, steps));
+                continue;
+            }
+            self.stillness.remove(&entity_index);
+            if let Some(bounds) = self.entity_bounds.get(&entity_index).copied() {
+                self.store_velocity(entity_index, bounds, Vector2::new(0.0, 0.0));
+            }
+            self.asleep.insert(entity_index);
+        }
+    }
+}
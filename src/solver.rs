@@ -0,0 +1,215 @@
+//! Pushing overlapping bodies apart, splitting each correction between the two entities by
+//! their masses, and stopping them from moving further into each other.
+
+use crate::{targets::Targets, Body, GridPhysics, Vector2};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::ControlFlow,
+};
+
+/// An overlap between a dynamic body and another body, found by one push-out pass.
+struct Contact {
+    entity_index: usize,
+    /// The other entity, unless it is static.
+    other: Option<usize>,
+    /// Unit direction from the other body towards this one.
+    normal: Vector2,
+    depth: f32,
+    inverse_masses: (f32, f32),
+    friction: f32,
+}
+
+/// Body pair in a fixed order, so a contact found from both sides is resolved once.
+type PairKey = ((usize, usize), (bool, usize, usize));
+
+fn pair_key(body: &Body, other: &Body) -> PairKey {
+    let a = (body.entity_index(), body.body_index());
+    let b = (other.entity_index(), other.body_index());
+    if other.is_static() || a <= b {
+        (a, (other.is_static(), b.0, b.1))
+    } else {
+        (b, (body.is_static(), a.0, a.1))
+    }
+}
+
+impl GridPhysics {
+    /// Mass of an entity, one unless set with `set_mass`.
+    pub(crate) fn mass_of(&self, entity_index: usize) -> f32 {
+        self.masses.get(&entity_index).copied().unwrap_or(1.0)
+    }
+
+    /// Friction coefficient of a body, zero unless set with `set_friction`.
+    pub(crate) fn friction_of(&self, entity_index: usize, body_index: usize) -> f32 {
+        self.frictions
+            .get(&(entity_index, body_index))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Inverse mass of the entity owning a body, zero for statics so they never move.
+    fn inverse_mass(&self, body: &Body) -> f32 {
+        if body.is_static() {
+            0.0
+        } else {
+            1.0 / self.mass_of(body.entity_index())
+        }
+    }
+
+    /// Runs up to `iterations` passes moving overlapping entities apart along the line
+    /// between their body centers, the lighter one taking the larger share of the
+    /// correction. Velocity moving the two further into each other is removed, and friction
+    /// slows their sliding along each other by up to the combined coefficient times the
+    /// removed speed. Sensors and frozen entities are left alone, and bodies of one entity
+    /// never push each other. An entity with several contacts moves by their average.
+    /// Returns how many contacts were corrected over all passes.
+    pub(crate) fn push_out(&mut self, iterations: usize) -> usize {
+        let mut resolved = 0;
+        for _ in 0..iterations {
+            let contacts = self.overlapping_contacts();
+            if contacts.is_empty() {
+                break;
+            }
+            resolved += contacts.len();
+
+            let mut moves: HashMap<usize, (Vector2, Vector2, usize)> = HashMap::new();
+            let zero = Vector2::new(0.0, 0.0);
+            for contact in &contacts {
+                let (own, theirs) = contact.inverse_masses;
+                let normal = contact.normal;
+                let share = contact.depth * own / (own + theirs);
+                let impulse = self.contact_impulse(contact);
+                let sides = [(Some(contact.entity_index), 1.0, own, share)]
+                    .into_iter()
+                    .chain([(contact.other, -1.0, theirs, contact.depth - share)]);
+                for (entity_index, sign, inverse_mass, share) in sides {
+                    let Some(entity_index) = entity_index else {
+                        continue;
+                    };
+                    let total = moves.entry(entity_index).or_insert((zero, zero, 0));
+                    total.0.x += sign * normal.x * share;
+                    total.0.y += sign * normal.y * share;
+                    total.1.x += sign * impulse.x * inverse_mass;
+                    total.1.y += sign * impulse.y * inverse_mass;
+                    total.2 += 1;
+                }
+            }
+            let mut moves: Vec<(usize, (Vector2, Vector2, usize))> = moves.into_iter().collect();
+            moves.sort_unstable_by_key(|(entity_index, _)| *entity_index);
+            for (entity_index, (push, change, count)) in moves {
+                let count = count as f32;
+                let mut transform = self
+                    .transforms
+                    .get(&entity_index)
+                    .copied()
+                    .unwrap_or_default();
+                transform.position.x += push.x / count;
+                transform.position.y += push.y / count;
+                // A push overflowing f32 is refused, leaving the entity where it was
+                if self.place_entity(entity_index, transform).is_err() {
+                    continue;
+                }
+                if change.x != 0.0 || change.y != 0.0 {
+                    let (x, y) = self.get_velocity(entity_index);
+                    let bounds = self.entity_bounds[&entity_index];
+                    let velocity = Vector2::new(x + change.x / count, y + change.y / count);
+                    if velocity.x.is_finite() && velocity.y.is_finite() {
+                        self.store_velocity(entity_index, bounds, velocity);
+                    }
+                }
+            }
+        }
+        resolved
+    }
+
+    /// Impulse on the dynamic side of a contact, and its opposite on the other side, that
+    /// stops them approaching along the normal and applies friction to their sliding.
+    fn contact_impulse(&self, contact: &Contact) -> Vector2 {
+        let own = self.get_velocity(contact.entity_index);
+        let theirs = contact
+            .other
+            .map_or((0.0, 0.0), |entity_index| self.get_velocity(entity_index));
+        let relative = Vector2::new(own.0 - theirs.0, own.1 - theirs.1);
+        let normal = contact.normal;
+        let approach = relative.x * normal.x + relative.y * normal.y;
+        if approach >= 0.0 {
+            return Vector2::new(0.0, 0.0);
+        }
+        let inverse_mass = contact.inverse_masses.0 + contact.inverse_masses.1;
+        let pushed = -approach / inverse_mass;
+
+        let tangent = Vector2::new(
+            relative.x - approach * normal.x,
+            relative.y - approach * normal.y,
+        );
+        let slide = tangent.x.hypot(tangent.y);
+        let mut impulse = Vector2::new(normal.x * pushed, normal.y * pushed);
+        if slide > 0.0 && contact.friction > 0.0 {
+            let rubbed = (slide / inverse_mass).min(contact.friction * pushed);
+            impulse.x -= tangent.x / slide * rubbed;
+            impulse.y -= tangent.y / slide * rubbed;
+        }
+        impulse
+    }
+
+    /// Overlapping contacts involving a dynamic body, each body pair once.
+    fn overlapping_contacts(&self) -> Vec<Contact> {
+        let mut entities: Vec<&usize> = self.dynamic_bodies.keys().collect();
+        entities.sort_unstable();
+        let bodies = entities
+            .into_iter()
+            .filter(|entity_index| !self.sensors.contains(entity_index))
+            .flat_map(|entity_index| &self.dynamic_bodies[entity_index])
+            .map(|placed| &placed.body);
+
+        let mut seen: HashSet<PairKey> = HashSet::new();
+        let mut contacts = Vec::new();
+        self.visit_collisions(bodies, Targets::All, |body, other| {
+            if other.entity_index() == body.entity_index()
+                || self.sensors.contains(&other.entity_index())
+                || !seen.insert(pair_key(body, other))
+            {
+                return ControlFlow::Continue(());
+            }
+            let depth = body.overlap(other);
+            let (own, theirs) = (self.inverse_mass(body), self.inverse_mass(other));
+            if depth <= 0.0 || own + theirs <= 0.0 {
+                return ControlFlow::Continue(());
+            }
+            let (dx, dy) = (body.pos.x - other.pos.x, body.pos.y - other.pos.y);
+            let length = dx.hypot(dy);
+            let normal = if length > 0.0 {
+                Vector2::new(dx / length, dy / length)
+            } else {
+                Vector2::new(1.0, 0.0)
+            };
+
+            let friction = (self.friction_of(body.entity_index(), body.body_index())
+                * self.friction_of(other.entity_index(), other.body_index()))
+            .sqrt();
+            contacts.push(Contact {
+                entity_index: body.entity_index(),
+                other: (!other.is_static()).then_some(other.entity_index()),
+                normal,
+                depth,
+                inverse_masses: (own, theirs),
+                friction,
+            });
+            ControlFlow::Continue(())
+        });
+        contacts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_pair_key_order() {
+        let a = Body::new(1, 0, Vector2::new(0.0, 0.0), 1.0, false, 0);
+        let b = Body::new(0, 2, Vector2::new(1.0, 0.0), 1.0, false, 0);
+        let wall = Body::new(0, 0, Vector2::new(1.0, 0.0), 1.0, true, 0);
+        assert_eq!(pair_key(&a, &b), pair_key(&b, &a));
+        assert_eq!(pair_key(&a, &wall), ((1, 0), (true, 0, 0)));
+    }
+}
@@ -0,0 +1,35 @@
+use crate::Body;
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+/// Which bodies collision queries test against, passed from Python by name, so terrain and
+/// entity interaction passes can run separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Targets {
+    #[default]
+    All,
+    Static,
+    Dynamic,
+}
+
+impl Targets {
+    pub fn admits(self, other: &Body) -> bool {
+        match self {
+            Targets::All => true,
+            Targets::Static => other.is_static(),
+            Targets::Dynamic => !other.is_static(),
+        }
+    }
+}
+
+impl<'source> FromPyObject<'source> for Targets {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        match ob.extract::<&str>()? {
+            "all" => Ok(Targets::All),
+            "static" => Ok(Targets::Static),
+            "dynamic" => Ok(Targets::Dynamic),
+            name => Err(PyValueError::new_err(format!(
+                "unknown targets {name:?}, expected \"all\", \"static\" or \"dynamic\""
+            ))),
+        }
+    }
+}
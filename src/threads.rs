@@ -0,0 +1,162 @@
+//! Control over the threads used by parallel queries.
+//!
+//! Without configuration, parallel work runs on the rayon global pool, sized by the
+//! `RAYON_NUM_THREADS` environment variable or the number of cores. `set_num_threads` and the
+//! per-call `num_threads` arguments run it on dedicated pools instead, built once per size.
+//!
+//! Builds without the `parallel` feature, such as for pyodide where wasm has no threads, run
+//! the same code on the calling thread: `prelude` then stands in for rayon's with sequential
+//! iterators and thread counts are ignored.
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use std::sync::Mutex;
+#[cfg(feature = "parallel")]
+use {
+    pyo3::exceptions::PyRuntimeError,
+    rayon::{ThreadPool, ThreadPoolBuilder},
+    std::{collections::HashMap, sync::Arc},
+};
+
+#[cfg(feature = "parallel")]
+pub use rayon::prelude;
+
+/// The rayon iterator methods used in the crate, running sequentially.
+#[cfg(not(feature = "parallel"))]
+pub mod prelude {
+    pub trait ParallelSlice<T> {
+        fn par_iter(&self) -> std::slice::Iter<'_, T>;
+        fn par_sort_by_key<K: Ord>(&mut self, key: impl FnMut(&T) -> K);
+    }
+
+    impl<T> ParallelSlice<T> for [T] {
+        fn par_iter(&self) -> std::slice::Iter<'_, T> {
+            self.iter()
+        }
+
+        fn par_sort_by_key<K: Ord>(&mut self, key: impl FnMut(&T) -> K) {
+            self.sort_by_key(key)
+        }
+    }
+
+    pub trait IntoParallelIterator: IntoIterator + Sized {
+        fn into_par_iter(self) -> Self::IntoIter {
+            self.into_iter()
+        }
+    }
+
+    impl<I: IntoIterator> IntoParallelIterator for I {}
+
+    pub trait ParallelIterator: Iterator + Sized {
+        fn flat_map_iter<U: IntoIterator, F: FnMut(Self::Item) -> U>(
+            self,
+            map: F,
+        ) -> std::iter::FlatMap<Self, U, F> {
+            self.flat_map(map)
+        }
+    }
+
+    impl<I: Iterator> ParallelIterator for I {}
+}
+
+/// Most threads a query may ask for, well past any core count but short of exhausting the
+/// process spawning them.
+const MAX_THREADS: usize = 1 << 10;
+
+static DEFAULT_THREADS: Mutex<Option<usize>> = Mutex::new(None);
+
+fn check_threads(num_threads: usize) -> PyResult<()> {
+    if num_threads > MAX_THREADS {
+        return Err(PyValueError::new_err(format!(
+            "{num_threads} threads asked for, at most {MAX_THREADS} are allowed"
+        )));
+    }
+    Ok(())
+}
+#[cfg(feature = "parallel")]
+static POOLS: Mutex<Option<HashMap<usize, Arc<ThreadPool>>>> = Mutex::new(None);
+
+#[cfg(feature = "parallel")]
+fn pool(num_threads: usize) -> PyResult<Arc<ThreadPool>> {
+    check_threads(num_threads)?;
+    let mut pools = POOLS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let pools = pools.get_or_insert_with(HashMap::new);
+    if let Some(pool) = pools.get(&num_threads) {
+        return Ok(pool.clone());
+    }
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+        .map_err(|error| PyRuntimeError::new_err(error.to_string()))?;
+    let pool = Arc::new(pool);
+    pools.insert(num_threads, pool.clone());
+    Ok(pool)
+}
+
+#[cfg(feature = "parallel")]
+fn default_threads() -> Option<usize> {
+    *DEFAULT_THREADS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Runs `op` with `num_threads` threads, falling back to the count set with `set_num_threads`
+/// and then to the rayon global pool.
+#[cfg(feature = "parallel")]
+pub fn install<R: Send>(num_threads: Option<usize>, op: impl FnOnce() -> R + Send) -> PyResult<R> {
+    match num_threads.or_else(default_threads) {
+        Some(num_threads) => Ok(pool(num_threads)?.install(op)),
+        None => Ok(op()),
+    }
+}
+
+/// Runs `op` on the calling thread, the only one there is.
+#[cfg(not(feature = "parallel"))]
+pub fn install<R: Send>(num_threads: Option<usize>, op: impl FnOnce() -> R + Send) -> PyResult<R> {
+    num_threads.map_or(Ok(()), check_threads)?;
+    Ok(op())
+}
+
+/// Sets the number of threads used by parallel queries that don't pass `num_threads`. `0`
+/// restores the default, taken from `RAYON_NUM_THREADS` or the number of cores. More than
+/// 1024 raise `ValueError`, as they do when passed to a query.
+#[pyfunction]
+pub fn set_num_threads(num_threads: usize) -> PyResult<()> {
+    check_threads(num_threads)?;
+    let mut default = DEFAULT_THREADS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *default = (num_threads > 0).then_some(num_threads);
+    Ok(())
+}
+
+/// Number of threads parallel queries currently run on by default.
+#[pyfunction]
+pub fn get_num_threads() -> usize {
+    #[cfg(feature = "parallel")]
+    return default_threads().unwrap_or_else(rayon::current_num_threads);
+    #[cfg(not(feature = "parallel"))]
+    1
+}
+
+#[cfg(all(test, feature = "parallel"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_thread_override() {
+        assert_eq!(install(Some(3), rayon::current_num_threads).unwrap(), 3);
+        assert!(install(Some(usize::MAX), || ()).is_err());
+        assert!(set_num_threads(MAX_THREADS + 1).is_err());
+        set_num_threads(2).unwrap();
+        assert_eq!(get_num_threads(), 2);
+        assert_eq!(install(None, rayon::current_num_threads).unwrap(), 2);
+        assert_eq!(install(Some(1), rayon::current_num_threads).unwrap(), 1);
+        set_num_threads(0).unwrap();
+        assert_eq!(
+            install(None, rayon::current_num_threads).unwrap(),
+            rayon::current_num_threads()
+        );
+    }
+}
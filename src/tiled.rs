@@ -0,0 +1,185 @@
+//! Static colliders from the object layers of Tiled JSON maps.
+//!
+//! Rectangles and ellipses become oriented boxes and ellipses, polygons are triangulated into
+//! meshes and polylines become chains. Each object is its own entity, indexed by its Tiled id
+//! and with its class (or legacy type) as kind. Coordinates are kept as Tiled stores them, with
+//! y pointing down.
+
+use crate::{GridPhysics, Vector2};
+use pyo3::{
+    exceptions::{PyKeyError, PyValueError},
+    PyResult,
+};
+use serde_json::Value;
+
+fn number(object: &Value, key: &str) -> f32 {
+    object.get(key).and_then(Value::as_f64).unwrap_or(0.0) as f32
+}
+
+fn points(object: &Value, key: &str) -> Option<Vec<Vector2>> {
+    let points = object.get(key)?.as_array()?;
+    Some(
+        points
+            .iter()
+            .map(|point| Vector2::new(number(point, "x"), number(point, "y")))
+            .collect(),
+    )
+}
+
+/// Finds the object layer named `name`, looking inside group layers.
+fn find_layer<'a>(layers: &'a [Value], name: &str) -> Option<&'a Value> {
+    layers
+        .iter()
+        .find_map(|layer| match layer.get("type").and_then(Value::as_str) {
+            Some("objectgroup") if layer.get("name").and_then(Value::as_str) == Some(name) => {
+                Some(layer)
+            }
+            Some("group") => find_layer(layer.get("layers")?.as_array()?, name),
+            _ => None,
+        })
+}
+
+fn area(polygon: &[Vector2]) -> f32 {
+    let mut twice = 0.0;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        twice += a.x * b.y - b.x * a.y;
+    }
+    twice / 2.0
+}
+
+/// Ear clipping triangulation of a simple polygon, as index triples into `polygon`.
+fn triangulate(polygon: &[Vector2]) -> Vec<(usize, usize, usize)> {
+    let orientation = area(polygon).signum();
+    let cross = |a: Vector2, b: Vector2, c: Vector2| {
+        ((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)) * orientation
+    };
+
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    let mut triangles = Vec::new();
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            );
+            let (pa, pb, pc) = (polygon[a], polygon[b], polygon[c]);
+            cross(pa, pb, pc) > 0.0
+                && remaining
+                    .iter()
+                    .filter(|index| ![a, b, c].contains(index))
+                    .all(|index| {
+                        let p = polygon[*index];
+                        cross(pa, pb, p) < 0.0 || cross(pb, pc, p) < 0.0 || cross(pc, pa, p) < 0.0
+                    })
+        });
+        // Degenerate polygons have no ear left, clip whatever corner is next
+        let i = ear.unwrap_or(0);
+        triangles.push((
+            remaining[(i + n - 1) % n],
+            remaining[i],
+            remaining[(i + 1) % n],
+        ));
+        remaining.remove(i);
+    }
+    if remaining.len() == 3 {
+        triangles.push((remaining[0], remaining[1], remaining[2]));
+    }
+    triangles
+}
+
+/// Builds a grid covering the map from the objects of `collision_layer`. The cell size
+/// defaults to the tile width.
+pub fn load(json: &str, collision_layer: &str, cell_size: Option<f32>) -> PyResult<GridPhysics> {
+    let map: Value = serde_json::from_str(json)
+        .map_err(|error| PyValueError::new_err(format!("invalid Tiled JSON map: {error}")))?;
+    let (tile_width, tile_height) = (number(&map, "tilewidth"), number(&map, "tileheight"));
+    let size = (number(&map, "width") * tile_width).max(number(&map, "height") * tile_height);
+    let cell_size = cell_size.unwrap_or(tile_width);
+    if size <= 0.0 || cell_size <= 0.0 {
+        return Err(PyValueError::new_err(
+            "Tiled map needs a positive width, height and tile size",
+        ));
+    }
+
+    let layers = map
+        .get("layers")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let layer = find_layer(layers, collision_layer)
+        .ok_or_else(|| PyKeyError::new_err(format!("no object layer named {collision_layer:?}")))?;
+
+    let mut grid = GridPhysics::try_new(size, cell_size)?;
+    let objects = layer.get("objects").and_then(Value::as_array);
+    for object in objects.into_iter().flatten() {
+        let entity_index =
+            object.get("id").and_then(Value::as_u64).ok_or_else(|| {
+                PyValueError::new_err(format!("Tiled object without an id: {object}"))
+            })? as usize;
+        let class = ["class", "type"]
+            .into_iter()
+            .find_map(|key| object.get(key).and_then(Value::as_str))
+            .filter(|class| !class.is_empty());
+        let kind = class.map_or(0, |class| grid.register_kind(class));
+        grid.check_body_indices(entity_index, 1, kind)?;
+
+        // Objects rotate clockwise on screen around their top-left corner (x, y)
+        let origin = Vector2::new(number(object, "x"), number(object, "y"));
+        let angle = number(object, "rotation").to_radians();
+        let (sin, cos) = angle.sin_cos();
+        let place = |p: Vector2| {
+            Vector2::new(
+                origin.x + p.x * cos - p.y * sin,
+                origin.y + p.x * sin + p.y * cos,
+            )
+        };
+        let (width, height) = (number(object, "width"), number(object, "height"));
+        let center = place(Vector2::new(width / 2.0, height / 2.0));
+
+        if object.get("point").and_then(Value::as_bool) == Some(true) {
+            continue;
+        } else if let Some(polygon) = points(object, "polygon") {
+            let vertices: Vec<Vector2> = polygon.into_iter().map(place).collect();
+            let triangles = triangulate(&vertices);
+            grid.add_static_trimesh(entity_index, &vertices, &triangles, kind)?;
+        } else if let Some(polyline) = points(object, "polyline") {
+            let points: Vec<Vector2> = polyline.into_iter().map(place).collect();
+            grid.add_static_chain(entity_index, &points, kind);
+        } else if object.get("ellipse").and_then(Value::as_bool) == Some(true) {
+            let radii = (width / 2.0, height / 2.0);
+            grid.add_ellipse(entity_index, center, radii, angle, true, kind);
+        } else if width > 0.0 && height > 0.0 {
+            let half_extents = (width / 2.0, height / 2.0);
+            grid.add_obb(entity_index, center, half_extents, angle, true, kind);
+        }
+    }
+    Ok(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_triangulation() {
+        // An L shape needs a reflex corner skipped
+        let l_shape = [
+            Vector2::new(0.0, 0.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(2.0, 1.0),
+            Vector2::new(1.0, 1.0),
+            Vector2::new(1.0, 2.0),
+            Vector2::new(0.0, 2.0),
+        ];
+        let triangles = triangulate(&l_shape);
+        assert_eq!(triangles.len(), 4);
+        let covered: f32 = triangles
+            .iter()
+            .map(|&(a, b, c)| area(&[l_shape[a], l_shape[b], l_shape[c]]).abs())
+            .sum();
+        assert!((covered - 3.0).abs() < 1e-5);
+    }
+}
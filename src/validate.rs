@@ -0,0 +1,210 @@
+//! Rejecting NaN and infinite input before it reaches the cell computations, where it would
+//! bin bodies into arbitrary cells.
+
+// `create_exception!` of pyo3 0.19 checks a cfg that newer compilers don't know.
+#![allow(unexpected_cfgs)]
+
+use crate::{shape::Shape, GridPhysics, Placed, Transform, Vector2};
+use pyo3::{
+    create_exception,
+    exceptions::{PyOverflowError, PyValueError},
+    PyErr, PyResult,
+};
+
+create_exception!(
+    radyx,
+    RadyxError,
+    PyValueError,
+    "Invalid input to a radyx world, such as a NaN or infinite position."
+);
+
+/// The first non-finite body of an add call.
+#[derive(Clone, Copy)]
+pub struct Invalid {
+    pub count: usize,
+    pub entity_index: usize,
+    pub body_index: usize,
+}
+
+fn finite(values: &[f32]) -> bool {
+    values.iter().all(|value| value.is_finite())
+}
+
+fn point(p: Vector2) -> bool {
+    finite(&[p.x, p.y])
+}
+
+/// Whether every coordinate, size and angle of a body is finite, and so are its bounds, which
+/// sizes near the top of f32 overflow.
+fn body_is_finite(body: &Placed) -> bool {
+    let shape = match body.shape() {
+        Shape::Circle => true,
+        Shape::Ellipse { radius_x, radius_y } => finite(&[radius_x, radius_y]),
+        Shape::Box { half_x, half_y } => finite(&[half_x, half_y]),
+        Shape::Segment { half_length } => half_length.is_finite(),
+        Shape::Triangle { corners } => corners.into_iter().all(point),
+    };
+    if !shape || !point(body.pos) || !point(body.offset) {
+        return false;
+    }
+    let (min_x, max_x, min_y, max_y) = body.aabb();
+    finite(&[
+        body.radius,
+        body.angle,
+        body.turn,
+        min_x,
+        max_x,
+        min_y,
+        max_y,
+    ])
+}
+
+/// Raises unless an entity index, the body indices below `bodies` and a kind all fit the 32
+/// bits a body stores them in.
+pub(crate) fn check_indices(entity_index: usize, bodies: usize, kind: usize) -> PyResult<()> {
+    let indices = [
+        ("entity index", entity_index),
+        ("body index", bodies.saturating_sub(1)),
+        ("kind", kind),
+    ];
+    match indices
+        .into_iter()
+        .find(|(_, value)| u32::try_from(*value).is_err())
+    {
+        Some((name, value)) => Err(PyOverflowError::new_err(format!(
+            "{name} {value} doesn't fit in 32 bits"
+        ))),
+        None => Ok(()),
+    }
+}
+
+impl GridPhysics {
+    /// `check_indices`, also raising for entity indices in namespaces the grid hasn't
+    /// registered.
+    pub(crate) fn check_body_indices(
+        &self,
+        entity_index: usize,
+        bodies: usize,
+        kind: usize,
+    ) -> PyResult<()> {
+        check_indices(entity_index, bodies, kind)?;
+        self.namespaces.check(entity_index)
+    }
+
+    /// Notes a body about to be inserted with a non-finite value, returning whether it is
+    /// skipped, which it always is.
+    pub(crate) fn screen_invalid(&mut self, body: &Placed) -> bool {
+        if body_is_finite(body) {
+            return false;
+        }
+        let first = Invalid {
+            count: 0,
+            entity_index: body.entity_index(),
+            body_index: body.body_index(),
+        };
+        self.invalid.get_or_insert(first).count += 1;
+        true
+    }
+
+    /// Raises for the non-finite bodies of the last add call in strict mode.
+    pub(crate) fn report_invalid(&mut self) -> PyResult<()> {
+        let Some(invalid) = self.invalid.take() else {
+            return Ok(());
+        };
+        if !self.strict {
+            return Ok(());
+        }
+        let Invalid {
+            count,
+            entity_index,
+            body_index,
+        } = invalid;
+        Err(RadyxError::new_err(format!(
+            "{count} bodies have NaN or infinite values, first body {body_index} of entity \
+             {entity_index}; they were not added"
+        )))
+    }
+
+    /// Checks a move before it is applied. `Ok(false)` means the move is dropped because
+    /// strict mode is off.
+    pub(crate) fn check_transform(
+        &self,
+        entity_index: usize,
+        transform: &Transform,
+    ) -> PyResult<bool> {
+        if point(transform.position) && transform.angle.is_finite() {
+            return Ok(true);
+        }
+        self.reject(format!(
+            "NaN or infinite transform ({}, {}, {}) for entity {entity_index}",
+            transform.position.x, transform.position.y, transform.angle
+        ))
+    }
+
+    /// Checks a move of an entity's current bodies, which may overflow even when the
+    /// transform is finite, as offsets far from it grow once rotated and added.
+    pub(crate) fn check_move(&self, entity_index: usize, transform: &Transform) -> PyResult<bool> {
+        if !self.check_transform(entity_index, transform)? {
+            return Ok(false);
+        }
+        let mut bodies = self.placed_of(entity_index);
+        if bodies.all(|body| body_is_finite(&body.transformed(*transform))) {
+            return Ok(true);
+        }
+        self.reject(format!(
+            "moving entity {entity_index} to ({}, {}) overflows its bodies",
+            transform.position.x, transform.position.y
+        ))
+    }
+
+    pub(crate) fn check_position(
+        &self,
+        entity_index: usize,
+        body_index: usize,
+        pos: Vector2,
+    ) -> PyResult<bool> {
+        if point(pos) {
+            return Ok(true);
+        }
+        self.reject(format!(
+            "NaN or infinite position ({}, {}) for body {body_index} of entity {entity_index}",
+            pos.x, pos.y
+        ))
+    }
+
+    /// Checks a velocity, impulse or force, named by `what`, given for an entity.
+    pub(crate) fn check_vector(
+        &self,
+        what: &str,
+        entity_index: usize,
+        value: Vector2,
+    ) -> PyResult<bool> {
+        if point(value) {
+            return Ok(true);
+        }
+        self.reject(format!(
+            "NaN or infinite {what} ({}, {}) for entity {entity_index}",
+            value.x, value.y
+        ))
+    }
+
+    /// Checks that scaling an entity by `factor` leaves every body finite.
+    pub(crate) fn check_scale(&self, entity_index: usize, factor: f32) -> PyResult<bool> {
+        let transform = self.transforms.get(&entity_index).copied();
+        let mut bodies = self.placed_of(entity_index);
+        if bodies.all(|body| body_is_finite(&body.scaled(factor, transform))) {
+            return Ok(true);
+        }
+        self.reject(format!(
+            "scaling entity {entity_index} by {factor} overflows its bodies"
+        ))
+    }
+
+    fn reject(&self, message: String) -> PyResult<bool> {
+        if self.strict {
+            Err(PyErr::new::<RadyxError, _>(message))
+        } else {
+            Ok(false)
+        }
+    }
+}
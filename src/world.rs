@@ -0,0 +1,239 @@
+use pyo3::{exceptions::PyKeyError, prelude::*};
+use std::collections::{HashMap, HashSet};
+use threads::prelude::*;
+
+use crate::{scene, targets::Targets, threads, Collision, GridPhysics, KindArg, Vector2};
+
+/// Several named `GridPhysics` layers (e.g. "ground", "air") driven together.
+#[pyclass(module = "radyx")]
+pub struct World {
+    layers: HashMap<String, Py<GridPhysics>>,
+}
+
+impl World {
+    fn layer_mut<'py>(
+        &'py self,
+        py: Python<'py>,
+        name: &str,
+    ) -> PyResult<PyRefMut<'py, GridPhysics>> {
+        Ok(self.layer(name)?.as_ref(py).try_borrow_mut()?)
+    }
+}
+
+#[pymethods]
+impl World {
+    #[new]
+    pub fn new() -> Self {
+        Self {
+            layers: HashMap::new(),
+        }
+    }
+
+    /// Creates a layer, replacing any existing layer with the same name.
+    pub fn add_layer(
+        &mut self,
+        py: Python,
+        name: String,
+        size: f32,
+        cell_size: f32,
+    ) -> PyResult<Py<GridPhysics>> {
+        let layer = Py::new(py, GridPhysics::try_new(size, cell_size)?)?;
+        self.layers.insert(name, layer.clone_ref(py));
+        Ok(layer)
+    }
+
+    pub fn layer(&self, name: &str) -> PyResult<&Py<GridPhysics>> {
+        self.layers
+            .get(name)
+            .ok_or_else(|| PyKeyError::new_err(format!("no layer named {name:?}")))
+    }
+
+    /// Serializes every layer as `{"version": 1, "layers": {name: scene}}`, using the scene
+    /// schema of `GridPhysics.to_json`.
+    #[pyo3(signature = (pretty=true))]
+    pub fn to_json(&self, py: Python, pretty: bool) -> PyResult<String> {
+        let borrowed = self
+            .layers
+            .iter()
+            .map(|(name, layer)| Ok((name.as_str(), layer.as_ref(py).try_borrow()?)))
+            .collect::<PyResult<Vec<_>>>()?;
+        let layers = borrowed.iter().map(|(name, layer)| (*name, &**layer));
+        scene::world_to_json(layers, pretty)
+    }
+
+    #[staticmethod]
+    pub fn from_json(py: Python, json: &str) -> PyResult<Self> {
+        let layers = scene::world_from_json(json)?
+            .into_iter()
+            .map(|(name, grid)| Ok((name, Py::new(py, grid)?)))
+            .collect::<PyResult<_>>()?;
+        Ok(Self { layers })
+    }
+
+    pub fn layer_names(&self) -> Vec<String> {
+        self.layers.keys().cloned().collect()
+    }
+
+    pub fn reset(&self, py: Python) -> PyResult<()> {
+        for layer in self.layers.values() {
+            layer.as_ref(py).try_borrow_mut()?.reset();
+        }
+        Ok(())
+    }
+
+    #[pyo3(signature = (layer, entity_index, pos, radius, kind=KindArg::default()))]
+    pub fn add_static_circle(
+        &self,
+        py: Python,
+        layer: &str,
+        entity_index: usize,
+        pos: Vector2,
+        radius: f32,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        self.layer_mut(py, layer)?
+            .py_add_static_circle(entity_index, pos, radius, kind)
+    }
+
+    #[pyo3(signature = (layer, entity_index, bodies, radius, kind=KindArg::default()))]
+    pub fn add_static_circles(
+        &self,
+        py: Python,
+        layer: &str,
+        entity_index: usize,
+        bodies: Vec<Vector2>,
+        radius: f32,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        self.layer_mut(py, layer)?
+            .py_add_static_circles(entity_index, bodies, radius, kind, None)
+    }
+
+    #[pyo3(signature = (layer, entity_index, pos, radius, kind=KindArg::default()))]
+    pub fn add_dynamic_circle(
+        &self,
+        py: Python,
+        layer: &str,
+        entity_index: usize,
+        pos: Vector2,
+        radius: f32,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        self.layer_mut(py, layer)?
+            .py_add_dynamic_circle(entity_index, pos, radius, kind)
+    }
+
+    #[pyo3(signature = (layer, entity_index, bodies, radius, kind=KindArg::default()))]
+    pub fn add_dynamic_circles(
+        &self,
+        py: Python,
+        layer: &str,
+        entity_index: usize,
+        bodies: Vec<Vector2>,
+        radius: f32,
+        kind: KindArg,
+    ) -> PyResult<()> {
+        self.layer_mut(py, layer)?
+            .py_add_dynamic_circles(entity_index, bodies, radius, kind, None)
+    }
+
+    /// Collisions of every layer, computed in parallel on `num_threads` threads with the GIL
+    /// released.
+    #[pyo3(signature = (with_overlap=false, num_threads=None))]
+    pub fn get_collisions(
+        &self,
+        py: Python,
+        with_overlap: bool,
+        num_threads: Option<usize>,
+    ) -> PyResult<HashMap<String, HashSet<Collision>>> {
+        let borrowed = self
+            .layers
+            .iter()
+            .map(|(name, layer)| Ok((name.as_str(), layer.as_ref(py).try_borrow()?)))
+            .collect::<PyResult<Vec<_>>>()?;
+        let layers: Vec<(&str, &GridPhysics)> = borrowed
+            .iter()
+            .map(|(name, layer)| (*name, &**layer))
+            .collect();
+
+        py.allow_threads(|| {
+            threads::install(num_threads, || {
+                layers
+                    .par_iter()
+                    .map(|(name, layer)| {
+                        (
+                            name.to_string(),
+                            layer.get_collisions(with_overlap, None, false, Targets::All),
+                        )
+                    })
+                    .collect()
+            })
+        })
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn check_layer_collisions() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut world = World::new();
+            world
+                .add_layer(py, "ground".to_string(), 100.0, 10.0)
+                .unwrap();
+            world.add_layer(py, "air".to_string(), 100.0, 20.0).unwrap();
+            world
+                .add_dynamic_circle(
+                    py,
+                    "ground",
+                    0,
+                    Vector2::new(5.0, 5.0),
+                    1.0,
+                    KindArg::default(),
+                )
+                .unwrap();
+            world
+                .add_static_circle(
+                    py,
+                    "ground",
+                    1,
+                    Vector2::new(5.0, 6.0),
+                    1.0,
+                    KindArg::default(),
+                )
+                .unwrap();
+            world
+                .add_dynamic_circle(
+                    py,
+                    "air",
+                    2,
+                    Vector2::new(5.0, 5.0),
+                    1.0,
+                    KindArg::default(),
+                )
+                .unwrap();
+            assert!(world
+                .add_dynamic_circle(
+                    py,
+                    "water",
+                    3,
+                    Vector2::new(5.0, 5.0),
+                    1.0,
+                    KindArg::default()
+                )
+                .is_err());
+
+            let collisions = world.get_collisions(py, false, None).unwrap();
+            assert_eq!(collisions["ground"].len(), 1);
+            assert!(collisions["air"].is_empty());
+        });
+    }
+}